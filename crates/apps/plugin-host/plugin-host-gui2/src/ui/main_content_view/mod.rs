@@ -4,6 +4,8 @@ use std::sync::{Arc, Mutex};
 use derivative::Derivative;
 use thiserror::Error;
 
+use audio_processor_analysis::spectrum::SpectrumAnalyzerHandle;
+use audio_processor_analysis::tempo::TempoEstimate;
 use augmented::audio::gc::Shared;
 use augmented::gui::iced::{Command, Element, Subscription};
 use plugin_host_lib::{
@@ -25,6 +27,7 @@ mod audio_chart;
 mod audio_file_chart;
 pub mod plugin_content;
 pub mod plugin_editor_window;
+mod spectrum_chart;
 pub mod status_bar;
 pub mod transport_controls;
 mod view;
@@ -54,6 +57,8 @@ pub struct MainContentView {
     volume_handle: Option<Shared<VolumeMeterProcessorHandle>>,
     rms_processor_handle: Option<Shared<RunningRMSProcessorHandle>>,
     audio_chart: Option<audio_chart::AudioChart>,
+    spectrum_analyzer_handle: Option<Shared<SpectrumAnalyzerHandle>>,
+    spectrum_chart: Option<spectrum_chart::SpectrumChart>,
     audio_file_model: audio_file_chart::AudioFileModel,
     start_stop_button_state: view::StartStopViewModel,
 }
@@ -69,7 +74,8 @@ pub enum Message {
     ReloadedPlugin(bool, StatusBar),
     VolumeMeter(volume_meter::Message),
     StartStopButtonClicked,
-    SetAudioFilePathResponse(String),
+    /// The input file & its tempo, if it could be estimated
+    SetAudioFilePathResponse(String, Option<TempoEstimate>),
     Exit,
     None,
 }
@@ -112,6 +118,8 @@ impl MainContentView {
                 volume_handle: None,
                 rms_processor_handle: None,
                 audio_chart: None,
+                spectrum_analyzer_handle: None,
+                spectrum_chart: None,
                 audio_file_model: AudioFileModel::empty(),
                 volume_meter_state: volume_meter::VolumeMeter::new(),
                 start_stop_button_state: view::StartStopViewModel {
@@ -128,6 +136,9 @@ impl MainContentView {
         if let Some(chart) = &mut self.audio_chart {
             chart.update();
         }
+        if let Some(chart) = &mut self.spectrum_chart {
+            chart.update();
+        }
         self.volume_meter_state
             .set_volume_info((&self.volume_handle).into());
         match message {
@@ -180,8 +191,8 @@ impl MainContentView {
                 let _ = self.editor_controller.close_window();
                 Command::none()
             }
-            Message::SetAudioFilePathResponse(input_file) => {
-                self.on_set_input_file_response(input_file);
+            Message::SetAudioFilePathResponse(input_file, tempo) => {
+                self.on_set_input_file_response(input_file, tempo);
                 Command::none()
             }
         }
@@ -200,6 +211,7 @@ impl MainContentView {
         let audio_io_settings = &mut self.audio_io_settings;
         let plugin_content = &mut self.plugin_content;
         let audio_chart = &mut self.audio_chart;
+        let spectrum_chart = &mut self.spectrum_chart;
         let transport_controls = &mut self.transport_controls;
         let status_message = &self.status_message;
         let volume_meter_state = &mut self.volume_meter_state;
@@ -210,6 +222,7 @@ impl MainContentView {
             audio_io_settings,
             plugin_content,
             audio_chart,
+            spectrum_chart,
             volume_meter_state,
             transport_controls,
             status_message,
@@ -293,11 +306,23 @@ impl MainContentView {
                 self.audio_chart = Some(audio_chart::AudioChart::new(buffer));
             }
         }
+        if self.spectrum_analyzer_handle.is_none() {
+            if let Ok(Some(handle)) = self
+                .plugin_host
+                .try_lock()
+                .map(|h| h.spectrum_analyzer_handle())
+            {
+                self.spectrum_analyzer_handle = Some(handle.clone());
+                self.spectrum_chart = Some(spectrum_chart::SpectrumChart::new(handle));
+            }
+        }
     }
 
     fn reset_handles(&mut self) {
         self.audio_chart = None;
         self.rms_processor_handle = None;
+        self.spectrum_chart = None;
+        self.spectrum_analyzer_handle = None;
         self.volume_handle = None;
     }
 }
@@ -309,7 +334,7 @@ impl MainContentView {
         Command::perform(
             MainContentView::handle_set_input_file_path(plugin_host, input_file.to_string()),
             move |result| match result {
-                Ok(input_file) => Message::SetAudioFilePathResponse(input_file),
+                Ok((input_file, tempo)) => Message::SetAudioFilePathResponse(input_file, tempo),
                 Err(err) => {
                     Message::SetStatus(StatusBar::new(format!("{}", err), status_bar::State::Error))
                 }
@@ -320,14 +345,19 @@ impl MainContentView {
     async fn handle_set_input_file_path(
         plugin_host: Arc<Mutex<TestPluginHost>>,
         input_file: String,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    ) -> Result<(String, Option<TempoEstimate>), Box<dyn std::error::Error>> {
         let path = PathBuf::from(&input_file);
-        tokio::task::spawn_blocking(move || plugin_host.lock().unwrap().set_audio_file_path(path))
-            .await??;
-        Ok(input_file)
+        let tempo = tokio::task::spawn_blocking(move || {
+            let mut plugin_host = plugin_host.lock().unwrap();
+            plugin_host
+                .set_audio_file_path(path)
+                .map(|_| plugin_host.audio_file_tempo())
+        })
+        .await??;
+        Ok((input_file, tempo))
     }
 
-    fn on_set_input_file_response(&mut self, input_file: String) {
+    fn on_set_input_file_response(&mut self, input_file: String, tempo: Option<TempoEstimate>) {
         self.reset_handles();
         self.host_state.audio_input_file_path = Some(input_file);
         self.host_options_service
@@ -335,6 +365,12 @@ impl MainContentView {
             .unwrap_or_else(|err| {
                 log::error!("Failed to store {:?}", err);
             });
+        if let Some(tempo) = tempo {
+            self.status_message = StatusBar::new(
+                format!("Loaded input file - {:.1} BPM", tempo.bpm),
+                status_bar::State::Idle,
+            );
+        }
     }
 }
 
@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+
+use iced::canvas::{Cursor, Fill, Frame, Geometry, Program, Stroke};
+use iced::{canvas, Canvas, Element, Length, Point, Rectangle, Size};
+
+use audio_garbage_collector::Shared;
+use audio_processor_analysis::spectrum::SpectrumAnalyzerHandle;
+use audio_processor_iced_design_system::colors::Colors;
+
+pub type Message = ();
+
+/// Lowest frequency shown, in Hz
+const MIN_FREQUENCY: f32 = 20.0;
+/// Magnitudes are drawn from this up to 0dB
+const MIN_DISPLAY_DB: f32 = -90.0;
+
+pub struct SpectrumChart {
+    frame: RefCell<Frame>,
+    handle: Shared<SpectrumAnalyzerHandle>,
+    magnitudes_db: Vec<f32>,
+    peaks_db: Vec<f32>,
+    last_frame_count: usize,
+}
+
+impl SpectrumChart {
+    pub fn new(handle: Shared<SpectrumAnalyzerHandle>) -> Self {
+        Self {
+            frame: RefCell::new(Frame::new(Size::new(100., 100.))),
+            handle,
+            magnitudes_db: Vec::new(),
+            peaks_db: Vec::new(),
+            last_frame_count: 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let frame_count = self.handle.frame_count();
+        if frame_count != self.last_frame_count {
+            self.last_frame_count = frame_count;
+            self.handle.read_magnitudes_db(&mut self.magnitudes_db);
+            self.handle.read_peaks_db(&mut self.peaks_db);
+        }
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        SpectrumChartView {
+            frame: &mut self.frame,
+            magnitudes_db: &self.magnitudes_db,
+            peaks_db: &self.peaks_db,
+            sample_rate: self.handle.sample_rate(),
+        }
+        .view()
+    }
+}
+
+pub struct SpectrumChartView<'a> {
+    frame: &'a mut RefCell<Frame>,
+    magnitudes_db: &'a [f32],
+    peaks_db: &'a [f32],
+    sample_rate: f32,
+}
+
+impl<'a> SpectrumChartView<'a> {
+    pub fn view(self) -> Element<'a, Message> {
+        Canvas::new(self)
+            .height(Length::Fill)
+            .width(Length::Fill)
+            .into()
+    }
+
+    /// Points of `values` on a logarithmic frequency axis, skipping bins below `MIN_FREQUENCY`
+    fn points(&self, values: &[f32], size: Size) -> Vec<Point> {
+        let num_bins = values.len();
+        let nyquist = self.sample_rate / 2.0;
+        if num_bins < 2 || nyquist <= MIN_FREQUENCY {
+            return vec![];
+        }
+
+        let log_range = (nyquist / MIN_FREQUENCY).ln();
+        values
+            .iter()
+            .enumerate()
+            .filter_map(|(bin, db)| {
+                let frequency = bin as f32 * nyquist / (num_bins - 1) as f32;
+                if frequency < MIN_FREQUENCY {
+                    return None;
+                }
+                let x = (frequency / MIN_FREQUENCY).ln() / log_range * size.width;
+                let level = ((db - MIN_DISPLAY_DB) / -MIN_DISPLAY_DB).max(0.0).min(1.0);
+                Some(Point::new(x, size.height - level * size.height))
+            })
+            .collect()
+    }
+}
+
+impl<'a> Program<Message> for SpectrumChartView<'a> {
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = self.frame.borrow_mut();
+        frame.resize(bounds.size());
+        let size = frame.size();
+
+        let mut path = canvas::path::Builder::new();
+        path.move_to(Point::new(0.0, size.height));
+        for point in self.points(self.magnitudes_db, size) {
+            path.line_to(point);
+        }
+        path.line_to(Point::new(size.width, size.height));
+        path.line_to(Point::new(0.0, size.height));
+        frame.fill(
+            &path.build(),
+            Fill::from(Colors::border_color().darken(-0.3)),
+        );
+
+        let peaks = self.points(self.peaks_db, size);
+        if let Some(first) = peaks.first() {
+            let mut path = canvas::path::Builder::new();
+            path.move_to(*first);
+            for point in &peaks[1..] {
+                path.line_to(*point);
+            }
+            frame.stroke(&path.build(), Stroke::default().with_color(Colors::text()));
+        }
+
+        vec![frame.geometry()]
+    }
+}
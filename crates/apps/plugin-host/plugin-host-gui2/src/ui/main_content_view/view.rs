@@ -9,10 +9,12 @@ use crate::ui::audio_io_settings;
 use crate::ui::main_content_view::audio_chart::AudioChart;
 use crate::ui::main_content_view::audio_file_chart::AudioFileModel;
 use crate::ui::main_content_view::plugin_content::View;
+use crate::ui::main_content_view::spectrum_chart::SpectrumChart;
 use crate::ui::main_content_view::status_bar::StatusBar;
 use crate::ui::main_content_view::transport_controls::TransportControlsView;
 use crate::ui::main_content_view::{
-    audio_chart, audio_file_chart, plugin_content, transport_controls, volume_meter, Message,
+    audio_chart, audio_file_chart, plugin_content, spectrum_chart, transport_controls,
+    volume_meter, Message,
 };
 
 pub struct StartStopViewModel {
@@ -24,6 +26,7 @@ pub struct MainContentViewModel<'a> {
     pub audio_io_settings: &'a mut audio_io_settings::Controller,
     pub plugin_content: &'a mut View,
     pub audio_chart: &'a mut Option<AudioChart>,
+    pub spectrum_chart: &'a mut Option<SpectrumChart>,
     pub volume_meter_state: &'a mut volume_meter::VolumeMeter,
     pub transport_controls: &'a mut TransportControlsView,
     pub status_message: &'a StatusBar,
@@ -36,6 +39,7 @@ pub fn main_content_view(view_model: MainContentViewModel) -> Element<Message> {
         audio_io_settings,
         plugin_content,
         audio_chart,
+        spectrum_chart,
         volume_meter_state,
         transport_controls,
         status_message,
@@ -69,6 +73,7 @@ pub fn main_content_view(view_model: MainContentViewModel) -> Element<Message> {
             .into(),
         bottom_visualisation_content_container(BottomVisualisationViewModel {
             audio_chart,
+            spectrum_chart,
             volume_meter_state,
         }),
         Rule::horizontal(1)
@@ -86,6 +91,7 @@ pub fn main_content_view(view_model: MainContentViewModel) -> Element<Message> {
 
 struct BottomVisualisationViewModel<'a> {
     audio_chart: &'a mut Option<audio_chart::AudioChart>,
+    spectrum_chart: &'a mut Option<spectrum_chart::SpectrumChart>,
     volume_meter_state: &'a mut volume_meter::VolumeMeter,
 }
 
@@ -106,6 +112,7 @@ fn bottom_visualisation_content_container(
 ) -> Element<Message> {
     let BottomVisualisationViewModel {
         audio_chart,
+        spectrum_chart,
         volume_meter_state,
     } = view_model;
     Container::new(
@@ -123,6 +130,19 @@ fn bottom_visualisation_content_container(
             .height(Length::Fill)
             .width(Length::Fill)
             .into(),
+            Container::new(
+                Container::new::<Element<Message>>(match spectrum_chart {
+                    Some(chart) => chart.view().map(|_| Message::None),
+                    None => Text::new("").into(),
+                })
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .style(Container0::default().border_radius(8.0)),
+            )
+            .padding(Spacing::base_spacing())
+            .height(Length::Fill)
+            .width(Length::Fill)
+            .into(),
             Container::new(volume_meter_state.view().map(Message::VolumeMeter))
                 .style(Container1::default().border())
                 .width(Length::Units(Spacing::base_control_size() * 2))
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use basedrop::{Handle, Shared, SharedCell};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::StreamConfig;
@@ -6,6 +8,7 @@ use audio_processor_standalone_midi::audio_thread::MidiAudioThreadHandler;
 use audio_processor_standalone_midi::host::MidiMessageQueue;
 use audio_processor_traits::InterleavedAudioBuffer;
 use audio_processor_traits::{AudioProcessor, AudioProcessorSettings, SilenceAudioProcessor};
+use convert_sample_rate::{Quality, RateAdapter};
 use error::AudioThreadError;
 use options::AudioThreadOptions;
 
@@ -34,6 +37,14 @@ pub struct AudioThread {
     input_stream: Option<cpal::Stream>,
     audio_thread_options: AudioThreadOptions,
     midi_message_queue: MidiMessageQueue,
+    resampling_latency: Duration,
+}
+
+/// Streams created for the current options
+struct AudioStreams {
+    input_stream: Option<cpal::Stream>,
+    output_stream: cpal::Stream,
+    resampling_latency: Duration,
 }
 
 unsafe impl Send for AudioThread {}
@@ -53,6 +64,7 @@ impl AudioThread {
             input_stream: None,
             midi_message_queue,
             audio_thread_options,
+            resampling_latency: Duration::ZERO,
         }
     }
 
@@ -60,8 +72,11 @@ impl AudioThread {
         let processor = self.processor.clone();
         let audio_thread_options = self.audio_thread_options.clone();
         let midi_message_queue = self.midi_message_queue.clone();
-        let (maybe_input_stream, output_stream) =
-            create_stream(&audio_thread_options, processor, midi_message_queue)?;
+        let AudioStreams {
+            input_stream: maybe_input_stream,
+            output_stream,
+            resampling_latency,
+        } = create_stream(&audio_thread_options, processor, midi_message_queue)?;
         log::info!("Starting CPAL output stream");
         if let Some(input_stream) = maybe_input_stream.as_ref() {
             input_stream.play()?;
@@ -69,10 +84,17 @@ impl AudioThread {
         output_stream.play()?;
         self.output_stream = Some(output_stream);
         self.input_stream = maybe_input_stream;
+        self.resampling_latency = resampling_latency;
         log::info!("Audio thread started");
         Ok(())
     }
 
+    /// Delay added by resampling between the device & processor sample rates. Zero when they
+    /// match.
+    pub fn resampling_latency(&self) -> Duration {
+        self.resampling_latency
+    }
+
     /// Change audio host & restart audio thread
     pub fn set_host_id(&mut self, host_id: AudioHostId) -> Result<(), AudioThreadError> {
         if host_id != self.audio_thread_options.host_id {
@@ -152,7 +174,7 @@ fn create_stream(
     options: &AudioThreadOptions,
     processor: Shared<SharedCell<ProcessorCell<AudioThreadProcessor>>>,
     midi_message_queue: MidiMessageQueue,
-) -> Result<(Option<cpal::Stream>, cpal::Stream), AudioThreadError> {
+) -> Result<AudioStreams, AudioThreadError> {
     let host = cpal_option_handling::get_cpal_host(&options.host_id);
 
     let output_device =
@@ -172,14 +194,15 @@ fn create_stream(
         None
     };
 
-    let stream = create_stream_inner(
+    let streams = create_stream_inner(
         processor,
         &output_device,
         &output_config,
         input_device.as_ref().zip(input_config.as_ref()),
         midi_message_queue,
+        options.processor_sample_rate,
     )?;
-    Ok(stream)
+    Ok(streams)
 }
 
 fn create_stream_inner(
@@ -188,7 +211,8 @@ fn create_stream_inner(
     output_config: &cpal::StreamConfig,
     input: Option<(&cpal::Device, &cpal::StreamConfig)>,
     midi_message_queue: MidiMessageQueue,
-) -> Result<(Option<cpal::Stream>, cpal::Stream), AudioThreadError> {
+    processor_sample_rate: Option<f32>,
+) -> Result<AudioStreams, AudioThreadError> {
     let buffer_size = match output_config.buffer_size {
         cpal::BufferSize::Default => Err(AudioThreadError::UnexpectedDefaultBufferSize),
         cpal::BufferSize::Fixed(buffer_size) => Ok(buffer_size),
@@ -197,6 +221,15 @@ fn create_stream_inner(
     log::info!("Buffer size {:?}", buffer_size);
 
     let num_channels: usize = output_config.channels.into();
+    let mut rate_adapter = build_rate_adapter(
+        output_config.sample_rate.0 as f32,
+        processor_sample_rate,
+        num_channels,
+        buffer_size as usize,
+    );
+    let resampling_latency = rate_adapter.as_ref().map_or(Duration::ZERO, |adapter| {
+        Duration::from_secs_f32(adapter.latency() as f32 / adapter.device_rate())
+    });
     let mut midi_message_handler = MidiAudioThreadHandler::default();
 
     let buffer = ringbuf::RingBuffer::new((buffer_size * 4) as usize);
@@ -230,6 +263,7 @@ fn create_stream_inner(
                 num_channels,
                 &mut midi_message_handler,
                 &mut consumer,
+                rate_adapter.as_mut(),
                 has_input,
                 data,
             );
@@ -237,7 +271,39 @@ fn create_stream_inner(
         error_callback,
     )?;
 
-    Ok((input_stream, output_stream))
+    Ok(AudioStreams {
+        input_stream,
+        output_stream,
+        resampling_latency,
+    })
+}
+
+/// Processors run at the device rate, unless a different rate is set in the options
+fn build_rate_adapter(
+    device_sample_rate: f32,
+    processor_sample_rate: Option<f32>,
+    num_channels: usize,
+    buffer_size: usize,
+) -> Option<RateAdapter> {
+    let processor_sample_rate = processor_sample_rate?;
+    if (processor_sample_rate - device_sample_rate).abs() < f32::EPSILON {
+        return None;
+    }
+
+    log::info!(
+        "Resampling device_sample_rate={} processor_sample_rate={}",
+        device_sample_rate,
+        processor_sample_rate
+    );
+    Some(RateAdapter::new(
+        num_channels,
+        device_sample_rate,
+        processor_sample_rate,
+        buffer_size,
+        // The output stream is opened with a fixed buffer size
+        buffer_size,
+        Quality::Medium,
+    ))
 }
 
 fn output_stream_callback(
@@ -246,6 +312,7 @@ fn output_stream_callback(
     num_channels: usize,
     midi_message_handler: &mut MidiAudioThreadHandler,
     consumer: &mut Consumer<f32>,
+    rate_adapter: Option<&mut RateAdapter>,
     has_input: bool,
     data: &mut [f32],
 ) {
@@ -267,21 +334,31 @@ fn output_stream_callback(
 
     midi_message_handler.collect_midi_messages(&midi_message_queue);
 
-    let mut audio_buffer = InterleavedAudioBuffer::new(num_channels, data);
-
     let shared_processor = processor.get();
     let processor_ptr = shared_processor.0.get();
-    match unsafe { &mut (*processor_ptr) } {
-        AudioThreadProcessor::Active(processor) => {
-            processor.process_midi(&midi_message_handler.buffer());
-            processor.process(&mut audio_buffer)
-        }
-        AudioThreadProcessor::Silence(processor) => (*processor).process(&mut audio_buffer),
+    let processor = unsafe { &mut (*processor_ptr) };
+    if let AudioThreadProcessor::Active(processor) = processor {
+        processor.process_midi(&midi_message_handler.buffer());
+    }
+
+    match rate_adapter {
+        Some(rate_adapter) => rate_adapter.process(data, |block| {
+            process_block(processor, num_channels, block);
+        }),
+        None => process_block(processor, num_channels, data),
     }
 
     midi_message_handler.clear();
 }
 
+fn process_block(processor: &mut AudioThreadProcessor, num_channels: usize, data: &mut [f32]) {
+    let mut audio_buffer = InterleavedAudioBuffer::new(num_channels, data);
+    match processor {
+        AudioThreadProcessor::Active(processor) => processor.process(&mut audio_buffer),
+        AudioThreadProcessor::Silence(processor) => processor.process(&mut audio_buffer),
+    }
+}
+
 fn input_stream_callback(producer: &mut ringbuf::Producer<f32>, data: &[f32]) {
     let mut output_behind = false;
     for sample in data {
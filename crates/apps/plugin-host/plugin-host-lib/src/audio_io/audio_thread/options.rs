@@ -70,6 +70,9 @@ pub struct AudioThreadOptions {
     pub input_device_id: Option<AudioDeviceId>,
     pub buffer_size: BufferSize,
     pub num_channels: usize,
+    /// Sample rate processors run at. When it differs from the output device's rate, audio is
+    /// resampled on the way in & out. `None` runs at the device's rate.
+    pub processor_sample_rate: Option<f32>,
 }
 
 impl Default for AudioThreadOptions {
@@ -80,6 +83,7 @@ impl Default for AudioThreadOptions {
             None,
             Default::default(),
             2,
+            None,
         )
     }
 }
@@ -91,6 +95,7 @@ impl AudioThreadOptions {
         input_device_id: Option<AudioDeviceId>,
         buffer_size: BufferSize,
         num_channels: usize,
+        processor_sample_rate: Option<f32>,
     ) -> Self {
         AudioThreadOptions {
             host_id,
@@ -98,6 +103,7 @@ impl AudioThreadOptions {
             input_device_id,
             buffer_size,
             num_channels,
+            processor_sample_rate,
         }
     }
 }
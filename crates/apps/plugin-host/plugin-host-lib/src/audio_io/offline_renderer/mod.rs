@@ -3,12 +3,17 @@ use std::time::{Duration, Instant};
 use thiserror::Error;
 use vst::plugin::Plugin;
 
-use audio_processor_traits::{AudioProcessorSettings, InterleavedAudioBuffer};
+use audio_garbage_collector::GarbageCollector;
+use audio_processor_analysis::loudness::{LoudnessMeterProcessor, LoudnessReport};
+use audio_processor_traits::{AudioProcessor, AudioProcessorSettings, InterleavedAudioBuffer};
+use convolution::ConvolutionProcessor;
+use oscillator::signal_generator::{SignalGeneratorOptions, SignalGeneratorProcessor};
 
 use crate::audio_io::cpal_vst_buffer_handler::CpalVstBufferHandler;
 use crate::audio_io::AudioHostPluginLoadError;
 use crate::processors::audio_file_processor::file_io::AudioFileError;
 use crate::processors::audio_file_processor::AudioFileProcessor;
+use crate::processors::impulse_response::{read_impulse_response, ImpulseResponseError};
 use crate::processors::output_file_processor::OutputAudioFileProcessor;
 use crate::processors::test_host_processor::flush_vst_output;
 use crate::TestPluginHost;
@@ -19,13 +24,28 @@ pub enum OfflineRenderError {
     AudioFileError(#[from] AudioFileError),
     #[error("Failed to load plug-in")]
     AudioHostPluginLoadError(#[from] AudioHostPluginLoadError),
+    #[error("Failed to load the impulse response")]
+    ImpulseResponseError(#[from] ImpulseResponseError),
+}
+
+/// What the offline renderer feeds into the plug-in
+enum OfflineInput {
+    AudioFile(String),
+    TestSignal(SignalGeneratorOptions),
+}
+
+/// [`OfflineInput`] once loaded
+enum OfflineInputProcessor {
+    AudioFile(AudioFileProcessor),
+    TestSignal(SignalGeneratorProcessor),
 }
 
 pub struct OfflineRenderer {
     audio_settings: AudioProcessorSettings,
-    input_file_path: String,
+    input: OfflineInput,
     output_file_path: String,
     plugin_path: String,
+    impulse_response_path: Option<String>,
 }
 
 impl OfflineRenderer {
@@ -37,31 +57,70 @@ impl OfflineRenderer {
     ) -> OfflineRenderer {
         OfflineRenderer {
             audio_settings,
-            input_file_path: String::from(input_file_path),
+            input: OfflineInput::AudioFile(String::from(input_file_path)),
             output_file_path: String::from(output_file_path),
             plugin_path: String::from(plugin_path),
+            impulse_response_path: None,
         }
     }
 
+    /// Render the plug-in's response to a test signal, for the signal's duration
+    pub fn new_with_test_signal(
+        audio_settings: AudioProcessorSettings,
+        test_signal_options: SignalGeneratorOptions,
+        output_file_path: &str,
+        plugin_path: &str,
+    ) -> OfflineRenderer {
+        OfflineRenderer {
+            audio_settings,
+            input: OfflineInput::TestSignal(test_signal_options),
+            output_file_path: String::from(output_file_path),
+            plugin_path: String::from(plugin_path),
+            impulse_response_path: None,
+        }
+    }
+
+    /// Convolve the plug-in output with the impulse response at `path` before writing it
+    pub fn set_impulse_response_path(&mut self, path: &str) {
+        self.impulse_response_path = Some(String::from(path));
+    }
+
     pub fn run(&self) -> Result<OfflineRenderDiagnostics, OfflineRenderError> {
         let mut buffer_handler = CpalVstBufferHandler::new(self.audio_settings);
-        let mut audio_file_processor =
-            AudioFileProcessor::from_path(self.audio_settings, &self.input_file_path)?;
+        let mut input_processor = self.build_input_processor()?;
         let mut plugin = TestPluginHost::load_vst_plugin(self.plugin_path.as_ref())?;
         let mut output_file_processor =
             OutputAudioFileProcessor::from_path(self.audio_settings, &self.output_file_path);
 
         plugin.set_sample_rate(self.audio_settings.sample_rate());
         plugin.set_block_size(self.audio_settings.block_size() as i64);
-        audio_file_processor.prepare(self.audio_settings);
+        match &mut input_processor {
+            OfflineInputProcessor::AudioFile(audio_file_processor) => {
+                audio_file_processor.prepare(self.audio_settings)
+            }
+            OfflineInputProcessor::TestSignal(generator) => generator.prepare(self.audio_settings),
+        }
         output_file_processor.prepare(self.audio_settings);
+        let mut convolution_processor = self.build_convolution_processor()?;
+        let garbage_collector = GarbageCollector::default();
+        let mut loudness_meter = LoudnessMeterProcessor::new(garbage_collector.handle());
+        loudness_meter.prepare(self.audio_settings);
 
-        let audio_file_buffer = audio_file_processor.buffer();
-        let audio_file_total_samples = audio_file_buffer[0].len();
-        let num_channels = audio_file_buffer.len();
+        let (input_total_samples, num_channels) = match &input_processor {
+            OfflineInputProcessor::AudioFile(audio_file_processor) => {
+                let audio_file_buffer = audio_file_processor.buffer();
+                (audio_file_buffer[0].len(), audio_file_buffer.len())
+            }
+            OfflineInputProcessor::TestSignal(generator) => (
+                generator
+                    .options()
+                    .duration_samples(self.audio_settings.sample_rate()),
+                self.audio_settings.input_channels(),
+            ),
+        };
         let block_size = self.audio_settings.block_size() as usize;
-        let total_blocks = audio_file_total_samples / block_size;
-        log::info!("Going to process input file with {} blocks", total_blocks);
+        let total_blocks = input_total_samples / block_size;
+        log::info!("Going to process input with {} blocks", total_blocks);
 
         let mut buffer = Vec::new();
         buffer.resize(block_size * self.audio_settings.input_channels(), 0.0);
@@ -77,16 +136,23 @@ impl OfflineRenderer {
 
         for _block_num in 0..total_blocks {
             let start = Instant::now();
-            let mut channel_number = 0;
-            #[allow(clippy::explicit_counter_loop)]
-            for channel in audio_file_buffer {
-                for i in 0..block_size {
-                    let interleaved_index = i * num_channels + channel_number;
-                    buffer[interleaved_index] = channel[audio_file_position + i]
+            match &mut input_processor {
+                OfflineInputProcessor::AudioFile(audio_file_processor) => {
+                    let mut channel_number = 0;
+                    #[allow(clippy::explicit_counter_loop)]
+                    for channel in audio_file_processor.buffer() {
+                        for i in 0..block_size {
+                            let interleaved_index = i * num_channels + channel_number;
+                            buffer[interleaved_index] = channel[audio_file_position + i]
+                        }
+                        channel_number += 1;
+                    }
+                    audio_file_position += block_size;
+                }
+                OfflineInputProcessor::TestSignal(generator) => {
+                    generator.process(&mut InterleavedAudioBuffer::new(num_channels, &mut buffer));
                 }
-                channel_number += 1;
             }
-            audio_file_position += block_size;
             audio_input_conversion_time += start.elapsed();
 
             let start = Instant::now();
@@ -111,10 +177,32 @@ impl OfflineRenderer {
             plugin_flush_time += flush_start.elapsed();
             plugin_conversions_time += start.elapsed();
 
+            if let Some(convolution_processor) = &mut convolution_processor {
+                convolution_processor.process(&mut interleaved_buffer);
+            }
+            loudness_meter.process(&mut interleaved_buffer);
+
             let start = Instant::now();
             output_file_processor.process(&mut buffer);
             audio_output_time += start.elapsed();
         }
+
+        // Let the impulse response ring out after the input ends
+        let mut tail_blocks = 0;
+        if let Some(convolution_processor) = &mut convolution_processor {
+            let impulse_response_length = convolution_processor.impulse_response().len();
+            tail_blocks = (impulse_response_length + block_size - 1) / block_size;
+            for _block_num in 0..tail_blocks {
+                buffer.iter_mut().for_each(|sample| *sample = 0.0);
+                let mut interleaved_buffer = InterleavedAudioBuffer::new(num_channels, &mut buffer);
+                convolution_processor.process(&mut interleaved_buffer);
+                loudness_meter.process(&mut interleaved_buffer);
+
+                let start = Instant::now();
+                output_file_processor.process(&mut buffer);
+                audio_output_time += start.elapsed();
+            }
+        }
         let total_runtime = start.elapsed().as_millis();
 
         log::info!(
@@ -134,12 +222,13 @@ impl OfflineRenderer {
         log::info!("Plugin runtime duration={}ms", plugin_time.as_millis());
         log::info!("Total runtime duration={}ms", total_runtime);
 
-        let audio_duration =
-            (total_blocks as f32 * block_size as f32) / self.audio_settings.sample_rate();
+        let audio_duration = ((total_blocks + tail_blocks) as f32 * block_size as f32)
+            / self.audio_settings.sample_rate();
         let audio_duration = Duration::from_secs_f32(audio_duration);
         log::info!("Audio duration : {}ms", audio_duration.as_millis());
         let realtime_relation = audio_duration.as_millis() as f32 / total_runtime as f32;
         log::info!("{:.1}x realtime", realtime_relation);
+        let loudness = loudness_meter.report();
 
         Ok(OfflineRenderDiagnostics {
             plugin_own_time: plugin_time,
@@ -153,8 +242,34 @@ impl OfflineRenderer {
             },
             output_audio_duration: audio_duration,
             realtime_ration: realtime_relation,
+            loudness,
+        })
+    }
+
+    fn build_input_processor(&self) -> Result<OfflineInputProcessor, AudioFileError> {
+        Ok(match &self.input {
+            OfflineInput::AudioFile(input_file_path) => OfflineInputProcessor::AudioFile(
+                AudioFileProcessor::from_path(self.audio_settings, input_file_path)?,
+            ),
+            OfflineInput::TestSignal(options) => {
+                OfflineInputProcessor::TestSignal(SignalGeneratorProcessor::new(*options))
+            }
         })
     }
+
+    /// Loads the impulse response at the session sample rate, if one was set
+    fn build_convolution_processor(
+        &self,
+    ) -> Result<Option<ConvolutionProcessor>, ImpulseResponseError> {
+        let path = match &self.impulse_response_path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let impulse_response = read_impulse_response(path, self.audio_settings.sample_rate())?;
+        let mut processor = ConvolutionProcessor::new(impulse_response);
+        processor.prepare(self.audio_settings);
+        Ok(Some(processor))
+    }
 }
 
 pub struct OfflineRenderDiagnostics {
@@ -169,6 +284,8 @@ pub struct OfflineRenderDiagnostics {
     /// Ratio between the offline render performance and a real-time workload
     /// e.g. 10x real-time
     pub realtime_ration: f32,
+    /// EBU R128 loudness of the rendered audio, after the impulse response
+    pub loudness: LoudnessReport,
 }
 
 pub struct HostOverheadDiagnostics {
@@ -9,8 +9,13 @@ use vst::host::{PluginInstance, PluginLoadError, PluginLoader};
 use vst::plugin::Plugin;
 
 use audio_garbage_collector::{GarbageCollector, GarbageCollectorError, Shared};
+use audio_processor_analysis::onset::OnsetDetectorHandle;
+use audio_processor_analysis::pitch::PitchDetectorHandle;
+use audio_processor_analysis::spectrum::SpectrumAnalyzerHandle;
+use audio_processor_analysis::tempo::TempoEstimate;
 use audio_processor_standalone_midi::host::{MidiError, MidiHost};
 use audio_processor_traits::{AudioProcessor, AudioProcessorSettings, SilenceAudioProcessor};
+use oscillator::signal_generator::SignalGeneratorOptions;
 
 use crate::audio_io::audio_thread::error::AudioThreadError;
 use crate::audio_io::audio_thread::options::{AudioDeviceId, AudioHostId, AudioThreadOptions};
@@ -19,7 +24,7 @@ use crate::processors::audio_file_processor::file_io::{default_read_audio_file,
 use crate::processors::audio_file_processor::AudioFileSettings;
 use crate::processors::running_rms_processor::RunningRMSProcessorHandle;
 use crate::processors::shared_processor::SharedProcessor;
-use crate::processors::test_host_processor::TestHostProcessor;
+use crate::processors::test_host_processor::{InputSettings, TestHostProcessor};
 use crate::processors::volume_meter_processor::VolumeMeterProcessorHandle;
 use crate::vst_host::AudioTestHost;
 
@@ -55,12 +60,15 @@ pub struct TestPluginHost {
     audio_thread: AudioThread,
     audio_settings: AudioProcessorSettings,
     audio_file_path: Option<PathBuf>,
+    test_signal_options: Option<SignalGeneratorOptions>,
     plugin_file_path: Option<PathBuf>,
     vst_plugin_instance: Option<SharedProcessor<PluginInstance>>,
     processor: Option<SharedProcessor<AudioThreadProcessor>>,
     midi_host: MidiHost,
     garbage_collector: GarbageCollector,
     mono_input: Option<usize>,
+    pitch_detection: bool,
+    onset_detection: bool,
     temporary_load_path: Option<String>,
     start_paused: bool,
 }
@@ -90,12 +98,15 @@ impl TestPluginHost {
             ),
             audio_settings,
             audio_file_path: None,
+            test_signal_options: None,
             plugin_file_path: None,
             vst_plugin_instance: None,
             processor: None,
             midi_host,
             garbage_collector,
             mono_input: None,
+            pitch_detection: false,
+            onset_detection: false,
             temporary_load_path: None,
             start_paused,
         }
@@ -130,16 +141,41 @@ impl TestPluginHost {
 
     pub fn set_audio_file_path(&mut self, path: PathBuf) -> Result<(), AudioHostPluginLoadError> {
         self.audio_file_path = Some(path);
+        self.test_signal_options = None;
         if let Some(path) = self.plugin_file_path.clone() {
             self.load_plugin(path.as_path())?;
         }
         Ok(())
     }
 
+    /// Use a generated test signal as input instead of an audio file, or neither if `None`
+    pub fn set_test_signal(
+        &mut self,
+        options: Option<SignalGeneratorOptions>,
+    ) -> Result<(), AudioHostPluginLoadError> {
+        self.test_signal_options = options;
+        if options.is_some() {
+            self.audio_file_path = None;
+        }
+        if let Some(path) = self.plugin_file_path.clone() {
+            self.load_plugin(path.as_path())?;
+        }
+        Ok(())
+    }
+
+    /// Delay added by resampling, when processors run at a different rate than the device
+    pub fn resampling_latency(&self) -> Duration {
+        self.audio_thread.resampling_latency()
+    }
+
     pub fn audio_file_path(&self) -> &Option<PathBuf> {
         &self.audio_file_path
     }
 
+    pub fn test_signal_options(&self) -> Option<SignalGeneratorOptions> {
+        self.test_signal_options
+    }
+
     pub fn plugin_file_path(&self) -> &Option<PathBuf> {
         &self.plugin_file_path
     }
@@ -153,6 +189,29 @@ impl TestPluginHost {
             .map(|h| h.running_rms_processor_handle().clone())
     }
 
+    pub fn spectrum_analyzer_handle(&self) -> Option<Shared<SpectrumAnalyzerHandle>> {
+        self.host_processor()
+            .map(|h| h.spectrum_analyzer_handle().clone())
+    }
+
+    /// `None` unless pitch detection was enabled with [`TestPluginHost::set_pitch_detection`]
+    pub fn pitch_detector_handle(&self) -> Option<Shared<PitchDetectorHandle>> {
+        self.host_processor()
+            .and_then(|h| h.pitch_detector_handle().cloned())
+    }
+
+    /// `None` unless onset detection was enabled with [`TestPluginHost::set_onset_detection`]
+    pub fn onset_detector_handle(&self) -> Option<Shared<OnsetDetectorHandle>> {
+        self.host_processor()
+            .and_then(|h| h.onset_detector_handle().cloned())
+    }
+
+    /// Estimates the tempo of the loaded audio file. Analyses the whole file, so it should be called
+    /// off the audio & GUI threads.
+    pub fn audio_file_tempo(&self) -> Option<TempoEstimate> {
+        self.host_processor().and_then(|h| h.audio_file_tempo())
+    }
+
     pub fn load_plugin(&mut self, path: &Path) -> Result<(), AudioHostPluginLoadError> {
         self.plugin_file_path = Some(path.into());
 
@@ -174,27 +233,34 @@ impl TestPluginHost {
             SharedProcessor::new(self.garbage_collector.handle(), vst_plugin_instance);
 
         let audio_settings = &self.audio_settings;
-        let maybe_audio_file_settings = self.audio_file_path.as_ref().map_or(
-            Ok(None),
-            |audio_file_path| -> Result<Option<AudioFileSettings>, AudioHostPluginLoadError> {
+        let maybe_input_settings = match (&self.test_signal_options, &self.audio_file_path) {
+            (Some(options), _) => Some(InputSettings::TestSignal(*options)),
+            (None, Some(audio_file_path)) => {
                 let audio_file = default_read_audio_file(
                     audio_file_path
                         .to_str()
                         .ok_or(AudioHostPluginLoadError::MissingPathError)?,
                 )?;
-                Ok(Some(AudioFileSettings::new(audio_file)))
-            },
-        )?;
+                Some(InputSettings::AudioFile(AudioFileSettings::new(audio_file)))
+            }
+            (None, None) => None,
+        };
 
         let mut test_host_processor = TestHostProcessor::new(
             self.garbage_collector.handle(),
-            maybe_audio_file_settings,
+            maybe_input_settings,
             vst_plugin_instance.clone(),
             audio_settings.sample_rate(),
             audio_settings.input_channels(),
             audio_settings.block_size(),
             self.mono_input,
         );
+        if self.pitch_detection {
+            test_host_processor.enable_pitch_detector(self.garbage_collector.handle());
+        }
+        if self.onset_detection {
+            test_host_processor.enable_onset_detector(self.garbage_collector.handle());
+        }
         test_host_processor.prepare(*audio_settings);
 
         if self.processor.is_none() && self.start_paused {
@@ -304,7 +370,7 @@ impl TestPluginHost {
         }
     }
 
-    /// Stop playback and go back to the start of the file
+    /// Stop playback and go back to the start of the file or signal
     pub fn stop(&self) {
         if let Some(processor) = self.host_processor() {
             log::info!("Stopping playback processor_id={}", processor.id());
@@ -312,7 +378,7 @@ impl TestPluginHost {
         }
     }
 
-    /// Whether the file is being played back
+    /// Whether the file or signal is being played back
     pub fn is_playing(&self) -> bool {
         self.host_processor()
             .map(|p| p.is_playing())
@@ -332,6 +398,17 @@ impl TestPluginHost {
         self.mono_input = input_channel;
     }
 
+    /// Track the pitch of the output, from the next time a plugin is loaded
+    pub fn set_pitch_detection(&mut self, enabled: bool) {
+        self.pitch_detection = enabled;
+    }
+
+    /// Detect onsets & track the tempo of the output, from the next time a plugin is loaded. The
+    /// tempo of an input file may be estimated without it, see [`TestPluginHost::audio_file_tempo`].
+    pub fn set_onset_detection(&mut self, enabled: bool) {
+        self.onset_detection = enabled;
+    }
+
     pub fn set_volume(&mut self, volume: f32) {
         if let Some(processor) = self.host_processor() {
             processor.set_volume(volume);
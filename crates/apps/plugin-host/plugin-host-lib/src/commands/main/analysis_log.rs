@@ -0,0 +1,44 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::audio_io::test_plugin_host::TestPluginHost;
+
+/// How often the analysis is logged
+const LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Log the analysis of the output, for the analyzers enabled on the host. Handles are looked up on
+/// every tick, so they're picked up again when the plug-in is reloaded.
+pub fn run_analysis_log_loop(host: Arc<Mutex<TestPluginHost>>) -> ! {
+    loop {
+        std::thread::sleep(LOG_INTERVAL);
+        let (pitch_detector_handle, onset_detector_handle) = {
+            let host = host.lock().unwrap();
+            (host.pitch_detector_handle(), host.onset_detector_handle())
+        };
+
+        if let Some(handle) = pitch_detector_handle {
+            match handle.estimate() {
+                Some(estimate) => log::info!(
+                    "Pitch: {} {:+.0} cents ({:.1}Hz, confidence {:.2})",
+                    estimate.note_name(),
+                    estimate.cents,
+                    estimate.frequency,
+                    estimate.confidence
+                ),
+                None => log::info!("Pitch: -"),
+            }
+        }
+
+        if let Some(handle) = onset_detector_handle {
+            match handle.tempo() {
+                Some(tempo) => log::info!(
+                    "Tempo: {:.1} BPM (confidence {:.2}, {} onsets)",
+                    tempo.bpm,
+                    tempo.confidence,
+                    handle.onset_count()
+                ),
+                None => log::info!("Tempo: - ({} onsets)", handle.onset_count()),
+            }
+        }
+    }
+}
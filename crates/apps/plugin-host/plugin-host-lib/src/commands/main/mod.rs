@@ -26,14 +26,16 @@ use crate::processors::shared_processor::SharedProcessor;
 use audio_processor_traits::AudioProcessorSettings;
 use std::thread::JoinHandle;
 
+mod analysis_log;
 mod file_watch;
 
 /// Entry-point for the run plug-in command. Mostly kicks-off other work:
 ///
 /// * Parses options
 /// * Creates the host, audio and other threads
-/// * Loads the audio-file (blocking before starting the plug-in)
+/// * Loads the audio-file or test signal (blocking before starting the plug-in)
 /// * Loads the audio-plugin
+/// * Starts logging the output analysis (if specified)
 /// * Creates a window for the plug-in & blocks on it (if specified)
 /// * Otherwise parks the current thread forever
 pub fn run_test(run_options: RunOptions) {
@@ -45,12 +47,15 @@ pub fn run_test(run_options: RunOptions) {
     let (audio_settings, audio_thread_options) = get_audio_options(&run_options);
     let mut host = TestPluginHost::new(audio_settings, audio_thread_options, false);
     host.set_mono_input(run_options.use_mono_input());
+    host.set_pitch_detection(run_options.tuner());
+    host.set_onset_detection(run_options.detect_tempo());
     run_load_audio_file(&run_options, &mut host);
     run_initialize_plugin(&run_options, &mut host);
 
     let host = Arc::new(Mutex::new(host));
     // This needs to be kept around otherwise the watcher will stop when dropped
     let _maybe_watcher = run_initialize_file_watch_thread(&run_options, &host);
+    run_initialize_analysis_log_thread(&run_options, &host);
 
     if run_options.open_editor() {
         let instance = host.lock().unwrap().plugin_instance();
@@ -135,6 +140,7 @@ fn get_audio_options(run_options: &RunOptions) -> (AudioProcessorSettings, Audio
             .unwrap_or_else(|| audio_settings.block_size()),
     );
     let mut audio_thread_options = AudioThreadOptions::default();
+    audio_thread_options.processor_sample_rate = run_options.sample_rate().map(|s| s as f32);
     audio_thread_options.buffer_size = run_options
         .buffer_size()
         .map(|s| BufferSize::Fixed(s as usize))
@@ -190,11 +196,20 @@ fn run_initialize_plugin(run_options: &RunOptions, host: &mut TestPluginHost) {
         log::error!("Failed to start host: {}", err);
         exit(1);
     }
+    if host.resampling_latency() > Duration::ZERO {
+        log::info!("Resampling latency: {:?}", host.resampling_latency());
+    }
 }
 
-/// Load the audio input file & exit the process on failure
+/// Load the audio input file or test signal & exit the process on failure
 fn run_load_audio_file(run_options: &RunOptions, host: &mut TestPluginHost) {
-    if let Some(input_audio) = run_options.input_audio() {
+    if let Some(test_signal) = run_options.test_signal() {
+        log::info!("Using a {} test signal as input", test_signal.signal_type);
+        if let Err(err) = host.set_test_signal(Some(test_signal)) {
+            log::error!("Failed to set test signal {}", err);
+            exit(1);
+        }
+    } else if let Some(input_audio) = run_options.input_audio() {
         if let Err(err) = host.set_audio_file_path(PathBuf::from(input_audio)) {
             log::error!("Failed to set input file-path {}", err);
             exit(1);
@@ -224,19 +239,40 @@ fn run_initialize_file_watch_thread(
     }
 }
 
+/// Start the thread logging the output analysis, if any analysis is enabled
+fn run_initialize_analysis_log_thread(run_options: &RunOptions, host: &Arc<Mutex<TestPluginHost>>) {
+    if run_options.tuner() || run_options.detect_tempo() {
+        let host = host.clone();
+        std::thread::spawn(move || analysis_log::run_analysis_log_loop(host));
+    }
+}
+
 /// Start the offline rendering command, rendering to an output file
 fn run_offline_rendering(run_options: RunOptions) {
     log::info!("Running offline rendering");
     let output_file_path = run_options.output_audio().clone().unwrap();
     let (audio_settings, _) = get_audio_options(&run_options);
-    let offline_renderer = OfflineRenderer::new(
-        audio_settings,
-        &run_options
-            .input_audio()
-            .clone()
-            .expect("The \"--input\" flag is required for offline rendering"),
-        &output_file_path,
-        run_options.plugin_path(),
-    );
-    offline_renderer.run().expect("Failed to render audio");
+    let mut offline_renderer = if let Some(test_signal) = run_options.test_signal() {
+        OfflineRenderer::new_with_test_signal(
+            audio_settings,
+            test_signal,
+            &output_file_path,
+            run_options.plugin_path(),
+        )
+    } else {
+        OfflineRenderer::new(
+            audio_settings,
+            &run_options
+                .input_audio()
+                .clone()
+                .expect("The \"--input\" or \"--signal\" flag is required for offline rendering"),
+            &output_file_path,
+            run_options.plugin_path(),
+        )
+    };
+    if let Some(impulse_response_path) = run_options.impulse_response() {
+        offline_renderer.set_impulse_response_path(impulse_response_path);
+    }
+    let diagnostics = offline_renderer.run().expect("Failed to render audio");
+    log::info!("Loudness report:\n{}", diagnostics.loudness);
 }
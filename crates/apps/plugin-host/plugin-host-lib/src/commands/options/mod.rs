@@ -1,10 +1,16 @@
+use std::time::Duration;
+
 use clap::{App, ArgMatches};
 
+use oscillator::signal_generator::SignalGeneratorOptions;
+
 #[derive(Clone)]
 pub struct RunOptions {
     plugin_path: String,
     input_audio: Option<String>,
+    test_signal: Option<SignalGeneratorOptions>,
     output_audio: Option<String>,
+    impulse_response: Option<String>,
     open_editor: bool,
     watch: bool,
     audio_host_id: Option<String>,
@@ -14,6 +20,8 @@ pub struct RunOptions {
     input_device_id: Option<String>,
     use_default_input_device: bool,
     use_mono_input: Option<usize>,
+    tuner: bool,
+    detect_tempo: bool,
 }
 
 impl RunOptions {
@@ -25,10 +33,19 @@ impl RunOptions {
         &self.input_audio
     }
 
+    /// Test signal to use as input instead of an audio file
+    pub fn test_signal(&self) -> Option<SignalGeneratorOptions> {
+        self.test_signal
+    }
+
     pub fn output_audio(&self) -> &Option<String> {
         &self.output_audio
     }
 
+    pub fn impulse_response(&self) -> &Option<String> {
+        &self.impulse_response
+    }
+
     pub fn open_editor(&self) -> bool {
         self.open_editor
     }
@@ -64,6 +81,16 @@ impl RunOptions {
     pub fn use_mono_input(&self) -> Option<usize> {
         self.use_mono_input
     }
+
+    /// Whether to log the pitch of the output
+    pub fn tuner(&self) -> bool {
+        self.tuner
+    }
+
+    /// Whether to log the onsets & tempo of the output
+    pub fn detect_tempo(&self) -> bool {
+        self.detect_tempo
+    }
 }
 
 /// Build RunOptions parser
@@ -76,9 +103,30 @@ pub fn build_run_command<'a, 'b>() -> App<'a, 'b> {
         .arg(clap::Arg::from_usage(
             "-i, --input=[INPUT_PATH] 'An audio file to process'",
         ))
+        .arg(clap::Arg::from_usage(
+            "--signal=[SIGNAL] 'Generate a test signal as input instead of reading a file: sine, sweep, impulse, noise, square or silence'",
+        ))
+        .arg(clap::Arg::from_usage(
+            "--signal-frequency=[FREQUENCY] 'Frequency of sine & square test signals, or where sweeps start, in Hz'",
+        ))
+        .arg(clap::Arg::from_usage(
+            "--signal-end-frequency=[FREQUENCY] 'Where sweeps end, in Hz'",
+        ))
+        .arg(clap::Arg::from_usage(
+            "--signal-level=[LEVEL] 'Peak level of the test signal, in dBFS'",
+        ))
+        .arg(clap::Arg::from_usage(
+            "--signal-duration=[SECONDS] 'Length of the test signal, which loops unless rendering offline'",
+        ))
+        .arg(clap::Arg::from_usage(
+            "--signal-channels=[CHANNELS] 'Comma separated channels which get the test signal, defaults to all'",
+        ))
         .arg(clap::Arg::from_usage(
             "-o, --output=[OUTPUT_PATH] 'If specified, will render offline into file'",
         ))
+        .arg(clap::Arg::from_usage(
+            "--impulse-response=[IR_PATH] 'When rendering offline, convolve the output with this impulse response'",
+        ))
         .arg(clap::Arg::from_usage(
             "-e, --editor 'Open the editor window'",
         ))
@@ -106,6 +154,12 @@ pub fn build_run_command<'a, 'b>() -> App<'a, 'b> {
         .arg(clap::Arg::from_usage(
             "--use-mono-input=[CHANNEL_NUMBER] 'If specified, the input stream will be mono-ed selecting the desired channel'",
         ))
+        .arg(clap::Arg::from_usage(
+            "--tuner 'Log the pitch of the output, with the nearest note & cents, once a second'",
+        ))
+        .arg(clap::Arg::from_usage(
+            "--detect-tempo 'Log the tempo & onset count of the output once a second'",
+        ))
 }
 
 /// Build 'RunOptions' from Clap matches
@@ -113,7 +167,11 @@ pub fn parse_run_options(matches: ArgMatches) -> Option<RunOptions> {
     let matches = matches.subcommand_matches("run")?;
     let plugin_path = matches.value_of("plugin")?.to_string();
     let input_audio = matches.value_of("input").map(|i| i.to_string());
+    let test_signal = parse_test_signal_options(matches);
     let output_audio = matches.value_of("output").map(|value| value.to_string());
+    let impulse_response = matches
+        .value_of("impulse-response")
+        .map(|value| value.to_string());
     let open_editor = matches.is_present("editor");
     let watch = matches.is_present("watch");
 
@@ -135,11 +193,15 @@ pub fn parse_run_options(matches: ArgMatches) -> Option<RunOptions> {
     let use_mono_input = matches
         .value_of("use-mono-input")
         .map(|s| s.parse().expect("Invalid channel number"));
+    let tuner = matches.is_present("tuner");
+    let detect_tempo = matches.is_present("detect-tempo");
 
     Some(RunOptions {
         plugin_path,
         input_audio,
+        test_signal,
         output_audio,
+        impulse_response,
         open_editor,
         watch,
         audio_host_id,
@@ -149,5 +211,40 @@ pub fn parse_run_options(matches: ArgMatches) -> Option<RunOptions> {
         input_device_id,
         use_default_input_device,
         use_mono_input,
+        tuner,
+        detect_tempo,
     })
 }
+
+/// Build the test signal options, if a signal was specified
+fn parse_test_signal_options(matches: &ArgMatches) -> Option<SignalGeneratorOptions> {
+    let signal_type = matches
+        .value_of("signal")?
+        .parse()
+        .unwrap_or_else(|err| panic!("{}", err));
+    let mut options = SignalGeneratorOptions {
+        signal_type,
+        ..SignalGeneratorOptions::default()
+    };
+    if let Some(frequency) = matches.value_of("signal-frequency") {
+        options.frequency = frequency.parse().expect("Invalid signal frequency");
+    }
+    if let Some(end_frequency) = matches.value_of("signal-end-frequency") {
+        options.end_frequency = end_frequency.parse().expect("Invalid signal end frequency");
+    }
+    if let Some(level) = matches.value_of("signal-level") {
+        options.level = level.parse().expect("Invalid signal level");
+    }
+    if let Some(duration) = matches.value_of("signal-duration") {
+        options.duration =
+            Duration::from_secs_f32(duration.parse().expect("Invalid signal duration"));
+    }
+    if let Some(channels) = matches.value_of("signal-channels") {
+        let channels: Vec<usize> = channels
+            .split(',')
+            .map(|channel| channel.trim().parse().expect("Invalid signal channel"))
+            .collect();
+        options.channel_mask = SignalGeneratorOptions::mask_for_channels(&channels);
+    }
+    Some(options)
+}
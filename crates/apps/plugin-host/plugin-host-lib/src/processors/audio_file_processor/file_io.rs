@@ -130,7 +130,7 @@ fn concat_buffers(buffers: Vec<SymphoniaAudioBuffer<f32>>) -> SymphoniaAudioBuff
     for buffer in buffers {
         let mut channel_size = 0;
 
-        for channel_num in 0..2 {
+        for channel_num in 0..buffer.spec().channels.count() {
             let mut cursor = output_cursor; // reading channels copy cursor to reset for each channel
 
             let output_channel = output.chan_mut(channel_num);
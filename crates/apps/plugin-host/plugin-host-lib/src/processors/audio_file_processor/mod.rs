@@ -5,6 +5,8 @@ use rayon::prelude::*;
 use symphonia::core::audio::AudioBuffer as SymphoniaAudioBuffer;
 use symphonia::core::probe::ProbeResult;
 
+use audio_processor_analysis::onset::detect_onsets;
+use audio_processor_analysis::tempo::{estimate_tempo, TempoEstimate};
 use audio_processor_traits::{AudioBuffer, AudioProcessorSettings};
 
 use crate::processors::audio_file_processor::file_io::AudioFileError;
@@ -127,6 +129,33 @@ impl AudioFileProcessor {
         );
     }
 
+    /// Estimates the tempo from the onsets of the channels mixed down to mono. This analyses the
+    /// whole file, so it shouldn't be called on the audio thread.
+    pub fn estimate_tempo(&self) -> Option<TempoEstimate> {
+        let start = Instant::now();
+        let num_channels = self.buffer.len();
+        let num_samples = self.buffer.iter().map(|channel| channel.len()).min();
+        let mono: Vec<f32> = (0..num_samples.unwrap_or(0))
+            .map(|i| {
+                self.buffer.iter().map(|channel| channel[i]).sum::<f32>() / num_channels as f32
+            })
+            .collect();
+        let analysis = detect_onsets(&mono, self.audio_settings.sample_rate(), Default::default());
+        let tempo = estimate_tempo(&analysis.novelty, analysis.frame_rate, Default::default());
+
+        match tempo {
+            Some(tempo) => log::info!(
+                "Estimated tempo bpm={:.1} confidence={:.2} onsets={} duration={}ms",
+                tempo.bpm,
+                tempo.confidence,
+                analysis.onsets.len(),
+                start.elapsed().as_millis()
+            ),
+            None => log::info!("Couldn't estimate the tempo of the input file"),
+        }
+        tempo
+    }
+
     pub fn process<BufferType: AudioBuffer<SampleType = f32>>(&mut self, data: &mut BufferType) {
         let is_playing = self.is_playing.load(Ordering::Relaxed);
 
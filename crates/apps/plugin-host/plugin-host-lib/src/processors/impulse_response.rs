@@ -0,0 +1,35 @@
+use rayon::prelude::*;
+use thiserror::Error;
+
+use convolution::ImpulseResponse;
+
+use crate::processors::audio_file_processor::file_io;
+use crate::processors::audio_file_processor::file_io::AudioFileError;
+
+#[derive(Error, Debug)]
+pub enum ImpulseResponseError {
+    #[error("Failed to open or decode the impulse response file")]
+    AudioFileError(#[from] AudioFileError),
+    #[error("Impulse responses must have 1, 2 or 4 channels, found {0}")]
+    UnsupportedChannelCount(usize),
+}
+
+/// Decode an impulse response from an audio file & resample it to `sample_rate`.
+///
+/// Mono files are applied to every channel, stereo files per channel & 4 channel files are read as
+/// true-stereo (L→L, L→R, R→L, R→R).
+pub fn read_impulse_response(
+    path: &str,
+    sample_rate: f32,
+) -> Result<ImpulseResponse, ImpulseResponseError> {
+    let mut audio_file = file_io::default_read_audio_file(path)?;
+    let contents = file_io::read_file_contents(&mut audio_file)?;
+    let num_channels = contents.spec().channels.count();
+    let channels = (0..num_channels)
+        .into_par_iter()
+        .map(|channel| file_io::convert_audio_file_sample_rate(&contents, sample_rate, channel))
+        .collect();
+
+    ImpulseResponse::from_channels(channels)
+        .ok_or(ImpulseResponseError::UnsupportedChannelCount(num_channels))
+}
@@ -1,6 +1,8 @@
 pub mod audio_file_processor;
+pub mod impulse_response;
 pub mod output_file_processor;
 pub mod running_rms_processor;
 pub mod shared_processor;
 pub mod test_host_processor;
+pub mod test_signal_processor;
 pub mod volume_meter_processor;
@@ -4,17 +4,37 @@ use vst::host::PluginInstance;
 use vst::plugin::Plugin;
 
 use audio_garbage_collector::{Handle, Shared};
+use audio_processor_analysis::onset::{
+    OnsetDetectorHandle, OnsetDetectorOptions, OnsetDetectorProcessor,
+};
+use audio_processor_analysis::pitch::{
+    PitchDetectorHandle, PitchDetectorOptions, PitchDetectorProcessor,
+};
+use audio_processor_analysis::spectrum::{
+    SpectrumAnalyzerHandle, SpectrumAnalyzerOptions, SpectrumAnalyzerProcessor,
+};
+use audio_processor_analysis::tempo::{TempoEstimate, TempoOptions};
 use audio_processor_standalone_midi::host::MidiMessageEntry;
 use audio_processor_standalone_midi::vst::MidiVSTConverter;
 use audio_processor_traits::{AtomicF32, AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use oscillator::signal_generator::SignalGeneratorOptions;
 
 use crate::audio_io::cpal_vst_buffer_handler::CpalVstBufferHandler;
 use crate::processors::audio_file_processor::{AudioFileProcessor, AudioFileSettings};
 use crate::processors::running_rms_processor::{RunningRMSProcessor, RunningRMSProcessorHandle};
 use crate::processors::shared_processor::SharedProcessor;
+use crate::processors::test_signal_processor::TestSignalProcessor;
 use crate::processors::volume_meter_processor::{VolumeMeterProcessor, VolumeMeterProcessorHandle};
 use std::time::Duration;
 
+/// Where the host's input comes from, other than the audio device
+pub enum InputSettings {
+    /// Play a file in loop, mixed onto the device input
+    AudioFile(AudioFileSettings),
+    /// Replace the device input with a generated test signal
+    TestSignal(SignalGeneratorOptions),
+}
+
 /// The app's main processor
 pub struct TestHostProcessor {
     id: String,
@@ -22,8 +42,12 @@ pub struct TestHostProcessor {
     audio_settings: AudioProcessorSettings,
     buffer_handler: CpalVstBufferHandler,
     maybe_audio_file_processor: Option<AudioFileProcessor>,
+    maybe_test_signal_processor: Option<TestSignalProcessor>,
     volume_meter_processor: VolumeMeterProcessor,
     running_rms_processor: RunningRMSProcessor,
+    spectrum_analyzer_processor: SpectrumAnalyzerProcessor,
+    pitch_detector_processor: Option<PitchDetectorProcessor>,
+    onset_detector_processor: Option<OnsetDetectorProcessor>,
     midi_converter: MidiVSTConverter,
     mono_input: Option<usize>,
     volume: AtomicF32,
@@ -35,7 +59,7 @@ unsafe impl Sync for TestHostProcessor {}
 impl TestHostProcessor {
     pub fn new(
         handle: &Handle,
-        maybe_audio_file_settings: Option<AudioFileSettings>,
+        maybe_input_settings: Option<InputSettings>,
         plugin_instance: SharedProcessor<PluginInstance>,
         sample_rate: f32,
         channels: usize,
@@ -44,19 +68,34 @@ impl TestHostProcessor {
     ) -> Self {
         let audio_settings =
             AudioProcessorSettings::new(sample_rate, channels, channels, buffer_size);
+        let (maybe_audio_file_processor, maybe_test_signal_processor) = match maybe_input_settings {
+            Some(InputSettings::AudioFile(audio_file_settings)) => (
+                Some(AudioFileProcessor::new(audio_file_settings, audio_settings)),
+                None,
+            ),
+            Some(InputSettings::TestSignal(options)) => {
+                (None, Some(TestSignalProcessor::new(options)))
+            }
+            None => (None, None),
+        };
         TestHostProcessor {
             id: uuid::Uuid::new_v4().to_string(),
             plugin_instance,
             audio_settings,
             buffer_handler: CpalVstBufferHandler::new(audio_settings),
-            maybe_audio_file_processor: maybe_audio_file_settings.map(|audio_file_settings| {
-                AudioFileProcessor::new(audio_file_settings, audio_settings)
-            }),
+            maybe_audio_file_processor,
+            maybe_test_signal_processor,
             volume_meter_processor: VolumeMeterProcessor::new(handle),
             running_rms_processor: RunningRMSProcessor::new_with_duration(
                 handle,
                 Duration::from_millis(300),
             ),
+            spectrum_analyzer_processor: SpectrumAnalyzerProcessor::new(
+                handle,
+                SpectrumAnalyzerOptions::default(),
+            ),
+            pitch_detector_processor: None,
+            onset_detector_processor: None,
             midi_converter: MidiVSTConverter::default(),
             mono_input,
             volume: AtomicF32::new(1.0),
@@ -72,6 +111,9 @@ impl TestHostProcessor {
         if let Some(audio_file_processor) = &self.maybe_audio_file_processor {
             audio_file_processor.play();
         }
+        if let Some(test_signal_processor) = &self.maybe_test_signal_processor {
+            test_signal_processor.play();
+        }
     }
 
     /// Pause playback
@@ -79,19 +121,27 @@ impl TestHostProcessor {
         if let Some(audio_file_processor) = &self.maybe_audio_file_processor {
             audio_file_processor.pause();
         }
+        if let Some(test_signal_processor) = &self.maybe_test_signal_processor {
+            test_signal_processor.pause();
+        }
     }
 
-    /// Stop playback and go back to the start of the file
+    /// Stop playback and go back to the start of the file or signal
     pub fn stop(&self) {
         if let Some(audio_file_processor) = &self.maybe_audio_file_processor {
             audio_file_processor.stop();
         }
+        if let Some(test_signal_processor) = &self.maybe_test_signal_processor {
+            test_signal_processor.stop();
+        }
     }
 
-    /// Whether the file is being played back
+    /// Whether the file or signal is being played back
     pub fn is_playing(&self) -> bool {
         if let Some(audio_file_processor) = &self.maybe_audio_file_processor {
             audio_file_processor.is_playing()
+        } else if let Some(test_signal_processor) = &self.maybe_test_signal_processor {
+            test_signal_processor.is_playing()
         } else {
             false
         }
@@ -109,6 +159,49 @@ impl TestHostProcessor {
         self.running_rms_processor.handle()
     }
 
+    pub fn spectrum_analyzer_handle(&self) -> &Shared<SpectrumAnalyzerHandle> {
+        self.spectrum_analyzer_processor.handle()
+    }
+
+    /// Track the pitch of the output too. Should be called before `prepare`.
+    pub fn enable_pitch_detector(&mut self, handle: &Handle) {
+        self.pitch_detector_processor = Some(PitchDetectorProcessor::new(
+            handle,
+            PitchDetectorOptions::default(),
+        ));
+    }
+
+    /// `None` unless the pitch detector was enabled
+    pub fn pitch_detector_handle(&self) -> Option<&Shared<PitchDetectorHandle>> {
+        self.pitch_detector_processor
+            .as_ref()
+            .map(|pitch_detector_processor| pitch_detector_processor.handle())
+    }
+
+    /// Detect onsets & track the tempo of the output too. Should be called before `prepare`.
+    pub fn enable_onset_detector(&mut self, handle: &Handle) {
+        self.onset_detector_processor = Some(OnsetDetectorProcessor::new(
+            handle,
+            OnsetDetectorOptions::default(),
+            TempoOptions::default(),
+        ));
+    }
+
+    /// `None` unless the onset detector was enabled
+    pub fn onset_detector_handle(&self) -> Option<&Shared<OnsetDetectorHandle>> {
+        self.onset_detector_processor
+            .as_ref()
+            .map(|onset_detector_processor| onset_detector_processor.handle())
+    }
+
+    /// Estimates the tempo of the input file, if there's one & it has a steady beat. Analyses the
+    /// whole file, so it shouldn't be called on the audio thread.
+    pub fn audio_file_tempo(&self) -> Option<TempoEstimate> {
+        self.maybe_audio_file_processor
+            .as_ref()
+            .and_then(|audio_file_processor| audio_file_processor.estimate_tempo())
+    }
+
     pub fn set_volume(&self, volume: f32) {
         self.volume.set(volume);
     }
@@ -136,8 +229,18 @@ impl AudioProcessor for TestHostProcessor {
         if let Some(audio_file_processor) = &mut self.maybe_audio_file_processor {
             audio_file_processor.prepare(audio_settings);
         }
+        if let Some(test_signal_processor) = &mut self.maybe_test_signal_processor {
+            test_signal_processor.prepare(audio_settings);
+        }
         self.volume_meter_processor.prepare(audio_settings);
         self.running_rms_processor.prepare(audio_settings);
+        self.spectrum_analyzer_processor.prepare(audio_settings);
+        if let Some(pitch_detector_processor) = &mut self.pitch_detector_processor {
+            pitch_detector_processor.prepare(audio_settings);
+        }
+        if let Some(onset_detector_processor) = &mut self.onset_detector_processor {
+            onset_detector_processor.prepare(audio_settings);
+        }
     }
 
     fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
@@ -153,6 +256,9 @@ impl AudioProcessor for TestHostProcessor {
         if let Some(audio_file_processor) = &mut self.maybe_audio_file_processor {
             audio_file_processor.process(output);
         }
+        if let Some(test_signal_processor) = &mut self.maybe_test_signal_processor {
+            test_signal_processor.process(output);
+        }
 
         // VST processing section
         self.buffer_handler.process(output);
@@ -174,6 +280,13 @@ impl AudioProcessor for TestHostProcessor {
         // Volume meter
         self.volume_meter_processor.process(output);
         self.running_rms_processor.process(output);
+        self.spectrum_analyzer_processor.process(output);
+        if let Some(pitch_detector_processor) = &mut self.pitch_detector_processor {
+            pitch_detector_processor.process(output);
+        }
+        if let Some(onset_detector_processor) = &mut self.onset_detector_processor {
+            onset_detector_processor.process(output);
+        }
     }
 }
 
@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use oscillator::signal_generator::{SignalGeneratorOptions, SignalGeneratorProcessor};
+
+/// An audio processor which plays a generated test signal, as an alternative to an input file
+pub struct TestSignalProcessor {
+    generator: SignalGeneratorProcessor,
+    is_playing: AtomicBool,
+    should_restart: AtomicBool,
+}
+
+impl TestSignalProcessor {
+    pub fn new(options: SignalGeneratorOptions) -> Self {
+        TestSignalProcessor {
+            generator: SignalGeneratorProcessor::new(options),
+            is_playing: AtomicBool::new(true),
+            should_restart: AtomicBool::new(false),
+        }
+    }
+
+    pub fn options(&self) -> &SignalGeneratorOptions {
+        self.generator.options()
+    }
+
+    /// Resume playback
+    pub fn play(&self) {
+        self.is_playing.store(true, Ordering::Relaxed);
+    }
+
+    /// Pause playback
+    pub fn pause(&self) {
+        self.is_playing.store(false, Ordering::Relaxed);
+    }
+
+    /// Stop playback and go back to the start of the signal
+    pub fn stop(&self) {
+        self.is_playing.store(false, Ordering::Relaxed);
+        self.should_restart.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the signal is being played back
+    pub fn is_playing(&self) -> bool {
+        self.is_playing.load(Ordering::Relaxed)
+    }
+
+    pub fn prepare(&mut self, audio_settings: AudioProcessorSettings) {
+        self.generator.prepare(audio_settings);
+    }
+
+    /// Replace `data` with the test signal, or with silence while it isn't playing
+    pub fn process<BufferType: AudioBuffer<SampleType = f32>>(&mut self, data: &mut BufferType) {
+        if self.should_restart.swap(false, Ordering::Relaxed) {
+            self.generator.reset();
+        }
+        // The device input is replaced while paused or stopped too, so it never reaches the plugin
+        if !self.is_playing() {
+            for sample in data.slice_mut() {
+                *sample = 0.0;
+            }
+            return;
+        }
+
+        self.generator.process(data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use super::*;
+
+    #[test]
+    fn test_paused_signal_silences_the_input() {
+        let mut processor = TestSignalProcessor::new(SignalGeneratorOptions::default());
+        processor.prepare(AudioProcessorSettings::default());
+        processor.pause();
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 512, 1.0);
+
+        processor.process(&mut buffer);
+
+        assert!(buffer.slice().iter().all(|sample| *sample == 0.0));
+    }
+}
@@ -4,14 +4,14 @@ use rimd::Status;
 use audio_processor_traits::{
     AudioBuffer, AudioProcessor, AudioProcessorSettings, MidiEventHandler, MidiMessageLike,
 };
-use dsp_filters::rbj::{FilterProcessor, FilterType};
+use dsp_filters::svf::{SvfMode, SvfProcessor};
 use voice::Voice;
 
 mod voice;
 
 pub struct Synthesizer {
     voices: [Voice; 4],
-    filter: FilterProcessor<f32>,
+    filter: SvfProcessor<f32>,
 }
 
 impl Synthesizer {
@@ -23,7 +23,7 @@ impl Synthesizer {
                 Voice::new(sample_rate),
                 Voice::new(sample_rate),
             ],
-            filter: FilterProcessor::new(FilterType::LowPass),
+            filter: SvfProcessor::new(SvfMode::LowPass),
         }
     }
 }
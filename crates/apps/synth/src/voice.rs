@@ -26,11 +26,11 @@ impl Voice {
         &self.current_note
     }
 
-    pub fn note_on(&mut self, note: u8, _velocity: u8) {
+    pub fn note_on(&mut self, note: u8, velocity: u8) {
         self.current_note = Some(note);
         self.oscillator
             .set_frequency(pitch_calc::hz_from_step(note as f32));
-        self.envelope.note_on();
+        self.envelope.note_on_with_velocity(velocity as f32 / 127.0);
     }
 
     pub fn note_off(&mut self) {
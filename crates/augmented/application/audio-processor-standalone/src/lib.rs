@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use basedrop::Handle;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{BufferSize, Host, SampleRate, StreamConfig};
@@ -9,6 +11,7 @@ use audio_processor_traits::{
     AudioProcessor, AudioProcessorSettings, InterleavedAudioBuffer, MidiEventHandler,
     MidiMessageLike,
 };
+use convert_sample_rate::{Quality, RateAdapter};
 
 trait StandaloneProcessor: Send + 'static {
     type Processor: AudioProcessor<SampleType = f32>;
@@ -66,15 +69,37 @@ where
     }
 }
 
+/// Options for running a processor stand-alone
+#[derive(Debug, Clone)]
+pub struct StandaloneOptions {
+    /// Sample rate the processor runs at. When it differs from the device's rate, audio is
+    /// resampled on the way in & out of the processor. `None` runs at the device's default rate.
+    pub sample_rate: Option<f32>,
+    /// Block size the processor is prepared with
+    pub buffer_size: usize,
+}
+
+impl Default for StandaloneOptions {
+    fn default() -> Self {
+        StandaloneOptions {
+            sample_rate: None,
+            buffer_size: 512,
+        }
+    }
+}
+
 pub struct StandaloneHandles {
     pub input_stream: cpal::Stream,
     pub output_stream: cpal::Stream,
     pub midi_host: Option<MidiHost>,
+    /// Delay added by resampling between the device & processor rates
+    pub resampling_latency: Duration,
 }
 
 fn standalone_start(
     mut app: impl StandaloneProcessor,
     handle: Option<&Handle>,
+    options: StandaloneOptions,
 ) -> StandaloneHandles {
     let _ = wisual_logger::try_init_from_env();
 
@@ -96,19 +121,43 @@ fn standalone_start(
     // Audio set-up
     let host = cpal::default_host();
     log::info!("Using host: {}", host.id().name());
-    let buffer_size = 512;
-    let sample_rate = 44100;
-    let (input_device, input_config) = configure_input_device(&host, buffer_size, sample_rate);
-    let (output_device, num_channels, output_config) =
-        configure_output_device(host, buffer_size, sample_rate);
+    let buffer_size = options.buffer_size;
+    let (output_device, num_channels, output_config) = configure_output_device(&host, buffer_size);
+    let device_sample_rate = output_config.sample_rate.0;
+    let (input_device, input_config) =
+        configure_input_device(&host, buffer_size, device_sample_rate);
+    let sample_rate = options.sample_rate.unwrap_or(device_sample_rate as f32);
     let settings = AudioProcessorSettings::new(
-        output_config.sample_rate.0 as f32,
+        sample_rate,
         input_config.channels.into(),
         output_config.channels.into(),
         buffer_size,
     );
     app.processor().prepare(settings);
 
+    let mut rate_adapter = if (sample_rate - device_sample_rate as f32).abs() > f32::EPSILON {
+        Some(RateAdapter::new(
+            num_channels,
+            device_sample_rate as f32,
+            sample_rate,
+            buffer_size,
+            // The streams are opened with a fixed buffer size
+            buffer_size,
+            Quality::Medium,
+        ))
+    } else {
+        None
+    };
+    let resampling_latency = rate_adapter.as_ref().map_or(Duration::ZERO, |adapter| {
+        Duration::from_secs_f32(adapter.latency() as f32 / device_sample_rate as f32)
+    });
+    log::info!(
+        "Device sample rate: {} Processor sample rate: {} Resampling latency: {:?}",
+        device_sample_rate,
+        sample_rate,
+        resampling_latency
+    );
+
     let buffer = ringbuf::RingBuffer::new((buffer_size * 10) as usize);
     let (mut producer, mut consumer) = buffer.split();
     let input_stream = input_device
@@ -130,6 +179,7 @@ fn standalone_start(
             move |data: &mut [f32], _output_info: &cpal::OutputCallbackInfo| {
                 output_stream_with_context(
                     midi_context.as_mut(),
+                    rate_adapter.as_mut(),
                     &mut app,
                     num_channels,
                     &mut consumer,
@@ -150,6 +200,7 @@ fn standalone_start(
         input_stream,
         output_stream,
         midi_host,
+        resampling_latency,
     }
 }
 
@@ -179,11 +230,23 @@ pub fn audio_processor_start_with_midi<
 >(
     audio_processor: Processor,
     handle: &Handle,
+) -> StandaloneHandles {
+    audio_processor_start_with_midi_and_options(audio_processor, handle, Default::default())
+}
+
+/// Start an [`AudioProcessor`] / [`MidiEventHandler`] as a stand-alone cpal app, with custom
+/// [`StandaloneOptions`].
+pub fn audio_processor_start_with_midi_and_options<
+    Processor: AudioProcessor<SampleType = f32> + MidiEventHandler + Send + 'static,
+>(
+    audio_processor: Processor,
+    handle: &Handle,
+    options: StandaloneOptions,
 ) -> StandaloneHandles {
     let app = StandaloneProcessorImpl {
         processor: audio_processor,
     };
-    standalone_start(app, Some(handle))
+    standalone_start(app, Some(handle), options)
 }
 
 /// Run an [`AudioProcessor`] stand-alone cpal app.
@@ -201,17 +264,38 @@ pub fn audio_processor_main<Processor: AudioProcessor<SampleType = f32> + Send +
 /// Returns the [`cpal::Stream`] streams. The audio-thread will keep running until these are dropped.
 pub fn audio_processor_start<Processor: AudioProcessor<SampleType = f32> + Send + 'static>(
     audio_processor: Processor,
+) -> StandaloneHandles {
+    audio_processor_start_with_options(audio_processor, Default::default())
+}
+
+/// Start an [`AudioProcessor`] as a stand-alone cpal app, with custom [`StandaloneOptions`].
+///
+/// For example, a processor may run at 48kHz on a 44.1kHz device:
+///
+/// ```ignore
+/// let options = StandaloneOptions {
+///     sample_rate: Some(48000.0),
+///     ..Default::default()
+/// };
+/// let handles = audio_processor_start_with_options(processor, options);
+/// log::info!("Added latency: {:?}", handles.resampling_latency);
+/// ```
+pub fn audio_processor_start_with_options<
+    Processor: AudioProcessor<SampleType = f32> + Send + 'static,
+>(
+    audio_processor: Processor,
+    options: StandaloneOptions,
 ) -> StandaloneHandles {
     let app = StandaloneAudioOnlyProcessor {
         processor: audio_processor,
     };
-    standalone_start(app, None)
+    standalone_start(app, None, options)
 }
 
 fn configure_input_device(
     host: &Host,
     buffer_size: usize,
-    sample_rate: usize,
+    sample_rate: u32,
 ) -> (cpal::Device, StreamConfig) {
     let input_device = host.default_input_device().unwrap();
     log::info!("Using input: {}", input_device.name().unwrap());
@@ -222,16 +306,13 @@ fn configure_input_device(
     let input_config = input_device.default_input_config().unwrap();
     let mut input_config: StreamConfig = input_config.into();
     input_config.channels = 2;
-    input_config.sample_rate = SampleRate(sample_rate as u32);
+    input_config.sample_rate = SampleRate(sample_rate);
     input_config.buffer_size = BufferSize::Fixed(buffer_size as u32);
     (input_device, input_config)
 }
 
-fn configure_output_device(
-    host: Host,
-    buffer_size: usize,
-    sample_rate: usize,
-) -> (cpal::Device, usize, StreamConfig) {
+/// Uses the device's default sample rate, which the processor's rate is adapted to
+fn configure_output_device(host: &Host, buffer_size: usize) -> (cpal::Device, usize, StreamConfig) {
     let output_device = host.default_output_device().unwrap();
     log::info!("Using output: {}", output_device.name().unwrap());
     let supported_configs = output_device.supported_input_configs().unwrap();
@@ -239,10 +320,9 @@ fn configure_output_device(
         log::info!("Supported config: {:?}", config);
     }
     let output_config = output_device.default_output_config().unwrap();
-    let num_channels: usize = output_config.channels().into();
     let mut output_config: StreamConfig = output_config.into();
     output_config.channels = 2;
-    output_config.sample_rate = SampleRate(sample_rate as u32);
+    let num_channels: usize = output_config.channels.into();
     output_config.buffer_size = BufferSize::Fixed(buffer_size as u32);
     (output_device, num_channels, output_config)
 }
@@ -266,6 +346,7 @@ struct MidiContext {
 
 fn output_stream_with_context<Processor: StandaloneProcessor>(
     midi_context: Option<&mut MidiContext>,
+    rate_adapter: Option<&mut RateAdapter>,
     processor: &mut Processor,
     num_channels: usize,
     consumer: &mut Consumer<f32>,
@@ -298,6 +379,14 @@ fn output_stream_with_context<Processor: StandaloneProcessor>(
         }
     }
 
-    let mut audio_buffer = InterleavedAudioBuffer::new(num_channels, data);
-    processor.processor().process(&mut audio_buffer);
+    match rate_adapter {
+        Some(rate_adapter) => rate_adapter.process(data, |block| {
+            let mut audio_buffer = InterleavedAudioBuffer::new(num_channels, block);
+            processor.processor().process(&mut audio_buffer);
+        }),
+        None => {
+            let mut audio_buffer = InterleavedAudioBuffer::new(num_channels, data);
+            processor.processor().process(&mut audio_buffer);
+        }
+    }
 }
@@ -1,16 +1,39 @@
+//! Envelope generators with delay, attack, hold, decay, sustain & release stages.
+//!
+//! Each time-based stage may have its own curve shape. Envelopes support velocity scaling of their
+//! peak level, different re-trigger modes and looping, so they may also be used as modulation
+//! sources.
 use std::time::Duration;
 
-enum EnvelopeStage {
+/// The stages an [`Envelope`] goes through, in order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopeStage {
     Idle,
+    Delay,
     Attack,
+    Hold,
     Decay,
     Sustain,
     Release,
 }
 
+/// What happens when `note_on` is called while the envelope is still running
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RetriggerMode {
+    /// Restart from the delay stage, moving from the current level
+    Retrigger,
+    /// Restart from the delay stage, jumping to zero first
+    Reset,
+    /// Keep going if the note is still held, restart if it's being released
+    Legato,
+}
+
 struct StageConfig {
     samples: f32,
     duration: Duration,
+    /// Curve shape; `0.0` is linear, positive values start slow & end fast, negative values start
+    /// fast & end slow
+    curve: f32,
 }
 
 impl Default for StageConfig {
@@ -21,7 +44,16 @@ impl Default for StageConfig {
 
 impl StageConfig {
     fn new(samples: f32, duration: Duration) -> Self {
-        StageConfig { samples, duration }
+        StageConfig {
+            samples,
+            duration,
+            curve: 0.0,
+        }
+    }
+
+    fn with_curve(mut self, curve: f32) -> Self {
+        self.curve = curve;
+        self
     }
 
     fn set_sample_rate(&mut self, sample_rate: f32) {
@@ -35,34 +67,67 @@ impl StageConfig {
 }
 
 struct EnvelopeConfig {
+    delay: StageConfig,
     attack: StageConfig,
     attack_level: f32,
+    hold: StageConfig,
     decay: StageConfig,
     sustain: f32,
     release: StageConfig,
     sample_rate: f32,
-    is_exp: bool,
+    velocity_sensitivity: f32,
+    retrigger_mode: RetriggerMode,
+    is_looping: bool,
 }
 
 impl Default for EnvelopeConfig {
     fn default() -> Self {
         EnvelopeConfig {
+            delay: StageConfig::default(),
             attack: StageConfig::new(0.0, Duration::from_secs_f32(0.2)),
             attack_level: 1.0,
+            hold: StageConfig::default(),
             decay: StageConfig::new(0.0, Duration::from_secs_f32(0.3)),
             sustain: 0.8,
             release: StageConfig::new(0.0, Duration::from_secs_f32(0.1)),
             sample_rate: 0.0,
-            is_exp: false,
+            velocity_sensitivity: 0.0,
+            retrigger_mode: RetriggerMode::Retrigger,
+            is_looping: false,
         }
     }
 }
 
 impl EnvelopeConfig {
     fn exp() -> Self {
+        let config = Self::default();
         Self {
-            is_exp: true,
-            ..Self::default()
+            attack: config.attack.with_curve(1.0),
+            decay: config.decay.with_curve(-1.0),
+            release: config.release.with_curve(-1.0),
+            ..config
+        }
+    }
+
+    fn stage_config(&self, stage: EnvelopeStage) -> Option<&StageConfig> {
+        match stage {
+            EnvelopeStage::Delay => Some(&self.delay),
+            EnvelopeStage::Attack => Some(&self.attack),
+            EnvelopeStage::Hold => Some(&self.hold),
+            EnvelopeStage::Decay => Some(&self.decay),
+            EnvelopeStage::Release => Some(&self.release),
+            EnvelopeStage::Idle | EnvelopeStage::Sustain => None,
+        }
+    }
+
+    fn stage_config_mut(&mut self, stage: EnvelopeStage) -> Option<&mut StageConfig> {
+        match stage {
+            EnvelopeStage::Delay => Some(&mut self.delay),
+            EnvelopeStage::Attack => Some(&mut self.attack),
+            EnvelopeStage::Hold => Some(&mut self.hold),
+            EnvelopeStage::Decay => Some(&mut self.decay),
+            EnvelopeStage::Release => Some(&mut self.release),
+            EnvelopeStage::Idle | EnvelopeStage::Sustain => None,
         }
     }
 }
@@ -71,6 +136,8 @@ struct EnvelopeState {
     current_samples: f32,
     stage_start_volume: f32,
     current_volume: f32,
+    /// Multiplier on the peak & sustain levels, set from the note velocity
+    level_scale: f32,
 }
 
 impl Default for EnvelopeState {
@@ -79,10 +146,29 @@ impl Default for EnvelopeState {
             current_samples: 0.0,
             stage_start_volume: 0.0,
             current_volume: 0.0,
+            level_scale: 1.0,
         }
     }
 }
 
+/// A DAHDSR envelope generator.
+///
+/// Call `note_on`/`note_off` to move between stages and `tick` once per sample (or use
+/// [`Envelope::process`] to fill a buffer of envelope values).
+///
+/// ```
+/// use std::time::Duration;
+/// use adsr_envelope::Envelope;
+///
+/// let mut envelope = Envelope::new();
+/// envelope.set_sample_rate(44100.0);
+/// envelope.set_delay(Duration::from_millis(10));
+/// envelope.set_hold(Duration::from_millis(50));
+/// envelope.note_on_with_velocity(0.5);
+///
+/// let mut buffer = [0.0; 512];
+/// envelope.process(&mut buffer);
+/// ```
 pub struct Envelope {
     stage: EnvelopeStage,
     state: EnvelopeState,
@@ -104,6 +190,7 @@ impl Envelope {
         }
     }
 
+    /// An envelope with exponential-like attack, decay & release curves
     pub fn exp() -> Self {
         Envelope {
             stage: EnvelopeStage::Idle,
@@ -114,17 +201,38 @@ impl Envelope {
 
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.config.sample_rate = sample_rate;
+        self.config.delay.set_sample_rate(sample_rate);
         self.config.attack.set_sample_rate(sample_rate);
+        self.config.hold.set_sample_rate(sample_rate);
         self.config.decay.set_sample_rate(sample_rate);
         self.config.release.set_sample_rate(sample_rate);
     }
 
+    /// Set the time between `note_on` and the start of the attack stage
+    pub fn set_delay(&mut self, duration: Duration) {
+        self.config
+            .delay
+            .set_duration(self.config.sample_rate, duration);
+    }
+
     pub fn set_attack(&mut self, duration: Duration) {
         self.config
             .attack
             .set_duration(self.config.sample_rate, duration);
     }
 
+    /// Set the level reached at the end of the attack stage, before velocity scaling
+    pub fn set_attack_level(&mut self, attack_level: f32) {
+        self.config.attack_level = attack_level;
+    }
+
+    /// Set the time the envelope will stay at the peak level after the attack stage
+    pub fn set_hold(&mut self, duration: Duration) {
+        self.config
+            .hold
+            .set_duration(self.config.sample_rate, duration);
+    }
+
     pub fn set_decay(&mut self, duration: Duration) {
         self.config
             .decay
@@ -141,57 +249,141 @@ impl Envelope {
             .set_duration(self.config.sample_rate, duration);
     }
 
+    /// Set the curve shape of a stage. `0.0` is linear, `1.0` and `-1.0` match the curves of
+    /// [`Envelope::exp`] for rising and falling stages respectively.
+    ///
+    /// Only the attack, decay & release stages have curves; other stages are ignored.
+    pub fn set_curve(&mut self, stage: EnvelopeStage, curve: f32) {
+        if let EnvelopeStage::Attack | EnvelopeStage::Decay | EnvelopeStage::Release = stage {
+            if let Some(stage_config) = self.config.stage_config_mut(stage) {
+                stage_config.curve = curve;
+            }
+        }
+    }
+
+    /// Set how much note velocity scales the peak & sustain levels, between `0.0` (not at all) and
+    /// `1.0` (levels are multiplied by velocity)
+    pub fn set_velocity_sensitivity(&mut self, velocity_sensitivity: f32) {
+        self.config.velocity_sensitivity = velocity_sensitivity.clamp(0.0, 1.0);
+    }
+
+    /// Set what happens when a note is triggered while the envelope is running
+    pub fn set_retrigger_mode(&mut self, retrigger_mode: RetriggerMode) {
+        self.config.retrigger_mode = retrigger_mode;
+    }
+
+    /// When looping, the envelope will go back to the delay stage after decay instead of
+    /// sustaining, until `note_off` is called.
+    pub fn set_loop(&mut self, is_looping: bool) {
+        self.config.is_looping = is_looping;
+    }
+
     pub fn volume(&self) -> f32 {
         self.state.current_volume
     }
 
+    /// The current stage
+    pub fn stage(&self) -> EnvelopeStage {
+        self.stage
+    }
+
+    /// Whether the envelope is producing output
+    pub fn is_active(&self) -> bool {
+        self.stage != EnvelopeStage::Idle
+    }
+
     pub fn tick(&mut self) {
         self.state.current_samples += 1.0;
         let current_samples = self.state.current_samples;
-        let maybe_stage_config = match self.stage {
-            EnvelopeStage::Idle => None,
+        let peak_level = self.config.attack_level * self.state.level_scale;
+        let sustain_level = self.config.sustain * self.state.level_scale;
+
+        match self.stage {
+            EnvelopeStage::Idle | EnvelopeStage::Delay => {}
             EnvelopeStage::Attack => {
-                self.state.current_volume =
-                    self.calculate_volume(self.config.attack_level, self.config.attack.samples);
-                Some(&self.config.attack)
+                self.state.current_volume = self.calculate_volume(peak_level, &self.config.attack);
+            }
+            EnvelopeStage::Hold => {
+                self.state.current_volume = peak_level;
             }
             EnvelopeStage::Decay => {
                 self.state.current_volume =
-                    self.calculate_volume(self.config.sustain, self.config.decay.samples);
-                Some(&self.config.decay)
+                    self.calculate_volume(sustain_level, &self.config.decay);
             }
             EnvelopeStage::Sustain => {
-                self.state.current_volume = self.config.sustain;
-                None
+                self.state.current_volume = sustain_level;
             }
             EnvelopeStage::Release => {
-                self.state.current_volume = self.calculate_volume(0.0, self.config.release.samples);
-                Some(&self.config.release)
+                self.state.current_volume = self.calculate_volume(0.0, &self.config.release);
             }
-        };
+        }
 
-        if let Some(stage_config) = maybe_stage_config {
+        if let Some(stage_config) = self.config.stage_config(self.stage) {
             if current_samples >= stage_config.samples {
                 self.next_stage();
             }
         }
     }
 
+    /// Fill `output` with envelope values, ticking once per sample
+    pub fn process(&mut self, output: &mut [f32]) {
+        for sample in output.iter_mut() {
+            *sample = self.volume();
+            self.tick();
+        }
+    }
+
     pub fn note_on(&mut self) {
-        self.set_stage(EnvelopeStage::Attack);
+        self.note_on_with_velocity(1.0);
+    }
+
+    /// Trigger the envelope with a velocity between `0.0` and `1.0`
+    pub fn note_on_with_velocity(&mut self, velocity: f32) {
+        let is_held = matches!(
+            self.stage,
+            EnvelopeStage::Delay
+                | EnvelopeStage::Attack
+                | EnvelopeStage::Hold
+                | EnvelopeStage::Decay
+                | EnvelopeStage::Sustain
+        );
+        if is_held && self.config.retrigger_mode == RetriggerMode::Legato {
+            return;
+        }
+
+        let velocity = velocity.clamp(0.0, 1.0);
+        let sensitivity = self.config.velocity_sensitivity;
+        self.state.level_scale = 1.0 - sensitivity + sensitivity * velocity;
+
+        if self.config.retrigger_mode == RetriggerMode::Reset {
+            self.state.current_volume = 0.0;
+        }
+        self.set_stage(EnvelopeStage::Delay);
     }
 
     pub fn note_off(&mut self) {
-        self.set_stage(EnvelopeStage::Release);
+        if self.stage != EnvelopeStage::Idle {
+            self.set_stage(EnvelopeStage::Release);
+        }
     }
 
     fn next_stage(&mut self) {
         match self.stage {
+            EnvelopeStage::Delay => {
+                self.set_stage(EnvelopeStage::Attack);
+            }
             EnvelopeStage::Attack => {
+                self.set_stage(EnvelopeStage::Hold);
+            }
+            EnvelopeStage::Hold => {
                 self.set_stage(EnvelopeStage::Decay);
             }
             EnvelopeStage::Decay => {
-                self.set_stage(EnvelopeStage::Sustain);
+                if self.config.is_looping {
+                    self.set_stage(EnvelopeStage::Delay);
+                } else {
+                    self.set_stage(EnvelopeStage::Sustain);
+                }
             }
             EnvelopeStage::Sustain => {
                 self.set_stage(EnvelopeStage::Release);
@@ -207,26 +399,32 @@ impl Envelope {
         self.state.stage_start_volume = self.state.current_volume;
         self.state.current_samples = 0.0;
         self.stage = stage;
+
+        // Delay & hold stages don't change the volume, so they're skipped when empty rather than
+        // adding a sample of latency
+        if let EnvelopeStage::Delay | EnvelopeStage::Hold = stage {
+            if self.config.stage_config(stage).unwrap().samples <= 0.0 {
+                self.next_stage();
+            }
+        }
     }
 
-    fn calculate_volume(&self, target: f32, duration_samples: f32) -> f32 {
+    fn calculate_volume(&self, target: f32, stage_config: &StageConfig) -> f32 {
         let start = self.state.stage_start_volume;
         let current_samples = self.state.current_samples;
+        let diff = target - start;
 
-        if self.config.is_exp {
-            let start = self.state.stage_start_volume;
-            let target = target;
-            let diff = target - start;
-            let perc = (current_samples / duration_samples).powf(if diff >= 0.0 {
-                2.0
-            } else {
-                1.0 / 2.0
-            });
-            return start + perc * diff;
-        }
+        let perc = if stage_config.samples <= 0.0 {
+            1.0
+        } else {
+            (current_samples / stage_config.samples).min(1.0)
+        };
+        let perc = if stage_config.curve == 0.0 {
+            perc
+        } else {
+            perc.powf(2.0_f32.powf(stage_config.curve))
+        };
 
-        let perc = current_samples / duration_samples;
-        let diff = target - start;
         start + perc * diff
     }
 }
@@ -285,6 +483,153 @@ mod test {
         generate_plot(envelope_buffer, "exp-envelope")
     }
 
+    fn ticks_until_stage(envelope: &mut Envelope, stage: EnvelopeStage) -> usize {
+        let mut ticks = 0;
+        while envelope.stage() != stage {
+            envelope.tick();
+            ticks += 1;
+            assert!(ticks < 44100 * 10, "Envelope never reached {:?}", stage);
+        }
+        ticks
+    }
+
+    #[test]
+    fn test_delay_and_hold_stages() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        envelope.set_delay(Duration::from_millis(100));
+        envelope.set_attack(Duration::from_millis(10));
+        envelope.set_hold(Duration::from_millis(50));
+        envelope.note_on();
+
+        assert_eq!(envelope.stage(), EnvelopeStage::Delay);
+        let delay_ticks = ticks_until_stage(&mut envelope, EnvelopeStage::Attack);
+        assert_eq!(delay_ticks, 100);
+        assert_eq!(envelope.volume(), 0.0);
+
+        ticks_until_stage(&mut envelope, EnvelopeStage::Hold);
+        let hold_ticks = ticks_until_stage(&mut envelope, EnvelopeStage::Decay);
+        assert_eq!(hold_ticks, 50);
+        assert!((envelope.volume() - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_empty_delay_and_hold_are_skipped() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        envelope.note_on();
+        assert_eq!(envelope.stage(), EnvelopeStage::Attack);
+        ticks_until_stage(&mut envelope, EnvelopeStage::Decay);
+    }
+
+    #[test]
+    fn test_velocity_scales_peak_and_sustain() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        envelope.set_velocity_sensitivity(1.0);
+        envelope.set_sustain(0.5);
+        envelope.note_on_with_velocity(0.5);
+
+        ticks_until_stage(&mut envelope, EnvelopeStage::Decay);
+        assert!((envelope.volume() - 0.5).abs() < 1e-4);
+        ticks_until_stage(&mut envelope, EnvelopeStage::Sustain);
+        envelope.tick();
+        assert!((envelope.volume() - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_velocity_is_ignored_without_sensitivity() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        envelope.note_on_with_velocity(0.1);
+        ticks_until_stage(&mut envelope, EnvelopeStage::Decay);
+        assert!((envelope.volume() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_legato_mode_keeps_the_current_stage() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        envelope.set_retrigger_mode(RetriggerMode::Legato);
+        envelope.note_on();
+        ticks_until_stage(&mut envelope, EnvelopeStage::Sustain);
+        envelope.note_on();
+        assert_eq!(envelope.stage(), EnvelopeStage::Sustain);
+
+        envelope.note_off();
+        envelope.tick();
+        envelope.note_on();
+        assert_eq!(envelope.stage(), EnvelopeStage::Attack);
+    }
+
+    #[test]
+    fn test_retrigger_modes_start_level() {
+        for (mode, expected_start) in [
+            (RetriggerMode::Retrigger, 0.8),
+            (RetriggerMode::Reset, 0.0),
+        ] {
+            let mut envelope = Envelope::new();
+            envelope.set_sample_rate(1000.0);
+            envelope.set_retrigger_mode(mode);
+            envelope.note_on();
+            ticks_until_stage(&mut envelope, EnvelopeStage::Sustain);
+            envelope.tick();
+            envelope.note_on();
+            assert_eq!(envelope.stage(), EnvelopeStage::Attack);
+            assert!((envelope.volume() - expected_start).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_looping_envelope_restarts_after_decay() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        envelope.set_loop(true);
+        envelope.note_on();
+        ticks_until_stage(&mut envelope, EnvelopeStage::Decay);
+        ticks_until_stage(&mut envelope, EnvelopeStage::Attack);
+
+        envelope.note_off();
+        assert_eq!(envelope.stage(), EnvelopeStage::Release);
+        ticks_until_stage(&mut envelope, EnvelopeStage::Idle);
+    }
+
+    #[test]
+    fn test_curves_change_the_stage_shape() {
+        fn mid_attack_volume(curve: f32) -> f32 {
+            let mut envelope = Envelope::new();
+            envelope.set_sample_rate(1000.0);
+            envelope.set_attack(Duration::from_millis(100));
+            envelope.set_curve(EnvelopeStage::Attack, curve);
+            envelope.note_on();
+            for _ in 0..50 {
+                envelope.tick();
+            }
+            envelope.volume()
+        }
+
+        assert!((mid_attack_volume(0.0) - 0.5).abs() < 1e-4);
+        assert!(mid_attack_volume(1.0) < 0.5);
+        assert!(mid_attack_volume(-1.0) > 0.5);
+    }
+
+    #[test]
+    fn test_process_fills_a_buffer() {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(1000.0);
+        let mut reference = Envelope::new();
+        reference.set_sample_rate(1000.0);
+        envelope.note_on();
+        reference.note_on();
+
+        let mut buffer = [0.0; 1000];
+        envelope.process(&mut buffer);
+        for sample in buffer.iter() {
+            assert_eq!(*sample, reference.volume());
+            reference.tick();
+        }
+    }
+
     fn generate_plot(output: Vec<(i32, f32)>, plot_name: &str) {
         let root_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
         let filename = root_dir.join(format!("src/__plots__/{}.png", plot_name));
@@ -3,8 +3,10 @@ use std::sync::{Arc, RwLock};
 
 pub use parameter::PluginParameter;
 pub use parameter::PluginParameterLike;
+pub use reader::ParameterReader;
 
 pub mod parameter;
+pub mod reader;
 
 /// Holder of parameters
 ///
@@ -95,7 +97,8 @@ impl ParameterStore {
     /// Get a parameter value by ID
     ///
     /// # Locking
-    /// This will block if the store is locked for writing.
+    /// This will block if the store is locked for writing. Use a [`ParameterReader`] to read
+    /// values on the audio thread.
     pub fn value(&self, id: &str) -> f32 {
         self.find_parameter(id).as_ref().unwrap().value()
     }
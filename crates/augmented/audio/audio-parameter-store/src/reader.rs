@@ -0,0 +1,113 @@
+use crate::{ParameterRef, ParameterStore};
+
+/// Reads a list of parameters on the audio thread
+///
+/// The parameters are looked-up once, when the reader is created, so reading their values never
+/// locks the store. Parameters added to the store afterwards aren't seen & IDs which aren't in
+/// the store are skipped.
+pub struct ParameterReader {
+    parameters: Vec<Option<ParameterRef>>,
+    /// Values last reported by `read_changes`, in ID order
+    values: Vec<f32>,
+}
+
+unsafe impl Send for ParameterReader {}
+unsafe impl Sync for ParameterReader {}
+
+impl ParameterReader {
+    /// Look-up `ids` in `store`
+    ///
+    /// # Locking
+    /// This will block if the store is locked for writing.
+    pub fn new<Id: AsRef<str>>(store: &ParameterStore, ids: &[Id]) -> Self {
+        ParameterReader {
+            parameters: ids
+                .iter()
+                .map(|id| store.find_parameter(id.as_ref()))
+                .collect(),
+            values: vec![f32::NAN; ids.len()],
+        }
+    }
+
+    /// Number of IDs the reader was created with
+    pub fn len(&self) -> usize {
+        self.parameters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    /// Value of the parameter at `index`, or `None` if it isn't in the store
+    pub fn value(&self, index: usize) -> Option<f32> {
+        let parameter = self.parameters.get(index)?.as_ref()?;
+        Some(parameter.value())
+    }
+
+    /// Call `apply` with the index & value of each parameter which changed since the last call.
+    /// Every parameter in the store is reported on the first call.
+    pub fn read_changes(&mut self, mut apply: impl FnMut(usize, f32)) {
+        for (index, (parameter, last_value)) in self
+            .parameters
+            .iter()
+            .zip(self.values.iter_mut())
+            .enumerate()
+        {
+            if let Some(parameter) = parameter {
+                let value = parameter.value();
+                if value != *last_value {
+                    *last_value = value;
+                    apply(index, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::PluginParameter;
+
+    use super::*;
+
+    fn store() -> Arc<ParameterStore> {
+        let mut store = ParameterStore::new();
+        for (id, value) in [("a", 1.0), ("b", 2.0)].iter() {
+            store.add_parameter(
+                id,
+                Arc::new(PluginParameter::builder().initial_value(*value).build()),
+            );
+        }
+        Arc::new(store)
+    }
+
+    #[test]
+    fn test_missing_ids_are_skipped() {
+        let store = store();
+        let mut reader = ParameterReader::new(&store, &["a", "missing", "b"]);
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.value(0), Some(1.0));
+        assert_eq!(reader.value(1), None);
+        assert_eq!(reader.value(3), None);
+
+        let mut changes = Vec::new();
+        reader.read_changes(|index, value| changes.push((index, value)));
+        assert_eq!(changes, vec![(0, 1.0), (2, 2.0)]);
+    }
+
+    #[test]
+    fn test_only_changes_are_reported() {
+        let store = store();
+        let mut reader = ParameterReader::new(&store, &["a", "b"]);
+        reader.read_changes(|_, _| {});
+
+        store.find_parameter("b").unwrap().set_value(5.0);
+        let mut changes = Vec::new();
+        reader.read_changes(|index, value| changes.push((index, value)));
+        assert_eq!(changes, vec![(1, 5.0)]);
+
+        reader.read_changes(|_, _| panic!("Nothing changed"));
+    }
+}
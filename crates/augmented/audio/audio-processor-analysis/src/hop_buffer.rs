@@ -0,0 +1,111 @@
+//! Splits a stream of samples into overlapping analysis windows.
+
+/// Average the channels of an interleaved frame into one sample. `None` for empty frames.
+#[inline]
+pub fn downmix(frame: &[f32]) -> Option<f32> {
+    if frame.is_empty() {
+        None
+    } else {
+        Some(frame.iter().sum::<f32>() / frame.len() as f32)
+    }
+}
+
+/// Keeps the last `window_size` samples pushed & says when a new window starts, every
+/// `hop_size` samples.
+///
+/// Each `process` call of an analyzer built on it runs at most `block_size / hop_size + 1`
+/// analyses. Allocates on construction only. An empty buffer, e.g. the default one, never has a
+/// window ready.
+#[derive(Debug, Clone, Default)]
+pub struct HopBuffer {
+    /// The last `window_size` samples, circular
+    samples: Vec<f32>,
+    /// Index of the oldest sample
+    position: usize,
+    hop_size: usize,
+    samples_until_window: usize,
+}
+
+impl HopBuffer {
+    /// Create a silent buffer. `hop_size` is at least 1.
+    pub fn new(window_size: usize, hop_size: usize) -> Self {
+        let hop_size = hop_size.max(1);
+        HopBuffer {
+            samples: vec![0.0; window_size],
+            position: 0,
+            hop_size,
+            samples_until_window: hop_size,
+        }
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Forget the input & start counting the hop again
+    pub fn reset(&mut self) {
+        self.samples.iter_mut().for_each(|sample| *sample = 0.0);
+        self.position = 0;
+        self.samples_until_window = self.hop_size;
+    }
+
+    /// Push a sample. Returns true every `hop_size` samples, when the window should be analysed.
+    #[inline]
+    pub fn push(&mut self, sample: f32) -> bool {
+        if self.samples.is_empty() {
+            return false;
+        }
+        self.samples[self.position] = sample;
+        self.position = (self.position + 1) % self.samples.len();
+
+        self.samples_until_window -= 1;
+        if self.samples_until_window > 0 {
+            return false;
+        }
+        self.samples_until_window = self.hop_size;
+        true
+    }
+
+    /// The last `window_size` samples, oldest first
+    pub fn window(&self) -> impl Iterator<Item = &f32> {
+        let (newest, oldest) = self.samples.split_at(self.position);
+        oldest.iter().chain(newest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_windows_start_every_hop() {
+        let mut buffer = HopBuffer::new(4, 2);
+        let ready: Vec<bool> = (1..=6).map(|sample| buffer.push(sample as f32)).collect();
+        assert_eq!(ready, vec![false, true, false, true, false, true]);
+        assert_eq!(
+            buffer.window().cloned().collect::<Vec<f32>>(),
+            vec![3.0, 4.0, 5.0, 6.0]
+        );
+
+        buffer.reset();
+        assert!(buffer.window().all(|sample| *sample == 0.0));
+        assert!(!buffer.push(1.0));
+    }
+
+    #[test]
+    fn test_empty_buffer_is_never_ready() {
+        let mut buffer = HopBuffer::default();
+        assert!(!buffer.push(1.0));
+        assert_eq!(buffer.window().count(), 0);
+    }
+
+    #[test]
+    fn test_downmix_averages_channels() {
+        assert_eq!(downmix(&[1.0, 0.0]), Some(0.5));
+        assert_eq!(downmix(&[]), None);
+    }
+}
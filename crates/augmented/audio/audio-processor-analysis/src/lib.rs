@@ -0,0 +1,15 @@
+//! Analysis `AudioProcessor` implementations, which leave their input untouched & publish what
+//! they measure through handles that may be read from any thread without locking.
+//!
+//! * [`loudness`] - EBU R128 loudness meter, with true-peak
+//! * [`onset`] - spectral flux onset detector, which also tracks the tempo
+//! * [`pitch`] - YIN pitch detector, for tuners & pitch tracking
+//! * [`spectrum`] - FFT spectrum analyzer with averaging & peak hold
+//! * [`tempo`] - tempo estimation from an onset envelope
+pub mod hop_buffer;
+pub mod loudness;
+pub mod onset;
+pub mod pitch;
+pub mod spectrum;
+pub mod tempo;
+pub mod window;
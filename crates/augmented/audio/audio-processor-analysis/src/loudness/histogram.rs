@@ -0,0 +1,94 @@
+use super::{energy_to_loudness, ABSOLUTE_GATE};
+
+/// Loudness of the highest bin. Louder blocks are counted in it.
+const MAX_LOUDNESS: f64 = 10.0;
+/// Width of each bin, in LU
+const BIN_WIDTH: f64 = 0.1;
+
+/// Energies of gating blocks, binned by loudness, so gated measurements over any duration take
+/// constant memory. Blocks below the absolute gate are dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct LoudnessHistogram {
+    counts: Vec<u64>,
+    energies: Vec<f64>,
+}
+
+impl LoudnessHistogram {
+    pub(crate) fn new() -> Self {
+        let num_bins = ((MAX_LOUDNESS - ABSOLUTE_GATE) / BIN_WIDTH).round() as usize;
+        LoudnessHistogram {
+            counts: vec![0; num_bins],
+            energies: vec![0.0; num_bins],
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+        self.energies.iter_mut().for_each(|energy| *energy = 0.0);
+    }
+
+    pub(crate) fn add(&mut self, energy: f64) {
+        let loudness = energy_to_loudness(energy);
+        if loudness < ABSOLUTE_GATE {
+            return;
+        }
+        let bin = (((loudness - ABSOLUTE_GATE) / BIN_WIDTH) as usize).min(self.counts.len() - 1);
+        self.counts[bin] += 1;
+        self.energies[bin] += energy;
+    }
+
+    fn bin_loudness(bin: usize) -> f64 {
+        ABSOLUTE_GATE + (bin as f64 + 0.5) * BIN_WIDTH
+    }
+
+    /// The loudness of all blocks above the absolute gate, plus `offset`
+    fn relative_gate(&self, offset: f64) -> Option<f64> {
+        let count: u64 = self.counts.iter().sum();
+        if count == 0 {
+            return None;
+        }
+        let energy: f64 = self.energies.iter().sum();
+        Some(energy_to_loudness(energy / count as f64) + offset)
+    }
+
+    /// Loudness of the blocks above a gate 10 LU below the loudness of all blocks, in LUFS
+    pub(crate) fn integrated(&self) -> Option<f64> {
+        let gate = self.relative_gate(-10.0)?;
+        let (count, energy) = (0..self.counts.len())
+            .filter(|bin| Self::bin_loudness(*bin) >= gate)
+            .fold((0, 0.0), |(count, energy), bin| {
+                (count + self.counts[bin], energy + self.energies[bin])
+            });
+        if count == 0 {
+            return None;
+        }
+        Some(energy_to_loudness(energy / count as f64))
+    }
+
+    /// Spread between the 10th & 95th percentiles of the blocks above a gate 20 LU below the
+    /// loudness of all blocks, in LU. See EBU Tech 3342.
+    pub(crate) fn range(&self) -> Option<f64> {
+        let gate = self.relative_gate(-20.0)?;
+        let gated = |bin: &usize| Self::bin_loudness(*bin) >= gate;
+        let count: u64 = (0..self.counts.len())
+            .filter(gated)
+            .map(|bin| self.counts[bin])
+            .sum();
+        if count == 0 {
+            return None;
+        }
+
+        let percentile = |fraction: f64| {
+            let target = (fraction * (count - 1) as f64).round() as u64;
+            let mut seen = 0;
+            for bin in (0..self.counts.len()).filter(gated) {
+                seen += self.counts[bin];
+                if seen > target {
+                    return Self::bin_loudness(bin);
+                }
+            }
+            Self::bin_loudness(self.counts.len() - 1)
+        };
+        Some(percentile(0.95) - percentile(0.1))
+    }
+}
@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+
+/// A biquad in direct form I, in double precision since the K-weighting high-pass cuts off at a
+/// tiny fraction of the sample rate
+#[derive(Debug, Clone, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    inputs: [f64; 2],
+    outputs: [f64; 2],
+}
+
+impl Biquad {
+    #[inline]
+    fn process1(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.inputs[0] + self.b[2] * self.inputs[1]
+            - self.a[0] * self.outputs[0]
+            - self.a[1] * self.outputs[1];
+        self.inputs = [input, self.inputs[0]];
+        self.outputs = [output, self.outputs[0]];
+        output
+    }
+
+    fn reset(&mut self) {
+        self.inputs = [0.0; 2];
+        self.outputs = [0.0; 2];
+    }
+}
+
+/// The ITU-R BS.1770 K-weighting filter: a high shelf modelling the acoustic effect of the head,
+/// followed by a high-pass.
+///
+/// The standard only lists coefficients for 48kHz. These are derived from the analog prototype, so
+/// they match them at 48kHz & work at any sample rate.
+#[derive(Debug, Clone)]
+pub struct KWeightingFilter {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeightingFilter {
+    pub fn new(sample_rate: f32) -> Self {
+        let sample_rate = sample_rate as f64;
+
+        let frequency = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * frequency / sample_rate).tan();
+        let vh = 10.0_f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Biquad::default()
+        };
+
+        let frequency = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * frequency / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Biquad::default()
+        };
+
+        KWeightingFilter { shelf, high_pass }
+    }
+
+    #[inline]
+    pub fn process1(&mut self, input: f32) -> f32 {
+        let shelved = self.shelf.process1(input as f64);
+        self.high_pass.process1(shelved) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.shelf.reset();
+        self.high_pass.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn gain_db(sample_rate: f32, frequency: f32) -> f32 {
+        let mut filter = KWeightingFilter::new(sample_rate);
+        let num_samples = sample_rate as usize;
+        let mut sum = 0.0;
+        for i in 0..num_samples {
+            let input = (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin();
+            let output = filter.process1(input);
+            if i >= num_samples / 2 {
+                sum += output * output;
+            }
+        }
+        10.0 * (sum / (num_samples / 2) as f32 / 0.5).log10()
+    }
+
+    #[test]
+    fn test_response_matches_the_standard() {
+        for sample_rate in [44100.0, 48000.0, 96000.0].iter() {
+            // The shelf adds about 0.7dB at 1kHz, which the loudness formula takes off again
+            assert!((gain_db(*sample_rate, 1000.0) - 0.691).abs() < 0.05);
+            assert!((gain_db(*sample_rate, 10000.0) - 4.0).abs() < 0.2);
+            assert!(gain_db(*sample_rate, 20.0) < -10.0);
+        }
+    }
+}
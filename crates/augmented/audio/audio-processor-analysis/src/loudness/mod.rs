@@ -0,0 +1,448 @@
+//! Loudness metering, following ITU-R BS.1770 & EBU R128.
+//!
+//! [`LoudnessMeterProcessor`] K-weights its input & measures momentary (400ms), short-term (3s) &
+//! gated integrated loudness in LUFS, the loudness range in LU & the true-peak in dBTP. It publishes
+//! them on a [`LoudnessHandle`] while running, and sums them up in a [`LoudnessReport`].
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use audio_garbage_collector::{Handle, Shared};
+use audio_processor_traits::{AtomicF32, AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use audio_volume::amplitude_to_db;
+
+use histogram::LoudnessHistogram;
+pub use k_weighting::KWeightingFilter;
+pub use true_peak::TruePeakDetector;
+
+mod histogram;
+mod k_weighting;
+mod true_peak;
+
+/// Gating blocks quieter than this are ignored, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Sub-blocks per momentary window; gating blocks overlap by 75%
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+/// Sub-blocks per short-term window
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+/// Loudness of a mean-square `energy`, summed over the weighted channels
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// BS.1770 channel weights: 1.41 for the surround channels of 5.0 & 5.1 layouts, 0 for the LFE &
+/// 1 for everything else
+fn default_channel_weights(num_channels: usize) -> Vec<f32> {
+    match num_channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; num_channels],
+    }
+}
+
+/// Loudness measurements. Values are `-inf` until there's enough audio to measure them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct LoudnessReport {
+    /// Gated loudness of everything measured, in LUFS
+    pub integrated: f32,
+    /// Loudness range, in LU
+    pub loudness_range: f32,
+    /// Loudest 400ms window, in LUFS
+    pub max_momentary: f32,
+    /// Loudest 3s window, in LUFS
+    pub max_short_term: f32,
+    /// Largest true-peak over all channels, in dBTP
+    pub true_peak: f32,
+}
+
+impl Default for LoudnessReport {
+    fn default() -> Self {
+        LoudnessReport {
+            integrated: f32::NEG_INFINITY,
+            loudness_range: 0.0,
+            max_momentary: f32::NEG_INFINITY,
+            max_short_term: f32::NEG_INFINITY,
+            true_peak: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl fmt::Display for LoudnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Integrated loudness: {:.1} LUFS", self.integrated)?;
+        writeln!(f, "Loudness range: {:.1} LU", self.loudness_range)?;
+        writeln!(f, "Max momentary loudness: {:.1} LUFS", self.max_momentary)?;
+        writeln!(
+            f,
+            "Max short-term loudness: {:.1} LUFS",
+            self.max_short_term
+        )?;
+        write!(f, "True peak: {:.1} dBTP", self.true_peak)
+    }
+}
+
+/// A shared "processor handle" to `LoudnessMeterProcessor`
+pub struct LoudnessHandle {
+    momentary: AtomicF32,
+    short_term: AtomicF32,
+    integrated: AtomicF32,
+    loudness_range: AtomicF32,
+    max_momentary: AtomicF32,
+    max_short_term: AtomicF32,
+    true_peak: AtomicF32,
+    reset_requested: AtomicBool,
+}
+
+impl Default for LoudnessHandle {
+    fn default() -> Self {
+        let report = LoudnessReport::default();
+        LoudnessHandle {
+            momentary: AtomicF32::new(f32::NEG_INFINITY),
+            short_term: AtomicF32::new(f32::NEG_INFINITY),
+            integrated: AtomicF32::new(report.integrated),
+            loudness_range: AtomicF32::new(report.loudness_range),
+            max_momentary: AtomicF32::new(report.max_momentary),
+            max_short_term: AtomicF32::new(report.max_short_term),
+            true_peak: AtomicF32::new(report.true_peak),
+            reset_requested: AtomicBool::new(false),
+        }
+    }
+}
+
+impl LoudnessHandle {
+    /// Loudness of the last 400ms, in LUFS
+    pub fn momentary(&self) -> f32 {
+        self.momentary.get()
+    }
+
+    /// Loudness of the last 3s, in LUFS
+    pub fn short_term(&self) -> f32 {
+        self.short_term.get()
+    }
+
+    pub fn report(&self) -> LoudnessReport {
+        LoudnessReport {
+            integrated: self.integrated.get(),
+            loudness_range: self.loudness_range.get(),
+            max_momentary: self.max_momentary.get(),
+            max_short_term: self.max_short_term.get(),
+            true_peak: self.true_peak.get(),
+        }
+    }
+
+    /// Ask the processor to start measuring from scratch on its next block
+    pub fn reset(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+
+    fn publish(&self, report: &LoudnessReport) {
+        self.integrated.set(report.integrated);
+        self.loudness_range.set(report.loudness_range);
+        self.max_momentary.set(report.max_momentary);
+        self.max_short_term.set(report.max_short_term);
+        self.true_peak.set(report.true_peak);
+    }
+}
+
+/// An `AudioProcessor` which measures the loudness of its input, leaving it untouched.
+///
+/// Loudness is measured over 100ms sub-blocks, so momentary & short-term values update 10 times
+/// a second. Integrated loudness & loudness range are gated over everything since the last reset,
+/// using histograms so memory doesn't grow with the duration.
+///
+/// Everything is allocated on `prepare`. It may also run offline, reading the result with
+/// [`LoudnessMeterProcessor::report`].
+pub struct LoudnessMeterProcessor {
+    handle: Shared<LoudnessHandle>,
+    /// Weights set with [`LoudnessMeterProcessor::set_channel_weights`], kept across `prepare`
+    custom_channel_weights: Option<Vec<f32>>,
+    channel_weights: Vec<f32>,
+    filters: Vec<KWeightingFilter>,
+    true_peak_detectors: Vec<TruePeakDetector>,
+    sub_block_size: usize,
+    sub_block_position: usize,
+    /// Weighted sum of squares of the current sub-block
+    sub_block_sum: f64,
+    /// Mean squares of the last sub-blocks, circular
+    sub_blocks: Vec<f64>,
+    sub_block_index: usize,
+    num_sub_blocks: usize,
+    momentary_histogram: LoudnessHistogram,
+    short_term_histogram: LoudnessHistogram,
+    report: LoudnessReport,
+}
+
+impl LoudnessMeterProcessor {
+    pub fn new(gc_handle: &Handle) -> Self {
+        LoudnessMeterProcessor {
+            handle: Shared::new(gc_handle, LoudnessHandle::default()),
+            custom_channel_weights: None,
+            channel_weights: Vec::new(),
+            filters: Vec::new(),
+            true_peak_detectors: Vec::new(),
+            sub_block_size: 0,
+            sub_block_position: 0,
+            sub_block_sum: 0.0,
+            sub_blocks: vec![0.0; SHORT_TERM_SUB_BLOCKS],
+            sub_block_index: 0,
+            num_sub_blocks: 0,
+            momentary_histogram: LoudnessHistogram::new(),
+            short_term_histogram: LoudnessHistogram::new(),
+            report: LoudnessReport::default(),
+        }
+    }
+
+    pub fn handle(&self) -> &Shared<LoudnessHandle> {
+        &self.handle
+    }
+
+    /// Measurements since the last reset
+    pub fn report(&self) -> LoudnessReport {
+        self.report
+    }
+
+    /// Override the BS.1770 weight of each channel. Channels past the end of `weights` keep their
+    /// default weight & extra weights are ignored. The weights are kept across `prepare` calls.
+    pub fn set_channel_weights(&mut self, weights: Vec<f32>) {
+        self.custom_channel_weights = Some(weights);
+        self.channel_weights = self.resolve_channel_weights(self.filters.len());
+    }
+
+    /// The custom weights, padded with the defaults or truncated to `num_channels`
+    fn resolve_channel_weights(&self, num_channels: usize) -> Vec<f32> {
+        let mut weights = default_channel_weights(num_channels);
+        if let Some(custom_weights) = &self.custom_channel_weights {
+            for (weight, custom_weight) in weights.iter_mut().zip(custom_weights) {
+                *weight = *custom_weight;
+            }
+        }
+        weights
+    }
+
+    /// Forget everything measured
+    pub fn reset(&mut self) {
+        self.filters.iter_mut().for_each(|filter| filter.reset());
+        self.true_peak_detectors
+            .iter_mut()
+            .for_each(|detector| detector.reset());
+        self.sub_block_position = 0;
+        self.sub_block_sum = 0.0;
+        self.sub_blocks.iter_mut().for_each(|energy| *energy = 0.0);
+        self.sub_block_index = 0;
+        self.num_sub_blocks = 0;
+        self.momentary_histogram.clear();
+        self.short_term_histogram.clear();
+        self.report = LoudnessReport::default();
+        self.handle.momentary.set(f32::NEG_INFINITY);
+        self.handle.short_term.set(f32::NEG_INFINITY);
+        self.handle.publish(&self.report);
+    }
+
+    /// Mean of the last `len` sub-blocks
+    fn window_energy(&self, len: usize) -> f64 {
+        let num_blocks = self.sub_blocks.len();
+        (0..len)
+            .map(|offset| {
+                self.sub_blocks[(self.sub_block_index + num_blocks - 1 - offset) % num_blocks]
+            })
+            .sum::<f64>()
+            / len as f64
+    }
+
+    fn finish_sub_block(&mut self) {
+        self.sub_blocks[self.sub_block_index] = self.sub_block_sum / self.sub_block_size as f64;
+        self.sub_block_index = (self.sub_block_index + 1) % self.sub_blocks.len();
+        self.num_sub_blocks = (self.num_sub_blocks + 1).min(self.sub_blocks.len());
+        self.sub_block_sum = 0.0;
+        self.sub_block_position = 0;
+
+        if self.num_sub_blocks >= MOMENTARY_SUB_BLOCKS {
+            let energy = self.window_energy(MOMENTARY_SUB_BLOCKS);
+            let momentary = energy_to_loudness(energy) as f32;
+            self.momentary_histogram.add(energy);
+            self.handle.momentary.set(momentary);
+            self.report.max_momentary = self.report.max_momentary.max(momentary);
+            if let Some(integrated) = self.momentary_histogram.integrated() {
+                self.report.integrated = integrated as f32;
+            }
+        }
+
+        if self.num_sub_blocks >= SHORT_TERM_SUB_BLOCKS {
+            let energy = self.window_energy(SHORT_TERM_SUB_BLOCKS);
+            let short_term = energy_to_loudness(energy) as f32;
+            self.short_term_histogram.add(energy);
+            self.handle.short_term.set(short_term);
+            self.report.max_short_term = self.report.max_short_term.max(short_term);
+            if let Some(loudness_range) = self.short_term_histogram.range() {
+                self.report.loudness_range = loudness_range as f32;
+            }
+        }
+
+        self.report.true_peak = self
+            .true_peak_detectors
+            .iter()
+            .fold(f32::NEG_INFINITY, |peak, detector| {
+                peak.max(amplitude_to_db(detector.peak(), 1.0))
+            });
+        self.handle.publish(&self.report);
+    }
+}
+
+impl AudioProcessor for LoudnessMeterProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        let num_channels = settings.input_channels();
+        let sample_rate = settings.sample_rate();
+        self.channel_weights = self.resolve_channel_weights(num_channels);
+        self.filters = (0..num_channels)
+            .map(|_| KWeightingFilter::new(sample_rate))
+            .collect();
+        self.true_peak_detectors = (0..num_channels)
+            .map(|_| TruePeakDetector::new(sample_rate))
+            .collect();
+        self.sub_block_size = ((sample_rate * 0.1).round() as usize).max(1);
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        if self.handle.reset_requested.swap(false, Ordering::Relaxed) {
+            self.reset();
+        }
+        if self.sub_block_size == 0 {
+            return;
+        }
+
+        for frame in data.frames() {
+            for (((sample, filter), detector), weight) in frame
+                .iter()
+                .zip(&mut self.filters)
+                .zip(&mut self.true_peak_detectors)
+                .zip(&self.channel_weights)
+            {
+                let weighted = filter.process1(*sample) as f64;
+                self.sub_block_sum += *weight as f64 * weighted * weighted;
+                detector.process1(*sample);
+            }
+
+            self.sub_block_position += 1;
+            if self.sub_block_position >= self.sub_block_size {
+                self.finish_sub_block();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_garbage_collector::GarbageCollector;
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn meter(gc: &GarbageCollector) -> LoudnessMeterProcessor {
+        let mut meter = LoudnessMeterProcessor::new(gc.handle());
+        meter.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        meter
+    }
+
+    /// Stereo 1kHz sine with each channel at `level_db` dBFS, for `seconds`
+    fn process_sine(meter: &mut LoudnessMeterProcessor, level_db: f32, seconds: f32) {
+        let amplitude = 10.0_f32.powf(level_db / 20.0);
+        let num_frames = (seconds * SAMPLE_RATE) as usize;
+        let mut samples: Vec<f32> = (0..num_frames)
+            .flat_map(|i| {
+                let value = amplitude
+                    * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / SAMPLE_RATE).sin();
+                vec![value; 2]
+            })
+            .collect();
+        for block in samples.chunks_mut(2 * 512) {
+            meter.process(&mut InterleavedAudioBuffer::new(2, block));
+        }
+    }
+
+    #[test]
+    fn test_stereo_sine_reads_its_level() {
+        // EBU Tech 3341 case 1
+        let gc = GarbageCollector::default();
+        let mut meter = meter(&gc);
+        process_sine(&mut meter, -23.0, 5.0);
+        let report = meter.report();
+        assert!((report.integrated + 23.0).abs() < 0.1, "{}", report);
+        assert!((meter.handle().momentary() + 23.0).abs() < 0.1);
+        assert!((meter.handle().short_term() + 23.0).abs() < 0.1);
+        assert!((report.true_peak + 23.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_quiet_passages_are_gated() {
+        // EBU Tech 3341 case 3, shortened
+        let gc = GarbageCollector::default();
+        let mut meter = meter(&gc);
+        process_sine(&mut meter, -36.0, 5.0);
+        process_sine(&mut meter, -23.0, 20.0);
+        process_sine(&mut meter, -36.0, 5.0);
+        let report = meter.report();
+        assert!((report.integrated + 23.0).abs() < 0.1, "{}", report);
+        assert!((report.max_momentary + 23.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_loudness_range() {
+        // EBU Tech 3342 case 1
+        let gc = GarbageCollector::default();
+        let mut meter = meter(&gc);
+        process_sine(&mut meter, -20.0, 20.0);
+        process_sine(&mut meter, -30.0, 20.0);
+        let report = meter.report();
+        assert!((report.loudness_range - 10.0).abs() < 1.0, "{}", report);
+    }
+
+    #[test]
+    fn test_channel_weights_are_kept_across_prepare() {
+        let gc = GarbageCollector::default();
+        let mut meter = LoudnessMeterProcessor::new(gc.handle());
+        meter.set_channel_weights(vec![1.0, 0.0]);
+        meter.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        process_sine(&mut meter, -23.0, 5.0);
+        // Only the left channel counts, so the sum is 3dB quieter
+        let report = meter.report();
+        assert!((report.integrated + 26.01).abs() < 0.1, "{}", report);
+    }
+
+    #[test]
+    fn test_short_channel_weights_are_padded_with_defaults() {
+        let gc = GarbageCollector::default();
+        let mut meter = LoudnessMeterProcessor::new(gc.handle());
+        meter.set_channel_weights(vec![1.0]);
+        meter.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        process_sine(&mut meter, -23.0, 5.0);
+        let report = meter.report();
+        assert!((report.integrated + 23.0).abs() < 0.1, "{}", report);
+    }
+
+    #[test]
+    fn test_handle_can_reset_the_measurements() {
+        let gc = GarbageCollector::default();
+        let mut meter = meter(&gc);
+        process_sine(&mut meter, -10.0, 1.0);
+        let handle = meter.handle().clone();
+        assert!(handle.report().integrated > -11.0);
+
+        handle.reset();
+        process_sine(&mut meter, -30.0, 1.0);
+        assert!((handle.report().integrated + 30.0).abs() < 0.1);
+        assert!(handle.report().true_peak < -29.0);
+    }
+}
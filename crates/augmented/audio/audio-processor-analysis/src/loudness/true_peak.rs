@@ -0,0 +1,78 @@
+use oversampling::half_band::Upsampler2x;
+use oversampling::OversamplingQuality;
+
+/// Estimates the peak of the continuous signal between samples, as in ITU-R BS.1770 annex 2, by
+/// upsampling to at least 192kHz & taking the largest absolute value.
+#[derive(Debug, Clone)]
+pub struct TruePeakDetector {
+    stages: Vec<Upsampler2x>,
+    peak: f32,
+}
+
+impl TruePeakDetector {
+    pub fn new(sample_rate: f32) -> Self {
+        let num_stages = if sample_rate < 96000.0 {
+            2
+        } else if sample_rate < 192000.0 {
+            1
+        } else {
+            0
+        };
+        let stages = (0..num_stages)
+            .map(|stage| Upsampler2x::new(OversamplingQuality::High.filter(stage)))
+            .collect();
+        TruePeakDetector { stages, peak: 0.0 }
+    }
+
+    /// The largest absolute value seen since the last reset
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    pub fn reset(&mut self) {
+        self.peak = 0.0;
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+
+    #[inline]
+    pub fn process1(&mut self, input: f32) {
+        self.peak = self.peak.max(input.abs());
+        match self.stages.as_mut_slice() {
+            [] => {}
+            [first] => {
+                for sample in first.process1(input).iter() {
+                    self.peak = self.peak.max(sample.abs());
+                }
+            }
+            [first, second, ..] => {
+                for sample in first.process1(input).iter() {
+                    for upsampled in second.process1(*sample).iter() {
+                        self.peak = self.peak.max(upsampled.abs());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_finds_peaks_between_samples() {
+        // A quarter of the sample rate, sampled 45 degrees off its peaks
+        let mut detector = TruePeakDetector::new(48000.0);
+        let mut sample_peak = 0.0_f32;
+        for i in 0..4800 {
+            let phase = std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4;
+            let sample = phase.sin();
+            sample_peak = sample_peak.max(sample.abs());
+            detector.process1(sample);
+        }
+        assert!(sample_peak < 0.71);
+        assert!(detector.peak() > 0.95, "{}", detector.peak());
+    }
+}
@@ -0,0 +1,555 @@
+//! Onset detection.
+//!
+//! [`SpectralFlux`] turns audio into a novelty function, which rises whenever energy appears in
+//! frequency bands that were quieter on the previous frame. [`PeakPicker`] finds onsets as the
+//! peaks of the novelty function above its recent mean.
+//!
+//! [`detect_onsets`] analyses a whole signal, e.g. a decoded file. [`OnsetDetectorProcessor`] runs
+//! in real-time, also estimating the tempo, & publishes what it finds on an
+//! [`OnsetDetectorHandle`].
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use audio_garbage_collector::{Handle, Shared};
+use audio_processor_traits::{AtomicF32, AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+use crate::hop_buffer::{downmix, HopBuffer};
+use crate::tempo::{TempoEstimate, TempoEstimator, TempoOptions};
+use crate::window::WindowFunction;
+
+/// Magnitudes are compressed with `log(1 + COMPRESSION * magnitude)`, so quiet onsets count
+const COMPRESSION: f32 = 100.0;
+/// Number of novelty frames the peak picker averages
+const MEAN_FRAMES: usize = 16;
+/// Seconds of novelty the processor estimates the tempo from
+const TEMPO_SECONDS: f32 = 8.0;
+
+/// Configuration of onset detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OnsetDetectorOptions {
+    /// Number of samples in each FFT. Rounded up to a power of 2, of at least 64
+    pub size: usize,
+    /// Fraction of each window shared with the next one, between 0 & 0.95
+    pub overlap: f32,
+    /// How far above its recent mean the novelty must peak for an onset
+    pub threshold: f32,
+    /// Shortest time between two onsets
+    pub min_interval: Duration,
+}
+
+impl Default for OnsetDetectorOptions {
+    fn default() -> Self {
+        OnsetDetectorOptions {
+            size: 1024,
+            overlap: 0.5,
+            threshold: 0.1,
+            min_interval: Duration::from_millis(50),
+        }
+    }
+}
+
+impl OnsetDetectorOptions {
+    /// The FFT size actually used
+    pub fn fft_size(&self) -> usize {
+        self.size.max(64).next_power_of_two()
+    }
+
+    /// Samples between the start of a window & the next
+    pub fn hop_size(&self) -> usize {
+        let overlap = self.overlap.clamp(0.0, 0.95);
+        ((self.fft_size() as f32 * (1.0 - overlap)).round() as usize).max(1)
+    }
+
+    /// Novelty values per second
+    pub fn frame_rate(&self, sample_rate: f32) -> f32 {
+        sample_rate / self.hop_size() as f32
+    }
+}
+
+/// Spectral flux novelty function. Allocates on construction only.
+pub struct SpectralFlux {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Scales FFT magnitudes so a full-scale sine reads 1
+    window_gain: f32,
+    input: HopBuffer,
+    fft_input: Vec<f32>,
+    fft_output: Vec<Complex<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    /// Compressed magnitudes of the previous frame
+    previous: Vec<f32>,
+}
+
+impl SpectralFlux {
+    pub fn new(options: &OnsetDetectorOptions) -> Self {
+        let fft_size = options.fft_size();
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        let window = WindowFunction::Hann.build(fft_size);
+        let window_gain = 2.0 / window.iter().sum::<f32>();
+
+        SpectralFlux {
+            window,
+            window_gain,
+            input: HopBuffer::new(fft_size, options.hop_size()),
+            fft_input: fft.make_input_vec(),
+            fft_output: fft.make_output_vec(),
+            fft_scratch: fft.make_scratch_vec(),
+            previous: vec![0.0; fft_size / 2 + 1],
+            fft,
+        }
+    }
+
+    pub fn hop_size(&self) -> usize {
+        self.input.hop_size()
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.input.window_size()
+    }
+
+    pub fn reset(&mut self) {
+        self.input.reset();
+        self.previous
+            .iter_mut()
+            .for_each(|magnitude| *magnitude = 0.0);
+    }
+
+    /// Push a sample. Returns the novelty of the last `fft_size` samples every `hop_size` samples.
+    #[inline]
+    pub fn process1(&mut self, sample: f32) -> Option<f32> {
+        if self.input.push(sample) {
+            Some(self.novelty())
+        } else {
+            None
+        }
+    }
+
+    fn novelty(&mut self) -> f32 {
+        for ((target, window), sample) in self
+            .fft_input
+            .iter_mut()
+            .zip(&self.window)
+            .zip(self.input.window())
+        {
+            *target = sample * window;
+        }
+        self.fft
+            .process_with_scratch(
+                &mut self.fft_input,
+                &mut self.fft_output,
+                &mut self.fft_scratch,
+            )
+            .expect("Forward FFT failed");
+
+        let mut flux = 0.0;
+        for (value, previous) in self.fft_output.iter().zip(&mut self.previous) {
+            let magnitude = (1.0 + COMPRESSION * value.norm() * self.window_gain).ln();
+            flux += (magnitude - *previous).max(0.0);
+            *previous = magnitude;
+        }
+        flux / self.previous.len() as f32
+    }
+}
+
+/// Finds onsets as peaks of a novelty function above its recent mean
+#[derive(Debug, Clone)]
+pub struct PeakPicker {
+    threshold: f32,
+    min_interval_frames: usize,
+    /// The last `MEAN_FRAMES` values, circular
+    history: [f32; MEAN_FRAMES],
+    history_position: usize,
+    /// The values 2 frames & 1 frame ago
+    previous: [f32; 2],
+    frames_since_onset: usize,
+}
+
+impl PeakPicker {
+    pub fn new(options: &OnsetDetectorOptions, frame_rate: f32) -> Self {
+        let min_interval_frames = (options.min_interval.as_secs_f32() * frame_rate).ceil() as usize;
+        PeakPicker {
+            threshold: options.threshold,
+            min_interval_frames,
+            history: [0.0; MEAN_FRAMES],
+            history_position: 0,
+            previous: [0.0; 2],
+            frames_since_onset: usize::MAX,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.history = [0.0; MEAN_FRAMES];
+        self.history_position = 0;
+        self.previous = [0.0; 2];
+        self.frames_since_onset = usize::MAX;
+    }
+
+    /// Push the next novelty value. Returns whether the previous one was an onset, since a peak is
+    /// only known once the novelty falls.
+    pub fn process(&mut self, novelty: f32) -> bool {
+        let [before, candidate] = self.previous;
+        self.history[self.history_position] = candidate;
+        self.history_position = (self.history_position + 1) % MEAN_FRAMES;
+        let mean = self.history.iter().sum::<f32>() / MEAN_FRAMES as f32;
+
+        self.frames_since_onset = self.frames_since_onset.saturating_add(1);
+        let is_onset = candidate > before
+            && candidate >= novelty
+            && candidate > mean + self.threshold
+            && self.frames_since_onset > self.min_interval_frames;
+        if is_onset {
+            self.frames_since_onset = 0;
+        }
+
+        self.previous = [candidate, novelty];
+        is_onset
+    }
+}
+
+/// Novelty function & onsets of a whole signal
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnsetAnalysis {
+    /// Novelty values per second
+    pub frame_rate: f32,
+    pub novelty: Vec<f32>,
+    /// Positions of the onsets, in samples
+    pub onsets: Vec<usize>,
+}
+
+/// Find the onsets of a mono signal
+pub fn detect_onsets(
+    samples: &[f32],
+    sample_rate: f32,
+    options: OnsetDetectorOptions,
+) -> OnsetAnalysis {
+    let mut flux = SpectralFlux::new(&options);
+    let frame_rate = options.frame_rate(sample_rate);
+    let mut peak_picker = PeakPicker::new(&options, frame_rate);
+    let hop_size = options.hop_size();
+    let half_window = options.fft_size() / 2;
+
+    let mut novelty = Vec::with_capacity(samples.len() / hop_size + 1);
+    let mut onsets = Vec::new();
+    for sample in samples {
+        if let Some(value) = flux.process1(*sample) {
+            if peak_picker.process(value) && !novelty.is_empty() {
+                onsets.push(frame_position(novelty.len() - 1, hop_size, half_window));
+            }
+            novelty.push(value);
+        }
+    }
+
+    OnsetAnalysis {
+        frame_rate,
+        novelty,
+        onsets,
+    }
+}
+
+/// Centre of the window of the novelty frame `frame`, in samples
+fn frame_position(frame: usize, hop_size: usize, half_window: usize) -> usize {
+    ((frame + 1) * hop_size).saturating_sub(half_window)
+}
+
+/// A shared "processor handle" to `OnsetDetectorProcessor`
+pub struct OnsetDetectorHandle {
+    novelty: AtomicF32,
+    onset_count: AtomicUsize,
+    last_onset: AtomicU64,
+    position: AtomicU64,
+    /// Zero when there's no estimate
+    bpm: AtomicF32,
+    tempo_confidence: AtomicF32,
+}
+
+impl Default for OnsetDetectorHandle {
+    fn default() -> Self {
+        OnsetDetectorHandle {
+            novelty: AtomicF32::new(0.0),
+            onset_count: AtomicUsize::new(0),
+            last_onset: AtomicU64::new(0),
+            position: AtomicU64::new(0),
+            bpm: AtomicF32::new(0.0),
+            tempo_confidence: AtomicF32::new(0.0),
+        }
+    }
+}
+
+impl OnsetDetectorHandle {
+    /// The last novelty value
+    pub fn novelty(&self) -> f32 {
+        self.novelty.get()
+    }
+
+    /// Number of onsets since the processor was prepared. Consumers may poll it to know whether
+    /// there's been a new one.
+    pub fn onset_count(&self) -> usize {
+        self.onset_count.load(Ordering::Acquire)
+    }
+
+    /// Position of the last onset, in samples since the processor was prepared
+    pub fn last_onset(&self) -> u64 {
+        self.last_onset.load(Ordering::Relaxed)
+    }
+
+    /// Number of samples processed since the processor was prepared
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// Tempo of the last few seconds, if there's one
+    pub fn tempo(&self) -> Option<TempoEstimate> {
+        let bpm = self.bpm.get();
+        if bpm <= 0.0 {
+            return None;
+        }
+        Some(TempoEstimate {
+            bpm,
+            confidence: self.tempo_confidence.get(),
+        })
+    }
+
+    fn clear(&self) {
+        self.novelty.set(0.0);
+        self.onset_count.store(0, Ordering::Relaxed);
+        self.last_onset.store(0, Ordering::Relaxed);
+        self.position.store(0, Ordering::Relaxed);
+        self.bpm.set(0.0);
+        self.tempo_confidence.set(0.0);
+    }
+}
+
+/// An `AudioProcessor` which detects onsets & estimates the tempo of its input, leaving it
+/// untouched.
+///
+/// The downmixed input goes through a [`SpectralFlux`] & a [`PeakPicker`], & once a second the
+/// tempo of the last 8 seconds is estimated, so the cost of each `process` call is bounded by the
+/// block size. Onsets are detected one hop late.
+///
+/// Everything is allocated on `prepare`.
+pub struct OnsetDetectorProcessor {
+    handle: Shared<OnsetDetectorHandle>,
+    options: OnsetDetectorOptions,
+    tempo_options: TempoOptions,
+    flux: Option<SpectralFlux>,
+    peak_picker: Option<PeakPicker>,
+    tempo_estimator: Option<TempoEstimator>,
+    position: u64,
+    frames_until_tempo: usize,
+    frames_per_tempo: usize,
+}
+
+impl OnsetDetectorProcessor {
+    pub fn new(
+        gc_handle: &Handle,
+        options: OnsetDetectorOptions,
+        tempo_options: TempoOptions,
+    ) -> Self {
+        OnsetDetectorProcessor {
+            handle: Shared::new(gc_handle, OnsetDetectorHandle::default()),
+            options,
+            tempo_options,
+            flux: None,
+            peak_picker: None,
+            tempo_estimator: None,
+            position: 0,
+            frames_until_tempo: 0,
+            frames_per_tempo: 1,
+        }
+    }
+
+    pub fn handle(&self) -> &Shared<OnsetDetectorHandle> {
+        &self.handle
+    }
+
+    pub fn options(&self) -> &OnsetDetectorOptions {
+        &self.options
+    }
+
+    /// Forget the input, the onsets & the tempo
+    pub fn reset(&mut self) {
+        if let Some(flux) = &mut self.flux {
+            flux.reset();
+        }
+        if let Some(peak_picker) = &mut self.peak_picker {
+            peak_picker.reset();
+        }
+        if let Some(tempo_estimator) = &mut self.tempo_estimator {
+            tempo_estimator.reset();
+        }
+        self.position = 0;
+        self.frames_until_tempo = self.frames_per_tempo;
+        self.handle.clear();
+    }
+
+    fn on_frame(&mut self, novelty: f32) {
+        if let Some(peak_picker) = &mut self.peak_picker {
+            if peak_picker.process(novelty) {
+                let hop_size = self.options.hop_size() as u64;
+                let half_window = self.options.fft_size() as u64 / 2;
+                // The onset was on the frame before this one
+                let onset = self.position.saturating_sub(hop_size + half_window);
+                self.handle.last_onset.store(onset, Ordering::Relaxed);
+                self.handle.onset_count.fetch_add(1, Ordering::Release);
+            }
+        }
+        self.handle.novelty.set(novelty);
+
+        if let Some(tempo_estimator) = &mut self.tempo_estimator {
+            tempo_estimator.push(novelty);
+            self.frames_until_tempo -= 1;
+            if self.frames_until_tempo == 0 {
+                self.frames_until_tempo = self.frames_per_tempo;
+                let (bpm, confidence) = tempo_estimator
+                    .estimate()
+                    .map_or((0.0, 0.0), |tempo| (tempo.bpm, tempo.confidence));
+                self.handle.bpm.set(bpm);
+                self.handle.tempo_confidence.set(confidence);
+            }
+        }
+    }
+}
+
+impl AudioProcessor for OnsetDetectorProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        let frame_rate = self.options.frame_rate(settings.sample_rate());
+        self.flux = Some(SpectralFlux::new(&self.options));
+        self.peak_picker = Some(PeakPicker::new(&self.options, frame_rate));
+        self.tempo_estimator = Some(TempoEstimator::new(
+            self.tempo_options,
+            frame_rate,
+            (TEMPO_SECONDS * frame_rate).round() as usize,
+        ));
+        self.frames_per_tempo = (frame_rate.round() as usize).max(1);
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        if self.flux.is_none() {
+            return;
+        }
+
+        for frame in data.frames() {
+            let mono = match downmix(frame) {
+                Some(mono) => mono,
+                None => continue,
+            };
+            self.position += 1;
+            let novelty = self.flux.as_mut().and_then(|flux| flux.process1(mono));
+            if let Some(novelty) = novelty {
+                self.on_frame(novelty);
+            }
+        }
+        self.handle.position.store(self.position, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_garbage_collector::GarbageCollector;
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use crate::tempo::estimate_tempo;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    /// Decaying noise bursts every beat of `bpm`, over a quiet tone
+    fn clicks(bpm: f32, seconds: f32) -> (Vec<f32>, Vec<usize>) {
+        let beat = (60.0 * SAMPLE_RATE / bpm) as usize;
+        let num_samples = (seconds * SAMPLE_RATE) as usize;
+        let mut seed = 1u32;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0
+        };
+        let samples = (0..num_samples)
+            .map(|i| {
+                let since_click = (i % beat) as f32 / SAMPLE_RATE;
+                let tone =
+                    0.05 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / SAMPLE_RATE).sin();
+                tone + 0.5 * noise() * (-since_click * 40.0).exp()
+            })
+            .collect();
+        let onsets = (0..num_samples).step_by(beat).collect();
+        (samples, onsets)
+    }
+
+    #[test]
+    fn test_clicks_are_detected_within_a_hop() {
+        let (samples, expected) = clicks(120.0, 5.0);
+        let options = OnsetDetectorOptions::default();
+        let analysis = detect_onsets(&samples, SAMPLE_RATE, options);
+
+        assert_eq!(
+            analysis.onsets.len(),
+            expected.len(),
+            "{:?}",
+            analysis.onsets
+        );
+        for (onset, expected) in analysis.onsets.iter().zip(&expected) {
+            let error = (*onset as i64 - *expected as i64).abs();
+            assert!(
+                error <= options.hop_size() as i64,
+                "{} != {}",
+                onset,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_steady_tone_has_no_onsets() {
+        let samples: Vec<f32> = (0..SAMPLE_RATE as usize * 3)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / SAMPLE_RATE).sin())
+            .collect();
+        let analysis = detect_onsets(&samples[4096..], SAMPLE_RATE, Default::default());
+        assert!(analysis.onsets.len() <= 1, "{:?}", analysis.onsets);
+    }
+
+    #[test]
+    fn test_tempo_of_a_click_track() {
+        for bpm in [90.0, 120.0, 135.0].iter() {
+            let (samples, _) = clicks(*bpm, 15.0);
+            let analysis = detect_onsets(&samples, SAMPLE_RATE, Default::default());
+            let tempo =
+                estimate_tempo(&analysis.novelty, analysis.frame_rate, Default::default()).unwrap();
+            assert!((tempo.bpm - bpm).abs() < 1.0, "{} != {}", tempo.bpm, bpm);
+        }
+    }
+
+    #[test]
+    fn test_processor_publishes_onsets_and_tempo() {
+        let gc = GarbageCollector::default();
+        let mut processor = OnsetDetectorProcessor::new(
+            gc.handle(),
+            OnsetDetectorOptions::default(),
+            TempoOptions::default(),
+        );
+        processor.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        let handle = processor.handle().clone();
+        assert!(handle.tempo().is_none());
+
+        let (samples, expected) = clicks(100.0, 10.0);
+        let mut samples: Vec<f32> = samples.iter().flat_map(|sample| vec![*sample; 2]).collect();
+        for block in samples.chunks_mut(2 * 512) {
+            processor.process(&mut InterleavedAudioBuffer::new(2, block));
+        }
+
+        assert_eq!(handle.position(), 10 * SAMPLE_RATE as u64);
+        assert_eq!(handle.onset_count(), expected.len());
+        let last_expected = *expected.last().unwrap() as i64;
+        assert!((handle.last_onset() as i64 - last_expected).abs() <= 512);
+        assert!((handle.tempo().unwrap().bpm - 100.0).abs() < 1.0);
+    }
+}
@@ -0,0 +1,538 @@
+//! Monophonic pitch detection.
+//!
+//! [`PitchDetector`] implements YIN (de Cheveigné & Kawahara, 2002), refining the period with
+//! parabolic interpolation. The difference function is computed from an FFT cross-correlation, so
+//! each analysis costs two forward FFTs & an inverse one, whatever the period range.
+//!
+//! [`PitchDetectorProcessor`] runs it every hop & publishes a [`PitchEstimate`] on a
+//! [`PitchDetectorHandle`], e.g. for a tuner.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use audio_garbage_collector::{Handle, Shared};
+use audio_processor_traits::{AtomicF32, AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+use crate::hop_buffer::{downmix, HopBuffer};
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Windows quieter than this mean square (-100dBFS) aren't analysed
+const SILENCE_POWER: f64 = 1e-10;
+
+/// Configuration of a [`PitchDetector`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchDetectorOptions {
+    /// Number of samples in each analysis. Rounded up to a power of 2, of at least 64. Periods of
+    /// up to half of it are detected.
+    pub size: usize,
+    /// Fraction of each analysis shared with the next one, between 0 & 0.95
+    pub overlap: f32,
+    /// YIN's threshold on the normalized difference function. The first period below it is taken,
+    /// lower values are less prone to octave errors but find a period less often.
+    pub threshold: f32,
+    /// Lowest frequency looked for, in Hz
+    pub min_frequency: f32,
+    /// Highest frequency looked for, in Hz
+    pub max_frequency: f32,
+    /// Frequency of A4, in Hz, used to find the nearest note
+    pub reference_pitch: f32,
+}
+
+impl Default for PitchDetectorOptions {
+    fn default() -> Self {
+        PitchDetectorOptions {
+            size: 4096,
+            overlap: 0.75,
+            threshold: 0.15,
+            min_frequency: 30.0,
+            max_frequency: 4000.0,
+            reference_pitch: 440.0,
+        }
+    }
+}
+
+impl PitchDetectorOptions {
+    /// The analysis size actually used
+    pub fn fft_size(&self) -> usize {
+        self.size.max(64).next_power_of_two()
+    }
+
+    /// Samples between the start of an analysis & the next
+    pub fn hop_size(&self) -> usize {
+        let overlap = self.overlap.clamp(0.0, 0.95);
+        ((self.fft_size() as f32 * (1.0 - overlap)).round() as usize).max(1)
+    }
+}
+
+/// A detected pitch & the note closest to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct PitchEstimate {
+    /// Fundamental frequency, in Hz
+    pub frequency: f32,
+    /// How periodic the input is, between 0 & 1. Below `1 - threshold` the input is likely noise
+    /// or a chord & the frequency meaningless.
+    pub confidence: f32,
+    /// MIDI note number of the nearest note
+    pub midi_note: u8,
+    /// Deviation from the nearest note, between -50 & 50 cents
+    pub cents: f32,
+}
+
+impl PitchEstimate {
+    pub fn new(frequency: f32, confidence: f32, reference_pitch: f32) -> Self {
+        let note = 69.0 + 12.0 * (frequency / reference_pitch).log2();
+        let nearest = note.round().clamp(0.0, 127.0);
+        PitchEstimate {
+            frequency,
+            confidence,
+            midi_note: nearest as u8,
+            cents: (100.0 * (note - nearest)).clamp(-50.0, 50.0),
+        }
+    }
+
+    /// Name & octave of the nearest note, e.g. "A4"
+    pub fn note_name(&self) -> String {
+        let octave = self.midi_note as i32 / 12 - 1;
+        format!("{}{}", NOTE_NAMES[self.midi_note as usize % 12], octave)
+    }
+}
+
+/// YIN pitch detector. Allocates on construction only.
+pub struct PitchDetector {
+    options: PitchDetectorOptions,
+    sample_rate: f32,
+    forward: Arc<dyn RealToComplex<f64>>,
+    inverse: Arc<dyn ComplexToReal<f64>>,
+    /// `power_prefix[i]` is the sum of the squares of the first `i` samples
+    power_prefix: Vec<f64>,
+    fft_input: Vec<f64>,
+    window_spectrum: Vec<Complex<f64>>,
+    input_spectrum: Vec<Complex<f64>>,
+    forward_scratch: Vec<Complex<f64>>,
+    inverse_scratch: Vec<Complex<f64>>,
+    correlation: Vec<f64>,
+    /// Difference function, `d(tau)`
+    difference: Vec<f64>,
+    /// Cumulative mean normalized difference function, `d'(tau)`
+    normalized_difference: Vec<f64>,
+}
+
+impl PitchDetector {
+    pub fn new(options: PitchDetectorOptions, sample_rate: f32) -> Self {
+        let fft_size = options.fft_size();
+        let mut planner = RealFftPlanner::<f64>::new();
+        let forward = planner.plan_fft_forward(fft_size);
+        let inverse = planner.plan_fft_inverse(fft_size);
+
+        PitchDetector {
+            options,
+            sample_rate,
+            power_prefix: vec![0.0; fft_size + 1],
+            fft_input: forward.make_input_vec(),
+            window_spectrum: forward.make_output_vec(),
+            input_spectrum: forward.make_output_vec(),
+            forward_scratch: forward.make_scratch_vec(),
+            inverse_scratch: inverse.make_scratch_vec(),
+            correlation: inverse.make_output_vec(),
+            difference: vec![0.0; fft_size / 2 + 1],
+            normalized_difference: vec![0.0; fft_size / 2 + 1],
+            forward,
+            inverse,
+        }
+    }
+
+    pub fn options(&self) -> &PitchDetectorOptions {
+        &self.options
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Estimate the pitch of the last `fft_size` samples of `samples`. Returns `None` if there are
+    /// fewer, if they're silent or if no period in range is found.
+    pub fn detect(&mut self, samples: &[f32]) -> Option<PitchEstimate> {
+        let fft_size = self.options.fft_size();
+        if samples.len() < fft_size {
+            return None;
+        }
+        let samples = &samples[samples.len() - fft_size..];
+        let window_size = fft_size / 2;
+
+        for (index, sample) in samples.iter().enumerate() {
+            let sample = *sample as f64;
+            self.power_prefix[index + 1] = self.power_prefix[index] + sample * sample;
+        }
+        if self.power_prefix[fft_size] / (fft_size as f64) < SILENCE_POWER {
+            return None;
+        }
+
+        self.cross_correlate(samples);
+
+        // d(tau) = sum((x[j] - x[j + tau])^2) = p(0..w) + p(tau..tau + w) - 2 r(tau)
+        let max_tau = ((self.sample_rate / self.options.min_frequency.max(1.0)) as usize)
+            .min(window_size - 1);
+        let min_tau = ((self.sample_rate / self.options.max_frequency.max(1.0)) as usize).max(2);
+        if min_tau >= max_tau {
+            return None;
+        }
+        let window_power = self.power_prefix[window_size];
+        let mut running_sum = 0.0;
+        self.difference[0] = 0.0;
+        self.normalized_difference[0] = 1.0;
+        for tau in 1..=max_tau + 1 {
+            let lagged_power = self.power_prefix[tau + window_size] - self.power_prefix[tau];
+            let difference = (window_power + lagged_power - 2.0 * self.correlation[tau]).max(0.0);
+            running_sum += difference;
+            self.difference[tau] = difference;
+            self.normalized_difference[tau] = if running_sum > 0.0 {
+                difference * tau as f64 / running_sum
+            } else {
+                1.0
+            };
+        }
+
+        let tau = self.find_period(min_tau, max_tau);
+        let shift = self.interpolate(tau);
+        let frequency = self.sample_rate / (tau as f32 + shift);
+        let confidence = (1.0 - self.normalized_difference[tau] as f32).clamp(0.0, 1.0);
+        Some(PitchEstimate::new(
+            frequency,
+            confidence,
+            self.options.reference_pitch,
+        ))
+    }
+
+    /// Fill `correlation` with `r(tau) = sum(x[j] * x[j + tau])` over the first half of `samples`
+    fn cross_correlate(&mut self, samples: &[f32]) {
+        let fft_size = samples.len();
+        let window_size = fft_size / 2;
+
+        for (index, target) in self.fft_input.iter_mut().enumerate() {
+            *target = if index < window_size {
+                samples[index] as f64
+            } else {
+                0.0
+            };
+        }
+        self.forward
+            .process_with_scratch(
+                &mut self.fft_input,
+                &mut self.window_spectrum,
+                &mut self.forward_scratch,
+            )
+            .expect("Forward FFT failed");
+
+        for (target, sample) in self.fft_input.iter_mut().zip(samples) {
+            *target = *sample as f64;
+        }
+        self.forward
+            .process_with_scratch(
+                &mut self.fft_input,
+                &mut self.input_spectrum,
+                &mut self.forward_scratch,
+            )
+            .expect("Forward FFT failed");
+
+        // The window is zero-padded to twice its length, so the circular correlation doesn't wrap
+        let scale = 1.0 / fft_size as f64;
+        for (input, window) in self.input_spectrum.iter_mut().zip(&self.window_spectrum) {
+            *input = window.conj() * *input * scale;
+        }
+        let last_bin = self.input_spectrum.len() - 1;
+        self.input_spectrum[0].im = 0.0;
+        self.input_spectrum[last_bin].im = 0.0;
+        self.inverse
+            .process_with_scratch(
+                &mut self.input_spectrum,
+                &mut self.correlation,
+                &mut self.inverse_scratch,
+            )
+            .expect("Inverse FFT failed");
+    }
+
+    /// The first local minimum below the threshold, or the global minimum if there's none
+    fn find_period(&self, min_tau: usize, max_tau: usize) -> usize {
+        let threshold = self.options.threshold as f64;
+        let mut best_tau = min_tau;
+        let mut tau = min_tau;
+        while tau <= max_tau {
+            if self.normalized_difference[tau] < threshold {
+                while tau < max_tau
+                    && self.normalized_difference[tau + 1] < self.normalized_difference[tau]
+                {
+                    tau += 1;
+                }
+                return tau;
+            }
+            if self.normalized_difference[tau] < self.normalized_difference[best_tau] {
+                best_tau = tau;
+            }
+            tau += 1;
+        }
+        best_tau
+    }
+
+    /// Offset of the minimum of the parabola through `d(tau)` & its neighbours, between -1 & 1.
+    /// The raw difference function is used, as normalizing it skews short periods.
+    fn interpolate(&self, tau: usize) -> f32 {
+        let previous = self.difference[tau - 1];
+        let current = self.difference[tau];
+        let next = self.difference[tau + 1];
+        let curvature = previous - 2.0 * current + next;
+        if curvature <= 0.0 {
+            return 0.0;
+        }
+        (0.5 * (previous - next) / curvature).clamp(-1.0, 1.0) as f32
+    }
+}
+
+/// A shared "processor handle" to `PitchDetectorProcessor`
+pub struct PitchDetectorHandle {
+    /// Zero when there's no estimate
+    frequency: AtomicF32,
+    confidence: AtomicF32,
+    reference_pitch: AtomicF32,
+    frame_count: AtomicUsize,
+}
+
+impl PitchDetectorHandle {
+    fn new(reference_pitch: f32) -> Self {
+        PitchDetectorHandle {
+            frequency: AtomicF32::new(0.0),
+            confidence: AtomicF32::new(0.0),
+            reference_pitch: AtomicF32::new(reference_pitch),
+            frame_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of analyses since the processor was prepared
+    pub fn frame_count(&self) -> usize {
+        self.frame_count.load(Ordering::Acquire)
+    }
+
+    /// The last detected pitch, or `None` if the input was silent or had no period in range
+    pub fn estimate(&self) -> Option<PitchEstimate> {
+        let frequency = self.frequency.get();
+        if frequency <= 0.0 {
+            return None;
+        }
+        Some(PitchEstimate::new(
+            frequency,
+            self.confidence.get(),
+            self.reference_pitch.get(),
+        ))
+    }
+
+    fn publish(&self, estimate: Option<PitchEstimate>) {
+        let (frequency, confidence) = estimate.map_or((0.0, 0.0), |estimate| {
+            (estimate.frequency, estimate.confidence)
+        });
+        self.frequency.set(frequency);
+        self.confidence.set(confidence);
+        self.frame_count.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// An `AudioProcessor` which tracks the pitch of its input, leaving it untouched.
+///
+/// The downmixed input is split into windows by a [`HopBuffer`] & each is run through a
+/// [`PitchDetector`], whose estimate is published on the [`PitchDetectorHandle`].
+///
+/// Everything is allocated on `prepare`.
+pub struct PitchDetectorProcessor {
+    handle: Shared<PitchDetectorHandle>,
+    options: PitchDetectorOptions,
+    detector: Option<PitchDetector>,
+    input: HopBuffer,
+    /// `input`'s window, for the detector
+    window: Vec<f32>,
+}
+
+impl PitchDetectorProcessor {
+    pub fn new(gc_handle: &Handle, options: PitchDetectorOptions) -> Self {
+        PitchDetectorProcessor {
+            handle: Shared::new(gc_handle, PitchDetectorHandle::new(options.reference_pitch)),
+            options,
+            detector: None,
+            input: HopBuffer::default(),
+            window: Vec::new(),
+        }
+    }
+
+    pub fn handle(&self) -> &Shared<PitchDetectorHandle> {
+        &self.handle
+    }
+
+    pub fn options(&self) -> &PitchDetectorOptions {
+        &self.options
+    }
+
+    /// Forget the input
+    pub fn reset(&mut self) {
+        self.input.reset();
+        self.handle.frequency.set(0.0);
+        self.handle.confidence.set(0.0);
+    }
+
+    fn analyse(&mut self) {
+        let detector = match &mut self.detector {
+            Some(detector) => detector,
+            None => return,
+        };
+
+        for (target, sample) in self.window.iter_mut().zip(self.input.window()) {
+            *target = *sample;
+        }
+        self.handle.publish(detector.detect(&self.window));
+    }
+}
+
+impl AudioProcessor for PitchDetectorProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        let fft_size = self.options.fft_size();
+        self.detector = Some(PitchDetector::new(self.options, settings.sample_rate()));
+        self.input = HopBuffer::new(fft_size, self.options.hop_size());
+        self.window = vec![0.0; fft_size];
+        self.handle
+            .reference_pitch
+            .set(self.options.reference_pitch);
+        self.handle.frame_count.store(0, Ordering::Relaxed);
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        if self.detector.is_none() {
+            return;
+        }
+
+        for frame in data.frames() {
+            if let Some(sample) = downmix(frame) {
+                if self.input.push(sample) {
+                    self.analyse();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_garbage_collector::GarbageCollector;
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    fn sine(frequency: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    fn cents(frequency: f32, expected: f32) -> f32 {
+        1200.0 * (frequency / expected).log2()
+    }
+
+    #[test]
+    fn test_sines_are_detected_within_a_cent() {
+        let mut detector = PitchDetector::new(PitchDetectorOptions::default(), SAMPLE_RATE);
+        for frequency in [41.2, 82.41, 196.0, 440.0, 1046.5, 3520.0].iter() {
+            let estimate = detector.detect(&sine(*frequency, 4096)).unwrap();
+            assert!(
+                cents(estimate.frequency, *frequency).abs() < 1.0,
+                "{} != {}",
+                estimate.frequency,
+                frequency
+            );
+            assert!(estimate.confidence > 0.95);
+        }
+    }
+
+    #[test]
+    fn test_harmonics_dont_cause_octave_errors() {
+        // A band-limited sawtooth, with a strong 2nd harmonic
+        let frequency = 110.0;
+        let samples: Vec<f32> = (0..4096)
+            .map(|i| {
+                (1..20)
+                    .map(|harmonic| {
+                        let phase = 2.0 * std::f32::consts::PI * frequency * harmonic as f32;
+                        (phase * i as f32 / SAMPLE_RATE).sin() / harmonic as f32
+                    })
+                    .sum::<f32>()
+                    * 0.3
+            })
+            .collect();
+        let mut detector = PitchDetector::new(PitchDetectorOptions::default(), SAMPLE_RATE);
+        let estimate = detector.detect(&samples).unwrap();
+        assert!(cents(estimate.frequency, frequency).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_silence_has_no_pitch() {
+        let mut detector = PitchDetector::new(PitchDetectorOptions::default(), SAMPLE_RATE);
+        assert!(detector.detect(&vec![0.0; 4096]).is_none());
+        assert!(detector.detect(&vec![0.1; 100]).is_none());
+    }
+
+    #[test]
+    fn test_nearest_note_and_cents() {
+        let estimate = PitchEstimate::new(440.0, 1.0, 440.0);
+        assert_eq!(estimate.midi_note, 69);
+        assert_eq!(estimate.note_name(), "A4");
+        assert!(estimate.cents.abs() < 1e-3);
+
+        let estimate = PitchEstimate::new(445.0, 1.0, 440.0);
+        assert_eq!(estimate.midi_note, 69);
+        assert!((estimate.cents - 19.56).abs() < 0.01);
+
+        let estimate = PitchEstimate::new(255.0, 1.0, 440.0);
+        assert_eq!(estimate.note_name(), "C4");
+        assert!((estimate.cents + 44.4).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_processor_publishes_estimates() {
+        let gc = GarbageCollector::default();
+        let mut processor =
+            PitchDetectorProcessor::new(gc.handle(), PitchDetectorOptions::default());
+        processor.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        let handle = processor.handle().clone();
+        assert!(handle.estimate().is_none());
+
+        let mut samples: Vec<f32> = sine(329.63, 8192)
+            .iter()
+            .flat_map(|sample| vec![*sample; 2])
+            .collect();
+        for block in samples.chunks_mut(2 * 512) {
+            processor.process(&mut InterleavedAudioBuffer::new(2, block));
+        }
+        assert_eq!(handle.frame_count(), 8192 / 1024);
+        let estimate = handle.estimate().unwrap();
+        assert_eq!(estimate.note_name(), "E4");
+        assert!(estimate.cents.abs() < 1.0);
+
+        let mut silence = vec![0.0; 2 * 8192];
+        for block in silence.chunks_mut(2 * 512) {
+            processor.process(&mut InterleavedAudioBuffer::new(2, block));
+        }
+        assert!(handle.estimate().is_none());
+    }
+}
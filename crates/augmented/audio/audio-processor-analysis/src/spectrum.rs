@@ -0,0 +1,495 @@
+//! FFT spectrum analyzer.
+//!
+//! [`SpectrumAnalyzerProcessor`] windows the input, runs an FFT every hop & publishes the magnitude
+//! of each bin on a [`SpectrumAnalyzerHandle`], which GUIs may poll from any thread.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use audio_garbage_collector::{Handle, Shared, SharedCell};
+use audio_processor_traits::{AtomicF32, AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+use crate::hop_buffer::{downmix, HopBuffer};
+use crate::window::WindowFunction;
+
+/// Magnitudes below this are reported as this, in dB
+pub const MIN_DB: f32 = -120.0;
+
+/// Configuration of a [`SpectrumAnalyzerProcessor`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumAnalyzerOptions {
+    /// Number of samples in each FFT. Rounded up to a power of 2, of at least 16
+    pub size: usize,
+    /// Fraction of each window shared with the next one, between 0 & 0.95
+    pub overlap: f32,
+    pub window: WindowFunction,
+    /// Time constant of the exponential average of the magnitudes. Zero publishes each frame as is
+    pub averaging: Duration,
+    /// How long peaks are held before falling
+    pub peak_hold: Duration,
+    /// How fast peaks fall once they've been held, in dB per second
+    pub peak_decay: f32,
+}
+
+impl Default for SpectrumAnalyzerOptions {
+    fn default() -> Self {
+        SpectrumAnalyzerOptions {
+            size: 2048,
+            overlap: 0.5,
+            window: WindowFunction::default(),
+            averaging: Duration::from_millis(100),
+            peak_hold: Duration::from_secs(1),
+            peak_decay: 12.0,
+        }
+    }
+}
+
+impl SpectrumAnalyzerOptions {
+    /// The FFT size actually used
+    pub fn fft_size(&self) -> usize {
+        self.size.max(16).next_power_of_two()
+    }
+
+    /// Samples between the start of a window & the next
+    pub fn hop_size(&self) -> usize {
+        let overlap = self.overlap.clamp(0.0, 0.95);
+        ((self.fft_size() as f32 * (1.0 - overlap)).round() as usize).max(1)
+    }
+
+    /// Number of magnitudes in each frame, from DC to the Nyquist frequency
+    pub fn num_bins(&self) -> usize {
+        self.fft_size() / 2 + 1
+    }
+}
+
+/// A copy of the last published frame, see [`SpectrumAnalyzerHandle::frame`]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct SpectrumFrame {
+    pub sample_rate: f32,
+    pub frame_count: usize,
+    pub magnitudes_db: Vec<f32>,
+    pub peaks_db: Vec<f32>,
+}
+
+impl SpectrumFrame {
+    pub fn num_bins(&self) -> usize {
+        self.magnitudes_db.len()
+    }
+
+    /// Centre frequency of `bin`, in Hz
+    pub fn bin_frequency(&self, bin: usize) -> f32 {
+        bin_frequency(bin, self.num_bins(), self.sample_rate)
+    }
+}
+
+fn bin_frequency(bin: usize, num_bins: usize, sample_rate: f32) -> f32 {
+    if num_bins < 2 {
+        return 0.0;
+    }
+    bin as f32 * sample_rate / ((num_bins - 1) * 2) as f32
+}
+
+/// A shared "processor handle" to `SpectrumAnalyzerProcessor`
+pub struct SpectrumAnalyzerHandle {
+    magnitudes_db: SharedCell<Vec<AtomicF32>>,
+    peaks_db: SharedCell<Vec<AtomicF32>>,
+    sample_rate: AtomicF32,
+    frame_count: AtomicUsize,
+}
+
+impl SpectrumAnalyzerHandle {
+    /// Create a new handle with empty buffers
+    fn new(gc_handle: &Handle) -> Self {
+        SpectrumAnalyzerHandle {
+            magnitudes_db: SharedCell::new(Shared::new(gc_handle, Vec::new())),
+            peaks_db: SharedCell::new(Shared::new(gc_handle, Vec::new())),
+            sample_rate: AtomicF32::new(0.0),
+            frame_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Replace the buffers with silent ones of `num_bins`
+    fn resize(&self, gc_handle: &Handle, num_bins: usize, sample_rate: f32) {
+        let silence = || (0..num_bins).map(|_| AtomicF32::new(MIN_DB)).collect();
+        self.magnitudes_db
+            .replace(Shared::new(gc_handle, silence()));
+        self.peaks_db.replace(Shared::new(gc_handle, silence()));
+        self.sample_rate.set(sample_rate);
+        self.frame_count.store(0, Ordering::Relaxed);
+    }
+
+    pub fn num_bins(&self) -> usize {
+        self.magnitudes_db.get().len()
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate.get()
+    }
+
+    /// Centre frequency of `bin`, in Hz
+    pub fn bin_frequency(&self, bin: usize) -> f32 {
+        bin_frequency(bin, self.num_bins(), self.sample_rate())
+    }
+
+    /// Number of frames published since the processor was prepared. GUIs may poll it to know
+    /// whether there's a new frame.
+    pub fn frame_count(&self) -> usize {
+        self.frame_count.load(Ordering::Acquire)
+    }
+
+    /// Averaged magnitude of `bin`, in dB relative to a full-scale sine
+    pub fn magnitude_db(&self, bin: usize) -> f32 {
+        self.magnitudes_db
+            .get()
+            .get(bin)
+            .map_or(MIN_DB, |magnitude| magnitude.get())
+    }
+
+    /// Held peak of `bin`, in dB relative to a full-scale sine
+    pub fn peak_db(&self, bin: usize) -> f32 {
+        self.peaks_db
+            .get()
+            .get(bin)
+            .map_or(MIN_DB, |peak| peak.get())
+    }
+
+    /// Copy the magnitudes of every bin into `target`. Doesn't allocate if `target` has the
+    /// capacity for them.
+    pub fn read_magnitudes_db(&self, target: &mut Vec<f32>) {
+        target.clear();
+        target.extend(
+            self.magnitudes_db
+                .get()
+                .iter()
+                .map(|magnitude| magnitude.get()),
+        );
+    }
+
+    /// Copy the held peaks of every bin into `target`
+    pub fn read_peaks_db(&self, target: &mut Vec<f32>) {
+        target.clear();
+        target.extend(self.peaks_db.get().iter().map(|peak| peak.get()));
+    }
+
+    /// Copy the last frame, e.g. to send it to a web editor
+    pub fn frame(&self) -> SpectrumFrame {
+        let mut frame = SpectrumFrame {
+            sample_rate: self.sample_rate(),
+            frame_count: self.frame_count(),
+            ..SpectrumFrame::default()
+        };
+        self.read_magnitudes_db(&mut frame.magnitudes_db);
+        self.read_peaks_db(&mut frame.peaks_db);
+        frame
+    }
+}
+
+/// An `AudioProcessor` which measures the spectrum of its input, leaving it untouched.
+///
+/// The downmixed input is split into windows by a [`HopBuffer`], each of which is tapered &
+/// transformed. Magnitudes are exponentially averaged & peaks are held, then published on the
+/// [`SpectrumAnalyzerHandle`].
+///
+/// Everything is allocated on `prepare`. When the handle's buffers need to be resized, they're
+/// replaced via an atomic pointer swap.
+pub struct SpectrumAnalyzerProcessor {
+    handle: Shared<SpectrumAnalyzerHandle>,
+    gc_handle: Handle,
+    options: SpectrumAnalyzerOptions,
+    sample_rate: f32,
+    fft: Option<Arc<dyn RealToComplex<f32>>>,
+    window: Vec<f32>,
+    /// Scales FFT magnitudes so a full-scale sine reads 0 dB
+    window_gain: f32,
+    input: HopBuffer,
+    fft_input: Vec<f32>,
+    fft_output: Vec<Complex<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    averaged_power: Vec<f32>,
+    peaks_db: Vec<f32>,
+    /// Seconds since each peak was set
+    peak_ages: Vec<f32>,
+}
+
+impl SpectrumAnalyzerProcessor {
+    pub fn new(gc_handle: &Handle, options: SpectrumAnalyzerOptions) -> Self {
+        SpectrumAnalyzerProcessor {
+            handle: Shared::new(gc_handle, SpectrumAnalyzerHandle::new(gc_handle)),
+            gc_handle: gc_handle.clone(),
+            options,
+            sample_rate: AudioProcessorSettings::default().sample_rate(),
+            fft: None,
+            window: Vec::new(),
+            window_gain: 1.0,
+            input: HopBuffer::default(),
+            fft_input: Vec::new(),
+            fft_output: Vec::new(),
+            fft_scratch: Vec::new(),
+            averaged_power: Vec::new(),
+            peaks_db: Vec::new(),
+            peak_ages: Vec::new(),
+        }
+    }
+
+    pub fn handle(&self) -> &Shared<SpectrumAnalyzerHandle> {
+        &self.handle
+    }
+
+    pub fn options(&self) -> &SpectrumAnalyzerOptions {
+        &self.options
+    }
+
+    /// Change the options. Re-allocates the buffers if the processor was prepared, so it shouldn't
+    /// be called on the audio thread.
+    pub fn set_options(&mut self, options: SpectrumAnalyzerOptions) {
+        self.options = options;
+        if self.fft.is_some() {
+            self.allocate();
+        }
+    }
+
+    /// Forget the input & the averaged magnitudes
+    pub fn reset(&mut self) {
+        self.input.reset();
+        self.averaged_power
+            .iter_mut()
+            .for_each(|power| *power = 0.0);
+        self.peaks_db.iter_mut().for_each(|peak| *peak = MIN_DB);
+        self.peak_ages.iter_mut().for_each(|age| *age = 0.0);
+    }
+
+    fn allocate(&mut self) {
+        let fft_size = self.options.fft_size();
+        let num_bins = self.options.num_bins();
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(fft_size);
+
+        self.window = self.options.window.build(fft_size);
+        self.window_gain = 2.0 / self.window.iter().sum::<f32>();
+        self.input = HopBuffer::new(fft_size, self.options.hop_size());
+        self.fft_input = fft.make_input_vec();
+        self.fft_output = fft.make_output_vec();
+        self.fft_scratch = fft.make_scratch_vec();
+        self.averaged_power = vec![0.0; num_bins];
+        self.peaks_db = vec![MIN_DB; num_bins];
+        self.peak_ages = vec![0.0; num_bins];
+        self.fft = Some(fft);
+        self.reset();
+
+        self.handle
+            .resize(&self.gc_handle, num_bins, self.sample_rate);
+    }
+
+    fn analyse(&mut self) {
+        let fft = match &self.fft {
+            Some(fft) => fft,
+            None => return,
+        };
+
+        for ((target, window), sample) in self
+            .fft_input
+            .iter_mut()
+            .zip(&self.window)
+            .zip(self.input.window())
+        {
+            *target = sample * window;
+        }
+        fft.process_with_scratch(
+            &mut self.fft_input,
+            &mut self.fft_output,
+            &mut self.fft_scratch,
+        )
+        .expect("Forward FFT failed");
+
+        let hop_seconds = self.options.hop_size() as f32 / self.sample_rate;
+        let averaging = self.options.averaging.as_secs_f32();
+        let smoothing = if averaging > 0.0 {
+            (-hop_seconds / averaging).exp()
+        } else {
+            0.0
+        };
+        let peak_hold = self.options.peak_hold.as_secs_f32();
+        let peak_fall = self.options.peak_decay * hop_seconds;
+
+        let magnitudes_db = self.handle.magnitudes_db.get();
+        let published_peaks_db = self.handle.peaks_db.get();
+        let last_bin = self.fft_output.len() - 1;
+        for (bin, value) in self.fft_output.iter().enumerate() {
+            // DC & Nyquist have no mirror image, so they aren't doubled
+            let scale = if bin == 0 || bin == last_bin {
+                self.window_gain / 2.0
+            } else {
+                self.window_gain
+            };
+            let amplitude = value.norm() * scale;
+            let power = &mut self.averaged_power[bin];
+            *power = smoothing * *power + (1.0 - smoothing) * amplitude * amplitude;
+            let magnitude_db = (10.0 * power.log10()).max(MIN_DB);
+
+            let peak_db = &mut self.peaks_db[bin];
+            let peak_age = &mut self.peak_ages[bin];
+            if magnitude_db >= *peak_db {
+                *peak_db = magnitude_db;
+                *peak_age = 0.0;
+            } else {
+                *peak_age += hop_seconds;
+                if *peak_age > peak_hold {
+                    *peak_db = (*peak_db - peak_fall).max(magnitude_db);
+                }
+            }
+
+            if let Some(published) = magnitudes_db.get(bin) {
+                published.set(magnitude_db);
+            }
+            if let Some(published) = published_peaks_db.get(bin) {
+                published.set(*peak_db);
+            }
+        }
+        self.handle.frame_count.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl AudioProcessor for SpectrumAnalyzerProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.allocate();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        if self.fft.is_none() {
+            return;
+        }
+
+        for frame in data.frames() {
+            if let Some(sample) = downmix(frame) {
+                if self.input.push(sample) {
+                    self.analyse();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_garbage_collector::GarbageCollector;
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    fn sine(frequency: f32, amplitude: f32, num_frames: usize) -> Vec<f32> {
+        (0..num_frames)
+            .flat_map(|i| {
+                let value =
+                    amplitude * (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin();
+                vec![value; 2]
+            })
+            .collect()
+    }
+
+    fn loudest_bin(handle: &SpectrumAnalyzerHandle) -> usize {
+        let mut magnitudes = Vec::new();
+        handle.read_magnitudes_db(&mut magnitudes);
+        (0..magnitudes.len())
+            .max_by(|a, b| magnitudes[*a].partial_cmp(&magnitudes[*b]).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sine_peaks_at_its_frequency() {
+        let gc = GarbageCollector::default();
+        let options = SpectrumAnalyzerOptions {
+            averaging: Duration::from_secs(0),
+            ..SpectrumAnalyzerOptions::default()
+        };
+        let mut analyzer = SpectrumAnalyzerProcessor::new(gc.handle(), options);
+        analyzer.prepare(AudioProcessorSettings::new(44100.0, 2, 2, 512));
+        let handle = analyzer.handle().clone();
+        assert_eq!(handle.num_bins(), 1025);
+
+        // Exactly on bin 93, so there's no scalloping loss
+        let frequency = handle.bin_frequency(93);
+        let mut samples = sine(frequency, 0.5, 8192);
+        analyzer.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+
+        assert_eq!(loudest_bin(&handle), 93);
+        let expected_db = 20.0 * 0.5_f32.log10();
+        assert!((handle.magnitude_db(93) - expected_db).abs() < 0.1);
+        assert!(handle.magnitude_db(400) < -80.0);
+    }
+
+    #[test]
+    fn test_frames_are_published_every_hop() {
+        let gc = GarbageCollector::default();
+        let options = SpectrumAnalyzerOptions {
+            size: 1024,
+            overlap: 0.75,
+            ..SpectrumAnalyzerOptions::default()
+        };
+        let mut analyzer = SpectrumAnalyzerProcessor::new(gc.handle(), options);
+        analyzer.prepare(AudioProcessorSettings::default());
+        let mut samples = sine(1000.0, 1.0, 2560);
+        analyzer.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+        assert_eq!(analyzer.handle().frame_count(), 10);
+    }
+
+    #[test]
+    fn test_peaks_are_held_then_fall() {
+        let gc = GarbageCollector::default();
+        let options = SpectrumAnalyzerOptions {
+            averaging: Duration::from_secs(0),
+            peak_hold: Duration::from_millis(300),
+            peak_decay: 20.0,
+            ..SpectrumAnalyzerOptions::default()
+        };
+        let mut analyzer = SpectrumAnalyzerProcessor::new(gc.handle(), options);
+        analyzer.prepare(AudioProcessorSettings::default());
+        let handle = analyzer.handle().clone();
+        let bin = 50;
+        let mut samples = sine(handle.bin_frequency(bin), 1.0, 8192);
+        analyzer.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+        let peak = handle.peak_db(bin);
+
+        // Within the hold time the peak stays while the magnitude drops
+        let mut silence = vec![0.0; 2 * 4096];
+        analyzer.process(&mut InterleavedAudioBuffer::new(2, &mut silence));
+        assert_eq!(handle.magnitude_db(bin), MIN_DB);
+        assert_eq!(handle.peak_db(bin), peak);
+
+        // A second later it has fallen by about 20dB/s for the time past the hold
+        let mut silence = vec![0.0; 2 * 44100];
+        analyzer.process(&mut InterleavedAudioBuffer::new(2, &mut silence));
+        let fallen = peak - handle.peak_db(bin);
+        assert!(fallen > 10.0 && fallen < 20.0, "fell {}dB", fallen);
+    }
+
+    #[test]
+    fn test_averaging_smooths_changes() {
+        let gc = GarbageCollector::default();
+        let options = SpectrumAnalyzerOptions {
+            averaging: Duration::from_secs(1),
+            ..SpectrumAnalyzerOptions::default()
+        };
+        let mut analyzer = SpectrumAnalyzerProcessor::new(gc.handle(), options);
+        analyzer.prepare(AudioProcessorSettings::default());
+        let handle = analyzer.handle().clone();
+        let bin = 50;
+        let mut samples = sine(handle.bin_frequency(bin), 1.0, 4096);
+        analyzer.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+        // A tenth of a second in, the average is still well below the 0dB sine
+        assert!(handle.magnitude_db(bin) < -6.0);
+        assert!(handle.magnitude_db(bin) > MIN_DB);
+    }
+}
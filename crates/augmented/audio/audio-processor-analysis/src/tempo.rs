@@ -0,0 +1,250 @@
+//! Tempo estimation.
+//!
+//! The tempo is found from the autocorrelation of an onset envelope, such as the novelty function
+//! of [`crate::onset::SpectralFlux`]. Each lag in the tempo range is weighted by a log-normal prior
+//! around `preferred_bpm`, which resolves the ambiguity between a tempo & its double or half. Fast
+//! material may still be reported at half its tempo, unless `preferred_bpm` is raised.
+//!
+//! [`estimate_tempo`] analyses a whole envelope, e.g. from [`crate::onset::detect_onsets`], while
+//! [`TempoEstimator`] keeps track of the last few seconds of a live one.
+
+/// Width of the tempo prior, in octaves
+const PRIOR_WIDTH: f32 = 1.0;
+
+/// Configuration of tempo estimation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoOptions {
+    /// Slowest tempo looked for, in beats per minute
+    pub min_bpm: f32,
+    /// Fastest tempo looked for, in beats per minute
+    pub max_bpm: f32,
+    /// Tempo preferred when the envelope fits several, in beats per minute
+    pub preferred_bpm: f32,
+}
+
+impl Default for TempoOptions {
+    fn default() -> Self {
+        TempoOptions {
+            min_bpm: 60.0,
+            max_bpm: 200.0,
+            preferred_bpm: 120.0,
+        }
+    }
+}
+
+/// A detected tempo
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct TempoEstimate {
+    /// Beats per minute
+    pub bpm: f32,
+    /// Normalized autocorrelation of the envelope at the beat period, between 0 & 1
+    pub confidence: f32,
+}
+
+impl TempoEstimate {
+    /// Duration of a beat, in samples at `sample_rate`
+    pub fn beat_samples(&self, sample_rate: f32) -> f32 {
+        60.0 * sample_rate / self.bpm
+    }
+}
+
+/// Estimate the tempo of `envelope`, an onset envelope with `frame_rate` values per second.
+/// Returns `None` if it's flat or shorter than two periods of `min_bpm`.
+pub fn estimate_tempo(
+    envelope: &[f32],
+    frame_rate: f32,
+    options: TempoOptions,
+) -> Option<TempoEstimate> {
+    let mut correlation = Vec::new();
+    estimate_with(envelope, frame_rate, &options, &mut correlation)
+}
+
+/// [`estimate_tempo`], with `correlation` as scratch space
+fn estimate_with(
+    envelope: &[f32],
+    frame_rate: f32,
+    options: &TempoOptions,
+    correlation: &mut Vec<f32>,
+) -> Option<TempoEstimate> {
+    let min_lag = ((60.0 * frame_rate / options.max_bpm.max(1.0)).floor() as usize).max(2);
+    let max_lag = (60.0 * frame_rate / options.min_bpm.max(1.0)).ceil() as usize;
+    if min_lag >= max_lag || envelope.len() < 2 * (max_lag + 1) {
+        return None;
+    }
+
+    let num_frames = envelope.len();
+    let mean = envelope.iter().sum::<f32>() / num_frames as f32;
+    let energy = envelope
+        .iter()
+        .map(|value| (value - mean) * (value - mean))
+        .sum::<f32>()
+        / num_frames as f32;
+    if energy <= f32::EPSILON {
+        return None;
+    }
+
+    // Unbiased & normalized, so every lag in range is comparable
+    correlation.clear();
+    correlation.extend((0..=max_lag + 1).map(|lag| {
+        if lag + 1 < min_lag {
+            return 0.0;
+        }
+        let sum: f32 = envelope
+            .iter()
+            .zip(&envelope[lag..])
+            .map(|(value, lagged)| (value - mean) * (lagged - mean))
+            .sum();
+        sum / (num_frames - lag) as f32 / energy
+    }));
+
+    let preferred_lag = 60.0 * frame_rate / options.preferred_bpm.max(1.0);
+    let weight = |lag: usize| {
+        let octaves = (lag as f32 / preferred_lag).log2() / PRIOR_WIDTH;
+        (-0.5 * octaves * octaves).exp()
+    };
+    let lag = (min_lag..=max_lag).max_by(|a, b| {
+        (weight(*a) * correlation[*a])
+            .partial_cmp(&(weight(*b) * correlation[*b]))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+    if correlation[lag] <= 0.0 {
+        return None;
+    }
+
+    let (previous, current, next) = (correlation[lag - 1], correlation[lag], correlation[lag + 1]);
+    let curvature = previous - 2.0 * current + next;
+    let shift = if curvature < 0.0 {
+        (0.5 * (previous - next) / curvature).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+
+    Some(TempoEstimate {
+        bpm: 60.0 * frame_rate / (lag as f32 + shift),
+        confidence: current.clamp(0.0, 1.0),
+    })
+}
+
+/// Estimates the tempo of the last few seconds of a live onset envelope. Allocates on construction
+/// only.
+pub struct TempoEstimator {
+    options: TempoOptions,
+    frame_rate: f32,
+    /// The last values of the envelope, circular
+    envelope: Vec<f32>,
+    position: usize,
+    num_frames: usize,
+    /// `envelope` in order
+    window: Vec<f32>,
+    correlation: Vec<f32>,
+}
+
+impl TempoEstimator {
+    /// Keep the last `num_frames` values of an envelope with `frame_rate` values per second
+    pub fn new(options: TempoOptions, frame_rate: f32, num_frames: usize) -> Self {
+        let num_frames = num_frames.max(1);
+        let max_lag = (60.0 * frame_rate / options.min_bpm.max(1.0)).ceil() as usize;
+        TempoEstimator {
+            options,
+            frame_rate,
+            envelope: vec![0.0; num_frames],
+            position: 0,
+            num_frames: 0,
+            window: Vec::with_capacity(num_frames),
+            correlation: Vec::with_capacity(max_lag + 2),
+        }
+    }
+
+    pub fn options(&self) -> &TempoOptions {
+        &self.options
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope.iter_mut().for_each(|value| *value = 0.0);
+        self.position = 0;
+        self.num_frames = 0;
+    }
+
+    pub fn push(&mut self, value: f32) {
+        self.envelope[self.position] = value;
+        self.position = (self.position + 1) % self.envelope.len();
+        self.num_frames = (self.num_frames + 1).min(self.envelope.len());
+    }
+
+    /// Estimate the tempo of the values pushed so far
+    pub fn estimate(&mut self) -> Option<TempoEstimate> {
+        let capacity = self.envelope.len();
+        let start = (self.position + capacity - self.num_frames) % capacity;
+        let envelope = &self.envelope;
+        self.window.clear();
+        self.window
+            .extend((0..self.num_frames).map(|index| envelope[(start + index) % capacity]));
+        estimate_with(
+            &self.window,
+            self.frame_rate,
+            &self.options,
+            &mut self.correlation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FRAME_RATE: f32 = 100.0;
+
+    /// A decaying pulse every beat of `bpm`, with smaller ones on the off-beats
+    fn pulses(bpm: f32, seconds: f32) -> Vec<f32> {
+        let beat = 60.0 * FRAME_RATE / bpm;
+        (0..(seconds * FRAME_RATE) as usize)
+            .map(|frame| {
+                let since_beat = frame as f32 % beat;
+                let since_off_beat = (frame as f32 + beat / 2.0) % beat;
+                (-since_beat / 2.0).exp() + 0.3 * (-since_off_beat / 2.0).exp()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pulse_trains_are_estimated_within_a_bpm() {
+        for bpm in [72.0, 90.0, 100.0, 120.0, 128.0, 145.0].iter() {
+            let estimate =
+                estimate_tempo(&pulses(*bpm, 20.0), FRAME_RATE, TempoOptions::default()).unwrap();
+            assert!(
+                (estimate.bpm - bpm).abs() < 1.0,
+                "{} != {}",
+                estimate.bpm,
+                bpm
+            );
+            assert!(estimate.confidence > 0.5);
+        }
+    }
+
+    #[test]
+    fn test_flat_or_short_envelopes_have_no_tempo() {
+        let options = TempoOptions::default();
+        assert!(estimate_tempo(&vec![0.5; 2000], FRAME_RATE, options).is_none());
+        assert!(estimate_tempo(&pulses(120.0, 1.0), FRAME_RATE, options).is_none());
+    }
+
+    #[test]
+    fn test_estimator_follows_the_last_frames() {
+        let mut estimator =
+            TempoEstimator::new(TempoOptions::default(), FRAME_RATE, 8 * FRAME_RATE as usize);
+        assert!(estimator.estimate().is_none());
+        for value in pulses(100.0, 20.0) {
+            estimator.push(value);
+        }
+        assert!((estimator.estimate().unwrap().bpm - 100.0).abs() < 1.0);
+        for value in pulses(140.0, 10.0) {
+            estimator.push(value);
+        }
+        assert!((estimator.estimate().unwrap().bpm - 140.0).abs() < 1.0);
+    }
+}
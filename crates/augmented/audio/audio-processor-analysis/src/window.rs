@@ -0,0 +1,91 @@
+//! Window functions, which taper a block of samples before an FFT to reduce spectral leakage.
+
+/// Shape of the window applied before an FFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindowFunction {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    /// 4-term Blackman-Harris, with the lowest side-lobes but the widest main lobe
+    BlackmanHarris,
+}
+
+impl WindowFunction {
+    /// All windows, in the order used by [`WindowFunction::from_index`]
+    pub const ALL: [WindowFunction; 5] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+        WindowFunction::BlackmanHarris,
+    ];
+
+    pub fn index(&self) -> usize {
+        match self {
+            WindowFunction::Rectangular => 0,
+            WindowFunction::Hann => 1,
+            WindowFunction::Hamming => 2,
+            WindowFunction::Blackman => 3,
+            WindowFunction::BlackmanHarris => 4,
+        }
+    }
+
+    /// Window at `index`, or the last one if it's out of range
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index.min(Self::ALL.len() - 1)]
+    }
+
+    /// Value of the window at `index` out of `size` samples. Periodic, so overlapping windows add
+    /// up to a constant.
+    pub fn value(&self, index: usize, size: usize) -> f32 {
+        let phase = 2.0 * std::f64::consts::PI * index as f64 / size as f64;
+        let cosine_sum = |coefficients: &[f64]| {
+            coefficients
+                .iter()
+                .enumerate()
+                .map(|(k, coefficient)| {
+                    let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                    sign * coefficient * (k as f64 * phase).cos()
+                })
+                .sum::<f64>()
+        };
+        let value = match self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => cosine_sum(&[0.5, 0.5]),
+            WindowFunction::Hamming => cosine_sum(&[0.54, 0.46]),
+            WindowFunction::Blackman => cosine_sum(&[0.42, 0.5, 0.08]),
+            WindowFunction::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168]),
+        };
+        value as f32
+    }
+
+    /// The whole window, `size` samples long
+    pub fn build(&self, size: usize) -> Vec<f32> {
+        (0..size).map(|index| self.value(index, size)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_windows_peak_in_the_middle() {
+        for window in WindowFunction::ALL.iter() {
+            let values = window.build(64);
+            assert!((values[32] - 1.0).abs() < 1e-3, "{:?}", window);
+            assert!(values[0] <= values[16] && values[16] <= values[32]);
+        }
+    }
+
+    #[test]
+    fn test_half_overlapping_hann_windows_add_up_to_one() {
+        let values = WindowFunction::Hann.build(64);
+        for index in 0..32 {
+            assert!((values[index] + values[index + 32] - 1.0).abs() < 1e-6);
+        }
+    }
+}
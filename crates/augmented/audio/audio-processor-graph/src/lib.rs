@@ -5,6 +5,8 @@ use daggy::Walker;
 use thiserror::Error;
 
 mod connection;
+/// Input strips, aux sends, return buses & a master bus
+pub mod mixer;
 
 pub type NodeIndex = daggy::NodeIndex<u32>;
 pub type ConnectionIndex = daggy::EdgeIndex<u32>;
@@ -0,0 +1,252 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use audio_processor_traits::AtomicF32;
+use audio_volume::db_to_amplitude;
+
+/// Peak & RMS levels of the last processed block, for the left & right channels
+pub struct MeterHandle {
+    peak: [AtomicF32; 2],
+    rms: [AtomicF32; 2],
+}
+
+impl Default for MeterHandle {
+    fn default() -> Self {
+        MeterHandle {
+            peak: [AtomicF32::new(0.0), AtomicF32::new(0.0)],
+            rms: [AtomicF32::new(0.0), AtomicF32::new(0.0)],
+        }
+    }
+}
+
+impl MeterHandle {
+    /// Linear peak level of `channel`, 0 for left & 1 for right
+    pub fn peak(&self, channel: usize) -> f32 {
+        self.peak.get(channel).map_or(0.0, |peak| peak.get())
+    }
+
+    /// Linear RMS level of `channel`, 0 for left & 1 for right
+    pub fn rms(&self, channel: usize) -> f32 {
+        self.rms.get(channel).map_or(0.0, |rms| rms.get())
+    }
+
+    pub(crate) fn set(&self, peak: [f32; 2], rms: [f32; 2]) {
+        for channel in 0..2 {
+            self.peak[channel].set(peak[channel]);
+            self.rms[channel].set(rms[channel]);
+        }
+    }
+}
+
+/// Level of a strip's signal into a return bus
+pub struct SendHandle {
+    level: AtomicF32,
+    pre_fader: AtomicBool,
+}
+
+impl Default for SendHandle {
+    /// A silent post-fader send
+    fn default() -> Self {
+        SendHandle {
+            level: AtomicF32::new(0.0),
+            pre_fader: AtomicBool::new(false),
+        }
+    }
+}
+
+impl SendHandle {
+    /// Linear send level
+    pub fn level(&self) -> f32 {
+        self.level.get()
+    }
+
+    pub fn set_level(&self, level: f32) {
+        self.level.set(level.max(0.0));
+    }
+
+    /// Whether the send taps the strip before its fader & pan, so it doesn't follow them
+    pub fn is_pre_fader(&self) -> bool {
+        self.pre_fader.load(Ordering::Relaxed)
+    }
+
+    pub fn set_pre_fader(&self, pre_fader: bool) {
+        self.pre_fader.store(pre_fader, Ordering::Relaxed);
+    }
+}
+
+/// Controls & meter of an input strip
+pub struct StripHandle {
+    gain: AtomicF32,
+    pan: AtomicF32,
+    mute: AtomicBool,
+    solo: AtomicBool,
+    phase_inverted: AtomicBool,
+    sends: Vec<SendHandle>,
+    meter: MeterHandle,
+}
+
+impl StripHandle {
+    pub(crate) fn new(num_buses: usize) -> Self {
+        StripHandle {
+            gain: AtomicF32::new(1.0),
+            pan: AtomicF32::new(0.0),
+            mute: AtomicBool::new(false),
+            solo: AtomicBool::new(false),
+            phase_inverted: AtomicBool::new(false),
+            sends: (0..num_buses).map(|_| SendHandle::default()).collect(),
+            meter: MeterHandle::default(),
+        }
+    }
+
+    /// Linear fader gain
+    pub fn gain(&self) -> f32 {
+        self.gain.get()
+    }
+
+    pub fn set_gain(&self, gain: f32) {
+        self.gain.set(gain.max(0.0));
+    }
+
+    /// Set the fader gain in dB
+    pub fn set_gain_db(&self, gain_db: f32) {
+        self.set_gain(db_to_amplitude(gain_db, 1.0));
+    }
+
+    /// A number between -1 and 1, -1 is hard left & 1 is hard right
+    pub fn pan(&self) -> f32 {
+        self.pan.get()
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        self.pan.set(pan.clamp(-1.0, 1.0));
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.mute.load(Ordering::Relaxed)
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.mute.store(muted, Ordering::Relaxed);
+    }
+
+    /// Soloed strips silence all the strips which aren't
+    pub fn is_soloed(&self) -> bool {
+        self.solo.load(Ordering::Relaxed)
+    }
+
+    pub fn set_soloed(&self, soloed: bool) {
+        self.solo.store(soloed, Ordering::Relaxed);
+    }
+
+    pub fn is_phase_inverted(&self) -> bool {
+        self.phase_inverted.load(Ordering::Relaxed)
+    }
+
+    pub fn set_phase_inverted(&self, phase_inverted: bool) {
+        self.phase_inverted.store(phase_inverted, Ordering::Relaxed);
+    }
+
+    /// The send into return bus `bus`
+    pub fn send(&self, bus: usize) -> Option<&SendHandle> {
+        self.sends.get(bus)
+    }
+
+    pub fn sends(&self) -> &[SendHandle] {
+        &self.sends
+    }
+
+    /// Level after the fader & pan
+    pub fn meter(&self) -> &MeterHandle {
+        &self.meter
+    }
+}
+
+/// Controls & meter of a return bus or the master bus
+pub struct BusHandle {
+    gain: AtomicF32,
+    mute: AtomicBool,
+    meter: MeterHandle,
+}
+
+impl Default for BusHandle {
+    fn default() -> Self {
+        BusHandle {
+            gain: AtomicF32::new(1.0),
+            mute: AtomicBool::new(false),
+            meter: MeterHandle::default(),
+        }
+    }
+}
+
+impl BusHandle {
+    /// Linear fader gain
+    pub fn gain(&self) -> f32 {
+        self.gain.get()
+    }
+
+    pub fn set_gain(&self, gain: f32) {
+        self.gain.set(gain.max(0.0));
+    }
+
+    /// Set the fader gain in dB
+    pub fn set_gain_db(&self, gain_db: f32) {
+        self.set_gain(db_to_amplitude(gain_db, 1.0));
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.mute.load(Ordering::Relaxed)
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.mute.store(muted, Ordering::Relaxed);
+    }
+
+    /// Level after the fader
+    pub fn meter(&self) -> &MeterHandle {
+        &self.meter
+    }
+}
+
+/// Lock-free controls & meters of a [`super::MixerProcessor`], shared with UIs & other threads
+pub struct MixerHandle {
+    strips: Vec<StripHandle>,
+    buses: Vec<BusHandle>,
+    master: BusHandle,
+}
+
+impl MixerHandle {
+    pub(crate) fn new(num_strips: usize, num_buses: usize) -> Self {
+        MixerHandle {
+            strips: (0..num_strips)
+                .map(|_| StripHandle::new(num_buses))
+                .collect(),
+            buses: (0..num_buses).map(|_| BusHandle::default()).collect(),
+            master: BusHandle::default(),
+        }
+    }
+
+    pub fn strip(&self, index: usize) -> Option<&StripHandle> {
+        self.strips.get(index)
+    }
+
+    pub fn strips(&self) -> &[StripHandle] {
+        &self.strips
+    }
+
+    /// Return bus `index`
+    pub fn bus(&self, index: usize) -> Option<&BusHandle> {
+        self.buses.get(index)
+    }
+
+    pub fn buses(&self) -> &[BusHandle] {
+        &self.buses
+    }
+
+    pub fn master(&self) -> &BusHandle {
+        &self.master
+    }
+
+    /// Whether any strip is soloed
+    pub fn is_any_soloed(&self) -> bool {
+        self.strips.iter().any(|strip| strip.is_soloed())
+    }
+}
@@ -0,0 +1,574 @@
+//! A mixer with input strips, aux sends into return buses & a master bus.
+//!
+//! Each strip has a gain, pan, mute, solo & phase invert, a meter, and a send into each return
+//! bus, tapped before or after its fader. Return buses & the master have a gain, mute & meter.
+//! Everything is controlled through a [`MixerHandle`], which may be shared with other threads &
+//! doesn't lock; changes are picked up once per block & smoothed.
+//!
+//! [`MixerProcessor::process_inputs`] mixes a buffer per strip, e.g. the output of each plug-in
+//! chain or loop track. As an [`AudioProcessor`], such as a graph node, strips read consecutive
+//! pairs of channels of the buffer instead. Strips are stereo; mono inputs are panned following
+//! the mixer's [`PanLaw`], while stereo inputs are balanced, like the utility `PanProcessor` does
+//! with mono & stereo sources.
+use std::time::Duration;
+
+use audio_garbage_collector::{Handle, Shared};
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use audio_processor_utility::pan::PanLaw;
+use smooth_value::InterpolatedValue;
+
+pub use handle::{BusHandle, MeterHandle, MixerHandle, SendHandle, StripHandle};
+
+mod handle;
+
+/// How long control changes are ramped for
+const SMOOTHING_TIME: Duration = Duration::from_millis(10);
+
+/// A control starting at `value`, at the default sample rate until the mixer is prepared
+fn smoothed_value(value: f32) -> InterpolatedValue {
+    let sample_rate = AudioProcessorSettings::default().sample_rate();
+    InterpolatedValue::new(sample_rate, SMOOTHING_TIME, value)
+}
+
+/// Layout of a [`MixerProcessor`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixerOptions {
+    pub num_strips: usize,
+    /// Number of return buses, each strip has a send into every one
+    pub num_buses: usize,
+    /// How mono strips are panned
+    pub pan_law: PanLaw,
+}
+
+impl Default for MixerOptions {
+    /// 8 strips & 2 return buses, with a -3dB pan law
+    fn default() -> Self {
+        MixerOptions {
+            num_strips: 8,
+            num_buses: 2,
+            pan_law: PanLaw::Minus3Db,
+        }
+    }
+}
+
+/// A frame of a strip's input
+#[derive(Clone, Copy)]
+enum StripInput {
+    Mono(f32),
+    Stereo(f32, f32),
+}
+
+impl StripInput {
+    fn scale(self, gain: f32) -> StripInput {
+        match self {
+            StripInput::Mono(sample) => StripInput::Mono(sample * gain),
+            StripInput::Stereo(left, right) => StripInput::Stereo(left * gain, right * gain),
+        }
+    }
+
+    fn to_stereo(self) -> (f32, f32) {
+        match self {
+            StripInput::Mono(sample) => (sample, sample),
+            StripInput::Stereo(left, right) => (left, right),
+        }
+    }
+
+    fn pan(self, pan_law: PanLaw, pan: f32) -> (f32, f32) {
+        match self {
+            StripInput::Mono(sample) => {
+                let (left, right) = pan_law.mono_gains(pan);
+                (sample * left, sample * right)
+            }
+            StripInput::Stereo(left, right) if pan > 0.0 => {
+                let (stay, moved) = pan_law.gains(pan);
+                (left * stay, right + left * moved)
+            }
+            StripInput::Stereo(left, right) if pan < 0.0 => {
+                let (stay, moved) = pan_law.gains(-pan);
+                (left + right * moved, right * stay)
+            }
+            StripInput::Stereo(left, right) => (left, right),
+        }
+    }
+}
+
+/// Peak & sum of squares over the current block
+#[derive(Default)]
+struct MeterState {
+    peak: [f32; 2],
+    sum_squares: [f32; 2],
+    num_frames: usize,
+}
+
+impl MeterState {
+    fn accumulate(&mut self, (left, right): (f32, f32)) {
+        for (channel, sample) in [left, right].iter().enumerate() {
+            self.peak[channel] = self.peak[channel].max(sample.abs());
+            self.sum_squares[channel] += sample * sample;
+        }
+        self.num_frames += 1;
+    }
+
+    fn flush(&mut self, handle: &MeterHandle) {
+        if self.num_frames == 0 {
+            return;
+        }
+        let num_frames = self.num_frames as f32;
+        handle.set(
+            self.peak,
+            [
+                (self.sum_squares[0] / num_frames).sqrt(),
+                (self.sum_squares[1] / num_frames).sqrt(),
+            ],
+        );
+        *self = MeterState::default();
+    }
+}
+
+struct SendState {
+    level: InterpolatedValue,
+    pre_fader: bool,
+}
+
+struct StripState {
+    gain: InterpolatedValue,
+    /// 0 when muted or silenced by another strip's solo, 1 otherwise
+    audible: InterpolatedValue,
+    /// -1 when the phase is inverted
+    phase: InterpolatedValue,
+    pan: InterpolatedValue,
+    sends: Vec<SendState>,
+    meter: MeterState,
+}
+
+impl StripState {
+    fn new(num_buses: usize) -> Self {
+        StripState {
+            gain: smoothed_value(1.0),
+            audible: smoothed_value(1.0),
+            phase: smoothed_value(1.0),
+            pan: smoothed_value(0.0),
+            sends: (0..num_buses)
+                .map(|_| SendState {
+                    level: smoothed_value(0.0),
+                    pre_fader: false,
+                })
+                .collect(),
+            meter: MeterState::default(),
+        }
+    }
+
+    fn smoothed_values(&mut self) -> impl Iterator<Item = &mut InterpolatedValue> {
+        vec![
+            &mut self.gain,
+            &mut self.audible,
+            &mut self.phase,
+            &mut self.pan,
+        ]
+        .into_iter()
+        .chain(self.sends.iter_mut().map(|send| &mut send.level))
+    }
+}
+
+struct BusState {
+    /// The fader gain, or 0 when muted
+    gain: InterpolatedValue,
+    meter: MeterState,
+}
+
+impl BusState {
+    fn new() -> Self {
+        BusState {
+            gain: smoothed_value(1.0),
+            meter: MeterState::default(),
+        }
+    }
+
+    fn update_target(&mut self, handle: &BusHandle) {
+        self.gain.set(if handle.is_muted() {
+            0.0
+        } else {
+            handle.gain()
+        });
+    }
+
+    fn process(&mut self, (left, right): (f32, f32)) -> (f32, f32) {
+        let gain = self.gain.next_sample();
+        let output = (left * gain, right * gain);
+        self.meter.accumulate(output);
+        output
+    }
+}
+
+/// Mixes input strips into a stereo master bus, through optional return buses. See the
+/// [module docs](self).
+///
+/// Buffers are allocated on construction only.
+pub struct MixerProcessor {
+    handle: Shared<MixerHandle>,
+    options: MixerOptions,
+    strips: Vec<StripState>,
+    buses: Vec<BusState>,
+    master: BusState,
+    /// Sum of the sends into each return bus for the current frame
+    bus_frames: Vec<(f32, f32)>,
+}
+
+impl MixerProcessor {
+    pub fn new(handle: &Handle, options: MixerOptions) -> Self {
+        MixerProcessor {
+            handle: Shared::new(
+                handle,
+                MixerHandle::new(options.num_strips, options.num_buses),
+            ),
+            options,
+            strips: (0..options.num_strips)
+                .map(|_| StripState::new(options.num_buses))
+                .collect(),
+            buses: (0..options.num_buses).map(|_| BusState::new()).collect(),
+            master: BusState::new(),
+            bus_frames: vec![(0.0, 0.0); options.num_buses],
+        }
+    }
+
+    pub fn handle(&self) -> &Shared<MixerHandle> {
+        &self.handle
+    }
+
+    pub fn options(&self) -> &MixerOptions {
+        &self.options
+    }
+
+    /// Mix `inputs`, one buffer per strip, into `output`.
+    ///
+    /// Mono buffers are panned, stereo ones balanced & only the first two channels of wider ones
+    /// are used. Strips without an input, or past its end, are silent. The master is written into
+    /// the first two channels of `output`; mono outputs get the sum of both sides & other channels
+    /// are silenced.
+    pub fn process_inputs<InputBufferType, OutputBufferType>(
+        &mut self,
+        inputs: &[InputBufferType],
+        output: &mut OutputBufferType,
+    ) where
+        InputBufferType: AudioBuffer<SampleType = f32>,
+        OutputBufferType: AudioBuffer<SampleType = f32>,
+    {
+        self.update_targets();
+        for frame in 0..output.num_samples() {
+            let master = self.mix_frame(|strip| {
+                inputs.get(strip).map_or(StripInput::Mono(0.0), |input| {
+                    read_channels(input, 0, frame)
+                })
+            });
+            write_master(output, frame, master);
+        }
+        self.flush_meters();
+    }
+
+    /// Read the controls of the handle
+    fn update_targets(&mut self) {
+        let any_soloed = self.handle.is_any_soloed();
+        for (strip, handle) in self.strips.iter_mut().zip(self.handle.strips()) {
+            let audible = !handle.is_muted() && (!any_soloed || handle.is_soloed());
+            strip.audible.set(if audible { 1.0 } else { 0.0 });
+            strip.gain.set(handle.gain());
+            strip.phase.set(if handle.is_phase_inverted() {
+                -1.0
+            } else {
+                1.0
+            });
+            strip.pan.set(handle.pan());
+            for (send, send_handle) in strip.sends.iter_mut().zip(handle.sends()) {
+                send.level.set(send_handle.level());
+                send.pre_fader = send_handle.is_pre_fader();
+            }
+        }
+        for (bus, handle) in self.buses.iter_mut().zip(self.handle.buses()) {
+            bus.update_target(handle);
+        }
+        self.master.update_target(self.handle.master());
+    }
+
+    fn mix_frame(&mut self, input: impl Fn(usize) -> StripInput) -> (f32, f32) {
+        let pan_law = self.options.pan_law;
+        for bus_frame in self.bus_frames.iter_mut() {
+            *bus_frame = (0.0, 0.0);
+        }
+
+        let mut master = (0.0, 0.0);
+        for (index, strip) in self.strips.iter_mut().enumerate() {
+            let pre_fader =
+                input(index).scale(strip.audible.next_sample() * strip.phase.next_sample());
+            let gain = strip.gain.next_sample();
+            let (left, right) = pre_fader.pan(pan_law, strip.pan.next_sample());
+            let post_fader = (left * gain, right * gain);
+            strip.meter.accumulate(post_fader);
+            master.0 += post_fader.0;
+            master.1 += post_fader.1;
+
+            let pre_fader = pre_fader.to_stereo();
+            for (send, bus_frame) in strip.sends.iter_mut().zip(self.bus_frames.iter_mut()) {
+                let level = send.level.next_sample();
+                let source = if send.pre_fader {
+                    pre_fader
+                } else {
+                    post_fader
+                };
+                bus_frame.0 += source.0 * level;
+                bus_frame.1 += source.1 * level;
+            }
+        }
+
+        for (bus, bus_frame) in self.buses.iter_mut().zip(&self.bus_frames) {
+            let (left, right) = bus.process(*bus_frame);
+            master.0 += left;
+            master.1 += right;
+        }
+
+        self.master.process(master)
+    }
+
+    fn flush_meters(&mut self) {
+        for (strip, handle) in self.strips.iter_mut().zip(self.handle.strips()) {
+            strip.meter.flush(handle.meter());
+        }
+        for (bus, handle) in self.buses.iter_mut().zip(self.handle.buses()) {
+            bus.meter.flush(handle.meter());
+        }
+        self.master.meter.flush(self.handle.master().meter());
+    }
+}
+
+impl AudioProcessor for MixerProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.update_targets();
+        let sample_rate = settings.sample_rate();
+        let smoothed_values = self
+            .strips
+            .iter_mut()
+            .flat_map(|strip| strip.smoothed_values())
+            .chain(self.buses.iter_mut().map(|bus| &mut bus.gain))
+            .chain(std::iter::once(&mut self.master.gain));
+        for value in smoothed_values {
+            value.set_sample_rate(sample_rate);
+            value.jump();
+        }
+    }
+
+    /// Strip `n` reads channels `2n` & `2n + 1`, or only `2n` if the buffer ends there. The master
+    /// replaces the first two channels & the others are silenced.
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        self.update_targets();
+        for frame in 0..data.num_samples() {
+            let master = {
+                let data = &*data;
+                self.mix_frame(|strip| read_channels(data, 2 * strip, frame))
+            };
+            write_master(data, frame, master);
+        }
+        self.flush_meters();
+    }
+}
+
+/// Read `frame` of the channel pair starting at `channel`, silence if the buffer is narrower or
+/// shorter
+fn read_channels<BufferType: AudioBuffer<SampleType = f32>>(
+    buffer: &BufferType,
+    channel: usize,
+    frame: usize,
+) -> StripInput {
+    if channel >= buffer.num_channels() || frame >= buffer.num_samples() {
+        StripInput::Mono(0.0)
+    } else if channel + 1 < buffer.num_channels() {
+        StripInput::Stereo(*buffer.get(channel, frame), *buffer.get(channel + 1, frame))
+    } else {
+        StripInput::Mono(*buffer.get(channel, frame))
+    }
+}
+
+fn write_master<BufferType: AudioBuffer<SampleType = f32>>(
+    buffer: &mut BufferType,
+    frame: usize,
+    (left, right): (f32, f32),
+) {
+    match buffer.num_channels() {
+        0 => {}
+        1 => buffer.set(0, frame, left + right),
+        num_channels => {
+            buffer.set(0, frame, left);
+            buffer.set(1, frame, right);
+            for channel in 2..num_channels {
+                buffer.set(channel, frame, 0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_garbage_collector::GarbageCollector;
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    fn mixer(gc: &GarbageCollector, num_strips: usize, num_buses: usize) -> MixerProcessor {
+        MixerProcessor::new(
+            gc.handle(),
+            MixerOptions {
+                num_strips,
+                num_buses,
+                ..MixerOptions::default()
+            },
+        )
+    }
+
+    fn constant(num_channels: usize, value: f32) -> VecAudioBuffer<f32> {
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(num_channels, 64, value);
+        buffer
+    }
+
+    fn process(mixer: &mut MixerProcessor, inputs: &[VecAudioBuffer<f32>]) -> (f32, f32) {
+        let mut output = constant(2, 0.0);
+        mixer.process_inputs(inputs, &mut output);
+        (*output.get(0, 63), *output.get(1, 63))
+    }
+
+    fn assert_close((left, right): (f32, f32), (expected_left, expected_right): (f32, f32)) {
+        assert!(
+            (left - expected_left).abs() < 1e-4 && (right - expected_right).abs() < 1e-4,
+            "({}, {}) != ({}, {})",
+            left,
+            right,
+            expected_left,
+            expected_right
+        );
+    }
+
+    #[test]
+    fn test_strips_are_summed_into_the_master() {
+        let gc = GarbageCollector::default();
+        let mut mixer = mixer(&gc, 2, 0);
+        mixer.handle().strip(1).unwrap().set_pan(1.0);
+        mixer.prepare(AudioProcessorSettings::default());
+
+        // A centred mono strip is at -3dB on each side & a stereo one is untouched
+        let half_power = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(
+            process(&mut mixer, &[constant(1, 1.0)]),
+            (half_power, half_power),
+        );
+        assert_close(
+            process(&mut mixer, &[constant(2, 0.5), constant(1, 0.5)]),
+            (0.5, 1.0),
+        );
+    }
+
+    #[test]
+    fn test_mute_solo_and_phase() {
+        let gc = GarbageCollector::default();
+        let mut mixer = mixer(&gc, 3, 0);
+        mixer.prepare(AudioProcessorSettings::default());
+        let inputs = [constant(2, 0.5), constant(2, 0.25), constant(2, 0.125)];
+
+        mixer.handle().strip(0).unwrap().set_muted(true);
+        for _ in 0..100 {
+            process(&mut mixer, &inputs);
+        }
+        assert_close(process(&mut mixer, &inputs), (0.375, 0.375));
+
+        mixer.handle().strip(0).unwrap().set_soloed(true);
+        mixer.handle().strip(2).unwrap().set_soloed(true);
+        for _ in 0..100 {
+            process(&mut mixer, &inputs);
+        }
+        // Muting wins over soloing
+        assert_close(process(&mut mixer, &inputs), (0.125, 0.125));
+
+        mixer.handle().strip(2).unwrap().set_phase_inverted(true);
+        for _ in 0..100 {
+            process(&mut mixer, &inputs);
+        }
+        assert_close(process(&mut mixer, &inputs), (-0.125, -0.125));
+    }
+
+    #[test]
+    fn test_pre_and_post_fader_sends() {
+        let gc = GarbageCollector::default();
+        let mut mixer = mixer(&gc, 2, 2);
+        let handle = mixer.handle().clone();
+        let strip = handle.strip(0).unwrap();
+        strip.set_gain(0.0);
+        strip.send(0).unwrap().set_level(0.5);
+        strip.send(0).unwrap().set_pre_fader(true);
+        strip.send(1).unwrap().set_level(1.0);
+        handle.strip(1).unwrap().set_gain(0.5);
+        handle.strip(1).unwrap().send(1).unwrap().set_level(1.0);
+        handle.bus(1).unwrap().set_gain(0.5);
+        mixer.prepare(AudioProcessorSettings::default());
+
+        // The pre-fader send ignores the closed fader, the post-fader one follows strip 1's
+        let output = process(&mut mixer, &[constant(2, 1.0), constant(2, 1.0)]);
+        assert_close(output, (0.5 + 0.5 + 0.25, 0.5 + 0.5 + 0.25));
+        assert!((handle.bus(0).unwrap().meter().peak(0) - 0.5).abs() < 1e-4);
+        assert!((handle.bus(1).unwrap().meter().rms(1) - 0.25).abs() < 1e-4);
+
+        handle.bus(0).unwrap().set_muted(true);
+        handle.master().set_gain_db(-6.0);
+        for _ in 0..100 {
+            process(&mut mixer, &[constant(2, 1.0), constant(2, 1.0)]);
+        }
+        let output = process(&mut mixer, &[constant(2, 1.0), constant(2, 1.0)]);
+        let minus_6_db = 10.0_f32.powf(-6.0 / 20.0);
+        assert_close(output, (0.75 * minus_6_db, 0.75 * minus_6_db));
+    }
+
+    #[test]
+    fn test_meters_and_smoothing() {
+        let gc = GarbageCollector::default();
+        let mut mixer = mixer(&gc, 1, 0);
+        mixer.prepare(AudioProcessorSettings::default());
+        let handle = mixer.handle().clone();
+
+        let mut input = constant(2, 0.0);
+        input.set(0, 0, -0.8);
+        input.set(1, 0, 0.4);
+        process(&mut mixer, &[input]);
+        let meter = handle.strip(0).unwrap().meter();
+        assert!((meter.peak(0) - 0.8).abs() < 1e-6);
+        assert!((meter.peak(1) - 0.4).abs() < 1e-6);
+        assert!((meter.rms(0) - 0.1).abs() < 1e-6);
+        assert!((handle.master().meter().peak(0) - 0.8).abs() < 1e-6);
+
+        // Fading the strip out ramps rather than jumps
+        handle.strip(0).unwrap().set_gain(0.0);
+        let mut output = constant(2, 0.0);
+        mixer.process_inputs(&[constant(2, 1.0)], &mut output);
+        let left: Vec<f32> = output.frames().map(|frame| frame[0]).collect();
+        assert!(left[0] > 0.99);
+        assert!(left.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn test_processing_channel_pairs_in_place() {
+        let gc = GarbageCollector::default();
+        let mut mixer = mixer(&gc, 3, 0);
+        mixer.prepare(AudioProcessorSettings::default());
+
+        // Two stereo strips & a mono one
+        let mut samples = [0.1, 0.2, 0.3, 0.4, 0.5];
+        let mut buffer = InterleavedAudioBuffer::new(5, &mut samples);
+        mixer.process(&mut buffer);
+
+        let mono = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((samples[0] - (0.4 + mono)).abs() < 1e-4);
+        assert!((samples[1] - (0.6 + mono)).abs() < 1e-4);
+        assert_eq!(&samples[2..], &[0.0, 0.0, 0.0]);
+    }
+}
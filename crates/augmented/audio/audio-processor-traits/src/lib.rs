@@ -106,6 +106,11 @@ pub trait AudioProcessor {
         &mut self,
         data: &mut BufferType,
     );
+
+    /// Delay this processor adds to its output, in samples, so hosts can compensate for it
+    fn latency(&self) -> usize {
+        0
+    }
 }
 
 /// Auto-implemented object version of the audio-processor trait.
@@ -114,6 +119,9 @@ pub trait AudioProcessor {
 pub trait ObjectAudioProcessor<BufferType> {
     fn prepare_obj(&mut self, _settings: AudioProcessorSettings) {}
     fn process_obj(&mut self, data: &mut BufferType);
+    fn latency_obj(&self) -> usize {
+        0
+    }
 }
 
 impl<SampleType, BufferType, Processor> ObjectAudioProcessor<BufferType> for Processor
@@ -129,11 +137,27 @@ where
     fn process_obj(&mut self, data: &mut BufferType) {
         <Processor as AudioProcessor>::process(self, data);
     }
+
+    fn latency_obj(&self) -> usize {
+        <Processor as AudioProcessor>::latency(self)
+    }
 }
 
 /// An audio-processor which doesn't do any work.
 pub struct NoopAudioProcessor<SampleType>(PhantomData<SampleType>);
 
+impl<SampleType> NoopAudioProcessor<SampleType> {
+    pub fn new() -> Self {
+        NoopAudioProcessor(PhantomData)
+    }
+}
+
+impl<SampleType> Default for NoopAudioProcessor<SampleType> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<SampleType: Send> AudioProcessor for NoopAudioProcessor<SampleType> {
     type SampleType = SampleType;
 
@@ -0,0 +1,178 @@
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings, Float};
+
+use smooth_value::InterpolatedValue;
+
+use crate::smoothed_value;
+
+/// An `AudioProcessor` which routes `num_inputs` channels to `num_outputs` channels, each output
+/// being a weighted sum of the inputs.
+///
+/// Changes to the gains are smoothed. Processing in place reads & writes the first channels of the
+/// buffer; channels past `num_outputs` are left untouched, & inputs the buffer doesn't have are
+/// silent. [`ChannelMatrixProcessor::process_buffers`] routes between buffers of different widths.
+pub struct ChannelMatrixProcessor<SampleType> {
+    num_inputs: usize,
+    num_outputs: usize,
+    /// Gain of each input in each output, `gains[output * num_inputs + input]`
+    gains: Vec<InterpolatedValue>,
+    /// The inputs of the current frame
+    frame: Vec<SampleType>,
+}
+
+impl<SampleType: Float> ChannelMatrixProcessor<SampleType> {
+    /// Create a matrix which routes each input to the output of the same index
+    pub fn new(num_inputs: usize, num_outputs: usize) -> Self {
+        let gains = (0..num_outputs)
+            .flat_map(|output| {
+                (0..num_inputs)
+                    .map(move |input| smoothed_value(if input == output { 1.0 } else { 0.0 }))
+            })
+            .collect();
+        ChannelMatrixProcessor {
+            num_inputs,
+            num_outputs,
+            gains,
+            frame: vec![SampleType::zero(); num_inputs],
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.num_outputs
+    }
+
+    /// Gain of `input` in `output`. Zero if either is out of range.
+    pub fn gain(&self, input: usize, output: usize) -> SampleType {
+        if input >= self.num_inputs || output >= self.num_outputs {
+            return SampleType::zero();
+        }
+        SampleType::from(self.gains[output * self.num_inputs + input].target()).unwrap()
+    }
+
+    /// Set the gain of `input` in `output`. Ignored if either is out of range.
+    pub fn set_gain(&mut self, input: usize, output: usize, gain: SampleType) {
+        if input >= self.num_inputs || output >= self.num_outputs {
+            return;
+        }
+        self.gains[output * self.num_inputs + input].set(gain.to_f32().unwrap());
+    }
+
+    /// Route the frames of `input` into `output`. Processes as many frames as the shorter has;
+    /// output channels past `num_outputs` are left untouched.
+    pub fn process_buffers<InputType, OutputType>(
+        &mut self,
+        input: &InputType,
+        output: &mut OutputType,
+    ) where
+        InputType: AudioBuffer<SampleType = SampleType>,
+        OutputType: AudioBuffer<SampleType = SampleType>,
+    {
+        for (input_frame, output_frame) in input.frames().zip(output.frames_mut()) {
+            self.read_frame(input_frame);
+            self.write_frame(output_frame);
+        }
+    }
+
+    fn read_frame(&mut self, input: &[SampleType]) {
+        for (index, target) in self.frame.iter_mut().enumerate() {
+            *target = input.get(index).copied().unwrap_or_else(SampleType::zero);
+        }
+    }
+
+    fn write_frame(&mut self, output: &mut [SampleType]) {
+        let num_inputs = self.num_inputs;
+        for (sample, gains) in output
+            .iter_mut()
+            .zip(self.gains.chunks_mut(num_inputs.max(1)))
+        {
+            *sample = gains
+                .iter_mut()
+                .zip(&self.frame)
+                .fold(SampleType::zero(), |sum, (gain, input)| {
+                    sum + SampleType::from(gain.next_sample()).unwrap() * *input
+                });
+        }
+    }
+}
+
+impl<SampleType> AudioProcessor for ChannelMatrixProcessor<SampleType>
+where
+    SampleType: Float + Sync + Send,
+{
+    type SampleType = SampleType;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        for gain in &mut self.gains {
+            gain.set_sample_rate(settings.sample_rate());
+            gain.jump();
+        }
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            self.read_frame(frame);
+            self.write_frame(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_default_matrix_is_identity() {
+        let mut matrix = ChannelMatrixProcessor::new(2, 2);
+        let mut samples = [0.1, 0.2, 0.3, 0.4];
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+        matrix.process(&mut input);
+        assert_eq!(samples, [0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_swap_and_downmix() {
+        let mut swap = ChannelMatrixProcessor::new(2, 2);
+        swap.set_gain(0, 0, 0.0);
+        swap.set_gain(1, 1, 0.0);
+        swap.set_gain(0, 1, 1.0);
+        swap.set_gain(1, 0, 1.0);
+        swap.prepare(AudioProcessorSettings::default());
+        let mut samples = [0.1, 0.2];
+        swap.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+        assert_eq!(samples, [0.2, 0.1]);
+
+        // 4 channels down to stereo, into a narrower buffer
+        let mut downmix = ChannelMatrixProcessor::new(4, 2);
+        downmix.set_gain(2, 0, 0.5);
+        downmix.set_gain(3, 1, 0.5);
+        downmix.prepare(AudioProcessorSettings::default());
+        let mut input_samples = [0.2, 0.4, 0.2, 0.4];
+        let mut output_samples = [0.0, 0.0];
+        downmix.process_buffers(
+            &InterleavedAudioBuffer::new(4, &mut input_samples),
+            &mut InterleavedAudioBuffer::new(2, &mut output_samples),
+        );
+        assert!((output_samples[0] - 0.3).abs() < 1e-6);
+        assert!((output_samples[1] - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gain_changes_are_smoothed() {
+        let mut matrix = ChannelMatrixProcessor::new(1, 1);
+        matrix.prepare(AudioProcessorSettings::default());
+        matrix.set_gain(0, 0, 0.0);
+        let mut samples = [1.0; 5000];
+        matrix.process(&mut InterleavedAudioBuffer::new(1, &mut samples));
+        assert!(samples[0] > 0.9);
+        assert!(samples.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(samples[4999] < 1e-3);
+    }
+}
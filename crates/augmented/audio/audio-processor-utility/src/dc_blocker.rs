@@ -0,0 +1,131 @@
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings, Float};
+
+use smooth_value::InterpolatedValue;
+
+use crate::smoothed_value;
+
+/// An `AudioProcessor` which removes DC offset with a one-pole high-pass,
+/// `y[n] = x[n] - x[n - 1] + r * y[n - 1]`.
+///
+/// Channel state is allocated on `prepare`, for the input channels; until then it's a no-op.
+pub struct DcBlockerProcessor<SampleType> {
+    cutoff: f32,
+    sample_rate: f32,
+    /// Pole of the filter, smoothed when the cutoff changes
+    coefficient: InterpolatedValue,
+    /// The previous input & output of each channel
+    state: Vec<(SampleType, SampleType)>,
+}
+
+impl<SampleType: Float> Default for DcBlockerProcessor<SampleType> {
+    /// A DC blocker with a 10Hz cutoff
+    fn default() -> Self {
+        Self::new(10.0)
+    }
+}
+
+impl<SampleType: Float> DcBlockerProcessor<SampleType> {
+    /// Create a DC blocker with a `cutoff` in Hz
+    pub fn new(cutoff: f32) -> Self {
+        let sample_rate = AudioProcessorSettings::default().sample_rate();
+        DcBlockerProcessor {
+            cutoff,
+            sample_rate,
+            coefficient: smoothed_value(Self::coefficient(cutoff, sample_rate)),
+            state: Vec::new(),
+        }
+    }
+
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    /// Set the cutoff, in Hz
+    pub fn set_cutoff(&mut self, cutoff: f32) {
+        self.cutoff = cutoff;
+        self.coefficient
+            .set(Self::coefficient(cutoff, self.sample_rate));
+    }
+
+    /// Forget the previous samples
+    pub fn reset(&mut self) {
+        for state in &mut self.state {
+            *state = (SampleType::zero(), SampleType::zero());
+        }
+    }
+
+    fn coefficient(cutoff: f32, sample_rate: f32) -> f32 {
+        let cutoff = cutoff.max(0.0).min(sample_rate / 2.0);
+        (-2.0 * std::f32::consts::PI * cutoff / sample_rate).exp()
+    }
+}
+
+impl<SampleType> AudioProcessor for DcBlockerProcessor<SampleType>
+where
+    SampleType: Float + Sync + Send,
+{
+    type SampleType = SampleType;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.coefficient.set_sample_rate(self.sample_rate);
+        self.coefficient
+            .set(Self::coefficient(self.cutoff, self.sample_rate));
+        self.coefficient.jump();
+        self.state = vec![(SampleType::zero(), SampleType::zero()); settings.input_channels()];
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            let coefficient = SampleType::from(self.coefficient.next_sample()).unwrap();
+            for (sample, (previous_input, previous_output)) in frame.iter_mut().zip(&mut self.state)
+            {
+                let output = *sample - *previous_input + coefficient * *previous_output;
+                *previous_input = *sample;
+                *previous_output = output;
+                *sample = output;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_offset_is_removed() {
+        let mut dc_blocker = DcBlockerProcessor::default();
+        dc_blocker.prepare(AudioProcessorSettings::new(44100.0, 2, 2, 512));
+
+        let mut samples: Vec<f32> = (0..44100 * 2)
+            .map(|i| {
+                0.5 + 0.25 * (2.0 * std::f32::consts::PI * 1000.0 * (i / 2) as f32 / 44100.0).sin()
+            })
+            .collect();
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+        dc_blocker.process(&mut input);
+
+        let tail = &samples[44100..];
+        let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+        let peak = tail
+            .iter()
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        assert!(mean.abs() < 1e-3, "{}", mean);
+        assert!((peak - 0.25).abs() < 0.01, "{}", peak);
+    }
+
+    #[test]
+    fn test_unprepared_processor_is_a_noop() {
+        let mut dc_blocker = DcBlockerProcessor::default();
+        let mut samples = [0.5, 0.5];
+        let mut input = InterleavedAudioBuffer::new(1, &mut samples);
+        dc_blocker.process(&mut input);
+        assert_eq!(samples, [0.5, 0.5]);
+    }
+}
@@ -0,0 +1,358 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use audio_volume::db_to_amplitude;
+
+use super::{level_db, DetectorMode, DynamicsHandle, DynamicsKind, EnvelopeFollower, GainComputer};
+
+/// A compressor, expander or gate.
+///
+/// Detection runs on the input, or on a sidechain passed to
+/// [`DynamicsProcessor::process_with_sidechain`]. When channels are linked, the loudest
+/// channel's level sets the gain of all channels.
+pub struct DynamicsProcessor {
+    computer: GainComputer,
+    detector_mode: DetectorMode,
+    attack: Duration,
+    release: Duration,
+    makeup_gain_db: f32,
+    stereo_link: bool,
+    sample_rate: f32,
+    detectors: Vec<EnvelopeFollower>,
+    handle: Arc<DynamicsHandle>,
+}
+
+impl DynamicsProcessor {
+    pub fn new(kind: DynamicsKind) -> Self {
+        DynamicsProcessor {
+            computer: GainComputer::new(kind),
+            detector_mode: DetectorMode::Peak,
+            attack: Duration::from_millis(10),
+            release: Duration::from_millis(100),
+            makeup_gain_db: 0.0,
+            stereo_link: true,
+            sample_rate: 44100.0,
+            detectors: Vec::new(),
+            handle: Arc::new(DynamicsHandle::default()),
+        }
+    }
+
+    /// A 4:1 compressor
+    pub fn compressor() -> Self {
+        Self::new(DynamicsKind::Compressor)
+    }
+
+    /// A 2:1 downward expander
+    pub fn expander() -> Self {
+        let mut processor = Self::new(DynamicsKind::Expander);
+        processor.computer.ratio = 2.0;
+        processor.computer.threshold_db = -40.0;
+        processor
+    }
+
+    /// A gate, with a fast attack so transients come through
+    pub fn gate() -> Self {
+        let mut processor = Self::new(DynamicsKind::Gate);
+        processor.computer.threshold_db = -50.0;
+        processor.computer.knee_db = 2.0;
+        processor.attack = Duration::from_millis(1);
+        processor
+    }
+
+    /// Metering, which may be read from any thread
+    pub fn handle(&self) -> &Arc<DynamicsHandle> {
+        &self.handle
+    }
+
+    pub fn kind(&self) -> DynamicsKind {
+        self.computer.kind
+    }
+
+    pub fn set_kind(&mut self, kind: DynamicsKind) {
+        self.computer.kind = kind;
+    }
+
+    pub fn threshold_db(&self) -> f32 {
+        self.computer.threshold_db
+    }
+
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.computer.threshold_db = threshold_db;
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.computer.ratio
+    }
+
+    /// Set the ratio, 1 or more. Ignored by gates.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.computer.ratio = ratio.max(1.0);
+    }
+
+    pub fn knee_db(&self) -> f32 {
+        self.computer.knee_db
+    }
+
+    /// Set the width of the soft knee, in dB. 0 is a hard knee.
+    pub fn set_knee_db(&mut self, knee_db: f32) {
+        self.computer.knee_db = knee_db.max(0.0);
+    }
+
+    pub fn range_db(&self) -> f32 {
+        self.computer.range_db
+    }
+
+    /// Set the most the gain may be reduced by, in dB
+    pub fn set_range_db(&mut self, range_db: f32) {
+        self.computer.range_db = range_db.max(0.0);
+    }
+
+    pub fn makeup_gain_db(&self) -> f32 {
+        self.makeup_gain_db
+    }
+
+    pub fn set_makeup_gain_db(&mut self, makeup_gain_db: f32) {
+        self.makeup_gain_db = makeup_gain_db;
+    }
+
+    pub fn attack(&self) -> Duration {
+        self.attack
+    }
+
+    pub fn set_attack(&mut self, attack: Duration) {
+        self.attack = attack;
+        for detector in &mut self.detectors {
+            detector.set_attack(attack);
+        }
+    }
+
+    pub fn release(&self) -> Duration {
+        self.release
+    }
+
+    pub fn set_release(&mut self, release: Duration) {
+        self.release = release;
+        for detector in &mut self.detectors {
+            detector.set_release(release);
+        }
+    }
+
+    pub fn detector_mode(&self) -> DetectorMode {
+        self.detector_mode
+    }
+
+    pub fn set_detector_mode(&mut self, detector_mode: DetectorMode) {
+        self.detector_mode = detector_mode;
+        for detector in &mut self.detectors {
+            detector.set_mode(detector_mode);
+        }
+    }
+
+    pub fn stereo_link(&self) -> bool {
+        self.stereo_link
+    }
+
+    /// When linked, all channels get the same gain
+    pub fn set_stereo_link(&mut self, stereo_link: bool) {
+        self.stereo_link = stereo_link;
+    }
+
+    /// Process `data`, detecting levels on `sidechain`. The sidechain should be as long as `data`;
+    /// its channels are wrapped around if it has fewer.
+    pub fn process_with_sidechain<
+        BufferType: AudioBuffer<SampleType = f32>,
+        SidechainType: AudioBuffer<SampleType = f32>,
+    >(
+        &mut self,
+        data: &mut BufferType,
+        sidechain: &SidechainType,
+    ) {
+        let num_channels = data.num_channels().min(self.detectors.len());
+        let sidechain_channels = sidechain.num_channels();
+        if num_channels == 0 || sidechain_channels == 0 {
+            return;
+        }
+
+        let mut max_reduction_db: f32 = 0.0;
+        let num_samples = data.num_samples().min(sidechain.num_samples());
+        for sample_index in 0..num_samples {
+            let mut linked_level: f32 = 0.0;
+            for (channel, detector) in self.detectors[..num_channels].iter_mut().enumerate() {
+                let input = *sidechain.get(channel % sidechain_channels, sample_index);
+                linked_level = linked_level.max(detector.process1(input));
+            }
+
+            for channel in 0..num_channels {
+                let level = if self.stereo_link {
+                    linked_level
+                } else {
+                    self.detectors[channel].level()
+                };
+                let gain_db = self.computer.gain_db(level_db(level));
+                max_reduction_db = max_reduction_db.min(gain_db);
+
+                let gain = db_to_amplitude(gain_db + self.makeup_gain_db, 1.0);
+                let sample = *data.get(channel, sample_index);
+                data.set(channel, sample_index, sample * gain);
+            }
+        }
+
+        self.handle.set_gain_reduction_db(-max_reduction_db);
+    }
+}
+
+impl AudioProcessor for DynamicsProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.detectors = (0..settings.output_channels())
+            .map(|_| {
+                let mut detector =
+                    EnvelopeFollower::new(self.detector_mode, self.attack, self.release);
+                detector.set_sample_rate(self.sample_rate);
+                detector
+            })
+            .collect();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let num_channels = data.num_channels().min(self.detectors.len());
+        if num_channels == 0 {
+            return;
+        }
+
+        let mut max_reduction_db: f32 = 0.0;
+        for frame in data.frames_mut() {
+            let frame = &mut frame[..num_channels];
+            let mut linked_level: f32 = 0.0;
+            for (sample, detector) in frame.iter().zip(&mut self.detectors) {
+                linked_level = linked_level.max(detector.process1(*sample));
+            }
+
+            for (sample, detector) in frame.iter_mut().zip(&self.detectors) {
+                let level = if self.stereo_link {
+                    linked_level
+                } else {
+                    detector.level()
+                };
+                let gain_db = self.computer.gain_db(level_db(level));
+                max_reduction_db = max_reduction_db.min(gain_db);
+                *sample *= db_to_amplitude(gain_db + self.makeup_gain_db, 1.0);
+            }
+        }
+
+        self.handle.set_gain_reduction_db(-max_reduction_db);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::{AudioProcessorSettings, InterleavedAudioBuffer};
+
+    use super::*;
+
+    fn sine(amplitude: f32, num_frames: usize, num_channels: usize) -> Vec<f32> {
+        (0..num_frames)
+            .flat_map(|i| {
+                let sample =
+                    amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin();
+                vec![sample; num_channels]
+            })
+            .collect()
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples
+            .iter()
+            .fold(0.0, |peak, sample| peak.max(sample.abs()))
+    }
+
+    #[test]
+    fn test_compressor_reduces_loud_signals() {
+        let mut compressor = DynamicsProcessor::compressor();
+        compressor.set_threshold_db(-20.0);
+        compressor.set_ratio(4.0);
+        compressor.set_knee_db(0.0);
+        compressor.prepare(AudioProcessorSettings::default());
+
+        // A 0dB sine, 20dB over the threshold, should end up ~15dB down
+        let mut samples = sine(1.0, 44100, 2);
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        compressor.process(&mut buffer);
+
+        let output_db = level_db(peak(&samples[44100..]));
+        assert!((output_db + 15.0).abs() < 1.5, "{}", output_db);
+        let gain_reduction = compressor.handle().gain_reduction_db();
+        assert!((gain_reduction - 15.0).abs() < 1.5, "{}", gain_reduction);
+    }
+
+    #[test]
+    fn test_gate_silences_quiet_signals() {
+        let mut gate = DynamicsProcessor::gate();
+        gate.set_threshold_db(-40.0);
+        gate.prepare(AudioProcessorSettings::default());
+
+        let mut samples = sine(0.001, 44100, 2);
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        gate.process(&mut buffer);
+        assert!(peak(&samples[44100..]) < 1e-6);
+    }
+
+    #[test]
+    fn test_sidechain_drives_the_gain() {
+        let mut compressor = DynamicsProcessor::compressor();
+        compressor.set_threshold_db(-20.0);
+        compressor.set_ratio(f32::INFINITY);
+        compressor.set_knee_db(0.0);
+        compressor.prepare(AudioProcessorSettings::default());
+
+        // A quiet signal ducked by a loud sidechain
+        let mut samples = sine(0.01, 44100, 2);
+        let mut sidechain_samples = sine(1.0, 44100, 2);
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        let sidechain = InterleavedAudioBuffer::new(2, &mut sidechain_samples);
+        compressor.process_with_sidechain(&mut buffer, &sidechain);
+
+        let output_db = level_db(peak(&samples[44100..]));
+        assert!((output_db + 60.0).abs() < 1.5, "{}", output_db);
+    }
+
+    #[test]
+    fn test_unlinked_channels_are_independent() {
+        let mut compressor = DynamicsProcessor::compressor();
+        compressor.set_stereo_link(false);
+        compressor.prepare(AudioProcessorSettings::default());
+
+        // Loud left channel, quiet right channel
+        let mut samples: Vec<f32> = sine(1.0, 44100, 1)
+            .iter()
+            .flat_map(|sample| vec![*sample, sample * 0.01])
+            .collect();
+        let input = samples.clone();
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        compressor.process(&mut buffer);
+
+        let right_output = peak(
+            &samples
+                .iter()
+                .skip(44101)
+                .step_by(2)
+                .copied()
+                .collect::<Vec<_>>(),
+        );
+        let right_input = peak(
+            &input
+                .iter()
+                .skip(44101)
+                .step_by(2)
+                .copied()
+                .collect::<Vec<_>>(),
+        );
+        assert!((right_output - right_input).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+/// How an [`EnvelopeFollower`] measures its input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DetectorMode {
+    /// Follows the absolute value of the input. Reacts to transients
+    #[default]
+    Peak,
+    /// Follows the mean square of the input. Closer to perceived loudness
+    Rms,
+}
+
+/// Follows the level of a signal, rising with the attack time & falling with the release time.
+pub struct EnvelopeFollower {
+    mode: DetectorMode,
+    attack: Duration,
+    release: Duration,
+    sample_rate: f32,
+    attack_coefficient: f32,
+    release_coefficient: f32,
+    /// Absolute value or mean square, depending on the mode
+    envelope: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new(mode: DetectorMode, attack: Duration, release: Duration) -> Self {
+        let mut follower = EnvelopeFollower {
+            mode,
+            attack,
+            release,
+            sample_rate: 44100.0,
+            attack_coefficient: 0.0,
+            release_coefficient: 0.0,
+            envelope: 0.0,
+        };
+        follower.update_coefficients();
+        follower
+    }
+
+    pub fn mode(&self) -> DetectorMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: DetectorMode) {
+        self.mode = mode;
+        self.reset();
+    }
+
+    pub fn attack(&self) -> Duration {
+        self.attack
+    }
+
+    pub fn set_attack(&mut self, attack: Duration) {
+        self.attack = attack;
+        self.update_coefficients();
+    }
+
+    pub fn release(&self) -> Duration {
+        self.release
+    }
+
+    pub fn set_release(&mut self, release: Duration) {
+        self.release = release;
+        self.update_coefficients();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+
+    /// The current level, as a linear amplitude
+    pub fn level(&self) -> f32 {
+        match self.mode {
+            DetectorMode::Peak => self.envelope,
+            DetectorMode::Rms => self.envelope.sqrt(),
+        }
+    }
+
+    /// Follow one sample & return the level, as a linear amplitude
+    #[inline]
+    pub fn process1(&mut self, input: f32) -> f32 {
+        let input = match self.mode {
+            DetectorMode::Peak => input.abs(),
+            DetectorMode::Rms => input * input,
+        };
+        let coefficient = if input > self.envelope {
+            self.attack_coefficient
+        } else {
+            self.release_coefficient
+        };
+        self.envelope = input + coefficient * (self.envelope - input);
+        self.level()
+    }
+
+    fn update_coefficients(&mut self) {
+        self.attack_coefficient = coefficient(self.attack, self.sample_rate);
+        self.release_coefficient = coefficient(self.release, self.sample_rate);
+    }
+}
+
+/// One-pole coefficient reaching ~63% of a step in `time`
+fn coefficient(time: Duration, sample_rate: f32) -> f32 {
+    let samples = time.as_secs_f32() * sample_rate;
+    if samples < 1.0 {
+        0.0
+    } else {
+        (-1.0 / samples).exp()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_attack_is_faster_than_release() {
+        let mut follower = EnvelopeFollower::new(
+            DetectorMode::Peak,
+            Duration::from_millis(1),
+            Duration::from_millis(100),
+        );
+        follower.set_sample_rate(1000.0);
+
+        follower.process1(1.0);
+        let attacked = follower.process1(1.0);
+        assert!(attacked > 0.8);
+
+        follower.process1(0.0);
+        let released = follower.process1(0.0);
+        assert!(released > 0.95 * attacked);
+    }
+
+    #[test]
+    fn test_rms_of_a_sine() {
+        let mut follower = EnvelopeFollower::new(
+            DetectorMode::Rms,
+            Duration::from_millis(50),
+            Duration::from_millis(50),
+        );
+        let mut level = 0.0;
+        for i in 0..44100 {
+            let input = (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin();
+            level = follower.process1(input);
+        }
+        assert!((level - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.02);
+    }
+}
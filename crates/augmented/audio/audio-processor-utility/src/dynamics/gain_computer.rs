@@ -0,0 +1,117 @@
+/// The direction a [`GainComputer`] acts in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicsKind {
+    /// Turns levels above the threshold down
+    Compressor,
+    /// Turns levels below the threshold further down
+    Expander,
+    /// An expander with a very high ratio, silencing levels below the threshold
+    Gate,
+}
+
+/// Ratio used by [`DynamicsKind::Gate`]
+const GATE_RATIO: f32 = 100.0;
+
+/// The static curve of a dynamics processor, mapping input level to gain.
+///
+/// Levels within `knee_db / 2` of the threshold are on a quadratic soft knee.
+#[derive(Debug, Clone)]
+pub struct GainComputer {
+    pub kind: DynamicsKind,
+    pub threshold_db: f32,
+    /// Input dB per output dB, past the threshold. `f32::INFINITY` for a limiter
+    pub ratio: f32,
+    pub knee_db: f32,
+    /// The most the gain may be reduced by, in dB
+    pub range_db: f32,
+}
+
+impl GainComputer {
+    pub fn new(kind: DynamicsKind) -> Self {
+        GainComputer {
+            kind,
+            threshold_db: -20.0,
+            ratio: 4.0,
+            knee_db: 6.0,
+            range_db: 80.0,
+        }
+    }
+
+    /// The gain, in dB, for an input level in dB. Never positive.
+    pub fn gain_db(&self, level_db: f32) -> f32 {
+        let output_db = match self.kind {
+            DynamicsKind::Compressor => self.compress(level_db),
+            DynamicsKind::Expander => self.expand(level_db, self.ratio),
+            DynamicsKind::Gate => self.expand(level_db, GATE_RATIO),
+        };
+        (output_db - level_db).min(0.0).max(-self.range_db)
+    }
+
+    fn compress(&self, level_db: f32) -> f32 {
+        let slope = 1.0 / self.ratio.max(1.0) - 1.0;
+        let overshoot = level_db - self.threshold_db;
+        if 2.0 * overshoot < -self.knee_db {
+            level_db
+        } else if 2.0 * overshoot.abs() <= self.knee_db {
+            let knee_position = overshoot + self.knee_db / 2.0;
+            level_db + slope * knee_position * knee_position / (2.0 * self.knee_db)
+        } else {
+            level_db + slope * overshoot
+        }
+    }
+
+    fn expand(&self, level_db: f32, ratio: f32) -> f32 {
+        let slope = ratio.max(1.0) - 1.0;
+        let overshoot = level_db - self.threshold_db;
+        if 2.0 * overshoot > self.knee_db {
+            level_db
+        } else if 2.0 * overshoot.abs() <= self.knee_db {
+            let knee_position = overshoot - self.knee_db / 2.0;
+            level_db - slope * knee_position * knee_position / (2.0 * self.knee_db)
+        } else {
+            level_db + slope * overshoot
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compressor_curve() {
+        let mut computer = GainComputer::new(DynamicsKind::Compressor);
+        computer.knee_db = 0.0;
+        assert_eq!(computer.gain_db(-30.0), 0.0);
+        // 20dB over the threshold at 4:1 comes out 5dB over
+        assert!((computer.gain_db(0.0) + 15.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_soft_knee_is_continuous() {
+        for kind in [DynamicsKind::Compressor, DynamicsKind::Expander].iter() {
+            let computer = GainComputer::new(*kind);
+            let mut previous = computer.gain_db(-40.0);
+            let mut level_db = -40.0;
+            while level_db < 0.0 {
+                level_db += 0.01;
+                let gain_db = computer.gain_db(level_db);
+                assert!((gain_db - previous).abs() < 0.05, "{:?} {}", kind, level_db);
+                previous = gain_db;
+            }
+        }
+    }
+
+    #[test]
+    fn test_expander_and_gate_act_below_the_threshold() {
+        let mut computer = GainComputer::new(DynamicsKind::Expander);
+        computer.knee_db = 0.0;
+        computer.ratio = 2.0;
+        assert_eq!(computer.gain_db(-10.0), 0.0);
+        assert!((computer.gain_db(-30.0) + 10.0).abs() < 1e-4);
+
+        computer.kind = DynamicsKind::Gate;
+        computer.range_db = 60.0;
+        assert_eq!(computer.gain_db(-30.0), -60.0);
+    }
+}
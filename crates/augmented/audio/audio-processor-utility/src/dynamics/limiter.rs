@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use audio_volume::db_to_amplitude;
+
+use super::{level_db, DynamicsHandle};
+
+/// A brickwall limiter with lookahead.
+///
+/// For every frame, the gain which would keep the loudest channel under the ceiling is found.
+/// Gains are held for the lookahead time & averaged over it, so the gain ramps down ahead of
+/// each peak. The input is delayed to match, see [`LimiterProcessor::latency`]. Channels are
+/// always linked.
+pub struct LimiterProcessor {
+    ceiling_db: f32,
+    lookahead: Duration,
+    release: Duration,
+    sample_rate: f32,
+    num_channels: usize,
+    /// Frames the gain is held & averaged over
+    window_size: usize,
+    release_coefficient: f32,
+    /// Released gain, before lookahead
+    envelope: f32,
+    frame_count: usize,
+    /// (frame, gain) pairs with increasing gains, the front is the window's minimum
+    minimum_window: VecDeque<(usize, f32)>,
+    average_window: Vec<f32>,
+    average_position: usize,
+    average_sum: f64,
+    /// Interleaved, `window_size - 1` frames
+    delay_buffer: Vec<f32>,
+    delay_position: usize,
+    handle: Arc<DynamicsHandle>,
+}
+
+impl Default for LimiterProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LimiterProcessor {
+    pub fn new() -> Self {
+        LimiterProcessor {
+            ceiling_db: -0.3,
+            lookahead: Duration::from_millis(5),
+            release: Duration::from_millis(100),
+            sample_rate: 44100.0,
+            num_channels: 0,
+            window_size: 1,
+            release_coefficient: 0.0,
+            envelope: 1.0,
+            frame_count: 0,
+            minimum_window: VecDeque::new(),
+            average_window: Vec::new(),
+            average_position: 0,
+            average_sum: 0.0,
+            delay_buffer: Vec::new(),
+            delay_position: 0,
+            handle: Arc::new(DynamicsHandle::default()),
+        }
+    }
+
+    /// Metering, which may be read from any thread
+    pub fn handle(&self) -> &Arc<DynamicsHandle> {
+        &self.handle
+    }
+
+    pub fn ceiling_db(&self) -> f32 {
+        self.ceiling_db
+    }
+
+    /// Set the level the output never goes over
+    pub fn set_ceiling_db(&mut self, ceiling_db: f32) {
+        self.ceiling_db = ceiling_db;
+    }
+
+    pub fn lookahead(&self) -> Duration {
+        self.lookahead
+    }
+
+    /// Set the lookahead time. Reallocates the delay on the next `prepare`.
+    pub fn set_lookahead(&mut self, lookahead: Duration) {
+        self.lookahead = lookahead;
+    }
+
+    pub fn release(&self) -> Duration {
+        self.release
+    }
+
+    pub fn set_release(&mut self, release: Duration) {
+        self.release = release;
+        self.update_release_coefficient();
+    }
+
+    /// Clear the lookahead delay & gain state
+    pub fn reset(&mut self) {
+        self.envelope = 1.0;
+        self.frame_count = 0;
+        self.minimum_window.clear();
+        self.average_window.iter_mut().for_each(|gain| *gain = 1.0);
+        self.average_position = 0;
+        self.average_sum = self.window_size as f64;
+        self.delay_buffer
+            .iter_mut()
+            .for_each(|sample| *sample = 0.0);
+        self.delay_position = 0;
+    }
+
+    fn update_release_coefficient(&mut self) {
+        let release_samples = self.release.as_secs_f32() * self.sample_rate;
+        self.release_coefficient = if release_samples < 1.0 {
+            0.0
+        } else {
+            (-1.0 / release_samples).exp()
+        };
+    }
+
+    /// The gain for the frame `window_size - 1` frames ago, given the current frame's peak
+    #[inline]
+    fn next_gain(&mut self, peak: f32, ceiling: f32) -> f32 {
+        let target = if peak > ceiling { ceiling / peak } else { 1.0 };
+        self.envelope = if target < self.envelope {
+            target
+        } else {
+            target + self.release_coefficient * (self.envelope - target)
+        };
+
+        // Minimum over the window
+        while let Some((_, gain)) = self.minimum_window.back() {
+            if *gain < self.envelope {
+                break;
+            }
+            self.minimum_window.pop_back();
+        }
+        self.minimum_window
+            .push_back((self.frame_count, self.envelope));
+        while let Some((frame, _)) = self.minimum_window.front() {
+            if frame + self.window_size > self.frame_count {
+                break;
+            }
+            self.minimum_window.pop_front();
+        }
+        let minimum = self.minimum_window.front().map_or(1.0, |(_, gain)| *gain);
+        self.frame_count += 1;
+
+        // Average over the window, so the gain ramps down ahead of the peak
+        self.average_sum += (minimum - self.average_window[self.average_position]) as f64;
+        self.average_window[self.average_position] = minimum;
+        self.average_position = (self.average_position + 1) % self.window_size;
+        (self.average_sum / self.window_size as f64) as f32
+    }
+}
+
+impl AudioProcessor for LimiterProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.num_channels = settings.output_channels();
+        let lookahead_samples = (self.lookahead.as_secs_f32() * self.sample_rate).round() as usize;
+        self.window_size = lookahead_samples + 1;
+        self.minimum_window = VecDeque::with_capacity(self.window_size + 1);
+        self.average_window = vec![1.0; self.window_size];
+        self.delay_buffer = vec![0.0; (self.window_size - 1) * self.num_channels];
+        self.update_release_coefficient();
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let num_channels = data.num_channels().min(self.num_channels);
+        if num_channels == 0 {
+            return;
+        }
+
+        let ceiling = db_to_amplitude(self.ceiling_db, 1.0);
+        let mut min_gain: f32 = 1.0;
+        for frame in data.frames_mut() {
+            let frame = &mut frame[..num_channels];
+            let peak = frame
+                .iter()
+                .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+            let gain = self.next_gain(peak, ceiling);
+            min_gain = min_gain.min(gain);
+
+            if !self.delay_buffer.is_empty() {
+                let delayed = &mut self.delay_buffer[self.delay_position * self.num_channels..]
+                    [..num_channels];
+                for (sample, delayed) in frame.iter_mut().zip(delayed) {
+                    std::mem::swap(sample, delayed);
+                }
+                self.delay_position = (self.delay_position + 1) % (self.window_size - 1);
+            }
+
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
+
+        self.handle.set_gain_reduction_db(-level_db(min_gain));
+    }
+
+    /// Delay added by the lookahead, in frames
+    fn latency(&self) -> usize {
+        self.window_size - 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::{AudioProcessorSettings, InterleavedAudioBuffer};
+
+    use super::*;
+
+    #[test]
+    fn test_output_never_passes_the_ceiling() {
+        let mut limiter = LimiterProcessor::new();
+        limiter.set_ceiling_db(-6.0);
+        limiter.prepare(AudioProcessorSettings::default());
+
+        // Noise with sudden bursts
+        let mut samples: Vec<f32> = (0..44100 * 2)
+            .map(|i| {
+                let burst = if (i / 2000) % 3 == 0 { 4.0 } else { 0.5 };
+                burst * (rand::random::<f32>() * 2.0 - 1.0)
+            })
+            .collect();
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        limiter.process(&mut buffer);
+
+        let ceiling = db_to_amplitude(-6.0, 1.0);
+        for sample in &samples {
+            assert!(sample.abs() <= ceiling + 1e-5, "{}", sample);
+        }
+        assert!(limiter.handle().gain_reduction_db() > 6.0);
+    }
+
+    #[test]
+    fn test_quiet_signals_are_delayed_by_the_latency() {
+        let mut limiter = LimiterProcessor::new();
+        limiter.prepare(AudioProcessorSettings::new(1000.0, 1, 1, 512));
+        assert_eq!(limiter.latency(), 5);
+
+        let mut samples = vec![0.0; 10];
+        samples[0] = 0.5;
+        let mut buffer = InterleavedAudioBuffer::new(1, &mut samples);
+        limiter.process(&mut buffer);
+
+        let mut expected = vec![0.0; 10];
+        expected[5] = 0.5;
+        assert_eq!(samples, expected);
+    }
+}
@@ -0,0 +1,48 @@
+//! Compressor, expander, gate & limiter.
+//!
+//! [`DynamicsProcessor`] is a compressor, expander or gate, depending on its [`DynamicsKind`].
+//! Its level detector follows the input, or an external sidechain, with peak or RMS detection
+//! ([`DetectorMode`]). Channels may be linked, so the stereo image doesn't shift.
+//!
+//! [`LimiterProcessor`] is a brickwall limiter. It delays its input by a lookahead time, so the
+//! gain is turned down before peaks arrive & the output never passes the ceiling.
+//!
+//! Both publish their gain reduction on a [`DynamicsHandle`], which may be read from any thread.
+use audio_processor_traits::AtomicF32;
+use audio_volume::amplitude_to_db;
+
+pub use compressor::DynamicsProcessor;
+pub use envelope::{DetectorMode, EnvelopeFollower};
+pub use gain_computer::{DynamicsKind, GainComputer};
+pub use limiter::LimiterProcessor;
+
+mod compressor;
+mod envelope;
+mod gain_computer;
+mod limiter;
+
+/// Metering shared between a dynamics processor & UIs
+#[derive(Default)]
+pub struct DynamicsHandle {
+    gain_reduction_db: AtomicF32,
+}
+
+impl DynamicsHandle {
+    /// The largest gain reduction in the last processed block, as a positive number of dB
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.gain_reduction_db.get()
+    }
+
+    fn set_gain_reduction_db(&self, gain_reduction_db: f32) {
+        self.gain_reduction_db.set(gain_reduction_db);
+    }
+}
+
+/// Levels below this are treated as silence
+const MIN_LEVEL_DB: f32 = -120.0;
+
+/// A linear level in dB, with silence at [`MIN_LEVEL_DB`]
+#[inline]
+fn level_db(level: f32) -> f32 {
+    amplitude_to_db(level, 1.0).max(MIN_LEVEL_DB)
+}
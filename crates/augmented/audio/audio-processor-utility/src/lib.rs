@@ -1,8 +1,33 @@
+/// Route N input channels to M outputs
+pub mod channel_matrix;
+/// Remove DC offset
+pub mod dc_blocker;
+/// Compressor, expander, gate & limiter
+pub mod dynamics;
 /// Apply gain to input
 pub mod gain;
+/// Mid/side encoding & decoding, and stereo width
+pub mod mid_side;
 /// Convert stereo signals to mono
 pub mod mono;
 /// Pan signals to left/right
 pub mod pan;
+/// Invert the polarity of channels
+pub mod polarity;
 /// Convert mono signals to stereo
 pub mod stereo;
+
+use std::time::Duration;
+
+use audio_processor_traits::AudioProcessorSettings;
+use smooth_value::InterpolatedValue;
+
+/// How long parameter changes are ramped for
+const SMOOTHING_TIME: Duration = Duration::from_millis(10);
+
+/// A parameter starting at `value` & ramped over `SMOOTHING_TIME`, at the default sample rate
+/// until prepared
+fn smoothed_value(value: f32) -> InterpolatedValue {
+    let sample_rate = AudioProcessorSettings::default().sample_rate();
+    InterpolatedValue::new(sample_rate, SMOOTHING_TIME, value)
+}
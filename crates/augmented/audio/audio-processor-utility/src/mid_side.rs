@@ -0,0 +1,202 @@
+use std::marker::PhantomData;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings, Float};
+
+use smooth_value::InterpolatedValue;
+
+use crate::smoothed_value;
+
+/// An `AudioProcessor` which encodes left/right stereo into mid/side.
+///
+/// The mid, `(left + right) / 2`, is written to channel 0 & the side, `(left - right) / 2`, to
+/// channel 1. Frames with fewer than two channels are left untouched.
+pub struct MidSideEncoderProcessor<SampleType> {
+    phantom: PhantomData<SampleType>,
+}
+
+impl<SampleType> Default for MidSideEncoderProcessor<SampleType> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<SampleType> MidSideEncoderProcessor<SampleType> {
+    pub fn new() -> Self {
+        MidSideEncoderProcessor {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<SampleType> AudioProcessor for MidSideEncoderProcessor<SampleType>
+where
+    SampleType: Float + Sync + Send,
+{
+    type SampleType = SampleType;
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let half = SampleType::from(0.5).unwrap();
+        for frame in data.frames_mut() {
+            if frame.len() < 2 {
+                continue;
+            }
+            let (left, right) = (frame[0], frame[1]);
+            frame[0] = (left + right) * half;
+            frame[1] = (left - right) * half;
+        }
+    }
+}
+
+/// An `AudioProcessor` which decodes mid/side, as written by [`MidSideEncoderProcessor`], back into
+/// left/right stereo.
+///
+/// Frames with fewer than two channels are left untouched.
+pub struct MidSideDecoderProcessor<SampleType> {
+    phantom: PhantomData<SampleType>,
+}
+
+impl<SampleType> Default for MidSideDecoderProcessor<SampleType> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<SampleType> MidSideDecoderProcessor<SampleType> {
+    pub fn new() -> Self {
+        MidSideDecoderProcessor {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<SampleType> AudioProcessor for MidSideDecoderProcessor<SampleType>
+where
+    SampleType: Float + Sync + Send,
+{
+    type SampleType = SampleType;
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            if frame.len() < 2 {
+                continue;
+            }
+            let (mid, side) = (frame[0], frame[1]);
+            frame[0] = mid + side;
+            frame[1] = mid - side;
+        }
+    }
+}
+
+/// An `AudioProcessor` which narrows or widens a stereo signal by scaling its side.
+///
+/// A width of 0 sums to mono, 1 leaves the signal untouched & 2 doubles the side. Changes to the
+/// width are smoothed. Frames with fewer than two channels are left untouched.
+pub struct StereoWidthProcessor<SampleType> {
+    width: InterpolatedValue,
+    phantom: PhantomData<SampleType>,
+}
+
+impl<SampleType: Float> Default for StereoWidthProcessor<SampleType> {
+    fn default() -> Self {
+        Self::new(SampleType::one())
+    }
+}
+
+impl<SampleType: Float> StereoWidthProcessor<SampleType> {
+    pub fn new(width: SampleType) -> Self {
+        StereoWidthProcessor {
+            width: smoothed_value(width.max(SampleType::zero()).to_f32().unwrap()),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn width(&self) -> SampleType {
+        SampleType::from(self.width.target()).unwrap()
+    }
+
+    /// Set the width, 0 or more
+    pub fn set_width(&mut self, width: SampleType) {
+        self.width
+            .set(width.max(SampleType::zero()).to_f32().unwrap());
+    }
+}
+
+impl<SampleType> AudioProcessor for StereoWidthProcessor<SampleType>
+where
+    SampleType: Float + Sync + Send,
+{
+    type SampleType = SampleType;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.width.set_sample_rate(settings.sample_rate());
+        self.width.jump();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let half = SampleType::from(0.5).unwrap();
+        for frame in data.frames_mut() {
+            if frame.len() < 2 {
+                continue;
+            }
+            let width = SampleType::from(self.width.next_sample()).unwrap();
+            let (left, right) = (frame[0], frame[1]);
+            let mid = (left + right) * half;
+            let side = (left - right) * half * width;
+            frame[0] = mid + side;
+            frame[1] = mid - side;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let mut samples = [1.0, 0.0, 0.5, 0.25, -0.3, 0.7];
+        let original = samples;
+
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+        MidSideEncoderProcessor::new().process(&mut input);
+        assert_eq!(input.slice()[..2], [0.5, 0.5]);
+        MidSideDecoderProcessor::new().process(&mut input);
+
+        for (sample, original) in samples.iter().zip(&original) {
+            assert!((sample - original).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_zero_width_is_mono() {
+        let mut width = StereoWidthProcessor::new(0.0);
+        let mut samples = [1.0, 0.0, 0.5, -0.5];
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+        width.process(&mut input);
+
+        assert_eq!(samples, [0.5, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_unit_width_is_a_noop() {
+        let mut width = StereoWidthProcessor::default();
+        let mut samples = [1.0, 0.0, 0.5, -0.5];
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+        width.process(&mut input);
+
+        assert_eq!(samples, [1.0, 0.0, 0.5, -0.5]);
+    }
+}
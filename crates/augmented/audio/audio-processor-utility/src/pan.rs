@@ -1,12 +1,91 @@
-use audio_processor_traits::{AudioBuffer, AudioProcessor, Float};
+use std::marker::PhantomData;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings, Float};
+use smooth_value::InterpolatedValue;
+
+use crate::smoothed_value;
+
+/// How a channel's level is split between the two sides as it's panned across.
+///
+/// Each is named after the level on both sides when the channel is split evenly, e.g. a mono
+/// source panned to the centre. When [`PanProcessor`] balances a stereo source instead, the law only
+/// shapes how a channel moves across once it's panned away from the centre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanLaw {
+    /// 0dB when split evenly; the channel only fades from its own side once it's past half-way
+    Linear,
+    /// Constant power, so a channel sounds as loud wherever it's panned on speakers
+    Minus3Db,
+    /// A compromise between constant power & constant amplitude
+    Minus4Point5Db,
+    /// Constant amplitude, so a channel sums to the same level in mono wherever it's panned
+    #[default]
+    Minus6Db,
+}
+
+impl PanLaw {
+    pub const ALL: [PanLaw; 4] = [
+        PanLaw::Linear,
+        PanLaw::Minus3Db,
+        PanLaw::Minus4Point5Db,
+        PanLaw::Minus6Db,
+    ];
+
+    /// Gains of a channel on its own side & on the other, once it's moved `position` of the way
+    /// across, between 0 & 1
+    pub fn gains<SampleType: Float>(&self, position: SampleType) -> (SampleType, SampleType) {
+        let one = SampleType::one();
+        let two = one + one;
+        let quarter_turn = SampleType::from(std::f64::consts::FRAC_PI_2).unwrap();
+        let angle = position * quarter_turn;
+        match self {
+            PanLaw::Linear => ((two * (one - position)).min(one), (two * position).min(one)),
+            PanLaw::Minus3Db => (angle.cos(), angle.sin()),
+            PanLaw::Minus4Point5Db => (
+                ((one - position) * angle.cos()).sqrt(),
+                (position * angle.sin()).sqrt(),
+            ),
+            PanLaw::Minus6Db => (one - position, position),
+        }
+    }
+
+    /// Left & right gains of a mono source at `panning`, between -1 (left) & 1 (right)
+    pub fn mono_gains<SampleType: Float>(&self, panning: SampleType) -> (SampleType, SampleType) {
+        let one = SampleType::one();
+        self.gains((panning + one) / (one + one))
+    }
+}
+
+/// What a [`PanProcessor`] pans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanSource {
+    /// Balance the first two channels; at the centre they're left untouched
+    #[default]
+    Stereo,
+    /// Pan the first channel across the first two, following the [`PanLaw`] at the centre too
+    Mono,
+}
 
 /// An `AudioProcessor` that applies panning on its input.
 ///
-/// Does not perform any bounds checking.
+/// With a [`PanSource::Stereo`] source, the default, this is a balance control: at the centre both
+/// channels are left untouched, whatever the [`PanLaw`]. Panning right moves the left channel
+/// across into the right one, following the law, so half-way to the right the left channel is
+/// split evenly between both sides, e.g. at -3dB on each with [`PanLaw::Minus3Db`]. Panning left
+/// does the opposite.
+///
+/// With a [`PanSource::Mono`] source, the first channel is panned into the first two following the
+/// law, e.g. at -3dB on both sides at the centre with [`PanLaw::Minus3Db`].
+///
+/// Changes to the panning are smoothed. Only the first two channels are written; frames with
+/// fewer are left untouched.
 pub struct PanProcessor<SampleType> {
     /// A number between -1 and 1
     /// -1 represents using the left channel only, 1 represents using the right channel only.
-    panning: SampleType,
+    panning: InterpolatedValue,
+    pan_law: PanLaw,
+    source: PanSource,
+    phantom: PhantomData<SampleType>,
 }
 
 impl<SampleType: Float> Default for PanProcessor<SampleType> {
@@ -19,19 +98,45 @@ impl<SampleType: Float> PanProcessor<SampleType> {
     /// Create a processor with panning.
     /// -1 represents using the left channel only, 1 represents using the right channel only.
     pub fn new(panning: SampleType) -> Self {
-        PanProcessor { panning }
+        Self::new_with_law(panning, PanLaw::default())
+    }
+
+    /// Create a processor with panning & a pan law
+    pub fn new_with_law(panning: SampleType, pan_law: PanLaw) -> Self {
+        PanProcessor {
+            panning: smoothed_value(panning.to_f32().unwrap()),
+            pan_law,
+            source: PanSource::default(),
+            phantom: PhantomData,
+        }
     }
 
     /// -1 represents using the left channel only, 1 represents using the right channel only.
     pub fn panning(&self) -> SampleType {
-        self.panning
+        SampleType::from(self.panning.target()).unwrap()
     }
 
     /// Set the panning.
     ///
     /// -1 represents using the left channel only, 1 represents using the right channel only.
     pub fn set_panning(&mut self, panning: SampleType) {
-        self.panning = panning;
+        self.panning.set(panning.to_f32().unwrap());
+    }
+
+    pub fn pan_law(&self) -> PanLaw {
+        self.pan_law
+    }
+
+    pub fn set_pan_law(&mut self, pan_law: PanLaw) {
+        self.pan_law = pan_law;
+    }
+
+    pub fn source(&self) -> PanSource {
+        self.source
+    }
+
+    pub fn set_source(&mut self, source: PanSource) {
+        self.source = source;
     }
 }
 
@@ -41,6 +146,11 @@ where
 {
     type SampleType = SampleType;
 
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.panning.set_sample_rate(settings.sample_rate());
+        self.panning.jump();
+    }
+
     fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
         &mut self,
         data: &mut BufferType,
@@ -48,23 +158,29 @@ where
         let zero = SampleType::zero();
         let one = SampleType::one();
         for frame in data.frames_mut() {
-            let panning = self.panning;
+            if frame.len() < 2 {
+                continue;
+            }
+            let panning = SampleType::from(self.panning.next_sample())
+                .unwrap()
+                .max(-one)
+                .min(one);
 
             let left_input = frame[0];
             let right_input = frame[1];
 
-            if panning > zero {
-                let left_output = left_input * (one - panning);
-                let right_output = right_input + left_input * panning;
-
-                frame[0] = left_output;
-                frame[1] = right_output;
+            if self.source == PanSource::Mono {
+                let (left, right) = self.pan_law.mono_gains(panning);
+                frame[0] = left_input * left;
+                frame[1] = left_input * right;
+            } else if panning > zero {
+                let (stay, moved) = self.pan_law.gains(panning);
+                frame[0] = left_input * stay;
+                frame[1] = right_input + left_input * moved;
             } else if panning < zero {
-                let left_output = left_input + right_input * (-panning);
-                let right_output = right_input * (one + panning);
-
-                frame[0] = left_output;
-                frame[1] = right_output;
+                let (stay, moved) = self.pan_law.gains(-panning);
+                frame[0] = left_input + right_input * moved;
+                frame[1] = right_input * stay;
             }
         }
     }
@@ -121,4 +237,100 @@ mod test {
             assert_eq!(left, 0.0);
         }
     }
+
+    #[test]
+    fn test_centre_leaves_stereo_untouched() {
+        for law in PanLaw::ALL.iter() {
+            let mut pan = PanProcessor::new_with_law(0.0, *law);
+            pan.prepare(AudioProcessorSettings::default());
+            let mut samples = [0.5, -0.25, 0.1, 0.2];
+            let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+            pan.process(&mut input);
+
+            assert_eq!(samples, [0.5, -0.25, 0.1, 0.2], "{:?}", law);
+        }
+    }
+
+    #[test]
+    fn test_pan_laws_half_way_across() {
+        let expected_db = [0.0, -3.0, -4.5, -6.0];
+        for (law, expected_db) in PanLaw::ALL.iter().zip(expected_db.iter()) {
+            let mut pan = PanProcessor::new_with_law(0.5, *law);
+            pan.prepare(AudioProcessorSettings::default());
+            // Only the left channel, which is moved across; then only the right, which stays
+            let mut samples = [1.0_f32, 0.0, 0.0, 1.0];
+            let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+            pan.process(&mut input);
+
+            assert!((samples[0] - samples[1]).abs() < 1e-6, "{:?}", law);
+            let db = 20.0 * samples[0].log10();
+            assert!((db - expected_db).abs() < 0.1, "{:?} {}", law, db);
+            assert_eq!(samples[2..], [0.0, 1.0], "{:?}", law);
+        }
+    }
+
+    #[test]
+    fn test_mono_source_pan_laws_at_the_centre() {
+        let expected_db = [0.0, -3.0, -4.5, -6.0];
+        for (law, expected_db) in PanLaw::ALL.iter().zip(expected_db.iter()) {
+            let mut pan = PanProcessor::new_with_law(0.0, *law);
+            pan.set_source(PanSource::Mono);
+            pan.prepare(AudioProcessorSettings::default());
+            // The second channel is replaced by the panned first one
+            let mut samples = [1.0_f32, 0.5];
+            let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+            pan.process(&mut input);
+
+            assert!((samples[0] - samples[1]).abs() < 1e-6, "{:?}", law);
+            let db = 20.0 * samples[0].log10();
+            assert!((db - expected_db).abs() < 0.1, "{:?} {}", law, db);
+        }
+    }
+
+    #[test]
+    fn test_mono_source_hard_pan() {
+        for law in PanLaw::ALL.iter() {
+            let mut pan = PanProcessor::new_with_law(-1.0, *law);
+            pan.set_source(PanSource::Mono);
+            pan.prepare(AudioProcessorSettings::default());
+            let mut samples = [1.0_f32, 0.5];
+            let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+            pan.process(&mut input);
+
+            assert!((samples[0] - 1.0).abs() < 1e-6, "{:?}", law);
+            assert!(samples[1].abs() < 1e-6, "{:?}", law);
+        }
+    }
+
+    #[test]
+    fn test_panning_changes_are_smoothed() {
+        let mut pan = PanProcessor::new_with_law(0.0, PanLaw::Minus3Db);
+        pan.prepare(AudioProcessorSettings::default());
+        pan.set_panning(1.0);
+        let mut samples = [1.0; 2 * 5000];
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+
+        pan.process(&mut input);
+
+        // The left channel fades out instead of jumping
+        let left: Vec<f32> = input.frames().map(|frame| frame[0]).collect();
+        assert!(left[0] > 0.99);
+        assert!(left.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(left[4999] < 0.01);
+    }
+
+    #[test]
+    fn test_mono_input_is_left_untouched() {
+        let mut pan = PanProcessor::new(1.0);
+        let mut samples = [1.0, 1.0, 1.0];
+        let mut input = InterleavedAudioBuffer::new(1, &mut samples);
+
+        pan.process(&mut input);
+
+        assert_eq!(samples, [1.0, 1.0, 1.0]);
+    }
 }
@@ -0,0 +1,106 @@
+use std::marker::PhantomData;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings, Float};
+
+use smooth_value::InterpolatedValue;
+
+use crate::smoothed_value;
+
+/// An `AudioProcessor` which inverts the polarity of some of its channels.
+///
+/// Toggling a channel ramps its gain between 1 & -1 rather than flipping it, so it doesn't click.
+/// Channels past `num_channels` are left untouched.
+pub struct PolarityProcessor<SampleType> {
+    gains: Vec<InterpolatedValue>,
+    phantom: PhantomData<SampleType>,
+}
+
+impl<SampleType: Float> PolarityProcessor<SampleType> {
+    /// Create a processor for `num_channels`, with none inverted
+    pub fn new(num_channels: usize) -> Self {
+        PolarityProcessor {
+            gains: (0..num_channels).map(|_| smoothed_value(1.0)).collect(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.gains.len()
+    }
+
+    /// Whether `channel` is inverted. Channels out of range aren't.
+    pub fn is_inverted(&self, channel: usize) -> bool {
+        matches!(self.gains.get(channel), Some(gain) if gain.target() < 0.0)
+    }
+
+    /// Invert `channel` or not. Channels out of range are ignored.
+    pub fn set_inverted(&mut self, channel: usize, inverted: bool) {
+        if let Some(gain) = self.gains.get_mut(channel) {
+            gain.set(if inverted { -1.0 } else { 1.0 });
+        }
+    }
+}
+
+impl<SampleType> AudioProcessor for PolarityProcessor<SampleType>
+where
+    SampleType: Float + Sync + Send,
+{
+    type SampleType = SampleType;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        for gain in &mut self.gains {
+            gain.set_sample_rate(settings.sample_rate());
+            gain.jump();
+        }
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            for (sample, gain) in frame.iter_mut().zip(&mut self.gains) {
+                *sample = *sample * SampleType::from(gain.next_sample()).unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_inverted_channels_are_flipped() {
+        let mut polarity = PolarityProcessor::new(2);
+        polarity.set_inverted(1, true);
+        polarity.set_inverted(5, true);
+        polarity.prepare(AudioProcessorSettings::default());
+        assert!(polarity.is_inverted(1));
+        assert!(!polarity.is_inverted(0));
+        assert!(!polarity.is_inverted(5));
+
+        let mut samples = [0.5, 0.5, 0.5, 0.5];
+        let mut input = InterleavedAudioBuffer::new(2, &mut samples);
+        polarity.process(&mut input);
+
+        assert_eq!(samples, [0.5, -0.5, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_toggling_ramps_through_zero() {
+        let mut polarity = PolarityProcessor::new(1);
+        polarity.prepare(AudioProcessorSettings::default());
+        polarity.set_inverted(0, true);
+
+        let mut samples = [1.0; 10000];
+        let mut input = InterleavedAudioBuffer::new(1, &mut samples);
+        polarity.process(&mut input);
+
+        assert!(samples.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(samples[0] > 0.9);
+        assert_eq!(samples[9999], -1.0);
+    }
+}
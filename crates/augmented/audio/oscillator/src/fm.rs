@@ -0,0 +1,436 @@
+//! Phase-modulation (FM) synthesis building blocks.
+//!
+//! An [`Operator`] is a sine [`Oscillator`] with its own [`Envelope`], frequency ratio and
+//! self-feedback, which accepts a phase-modulation input on every sample. An [`Algorithm`] routes
+//! operators into each other and into the output and an [`FMVoice`] runs 2-6 operators following
+//! an algorithm.
+//!
+//! ```
+//! use oscillator::fm::{Algorithm, FMVoice};
+//!
+//! // Operator 1 modulates operator 0, operator 0 is the carrier
+//! let mut voice = FMVoice::new(44100.0, Algorithm::stack(2));
+//! voice.operator_mut(1).set_ratio(2.0);
+//! voice.algorithm_mut().set_modulation(1, 0, 0.5);
+//! voice.note_on(440.0);
+//! let _sample = voice.next_sample();
+//! ```
+use adsr_envelope::Envelope;
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+use crate::{generators, Oscillator};
+
+/// Maximum number of operators an [`Algorithm`] / [`FMVoice`] may have
+pub const MAX_OPERATORS: usize = 6;
+
+/// A sine oscillator with a phase-modulation input, feedback and an envelope.
+///
+/// Phase modulation is expressed in cycles, so a modulation input of `1.0` offsets the phase by
+/// one full period (`2π` radians).
+pub struct Operator {
+    oscillator: Oscillator<f32>,
+    envelope: Envelope,
+    /// Note frequency, before the ratio is applied
+    base_frequency: f32,
+    /// Frequency ratio to the carrier / note frequency
+    ratio: f32,
+    /// Output level
+    level: f32,
+    /// Amount of this operator's output fed back into its own phase
+    feedback: f32,
+    /// Last two outputs, averaged for feedback to avoid the "hunting" oscillation of 1 sample
+    /// feedback loops
+    previous_outputs: [f32; 2],
+}
+
+impl Operator {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(sample_rate);
+        let oscillator = Oscillator::new_with_sample_rate(sample_rate, generators::sine_generator);
+        Operator {
+            base_frequency: oscillator.get_frequency(),
+            oscillator,
+            envelope,
+            ratio: 1.0,
+            level: 1.0,
+            feedback: 0.0,
+            previous_outputs: [0.0; 2],
+        }
+    }
+
+    /// Set the sample rate
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.oscillator.set_sample_rate(sample_rate);
+        self.envelope.set_sample_rate(sample_rate);
+    }
+
+    /// Set the frequency ratio to the note frequency. Integer ratios give harmonic spectra.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio;
+        self.set_base_frequency(self.base_frequency);
+    }
+
+    /// Get the frequency ratio to the note frequency
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    /// Set the output level
+    pub fn set_level(&mut self, level: f32) {
+        self.level = level;
+    }
+
+    /// Get the output level
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Set the self-feedback amount, in cycles per unit of output
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback;
+    }
+
+    /// Get the self-feedback amount
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Get the operator's envelope
+    pub fn envelope(&self) -> &Envelope {
+        &self.envelope
+    }
+
+    /// Get the operator's envelope for configuration
+    pub fn envelope_mut(&mut self) -> &mut Envelope {
+        &mut self.envelope
+    }
+
+    /// Set the note frequency. The oscillator will run at this frequency times the ratio.
+    pub fn set_base_frequency(&mut self, frequency: f32) {
+        self.base_frequency = frequency;
+        self.oscillator.set_frequency(frequency * self.ratio);
+    }
+
+    /// Trigger the envelope
+    pub fn note_on(&mut self) {
+        self.envelope.note_on();
+    }
+
+    /// Release the envelope
+    pub fn note_off(&mut self) {
+        self.envelope.note_off();
+    }
+
+    /// Produce the next sample with a phase-modulation input & tick the oscillator and envelope.
+    pub fn next_sample(&mut self, phase_modulation: f32) -> f32 {
+        let feedback = self.feedback * (self.previous_outputs[0] + self.previous_outputs[1]) * 0.5;
+        let phase = self.oscillator.phase() + phase_modulation + feedback;
+        let phase = phase - phase.floor();
+
+        let output = self.oscillator.value_for_phase(phase) * self.envelope.volume() * self.level;
+
+        self.previous_outputs[1] = self.previous_outputs[0];
+        self.previous_outputs[0] = output;
+        self.oscillator.tick();
+        self.envelope.tick();
+
+        output
+    }
+}
+
+/// Routes operators into each other and into the output.
+///
+/// Operators are numbered from `0` and modulation may only flow from a higher index into a lower
+/// one, so an algorithm never has cycles (use [`Operator::set_feedback`] for self-feedback).
+/// Operators are evaluated from the highest index down.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Algorithm {
+    num_operators: usize,
+    /// `modulation[source][destination]` is the amount `source` modulates `destination`
+    modulation: [[f32; MAX_OPERATORS]; MAX_OPERATORS],
+    /// Level of each operator in the voice output
+    output: [f32; MAX_OPERATORS],
+}
+
+impl Algorithm {
+    /// Create an algorithm with no routing
+    ///
+    /// # Panics
+    /// If `num_operators` isn't between 2 and [`MAX_OPERATORS`]
+    pub fn new(num_operators: usize) -> Self {
+        assert!(
+            (2..=MAX_OPERATORS).contains(&num_operators),
+            "FM algorithms must have between 2 and {} operators",
+            MAX_OPERATORS
+        );
+        Algorithm {
+            num_operators,
+            modulation: [[0.0; MAX_OPERATORS]; MAX_OPERATORS],
+            output: [0.0; MAX_OPERATORS],
+        }
+    }
+
+    /// Operators in series, each modulating the next with amount `1.0`. Operator `0` is the only
+    /// carrier.
+    pub fn stack(num_operators: usize) -> Self {
+        let mut algorithm = Algorithm::new(num_operators);
+        for source in 1..num_operators {
+            algorithm.set_modulation(source, source - 1, 1.0);
+        }
+        algorithm.set_output(0, 1.0);
+        algorithm
+    }
+
+    /// All operators are carriers mixed into the output with equal levels (additive synthesis)
+    pub fn parallel(num_operators: usize) -> Self {
+        let mut algorithm = Algorithm::new(num_operators);
+        for operator in 0..num_operators {
+            algorithm.set_output(operator, 1.0 / num_operators as f32);
+        }
+        algorithm
+    }
+
+    /// Number of operators in this algorithm
+    pub fn num_operators(&self) -> usize {
+        self.num_operators
+    }
+
+    /// Set the amount `source` modulates `destination`
+    ///
+    /// # Panics
+    /// If `source` isn't higher than `destination` or indexes are out of range
+    pub fn set_modulation(&mut self, source: usize, destination: usize, amount: f32) {
+        assert!(source < self.num_operators && destination < self.num_operators);
+        assert!(
+            source > destination,
+            "Modulation must flow from a higher to a lower operator index"
+        );
+        self.modulation[source][destination] = amount;
+    }
+
+    /// Get the amount `source` modulates `destination`
+    pub fn modulation(&self, source: usize, destination: usize) -> f32 {
+        self.modulation[source][destination]
+    }
+
+    /// Set the level of an operator in the output
+    pub fn set_output(&mut self, operator: usize, level: f32) {
+        assert!(operator < self.num_operators);
+        self.output[operator] = level;
+    }
+
+    /// Get the level of an operator in the output
+    pub fn output(&self, operator: usize) -> f32 {
+        self.output[operator]
+    }
+}
+
+/// A monophonic FM voice of 2-6 [`Operator`]s routed by an [`Algorithm`].
+///
+/// When used as an [`AudioProcessor`] the voice output is added onto all channels of the buffer.
+pub struct FMVoice {
+    operators: Vec<Operator>,
+    algorithm: Algorithm,
+    operator_outputs: [f32; MAX_OPERATORS],
+}
+
+impl FMVoice {
+    /// Create a voice with as many operators as the algorithm requires
+    pub fn new(sample_rate: f32, algorithm: Algorithm) -> Self {
+        let operators = (0..algorithm.num_operators())
+            .map(|_| Operator::new(sample_rate))
+            .collect();
+        FMVoice {
+            operators,
+            algorithm,
+            operator_outputs: [0.0; MAX_OPERATORS],
+        }
+    }
+
+    /// Set the sample rate on all operators
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for operator in &mut self.operators {
+            operator.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// Get an operator
+    pub fn operator(&self, index: usize) -> &Operator {
+        &self.operators[index]
+    }
+
+    /// Get an operator for configuration
+    pub fn operator_mut(&mut self, index: usize) -> &mut Operator {
+        &mut self.operators[index]
+    }
+
+    /// Get the routing algorithm
+    pub fn algorithm(&self) -> &Algorithm {
+        &self.algorithm
+    }
+
+    /// Get the routing algorithm for configuration. The number of operators can't be changed.
+    pub fn algorithm_mut(&mut self) -> &mut Algorithm {
+        &mut self.algorithm
+    }
+
+    /// Set the note frequency on all operators & trigger their envelopes
+    pub fn note_on(&mut self, frequency: f32) {
+        for operator in &mut self.operators {
+            operator.set_base_frequency(frequency);
+            operator.note_on();
+        }
+    }
+
+    /// Release all operator envelopes
+    pub fn note_off(&mut self) {
+        for operator in &mut self.operators {
+            operator.note_off();
+        }
+    }
+
+    /// Produce the next output sample
+    pub fn next_sample(&mut self) -> f32 {
+        let num_operators = self.operators.len();
+        let mut output = 0.0;
+
+        for index in (0..num_operators).rev() {
+            let mut phase_modulation = 0.0;
+            for source in (index + 1)..num_operators {
+                phase_modulation +=
+                    self.algorithm.modulation[source][index] * self.operator_outputs[source];
+            }
+
+            let value = self.operators[index].next_sample(phase_modulation);
+            self.operator_outputs[index] = value;
+            output += self.algorithm.output[index] * value;
+        }
+
+        output
+    }
+}
+
+impl AudioProcessor for FMVoice {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.set_sample_rate(settings.sample_rate());
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            let output = self.next_sample();
+            for sample in frame.iter_mut() {
+                *sample += output;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn collect(voice: &mut FMVoice, num_samples: usize) -> Vec<f32> {
+        (0..num_samples).map(|_| voice.next_sample()).collect()
+    }
+
+    #[test]
+    fn test_operator_without_modulation_is_a_sine() {
+        let mut operator = Operator::new(44100.0);
+        let mut reference = Oscillator::sine(44100.0);
+        operator.set_base_frequency(440.0);
+        reference.set_frequency(440.0);
+        operator.note_on();
+
+        let mut envelope = Envelope::new();
+        envelope.set_sample_rate(44100.0);
+        envelope.note_on();
+
+        for _ in 0..1000 {
+            let expected = reference.next_sample() * envelope.volume();
+            envelope.tick();
+            let output = operator.next_sample(0.0);
+            assert!((output - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_operator_ratio_is_applied_to_the_base_frequency() {
+        let mut operator = Operator::new(44100.0);
+        operator.set_base_frequency(100.0);
+        operator.set_ratio(3.0);
+        assert!((operator.oscillator.get_frequency() - 300.0).abs() < 1e-3);
+        operator.set_base_frequency(200.0);
+        assert!((operator.oscillator.get_frequency() - 600.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_operator_ratio_may_go_through_zero() {
+        let mut operator = Operator::new(44100.0);
+        operator.set_base_frequency(100.0);
+        operator.set_ratio(0.0);
+        assert_eq!(operator.oscillator.get_frequency(), 0.0);
+        operator.set_ratio(2.0);
+        assert!((operator.oscillator.get_frequency() - 200.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_modulation_changes_the_carrier_output() {
+        let mut dry = FMVoice::new(44100.0, Algorithm::stack(2));
+        dry.algorithm_mut().set_modulation(1, 0, 0.0);
+        dry.note_on(440.0);
+        let mut modulated = FMVoice::new(44100.0, Algorithm::stack(2));
+        modulated.note_on(440.0);
+
+        let dry = collect(&mut dry, 4410);
+        let modulated = collect(&mut modulated, 4410);
+        assert!(dry
+            .iter()
+            .zip(&modulated)
+            .any(|(d, m)| (d - m).abs() > 0.01));
+    }
+
+    #[test]
+    fn test_feedback_changes_the_operator_output() {
+        let mut dry = FMVoice::new(44100.0, Algorithm::parallel(2));
+        dry.note_on(440.0);
+        let mut with_feedback = FMVoice::new(44100.0, Algorithm::parallel(2));
+        with_feedback.operator_mut(0).set_feedback(0.5);
+        with_feedback.note_on(440.0);
+
+        let dry = collect(&mut dry, 4410);
+        let with_feedback = collect(&mut with_feedback, 4410);
+        assert!(dry
+            .iter()
+            .zip(&with_feedback)
+            .any(|(d, m)| (d - m).abs() > 0.01));
+        assert!(with_feedback.iter().all(|s| s.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_six_operator_voice() {
+        let mut voice = FMVoice::new(44100.0, Algorithm::stack(MAX_OPERATORS));
+        voice.note_on(220.0);
+        let output = collect(&mut voice, 4410);
+        assert!(output.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+        assert!(output.iter().any(|s| s.abs() > 0.1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_algorithm_rejects_cycles() {
+        let mut algorithm = Algorithm::new(2);
+        algorithm.set_modulation(0, 1, 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_algorithm_rejects_too_many_operators() {
+        Algorithm::new(MAX_OPERATORS + 1);
+    }
+}
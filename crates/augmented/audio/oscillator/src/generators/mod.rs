@@ -20,3 +20,7 @@ pub fn square_generator(phase: f32) -> f32 {
 pub fn saw_generator(phase: f32) -> f32 {
     (1.0 - (phase % 1.0)) * 2.0 - 1.0
 }
+
+pub fn triangle_generator(phase: f32) -> f32 {
+    4.0 * (((phase + 0.75) % 1.0) - 0.5).abs() - 1.0
+}
@@ -1,4 +1,10 @@
+/// Phase-modulation operators & algorithms for FM synthesis
+pub mod fm;
 pub mod generators;
+/// Seeded white, pink, brown & velvet noise generators
+pub mod noise;
+/// Sines, sweeps, impulses, noise & squares for measuring processors
+pub mod signal_generator;
 
 /// Calculate the phase step increment between samples.
 ///
@@ -0,0 +1,562 @@
+//! Seeded noise sources.
+//!
+//! All generators are real-time safe: they don't allocate or lock while producing samples and
+//! will output the same sequence given the same seed.
+//!
+//! ```
+//! use oscillator::noise::{NoiseGenerator, PinkNoise};
+//!
+//! let mut noise = PinkNoise::new(42);
+//! let _sample = noise.next_sample();
+//! ```
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+/// Seed used by the `Default` implementations
+static DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Small xorshift64* pseudo-random number generator.
+///
+/// This isn't suitable for anything but audio, but it's fast and has no state other than a `u64`.
+#[derive(Clone, Debug)]
+pub struct NoiseRng {
+    state: u64,
+}
+
+impl Default for NoiseRng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl NoiseRng {
+    /// Create a generator with a seed. A `0` seed is replaced with a non-zero value as xorshift
+    /// would otherwise only output zeros.
+    pub fn new(seed: u64) -> Self {
+        NoiseRng {
+            state: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+
+    /// Next random `u64`
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Next random number in the `[0, 1)` range
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        // Use the top 24 bits, which is the precision of an f32 mantissa
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Next random number in the `[-1, 1)` range
+    #[inline]
+    pub fn next_bipolar(&mut self) -> f32 {
+        self.next_f32() * 2.0 - 1.0
+    }
+}
+
+/// A source of noise samples
+pub trait NoiseGenerator {
+    /// Produce the next sample
+    fn next_sample(&mut self) -> f32;
+
+    /// Go back to the initial seed & clear any filter state
+    fn reset(&mut self);
+
+    /// Generators which depend on time (e.g. [`VelvetNoise`] density) will update their state
+    fn set_sample_rate(&mut self, _sample_rate: f32) {}
+}
+
+/// Uniformly distributed white noise in the `[-1, 1)` range
+pub struct WhiteNoise {
+    seed: u64,
+    rng: NoiseRng,
+}
+
+impl Default for WhiteNoise {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl WhiteNoise {
+    pub fn new(seed: u64) -> Self {
+        WhiteNoise {
+            seed,
+            rng: NoiseRng::new(seed),
+        }
+    }
+}
+
+impl NoiseGenerator for WhiteNoise {
+    #[inline]
+    fn next_sample(&mut self) -> f32 {
+        self.rng.next_bipolar()
+    }
+
+    fn reset(&mut self) {
+        self.rng = NoiseRng::new(self.seed);
+    }
+}
+
+/// Normally distributed white noise, generated with the Box-Muller transform.
+///
+/// Output isn't bounded, values will be over 1.0 about 0.3% of the time with the default standard
+/// deviation of `1 / 3`.
+pub struct GaussianNoise {
+    seed: u64,
+    rng: NoiseRng,
+    standard_deviation: f32,
+    spare: Option<f32>,
+}
+
+impl Default for GaussianNoise {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl GaussianNoise {
+    pub fn new(seed: u64) -> Self {
+        GaussianNoise {
+            seed,
+            rng: NoiseRng::new(seed),
+            standard_deviation: 1.0 / 3.0,
+            spare: None,
+        }
+    }
+
+    /// Set the standard deviation of the output
+    pub fn set_standard_deviation(&mut self, standard_deviation: f32) {
+        self.standard_deviation = standard_deviation;
+    }
+
+    /// Get the standard deviation of the output
+    pub fn standard_deviation(&self) -> f32 {
+        self.standard_deviation
+    }
+}
+
+impl NoiseGenerator for GaussianNoise {
+    #[inline]
+    fn next_sample(&mut self) -> f32 {
+        if let Some(spare) = self.spare.take() {
+            return spare * self.standard_deviation;
+        }
+
+        // `1 - x` so `u1` is in `(0, 1]` and `ln` is finite
+        let u1 = 1.0 - self.rng.next_f32();
+        let u2 = self.rng.next_f32();
+        let radius = (-2.0 * u1.ln()).sqrt();
+        let theta = 2.0 * std::f32::consts::PI * u2;
+
+        self.spare = Some(radius * theta.sin());
+        radius * theta.cos() * self.standard_deviation
+    }
+
+    fn reset(&mut self) {
+        self.rng = NoiseRng::new(self.seed);
+        self.spare = None;
+    }
+}
+
+/// Number of white noise rows summed by [`PinkNoise`]. Gives a -3dB/octave slope over ~16 octaves.
+const PINK_NOISE_ROWS: usize = 16;
+
+/// Pink noise (-3dB/octave) using the Voss-McCartney algorithm.
+///
+/// A set of white noise rows is summed. Row `n` is updated every `2^n` samples, picked using the
+/// number of trailing zeros of a running counter so a single row changes on each sample.
+pub struct PinkNoise {
+    seed: u64,
+    rng: NoiseRng,
+    rows: [f32; PINK_NOISE_ROWS],
+    running_sum: f32,
+    counter: u32,
+}
+
+impl Default for PinkNoise {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl PinkNoise {
+    pub fn new(seed: u64) -> Self {
+        let mut noise = PinkNoise {
+            seed,
+            rng: NoiseRng::new(seed),
+            rows: [0.0; PINK_NOISE_ROWS],
+            running_sum: 0.0,
+            counter: 0,
+        };
+        noise.reset();
+        noise
+    }
+}
+
+impl NoiseGenerator for PinkNoise {
+    #[inline]
+    fn next_sample(&mut self) -> f32 {
+        self.counter = self.counter.wrapping_add(1);
+        let row = (self.counter.trailing_zeros() as usize).min(PINK_NOISE_ROWS - 1);
+
+        let value = self.rng.next_bipolar();
+        self.running_sum += value - self.rows[row];
+        self.rows[row] = value;
+
+        // An extra white sample is added to fill the top octave
+        let white = self.rng.next_bipolar();
+        (self.running_sum + white) / (PINK_NOISE_ROWS + 1) as f32
+    }
+
+    fn reset(&mut self) {
+        self.rng = NoiseRng::new(self.seed);
+        self.counter = 0;
+        self.running_sum = 0.0;
+        for row in self.rows.iter_mut() {
+            *row = self.rng.next_bipolar();
+            self.running_sum += *row;
+        }
+    }
+}
+
+/// Brown noise (-6dB/octave), a leaky integration of white noise.
+pub struct BrownNoise {
+    seed: u64,
+    rng: NoiseRng,
+    last_output: f32,
+}
+
+impl Default for BrownNoise {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl BrownNoise {
+    pub fn new(seed: u64) -> Self {
+        BrownNoise {
+            seed,
+            rng: NoiseRng::new(seed),
+            last_output: 0.0,
+        }
+    }
+}
+
+impl NoiseGenerator for BrownNoise {
+    #[inline]
+    fn next_sample(&mut self) -> f32 {
+        let white = self.rng.next_bipolar();
+        // The leak keeps the integrator from drifting away from 0
+        self.last_output = (self.last_output + 0.02 * white) / 1.02;
+        // Compensate for the ~-11dB level drop
+        self.last_output * 3.5
+    }
+
+    fn reset(&mut self) {
+        self.rng = NoiseRng::new(self.seed);
+        self.last_output = 0.0;
+    }
+}
+
+/// Velvet noise, sparse impulses of random sign at random positions.
+///
+/// The time axis is split into periods of `sample_rate / density` samples and a single `1.0` or
+/// `-1.0` impulse is placed at a random position within each period. Everything else is silence.
+pub struct VelvetNoise {
+    seed: u64,
+    rng: NoiseRng,
+    sample_rate: f32,
+    /// Impulses per second
+    density: f32,
+    period_samples: usize,
+    cursor: usize,
+    impulse_position: usize,
+    impulse_value: f32,
+}
+
+impl Default for VelvetNoise {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl VelvetNoise {
+    /// Create velvet noise with 2000 impulses per second at 44.1kHz
+    pub fn new(seed: u64) -> Self {
+        let mut noise = VelvetNoise {
+            seed,
+            rng: NoiseRng::new(seed),
+            sample_rate: 44100.0,
+            density: 2000.0,
+            period_samples: 0,
+            cursor: 0,
+            impulse_position: 0,
+            impulse_value: 0.0,
+        };
+        noise.update_period();
+        noise.reset();
+        noise
+    }
+
+    /// Set the number of impulses per second
+    pub fn set_density(&mut self, density: f32) {
+        self.density = density;
+        self.update_period();
+    }
+
+    /// Get the number of impulses per second
+    pub fn density(&self) -> f32 {
+        self.density
+    }
+
+    fn update_period(&mut self) {
+        self.period_samples = ((self.sample_rate / self.density.max(f32::EPSILON)) as usize).max(1);
+    }
+
+    fn start_period(&mut self) {
+        self.cursor = 0;
+        self.impulse_position = (self.rng.next_f32() * self.period_samples as f32) as usize;
+        self.impulse_value = if self.rng.next_f32() < 0.5 { -1.0 } else { 1.0 };
+    }
+}
+
+impl NoiseGenerator for VelvetNoise {
+    #[inline]
+    fn next_sample(&mut self) -> f32 {
+        if self.cursor >= self.period_samples {
+            self.start_period();
+        }
+
+        let output = if self.cursor == self.impulse_position {
+            self.impulse_value
+        } else {
+            0.0
+        };
+        self.cursor += 1;
+        output
+    }
+
+    fn reset(&mut self) {
+        self.rng = NoiseRng::new(self.seed);
+        self.start_period();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_period();
+    }
+}
+
+/// How [`NoiseProcessor`] will write onto buffers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoiseProcessorMode {
+    /// Overwrite the buffer contents with noise
+    Replace,
+    /// Mix noise onto the buffer contents
+    Add,
+}
+
+/// An [`AudioProcessor`] which fills or adds noise into its buffer.
+///
+/// The same noise signal is written to all channels.
+///
+/// ```
+/// use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+/// use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
+/// use oscillator::noise::{NoiseProcessor, NoiseProcessorMode, WhiteNoise};
+///
+/// let mut buffer = VecAudioBuffer::new();
+/// buffer.resize(2, 512, 0.0);
+///
+/// let mut processor = NoiseProcessor::new(WhiteNoise::new(1234), NoiseProcessorMode::Add);
+/// processor.set_gain(0.1);
+/// processor.prepare(AudioProcessorSettings::default());
+/// processor.process(&mut buffer);
+/// ```
+pub struct NoiseProcessor<Generator: NoiseGenerator> {
+    generator: Generator,
+    mode: NoiseProcessorMode,
+    gain: f32,
+}
+
+impl<Generator: NoiseGenerator> NoiseProcessor<Generator> {
+    /// Create a processor with `1.0` gain
+    pub fn new(generator: Generator, mode: NoiseProcessorMode) -> Self {
+        NoiseProcessor {
+            generator,
+            mode,
+            gain: 1.0,
+        }
+    }
+
+    /// Set the linear gain applied to the noise
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    /// Get the linear gain applied to the noise
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Change whether noise will replace or be added to the input
+    pub fn set_mode(&mut self, mode: NoiseProcessorMode) {
+        self.mode = mode;
+    }
+
+    /// Get the generator
+    pub fn generator(&self) -> &Generator {
+        &self.generator
+    }
+
+    /// Get a mutable reference to the generator
+    pub fn generator_mut(&mut self) -> &mut Generator {
+        &mut self.generator
+    }
+}
+
+impl<Generator: NoiseGenerator> AudioProcessor for NoiseProcessor<Generator> {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.generator.set_sample_rate(settings.sample_rate());
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            let value = self.gain * self.generator.next_sample();
+            for sample in frame.iter_mut() {
+                match self.mode {
+                    NoiseProcessorMode::Replace => *sample = value,
+                    NoiseProcessorMode::Add => *sample += value,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use super::*;
+
+    fn collect<G: NoiseGenerator>(generator: &mut G, num_samples: usize) -> Vec<f32> {
+        (0..num_samples).map(|_| generator.next_sample()).collect()
+    }
+
+    fn mean(samples: &[f32]) -> f32 {
+        samples.iter().sum::<f32>() / samples.len() as f32
+    }
+
+    fn variance(samples: &[f32]) -> f32 {
+        let mean = mean(samples);
+        samples.iter().map(|s| (s - mean) * (s - mean)).sum::<f32>() / samples.len() as f32
+    }
+
+    #[test]
+    fn test_white_noise_is_bounded_and_centered() {
+        let samples = collect(&mut WhiteNoise::new(10), 44100);
+        assert!(samples.iter().all(|s| *s >= -1.0 && *s < 1.0));
+        assert!(mean(&samples).abs() < 0.02);
+        // Variance of uniform noise over [-1, 1) is 1/3
+        assert!((variance(&samples) - 1.0 / 3.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_noise_is_reproducible_with_the_same_seed() {
+        let first = collect(&mut PinkNoise::new(42), 1000);
+        let second = collect(&mut PinkNoise::new(42), 1000);
+        let other = collect(&mut PinkNoise::new(43), 1000);
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_reset_restarts_the_sequence() {
+        let mut noise = BrownNoise::new(42);
+        let first = collect(&mut noise, 100);
+        noise.reset();
+        let second = collect(&mut noise, 100);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_gaussian_noise_standard_deviation() {
+        let mut noise = GaussianNoise::new(10);
+        noise.set_standard_deviation(0.5);
+        let samples = collect(&mut noise, 44100);
+        assert!(mean(&samples).abs() < 0.02);
+        assert!((variance(&samples).sqrt() - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_pink_and_brown_noise_are_bounded() {
+        let pink = collect(&mut PinkNoise::new(10), 44100);
+        let brown = collect(&mut BrownNoise::new(10), 44100);
+        assert!(pink.iter().all(|s| s.abs() <= 1.0));
+        assert!(brown.iter().all(|s| s.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_coloured_noise_has_more_low_frequency_energy() {
+        // Difference between consecutive samples is a crude high-pass filter; its energy relative
+        // to the signal energy falls as the spectrum tilts towards low frequencies.
+        fn high_frequency_ratio(samples: &[f32]) -> f32 {
+            let diff: Vec<f32> = samples.windows(2).map(|w| w[1] - w[0]).collect();
+            variance(&diff) / variance(samples)
+        }
+
+        let white = high_frequency_ratio(&collect(&mut WhiteNoise::new(10), 44100));
+        let pink = high_frequency_ratio(&collect(&mut PinkNoise::new(10), 44100));
+        let brown = high_frequency_ratio(&collect(&mut BrownNoise::new(10), 44100));
+        assert!(white > pink);
+        assert!(pink > brown);
+    }
+
+    #[test]
+    fn test_velvet_noise_density() {
+        let mut noise = VelvetNoise::new(10);
+        noise.set_sample_rate(44100.0);
+        noise.set_density(1000.0);
+        noise.reset();
+        let samples = collect(&mut noise, 44100);
+        let impulses = samples.iter().filter(|s| **s != 0.0).count();
+        // Periods are rounded down to whole samples
+        assert!((impulses as i32 - 1000).abs() <= 10);
+        assert!(samples.iter().all(|s| *s == 0.0 || s.abs() == 1.0));
+    }
+
+    #[test]
+    fn test_noise_processor_replace_and_add() {
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 64, 1.0);
+        let mut processor = NoiseProcessor::new(WhiteNoise::new(1), NoiseProcessorMode::Replace);
+        processor.prepare(AudioProcessorSettings::default());
+        processor.process(&mut buffer);
+        let replaced: Vec<f32> = buffer.slice().to_vec();
+        for frame in buffer.frames() {
+            assert_eq!(frame[0], frame[1]);
+        }
+
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 64, 1.0);
+        let mut processor = NoiseProcessor::new(WhiteNoise::new(1), NoiseProcessorMode::Add);
+        processor.process(&mut buffer);
+        for (added, replaced) in buffer.slice().iter().zip(replaced) {
+            assert!((added - (replaced + 1.0)).abs() < f32::EPSILON);
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! Test signals for measuring processors.
+//!
+//! [`SignalGeneratorProcessor`] writes a sine, exponential sine sweep, impulse, white noise, square
+//! or silence onto the channels in its mask, at a peak level in dBFS, for a given duration. After
+//! that it either starts over or goes silent.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+//! use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
+//! use oscillator::signal_generator::{SignalGeneratorOptions, SignalGeneratorProcessor, SignalType};
+//!
+//! let mut buffer = VecAudioBuffer::new();
+//! buffer.resize(2, 512, 0.0);
+//!
+//! let mut generator = SignalGeneratorProcessor::new(SignalGeneratorOptions {
+//!     signal_type: SignalType::Sweep,
+//!     frequency: 20.0,
+//!     end_frequency: 20000.0,
+//!     duration: Duration::from_secs(5),
+//!     ..SignalGeneratorOptions::default()
+//! });
+//! generator.prepare(AudioProcessorSettings::default());
+//! generator.process(&mut buffer);
+//! ```
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use audio_volume::db_to_amplitude;
+
+use crate::generators;
+use crate::noise::{NoiseGenerator, WhiteNoise};
+
+/// The signals [`SignalGeneratorProcessor`] can produce
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalType {
+    /// A sine at `frequency`
+    Sine,
+    /// An exponential sine sweep from `frequency` to `end_frequency`, over the signal duration
+    Sweep,
+    /// A single full-level sample at the start of the signal
+    Impulse,
+    /// Uniform white noise
+    Noise,
+    /// A square wave at `frequency`
+    Square,
+    Silence,
+}
+
+impl SignalType {
+    pub const ALL: [SignalType; 6] = [
+        SignalType::Sine,
+        SignalType::Sweep,
+        SignalType::Impulse,
+        SignalType::Noise,
+        SignalType::Square,
+        SignalType::Silence,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignalType::Sine => "sine",
+            SignalType::Sweep => "sweep",
+            SignalType::Impulse => "impulse",
+            SignalType::Noise => "noise",
+            SignalType::Square => "square",
+            SignalType::Silence => "silence",
+        }
+    }
+}
+
+impl Display for SignalType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returned when parsing a name which isn't one of [`SignalType::ALL`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownSignalTypeError(pub String);
+
+impl Display for UnknownSignalTypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown signal type '{}', expected one of: sine, sweep, impulse, noise, square, silence",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownSignalTypeError {}
+
+impl FromStr for SignalType {
+    type Err = UnknownSignalTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SignalType::ALL
+            .iter()
+            .find(|signal_type| signal_type.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| UnknownSignalTypeError(s.to_string()))
+    }
+}
+
+/// Configuration of a [`SignalGeneratorProcessor`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignalGeneratorOptions {
+    pub signal_type: SignalType,
+    /// Frequency of sines & squares & where sweeps start, in Hz
+    pub frequency: f32,
+    /// Where sweeps end, in Hz
+    pub end_frequency: f32,
+    /// Peak level, in dBFS
+    pub level: f32,
+    /// Length of the signal. Sweeps cover their range in this time & impulses repeat at this
+    /// interval when looping.
+    pub duration: Duration,
+    /// Start the signal over after `duration`, rather than going silent
+    pub looping: bool,
+    /// Channels which get the signal, one bit per channel. The others are silenced.
+    pub channel_mask: u64,
+    /// Seed of the noise
+    pub seed: u64,
+}
+
+impl Default for SignalGeneratorOptions {
+    /// A looping 1kHz sine at -12dBFS on all channels
+    fn default() -> Self {
+        SignalGeneratorOptions {
+            signal_type: SignalType::Sine,
+            frequency: 1000.0,
+            end_frequency: 20000.0,
+            level: -12.0,
+            duration: Duration::from_secs(10),
+            looping: true,
+            channel_mask: u64::MAX,
+            seed: 0,
+        }
+    }
+}
+
+impl SignalGeneratorOptions {
+    /// Mask with the bits of `channels` set. Channels past 63 are ignored.
+    pub fn mask_for_channels(channels: &[usize]) -> u64 {
+        channels
+            .iter()
+            .filter(|channel| **channel < 64)
+            .fold(0, |mask, channel| mask | (1 << *channel))
+    }
+
+    /// Whether `channel` gets the signal
+    pub fn is_channel_enabled(&self, channel: usize) -> bool {
+        channel < 64 && self.channel_mask & (1 << channel) != 0
+    }
+
+    /// Linear gain for `level`
+    pub fn gain(&self) -> f32 {
+        db_to_amplitude(self.level, 1.0)
+    }
+
+    /// Length of the signal in samples at `sample_rate`
+    pub fn duration_samples(&self, sample_rate: f32) -> usize {
+        (self.duration.as_secs_f64() * sample_rate as f64).round() as usize
+    }
+}
+
+/// An [`AudioProcessor`] which replaces its buffer with a test signal.
+///
+/// Doesn't allocate or lock while processing, so options may be changed between blocks with
+/// [`SignalGeneratorProcessor::set_options`].
+pub struct SignalGeneratorProcessor {
+    options: SignalGeneratorOptions,
+    sample_rate: f32,
+    gain: f32,
+    duration_samples: usize,
+    /// Samples since the signal started
+    position: usize,
+    /// Phase of sines & squares, in cycles
+    phase: f64,
+    noise: WhiteNoise,
+}
+
+impl Default for SignalGeneratorProcessor {
+    fn default() -> Self {
+        Self::new(SignalGeneratorOptions::default())
+    }
+}
+
+impl SignalGeneratorProcessor {
+    pub fn new(options: SignalGeneratorOptions) -> Self {
+        let sample_rate = AudioProcessorSettings::default().sample_rate();
+        SignalGeneratorProcessor {
+            options,
+            sample_rate,
+            gain: options.gain(),
+            duration_samples: options.duration_samples(sample_rate),
+            position: 0,
+            phase: 0.0,
+            noise: WhiteNoise::new(options.seed),
+        }
+    }
+
+    pub fn options(&self) -> &SignalGeneratorOptions {
+        &self.options
+    }
+
+    /// Change the signal. It starts over if its type, duration or seed changed.
+    pub fn set_options(&mut self, options: SignalGeneratorOptions) {
+        let restart = options.signal_type != self.options.signal_type
+            || options.duration != self.options.duration
+            || options.seed != self.options.seed;
+        self.options = options;
+        self.gain = options.gain();
+        self.duration_samples = options.duration_samples(self.sample_rate);
+        if restart {
+            self.noise = WhiteNoise::new(options.seed);
+            self.reset();
+        }
+    }
+
+    /// Samples since the signal started
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Whether a signal which doesn't loop has played through
+    pub fn is_finished(&self) -> bool {
+        !self.options.looping && self.position >= self.duration_samples
+    }
+
+    /// Start the signal over
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.phase = 0.0;
+        self.noise.reset();
+    }
+
+    /// Produce the next sample of the signal
+    pub fn next_sample(&mut self) -> f32 {
+        if self.position >= self.duration_samples {
+            if !self.options.looping || self.duration_samples == 0 {
+                return 0.0;
+            }
+            self.position = 0;
+            self.phase = 0.0;
+        }
+
+        let value = match self.options.signal_type {
+            SignalType::Sine => {
+                let value = generators::sine_generator(self.phase as f32);
+                self.advance_phase();
+                value
+            }
+            SignalType::Square => {
+                let value = generators::square_generator(self.phase as f32);
+                self.advance_phase();
+                value
+            }
+            SignalType::Sweep => (2.0 * std::f64::consts::PI * self.sweep_phase()).sin() as f32,
+            SignalType::Impulse => {
+                if self.position == 0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            SignalType::Noise => self.noise.next_sample(),
+            SignalType::Silence => 0.0,
+        };
+        self.position += 1;
+        self.gain * value
+    }
+
+    fn advance_phase(&mut self) {
+        self.phase += self.options.frequency as f64 / self.sample_rate as f64;
+        self.phase -= self.phase.floor();
+    }
+
+    /// Phase of the sweep at the current position, in cycles. Computed from the start so it
+    /// doesn't drift over long sweeps.
+    fn sweep_phase(&self) -> f64 {
+        let start_frequency = self.options.frequency.max(f32::EPSILON) as f64;
+        let end_frequency = self.options.end_frequency.max(f32::EPSILON) as f64;
+        let time = self.position as f64 / self.sample_rate as f64;
+        let duration = self.duration_samples as f64 / self.sample_rate as f64;
+        let rate = (end_frequency / start_frequency).ln();
+        let phase = if rate.abs() < 1e-9 {
+            start_frequency * time
+        } else {
+            start_frequency * duration / rate * ((time / duration * rate).exp() - 1.0)
+        };
+        phase.fract()
+    }
+}
+
+impl AudioProcessor for SignalGeneratorProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.duration_samples = self.options.duration_samples(self.sample_rate);
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            let value = self.next_sample();
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                *sample = if self.options.is_channel_enabled(channel) {
+                    value
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use super::*;
+
+    fn generate(options: SignalGeneratorOptions, num_samples: usize) -> Vec<f32> {
+        let mut generator = SignalGeneratorProcessor::new(options);
+        generator.prepare(AudioProcessorSettings::new(48000.0, 1, 1, 512));
+        (0..num_samples).map(|_| generator.next_sample()).collect()
+    }
+
+    fn rising_zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn test_sine_frequency_and_level() {
+        let samples = generate(
+            SignalGeneratorOptions {
+                frequency: 100.0,
+                level: -6.0,
+                ..SignalGeneratorOptions::default()
+            },
+            48000,
+        );
+        let peak = samples.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.501).abs() < 0.01, "{}", peak);
+        assert!((rising_zero_crossings(&samples) as i32 - 100).abs() <= 1);
+    }
+
+    #[test]
+    fn test_sweep_rises_from_start_to_end_frequency() {
+        let options = SignalGeneratorOptions {
+            signal_type: SignalType::Sweep,
+            frequency: 100.0,
+            end_frequency: 10000.0,
+            duration: Duration::from_secs(2),
+            looping: false,
+            ..SignalGeneratorOptions::default()
+        };
+        let samples = generate(options, 3 * 48000);
+        // An octave takes 2s / log2(100), about 0.3s; the first & last 0.1s should be close to
+        // the start & end frequencies
+        let first = rising_zero_crossings(&samples[..4800]) as f32 / 0.1;
+        let last = rising_zero_crossings(&samples[2 * 48000 - 4800..2 * 48000]) as f32 / 0.1;
+        assert!(first > 100.0 && first < 130.0, "{}", first);
+        assert!(last > 8000.0 && last <= 10000.0, "{}", last);
+        // Then silence
+        assert!(samples[2 * 48000..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn test_impulses_repeat_when_looping() {
+        let samples = generate(
+            SignalGeneratorOptions {
+                signal_type: SignalType::Impulse,
+                level: 0.0,
+                duration: Duration::from_millis(10),
+                ..SignalGeneratorOptions::default()
+            },
+            1500,
+        );
+        let impulses: Vec<usize> = samples
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| **s != 0.0)
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(impulses, vec![0, 480, 960, 1440]);
+        assert_eq!(samples[0], 1.0);
+    }
+
+    #[test]
+    fn test_channel_mask() {
+        let mut generator = SignalGeneratorProcessor::new(SignalGeneratorOptions {
+            signal_type: SignalType::Noise,
+            channel_mask: SignalGeneratorOptions::mask_for_channels(&[1, 2]),
+            ..SignalGeneratorOptions::default()
+        });
+        generator.prepare(AudioProcessorSettings::new(44100.0, 4, 4, 64));
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(4, 64, 1.0);
+        generator.process(&mut buffer);
+
+        for frame in buffer.frames() {
+            assert_eq!(frame[0], 0.0);
+            assert_eq!(frame[1], frame[2]);
+            assert!(frame[1].abs() <= 0.26);
+            assert_eq!(frame[3], 0.0);
+        }
+        assert!(buffer.frames().any(|frame| frame[1] != 0.0));
+    }
+
+    #[test]
+    fn test_signal_types_parse_from_their_names() {
+        for signal_type in SignalType::ALL.iter() {
+            assert_eq!(signal_type.to_string().parse(), Ok(*signal_type));
+        }
+        assert_eq!("Sweep".parse(), Ok(SignalType::Sweep));
+        assert!("triangle".parse::<SignalType>().is_err());
+    }
+}
@@ -2,3 +2,6 @@ pub use audio_processor_standalone::audio_processor_main;
 pub use audio_processor_standalone::audio_processor_main_with_midi;
 pub use audio_processor_standalone::audio_processor_start;
 pub use audio_processor_standalone::audio_processor_start_with_midi;
+pub use audio_processor_standalone::audio_processor_start_with_midi_and_options;
+pub use audio_processor_standalone::audio_processor_start_with_options;
+pub use audio_processor_standalone::StandaloneOptions;
@@ -5,6 +5,7 @@ pub use cpal;
 pub use oscillator;
 
 pub mod processor {
+    pub use audio_processor_analysis as analysis;
     pub use audio_processor_graph as graph;
     pub use audio_processor_traits::*;
     pub use audio_processor_utility as utility;
@@ -1,2 +1,8 @@
 pub use convert_sample_rate::convert_sample_rate;
+pub use convolution;
+pub use delay_line;
+pub use distortion;
 pub use dsp_filters;
+pub use modulation_effects;
+pub use oversampling;
+pub use reverb;
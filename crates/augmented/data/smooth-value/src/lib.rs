@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+#[derive(Debug, Clone)]
 struct InterpolationState {
     /// The start value of this change
     start: f32,
@@ -7,11 +8,16 @@ struct InterpolationState {
     target: f32,
     /// The increment over current value per tick
     tick_increment: f32,
+    /// The value ticks are counted from, so rounding errors don't accumulate
+    origin: f32,
+    /// Number of ticks since `origin`
+    ticks: u32,
 }
 
 /// Wraps a certain numeric value with linear interpolation.
 ///
 /// Whenever a value change is requested, the change will be smoothed over a time window.
+#[derive(Debug, Clone)]
 pub struct InterpolatedValue {
     /// Sample rate
     sample_rate: f32,
@@ -59,16 +65,49 @@ impl InterpolatedValue {
     }
 
     /// Modify the target value
+    ///
+    /// Windows shorter than a sample change the value immediately. Setting the target of the
+    /// running movement again doesn't restart it, so targets may be set on every block.
     pub fn set(&mut self, target: f32) {
+        if target == self.target() {
+            return;
+        }
+
         let delta = target - self.current_value;
+        if delta == 0.0 || self.smoothing_samples < 1.0 {
+            self.current_value = target;
+            self.interpolation_state = None;
+            return;
+        }
+
         let tick_increment = delta / self.smoothing_samples;
         self.interpolation_state = Some(InterpolationState {
             start: self.current_value,
             target,
             tick_increment,
+            origin: self.current_value,
+            ticks: 0,
         })
     }
 
+    /// Return the value being moved towards, or the current value if there's no movement
+    pub fn target(&self) -> f32 {
+        self.interpolation_state
+            .as_ref()
+            .map_or(self.current_value, |state| state.target)
+    }
+
+    /// Whether a movement is running
+    pub fn is_smoothing(&self) -> bool {
+        self.interpolation_state.is_some()
+    }
+
+    /// Finish the running movement immediately
+    pub fn jump(&mut self) {
+        self.current_value = self.target();
+        self.interpolation_state = None;
+    }
+
     /// Get the current value and tick the internal state
     pub fn next_sample(&mut self) -> f32 {
         let value = self.get();
@@ -83,11 +122,18 @@ impl InterpolatedValue {
 
     /// Interpolates current value towards the target value
     pub fn tick(&mut self) {
-        if let Some(interpolation_state) = &self.interpolation_state {
-            self.current_value += interpolation_state.tick_increment;
+        if let Some(interpolation_state) = &mut self.interpolation_state {
+            interpolation_state.ticks += 1;
+            self.current_value = interpolation_state.origin
+                + interpolation_state.tick_increment * interpolation_state.ticks as f32;
 
             // Reset internal state & don't let the value exceed the target.
-            if self.current_value >= interpolation_state.target {
+            let has_reached_target = if interpolation_state.tick_increment > 0.0 {
+                self.current_value >= interpolation_state.target
+            } else {
+                self.current_value <= interpolation_state.target
+            };
+            if has_reached_target {
                 self.current_value = interpolation_state.target;
                 self.interpolation_state = None;
             }
@@ -110,17 +156,21 @@ impl InterpolatedValue {
 
         // Reset currently running interpolation
         if reset {
-            if let Some(target) = self.interpolation_state.as_ref().map(|s| s.target) {
-                self.set(target);
+            if let Some(state) = self.interpolation_state.take() {
+                self.set(state.target);
             }
             return;
         }
 
         // Update currently running interpolation
-        if let Some(state) = &mut self.interpolation_state {
+        if self.smoothing_samples < 1.0 {
+            self.jump();
+        } else if let Some(state) = &mut self.interpolation_state {
             let delta = state.target - state.start;
             let tick_increment = delta / self.smoothing_samples;
             state.tick_increment = tick_increment;
+            state.origin = self.current_value;
+            state.ticks = 0;
         }
     }
 }
@@ -206,11 +256,65 @@ mod tests {
         // Go back to 0
         value.set(0.0);
 
-        // Tick the value another 22.05k times, the current value should be 0.
+        // Going back takes the full 1s too; half-way through the value should be 25.
         for _ in 0..22050 {
             value.tick();
         }
-        assert_approx_equals(value.get(), 0.0);
+        assert!((value.get() - 25.0).abs() < 0.1);
+        for _ in 0..22050 {
+            value.tick();
+        }
+        assert_eq!(value.get(), 0.0);
+    }
+
+    #[test]
+    fn test_interpolation_downwards_stops_at_the_target() {
+        let mut value = InterpolatedValue::new(1000.0, Duration::from_millis(10), 1.0);
+        value.set(0.0);
+        assert!(value.is_smoothing());
+        assert_eq!(value.target(), 0.0);
+        for _ in 0..20 {
+            let new_value = value.next_sample();
+            assert!(new_value >= 0.0);
+        }
+        assert!(!value.is_smoothing());
+        assert_eq!(value.get(), 0.0);
+    }
+
+    #[test]
+    fn test_zero_duration_is_immediate() {
+        let mut value = InterpolatedValue::new(44100.0, Duration::from_secs(0), 0.0);
+        value.set(1.0);
+        assert_eq!(value.get(), 1.0);
+        value.set(1.0);
+        assert_eq!(value.get(), 1.0);
+        assert!(!value.is_smoothing());
+    }
+
+    #[test]
+    fn test_jump_finishes_the_movement() {
+        let mut value = InterpolatedValue::new(44100.0, Duration::from_secs(1), 0.0);
+        value.set(10.0);
+        value.tick();
+        value.jump();
+        assert_eq!(value.get(), 10.0);
+        assert!(!value.is_smoothing());
+    }
+
+    #[test]
+    fn test_setting_the_same_target_continues_the_movement() {
+        let mut value = InterpolatedValue::new(1000.0, Duration::from_millis(10), 0.0);
+        value.set(1.0);
+        for _ in 0..5 {
+            value.tick();
+            value.set(1.0);
+        }
+        assert!((value.get() - 0.5).abs() < 1e-6);
+        for _ in 0..5 {
+            value.tick();
+        }
+        assert_eq!(value.get(), 1.0);
+        assert!(!value.is_smoothing());
     }
 
     fn assert_approx_equals(value: f32, target: f32) {
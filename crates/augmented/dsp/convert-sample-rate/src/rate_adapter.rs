@@ -0,0 +1,329 @@
+//! Run a block based process callback at a different sample rate than an audio device.
+use crate::resampler::{Quality, Resampler};
+
+/// Adapts an audio device's callbacks to a processor running at another sample rate & block size.
+///
+/// Each device callback's input is resampled to the processor rate. The processor is then called
+/// with fixed size blocks until there's enough output, which is resampled back to the device rate.
+/// The processor always sees `block_size` frames, whatever the device buffer size is.
+///
+/// The adapter starts with a block of silence queued, so the processor never runs short of input.
+/// This, plus the two resampling filters, is reported by [`RateAdapter::latency`].
+///
+/// The queues between the device & the processor are fixed size ring buffers, sized for the largest
+/// device buffer the adapter is created for, so `process` never allocates. Larger device buffers
+/// are processed in chunks of that size.
+///
+/// ```
+/// use convert_sample_rate::rate_adapter::RateAdapter;
+/// use convert_sample_rate::resampler::Quality;
+///
+/// // Device at 44.1kHz with buffers of up to 256 frames, processor at 48kHz with blocks of 512
+/// let mut adapter = RateAdapter::new(2, 44100.0, 48000.0, 512, 256, Quality::Medium);
+/// let mut device_buffer = vec![0.0; 2 * 256];
+/// adapter.process(&mut device_buffer, |block| {
+///     assert_eq!(block.len(), 2 * 512);
+/// });
+/// ```
+pub struct RateAdapter {
+    num_channels: usize,
+    block_size: usize,
+    max_device_frames: usize,
+    device_rate: f32,
+    processor_rate: f32,
+    /// Device rate to processor rate
+    input_resampler: Resampler,
+    /// Processor rate to device rate
+    output_resampler: Resampler,
+    /// Processor rate input frames waiting for a full block
+    pending_input: SampleQueue,
+    /// Device rate output frames waiting for a device callback
+    pending_output: SampleQueue,
+    block: Vec<f32>,
+    scratch: Vec<f32>,
+}
+
+impl RateAdapter {
+    /// Create an adapter. Allocates all buffers for the given `block_size` & for device buffers of
+    /// up to `max_device_frames`.
+    pub fn new(
+        num_channels: usize,
+        device_rate: f32,
+        processor_rate: f32,
+        block_size: usize,
+        max_device_frames: usize,
+        quality: Quality,
+    ) -> Self {
+        assert!(num_channels > 0 && block_size > 0 && max_device_frames > 0);
+        let input_resampler = Resampler::new(num_channels, device_rate, processor_rate, quality);
+        let output_resampler = Resampler::new(num_channels, processor_rate, device_rate, quality);
+        let block_output_frames = output_resampler.max_output_frames(block_size);
+        let scratch_frames = input_resampler
+            .max_output_frames(block_size)
+            .max(block_output_frames);
+        // The input queue holds the priming frames plus up to one device buffer of input, the
+        // output queue up to one device buffer plus the rest of the last block
+        let input_queue_frames = 2 * block_size
+            + PRIMING_EXTRA_FRAMES
+            + input_resampler.max_output_frames(max_device_frames);
+        let output_queue_frames = max_device_frames + 2 * block_output_frames;
+
+        let mut adapter = RateAdapter {
+            num_channels,
+            block_size,
+            max_device_frames,
+            device_rate,
+            processor_rate,
+            input_resampler,
+            output_resampler,
+            pending_input: SampleQueue::new(input_queue_frames * num_channels),
+            pending_output: SampleQueue::new(output_queue_frames * num_channels),
+            block: vec![0.0; block_size * num_channels],
+            scratch: vec![0.0; scratch_frames * num_channels],
+        };
+        adapter.reset();
+        adapter
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The largest device buffer `process` handles in one go, in frames
+    pub fn max_device_frames(&self) -> usize {
+        self.max_device_frames
+    }
+
+    pub fn device_rate(&self) -> f32 {
+        self.device_rate
+    }
+
+    pub fn processor_rate(&self) -> f32 {
+        self.processor_rate
+    }
+
+    /// Round-trip delay from device input to device output, in device frames
+    pub fn latency(&self) -> usize {
+        let device_per_processor_frame = self.device_rate as f64 / self.processor_rate as f64;
+        let processor_frames = (self.priming_frames() + self.output_resampler.latency()) as f64;
+        self.input_resampler.latency()
+            + (processor_frames * device_per_processor_frame).round() as usize
+    }
+
+    /// Clear both filters & the queued audio
+    pub fn reset(&mut self) {
+        self.input_resampler.reset();
+        self.output_resampler.reset();
+        self.pending_output.clear();
+        self.pending_input.clear();
+        self.pending_input
+            .push_silence(self.priming_frames() * self.num_channels);
+    }
+
+    /// Replace the interleaved device buffer `data`, holding the device input if there's any, with
+    /// the output of `process`. `process` is called with interleaved processor rate blocks, in
+    /// place.
+    ///
+    /// Buffers longer than [`RateAdapter::max_device_frames`] are split into chunks, as if the
+    /// device had called back several times.
+    pub fn process(&mut self, data: &mut [f32], mut process: impl FnMut(&mut [f32])) {
+        let max_samples = self.max_device_frames * self.num_channels;
+        for chunk in data.chunks_mut(max_samples) {
+            self.process_chunk(chunk, &mut process);
+        }
+    }
+
+    fn process_chunk(&mut self, data: &mut [f32], process: &mut impl FnMut(&mut [f32])) {
+        let num_channels = self.num_channels;
+        let mut input = &data[..];
+        while !input.is_empty() {
+            let result = self.input_resampler.process(input, &mut self.scratch);
+            self.pending_input
+                .push(&self.scratch[..result.output_frames * num_channels]);
+            input = &input[result.input_frames * num_channels..];
+        }
+
+        while self.pending_output.len() < data.len() {
+            // Only short of input if the device buffer size grew, then the gap is filled with
+            // silence
+            let available = self.pending_input.pop(&mut self.block);
+            for sample in &mut self.block[available..] {
+                *sample = 0.0;
+            }
+
+            process(&mut self.block);
+
+            let mut block = &self.block[..];
+            while !block.is_empty() {
+                let result = self.output_resampler.process(block, &mut self.scratch);
+                self.pending_output
+                    .push(&self.scratch[..result.output_frames * num_channels]);
+                block = &block[result.input_frames * num_channels..];
+            }
+        }
+
+        self.pending_output.pop(data);
+    }
+
+    /// A block of input is queued up-front. The processor consumes at the rate the device input
+    /// arrives, so the queue never runs dry. The extra frames cover rounding in the resamplers.
+    fn priming_frames(&self) -> usize {
+        self.block_size + PRIMING_EXTRA_FRAMES
+    }
+}
+
+/// Frames queued on top of a block to cover rounding in the resamplers
+const PRIMING_EXTRA_FRAMES: usize = 2;
+
+/// Fixed capacity FIFO of interleaved samples
+struct SampleQueue {
+    buffer: Vec<f32>,
+    read_position: usize,
+    len: usize,
+}
+
+impl SampleQueue {
+    fn new(capacity: usize) -> Self {
+        SampleQueue {
+            buffer: vec![0.0; capacity],
+            read_position: 0,
+            len: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.read_position = 0;
+        self.len = 0;
+    }
+
+    /// Append `samples`. Panics if they don't fit, the capacity is an upper bound of what's queued.
+    fn push(&mut self, samples: &[f32]) {
+        let capacity = self.buffer.len();
+        assert!(self.len + samples.len() <= capacity, "Queue overflow");
+        let write_position = (self.read_position + self.len) % capacity;
+        let (first, second) = samples.split_at(samples.len().min(capacity - write_position));
+        self.buffer[write_position..write_position + first.len()].copy_from_slice(first);
+        self.buffer[..second.len()].copy_from_slice(second);
+        self.len += samples.len();
+    }
+
+    fn push_silence(&mut self, num_samples: usize) {
+        let capacity = self.buffer.len();
+        assert!(self.len + num_samples <= capacity, "Queue overflow");
+        for offset in 0..num_samples {
+            self.buffer[(self.read_position + self.len + offset) % capacity] = 0.0;
+        }
+        self.len += num_samples;
+    }
+
+    /// Move up to `output.len()` samples into the start of `output`, returning how many were moved
+    fn pop(&mut self, output: &mut [f32]) -> usize {
+        let capacity = self.buffer.len();
+        let count = output.len().min(self.len);
+        let first_len = count.min(capacity - self.read_position);
+        output[..first_len]
+            .copy_from_slice(&self.buffer[self.read_position..self.read_position + first_len]);
+        output[first_len..count].copy_from_slice(&self.buffer[..count - first_len]);
+        self.read_position = (self.read_position + count) % capacity;
+        self.len -= count;
+        count
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Low-passed noise, so the delay can't be confused with a period of the signal
+    fn smooth_noise(num_samples: usize) -> Vec<f32> {
+        let (mut first, mut second) = (0.0, 0.0);
+        (0..num_samples)
+            .map(|_| {
+                first += 0.02 * (rand::random::<f32>() * 2.0 - 1.0 - first);
+                second += 0.02 * (first - second);
+                second
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_processor_always_gets_full_blocks() {
+        let mut adapter = RateAdapter::new(2, 44100.0, 48000.0, 256, 512, Quality::Fast);
+        for device_frames in [64, 512, 1, 300, 256].iter() {
+            let mut data = vec![0.0; 2 * device_frames];
+            adapter.process(&mut data, |block| {
+                assert_eq!(block.len(), 2 * 256);
+            });
+        }
+    }
+
+    #[test]
+    fn test_passes_signal_delayed_by_latency() {
+        let device_rate = 44100.0;
+        let mut adapter = RateAdapter::new(1, device_rate, 48000.0, 512, 300, Quality::Medium);
+        let input = smooth_noise(44100);
+        let mut output = input.clone();
+        for block in output.chunks_mut(300) {
+            adapter.process(block, |_block| {});
+        }
+
+        // Find the delay with the least error, after everything has settled
+        let range = 10000..40000;
+        let error_at = |delay: usize| {
+            range
+                .clone()
+                .map(|i| (output[i] - input[i - delay]).powi(2))
+                .sum::<f32>()
+                / range.len() as f32
+        };
+        let best_delay = (0..2000)
+            .min_by(|a, b| error_at(*a).partial_cmp(&error_at(*b)).unwrap())
+            .unwrap();
+
+        assert!((best_delay as i64 - adapter.latency() as i64).abs() <= 2);
+        let power = input.iter().map(|sample| sample * sample).sum::<f32>() / input.len() as f32;
+        // The delay isn't a whole number of frames, allow for the fraction
+        assert!(error_at(best_delay) / power < 1e-3);
+    }
+
+    #[test]
+    fn test_device_buffers_over_the_maximum_are_split() {
+        let input = smooth_noise(2 * 20000);
+        let mut expected = input.clone();
+        let mut adapter = RateAdapter::new(2, 44100.0, 48000.0, 256, 1000, Quality::Fast);
+        for block in expected.chunks_mut(2 * 1000) {
+            adapter.process(block, |_block| {});
+        }
+
+        // Callbacks of up to 1000 frames, then larger ones, must give the same stream
+        let mut output = input;
+        let mut adapter = RateAdapter::new(2, 44100.0, 48000.0, 256, 128, Quality::Fast);
+        for block in output.chunks_mut(2 * 1000) {
+            adapter.process(block, |_block| {});
+        }
+
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_sample_queue_wraps_around() {
+        let mut queue = SampleQueue::new(4);
+        let mut output = [0.0; 3];
+        queue.push(&[1.0, 2.0, 3.0]);
+        assert_eq!(queue.pop(&mut output[..2]), 2);
+        queue.push(&[4.0, 5.0, 6.0]);
+        assert_eq!(queue.len(), 4);
+        assert_eq!(queue.pop(&mut output), 3);
+        assert_eq!(output, [3.0, 4.0, 5.0]);
+        assert_eq!(queue.pop(&mut output), 1);
+        assert_eq!(output[0], 6.0);
+    }
+}
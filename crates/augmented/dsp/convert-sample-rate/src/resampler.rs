@@ -0,0 +1,451 @@
+//! Streaming band-limited resampler.
+//!
+//! Interpolates with a Kaiser windowed-sinc low-pass, stored as a polyphase table & linearly
+//! interpolated between phases, so any ratio is supported. When down-sampling the filter cut-off
+//! follows the output Nyquist frequency, which removes aliasing.
+use std::f64::consts::PI;
+
+use crate::window::kaiser;
+
+/// Trade-off between speed & quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quality {
+    /// Short filter, fine for previews
+    Fast,
+    Medium,
+    /// Transparent for most material, the default
+    #[default]
+    High,
+    /// Very long filter, for offline use
+    Best,
+}
+
+struct QualityParameters {
+    /// Zero crossings of the sinc on each side, at the filter's cut-off
+    zero_crossings: f64,
+    /// Cut-off relative to the lower of the two Nyquist frequencies
+    rolloff: f64,
+    /// Kaiser window shape; higher values trade transition width for stop-band attenuation
+    beta: f64,
+    /// Number of polyphase table rows
+    phases: usize,
+}
+
+impl Quality {
+    fn parameters(&self) -> QualityParameters {
+        match self {
+            Quality::Fast => QualityParameters {
+                zero_crossings: 8.0,
+                rolloff: 0.85,
+                beta: 6.0,
+                phases: 128,
+            },
+            Quality::Medium => QualityParameters {
+                zero_crossings: 16.0,
+                rolloff: 0.9,
+                beta: 8.0,
+                phases: 256,
+            },
+            Quality::High => QualityParameters {
+                zero_crossings: 32.0,
+                rolloff: 0.94,
+                beta: 10.0,
+                phases: 512,
+            },
+            Quality::Best => QualityParameters {
+                zero_crossings: 64.0,
+                rolloff: 0.96,
+                beta: 12.0,
+                phases: 1024,
+            },
+        }
+    }
+}
+
+/// How many frames a call to [`Resampler::process`] read & wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResampleResult {
+    pub input_frames: usize,
+    pub output_frames: usize,
+}
+
+/// A streaming resampler for interleaved audio.
+///
+/// Feed it blocks of any size with [`Resampler::process`]. It consumes as much input as it can
+/// without overflowing the output & keeps the filter history between calls. Allocation happens
+/// only on construction & [`Resampler::set_rates`].
+///
+/// ```
+/// use convert_sample_rate::resampler::{Quality, Resampler};
+///
+/// let mut resampler = Resampler::new(2, 44100.0, 48000.0, Quality::High);
+/// let input = vec![0.0; 2 * 512];
+/// let mut output = vec![0.0; 2 * resampler.max_output_frames(512)];
+/// let result = resampler.process(&input, &mut output);
+/// assert_eq!(result.input_frames, 512);
+/// ```
+pub struct Resampler {
+    num_channels: usize,
+    input_rate: f32,
+    output_rate: f32,
+    quality: Quality,
+    /// Input frames per output frame
+    step: f64,
+    half_length: usize,
+    num_phases: usize,
+    /// `num_phases + 1` rows of `2 * half_length` taps
+    table: Vec<f32>,
+    /// Per channel, the last `2 * half_length` input samples written twice so the window is
+    /// always a contiguous slice
+    history: Vec<f32>,
+    write_position: usize,
+    /// Position of the next output, in input frames, relative to the centre of the window. An
+    /// output is due when it's below 1.
+    offset: f64,
+}
+
+impl Resampler {
+    pub fn new(num_channels: usize, input_rate: f32, output_rate: f32, quality: Quality) -> Self {
+        let mut resampler = Resampler {
+            num_channels,
+            input_rate,
+            output_rate,
+            quality,
+            step: 1.0,
+            half_length: 0,
+            num_phases: 0,
+            table: Vec::new(),
+            history: Vec::new(),
+            write_position: 0,
+            offset: 0.0,
+        };
+        resampler.set_rates(input_rate, output_rate);
+        resampler
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    pub fn input_rate(&self) -> f32 {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> f32 {
+        self.output_rate
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// Output frames per input frame
+    pub fn ratio(&self) -> f64 {
+        1.0 / self.step
+    }
+
+    /// Delay the filter adds, in input frames
+    pub fn latency(&self) -> usize {
+        self.half_length + 1
+    }
+
+    /// Upper bound on the output frames produced from `input_frames`, including frames left due
+    /// from a previous call
+    pub fn max_output_frames(&self, input_frames: usize) -> usize {
+        ((input_frames + 1) as f64 / self.step).ceil() as usize + 1
+    }
+
+    /// Change the conversion rates. Rebuilds the filter, which allocates, & resets the state.
+    pub fn set_rates(&mut self, input_rate: f32, output_rate: f32) {
+        assert!(input_rate > 0.0 && output_rate > 0.0);
+        self.input_rate = input_rate;
+        self.output_rate = output_rate;
+        self.step = input_rate as f64 / output_rate as f64;
+
+        let parameters = self.quality.parameters();
+        let cutoff = parameters.rolloff * (1.0 / self.step).min(1.0);
+        self.half_length = (parameters.zero_crossings / cutoff).ceil() as usize;
+        self.num_phases = parameters.phases;
+        self.table = build_table(self.half_length, self.num_phases, cutoff, parameters.beta);
+        self.history = vec![0.0; self.num_channels * self.window_length() * 2];
+        self.reset();
+    }
+
+    /// Clear the filter history. Output starts again right away, delayed by
+    /// [`Resampler::latency`].
+    pub fn reset(&mut self) {
+        self.history.iter_mut().for_each(|sample| *sample = 0.0);
+        self.write_position = 0;
+        self.offset = 0.0;
+    }
+
+    /// Resample interleaved `input` into interleaved `output`. Stops when either the input is
+    /// consumed or the output is full; unconsumed input should be passed in again.
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) -> ResampleResult {
+        let num_channels = self.num_channels;
+        let input_frames = input.len() / num_channels;
+        let output_frames = output.len() / num_channels;
+        let mut result = ResampleResult::default();
+
+        loop {
+            while self.offset < 1.0 && result.output_frames < output_frames {
+                let frame = &mut output[result.output_frames * num_channels..][..num_channels];
+                self.interpolate(frame);
+                self.offset += self.step;
+                result.output_frames += 1;
+            }
+
+            if self.offset < 1.0 || result.input_frames >= input_frames {
+                break;
+            }
+
+            let frame = &input[result.input_frames * num_channels..][..num_channels];
+            self.push(frame);
+            self.offset -= 1.0;
+            result.input_frames += 1;
+        }
+
+        result
+    }
+
+    fn window_length(&self) -> usize {
+        2 * self.half_length
+    }
+
+    fn push(&mut self, frame: &[f32]) {
+        let window_length = self.window_length();
+        for (channel, sample) in frame.iter().enumerate() {
+            let history = &mut self.history[channel * window_length * 2..][..window_length * 2];
+            history[self.write_position] = *sample;
+            history[self.write_position + window_length] = *sample;
+        }
+        self.write_position = (self.write_position + 1) % window_length;
+    }
+
+    fn interpolate(&self, output: &mut [f32]) {
+        let window_length = self.window_length();
+        let phase = self.offset * self.num_phases as f64;
+        let phase_index = (phase as usize).min(self.num_phases - 1);
+        let amount = (phase - phase_index as f64) as f32;
+        let taps = &self.table[phase_index * window_length..][..window_length * 2];
+        let (taps, next_taps) = taps.split_at(window_length);
+
+        for (channel, output) in output.iter_mut().enumerate() {
+            let history = &self.history[channel * window_length * 2..][..window_length * 2];
+            let window = &history[self.write_position..self.write_position + window_length];
+            let (mut sum, mut next_sum) = (0.0, 0.0);
+            for ((sample, tap), next_tap) in window.iter().zip(taps).zip(next_taps) {
+                sum += sample * tap;
+                next_sum += sample * next_tap;
+            }
+            *output = sum + (next_sum - sum) * amount;
+        }
+    }
+}
+
+/// Row `p` holds the taps for an output `p / num_phases` frames after the centre of the window.
+/// Window sample `j` sits `j - half_length + 1` frames from the centre. Rows are normalized to
+/// unity DC gain.
+fn build_table(half_length: usize, num_phases: usize, cutoff: f64, beta: f64) -> Vec<f32> {
+    let window_length = 2 * half_length;
+    let mut table = Vec::with_capacity((num_phases + 1) * window_length);
+    let mut row = vec![0.0; window_length];
+
+    for phase in 0..=num_phases {
+        let fraction = phase as f64 / num_phases as f64;
+        for (j, tap) in row.iter_mut().enumerate() {
+            let x = j as f64 - half_length as f64 + 1.0 - fraction;
+            let u = x / half_length as f64;
+            *tap = if u.abs() >= 1.0 {
+                0.0
+            } else {
+                cutoff * sinc(cutoff * x) * kaiser(u, beta)
+            };
+        }
+        let sum: f64 = row.iter().sum();
+        table.extend(row.iter().map(|tap| (tap / sum) as f32));
+    }
+
+    table
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Resample a whole mono buffer, filling `output`. Output sample `i` is the input at time
+/// `i / output_rate`; the filter delay is compensated.
+pub fn resample(
+    input_rate: f32,
+    input: &[f32],
+    output_rate: f32,
+    output: &mut [f32],
+    quality: Quality,
+) {
+    let mut resampler = Resampler::new(1, input_rate, output_rate, quality);
+    // Start with the first output centred on the first input sample
+    resampler.offset = resampler.latency() as f64;
+
+    let mut written = 0;
+    let mut remaining_input = input;
+    let silence = [0.0; 64];
+    while written < output.len() {
+        let block = if remaining_input.is_empty() {
+            &silence[..]
+        } else {
+            remaining_input
+        };
+        let result = resampler.process(block, &mut output[written..]);
+        written += result.output_frames;
+        if !remaining_input.is_empty() {
+            remaining_input = &remaining_input[result.input_frames..];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sine(sample_rate: f32, frequency: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| {
+                (2.0 * std::f64::consts::PI * frequency as f64 * i as f64 / sample_rate as f64)
+                    .sin() as f32
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        (samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    /// Signal to noise ratio of a resampled sine against the ideal sine at the output rate
+    fn sine_snr_db(input_rate: f32, output_rate: f32, frequency: f32, quality: Quality) -> f64 {
+        let input = sine(input_rate, frequency, input_rate as usize);
+        let mut output = vec![0.0; output_rate as usize];
+        resample(input_rate, &input, output_rate, &mut output, quality);
+
+        let expected = sine(output_rate, frequency, output.len());
+        let margin = output.len() / 10;
+        let range = margin..output.len() - margin;
+        let error: Vec<f32> = output[range.clone()]
+            .iter()
+            .zip(&expected[range.clone()])
+            .map(|(o, e)| o - e)
+            .collect();
+        20.0 * (rms(&expected[range]) / rms(&error)).log10()
+    }
+
+    #[test]
+    fn test_sine_snr() {
+        let cases = [
+            (44100.0, 48000.0),
+            (48000.0, 44100.0),
+            (44100.0, 96000.0),
+            (96000.0, 44100.0),
+            (44100.0, 44101.0),
+            (22050.0, 8000.0),
+        ];
+        for (input_rate, output_rate) in cases.iter() {
+            let snr = sine_snr_db(*input_rate, *output_rate, 1000.0, Quality::High);
+            assert!(
+                snr > 90.0,
+                "{} -> {} snr={}dB",
+                input_rate,
+                output_rate,
+                snr
+            );
+        }
+    }
+
+    #[test]
+    fn test_quality_presets_are_ordered() {
+        let snrs: Vec<f64> = [Quality::Fast, Quality::Medium, Quality::High, Quality::Best]
+            .iter()
+            .map(|quality| sine_snr_db(44100.0, 48000.0, 5000.0, *quality))
+            .collect();
+        assert!(snrs.windows(2).all(|pair| pair[1] > pair[0]), "{:?}", snrs);
+        assert!(snrs[0] > 40.0, "{:?}", snrs);
+    }
+
+    #[test]
+    fn test_down_sampling_removes_content_above_output_nyquist() {
+        for quality in [Quality::Medium, Quality::High, Quality::Best].iter() {
+            // 30kHz can't be represented at 44.1kHz & would alias down to 14.1kHz
+            let input = sine(96000.0, 30000.0, 96000);
+            let mut output = vec![0.0; 44100];
+            resample(96000.0, &input, 44100.0, &mut output, *quality);
+
+            let aliasing_db = 20.0 * (rms(&output[4410..39690]) / rms(&input)).log10();
+            assert!(
+                aliasing_db < -80.0,
+                "{:?} aliasing={}dB",
+                quality,
+                aliasing_db
+            );
+        }
+    }
+
+    #[test]
+    fn test_down_sampling_keeps_every_output_sample() {
+        let input = vec![1.0; 44100];
+        let mut output = vec![0.0; 10000];
+        resample(44100.0, &input, 10000.0, &mut output, Quality::High);
+
+        // DC passes through, no output index is skipped (apart from the filter ramping at the
+        // edges)
+        for sample in &output[100..9900] {
+            assert!((sample - 1.0).abs() < 1e-4, "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_streaming_matches_single_block() {
+        let input: Vec<f32> = sine(44100.0, 440.0, 4410)
+            .iter()
+            .flat_map(|sample| vec![*sample, -*sample])
+            .collect();
+
+        let mut single = Resampler::new(2, 44100.0, 48000.0, Quality::Medium);
+        let mut expected = vec![0.0; 2 * single.max_output_frames(4410)];
+        let result = single.process(&input, &mut expected);
+        assert_eq!(result.input_frames, 4410);
+        expected.truncate(2 * result.output_frames);
+
+        let mut streaming = Resampler::new(2, 44100.0, 48000.0, Quality::Medium);
+        let mut output = Vec::new();
+        let mut block = vec![0.0; 2 * 37];
+        let mut position = 0;
+        for block_size in [13, 64, 1, 500, 7].iter().cycle() {
+            if position >= 4410 {
+                break;
+            }
+            let end = (position + block_size).min(4410);
+            let mut input = &input[position * 2..end * 2];
+            // Small output blocks, so input is left over
+            while !input.is_empty() {
+                let result = streaming.process(input, &mut block);
+                output.extend_from_slice(&block[..result.output_frames * 2]);
+                input = &input[result.input_frames * 2..];
+            }
+            position = end;
+        }
+
+        assert_eq!(output, expected);
+        for (left, right) in output.chunks(2).map(|frame| (frame[0], frame[1])) {
+            assert_eq!(left, -right);
+        }
+    }
+
+    #[test]
+    fn test_ratio_and_latency() {
+        let resampler = Resampler::new(1, 48000.0, 24000.0, Quality::High);
+        assert!((resampler.ratio() - 0.5).abs() < 1e-12);
+        assert!(resampler.latency() > 32);
+    }
+}
@@ -0,0 +1,42 @@
+//! The Kaiser window, shared by the windowed-sinc filters of this crate & `oversampling`.
+
+/// Kaiser window at `position`, between -1 & 1. A larger `beta` narrows the window, trading a
+/// wider main lobe for lower side-lobes.
+pub fn kaiser(position: f64, beta: f64) -> f64 {
+    bessel_i0(beta * (1.0 - position * position).max(0.0).sqrt()) / bessel_i0(beta)
+}
+
+/// Zeroth order modified Bessel function of the first kind
+pub fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= (half_x / k as f64) * (half_x / k as f64);
+        sum += term;
+        if term < sum * 1e-16 {
+            break;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bessel_i0() {
+        assert_eq!(bessel_i0(0.0), 1.0);
+        assert!((bessel_i0(1.0) - 1.266_065_877_752_008_4).abs() < 1e-12);
+        assert!((bessel_i0(10.0) - 2_815.716_628_466_254).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_kaiser_is_one_at_the_centre_and_tapers() {
+        assert!((kaiser(0.0, 8.0) - 1.0).abs() < 1e-12);
+        assert!(kaiser(0.5, 8.0) < 1.0);
+        assert!((kaiser(1.0, 8.0) - 1.0 / bessel_i0(8.0)).abs() < 1e-12);
+        assert_eq!(kaiser(0.5, 8.0), kaiser(-0.5, 8.0));
+    }
+}
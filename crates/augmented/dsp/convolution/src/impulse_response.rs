@@ -0,0 +1,169 @@
+/// How the channels of an [`ImpulseResponse`] map onto the processed channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLayout {
+    /// A single response applied to every channel
+    Mono,
+    /// One response per channel; left on even channels, right on odd channels
+    Stereo,
+    /// Four responses: left to left, left to right, right to left & right to right
+    TrueStereo,
+}
+
+/// A route from an input channel, through an impulse response channel, into an output channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Route {
+    pub input: usize,
+    pub output: usize,
+    pub response: usize,
+}
+
+/// An impulse response, already at the sample rate it'll be used with.
+#[derive(Debug, Clone)]
+pub struct ImpulseResponse {
+    layout: ChannelLayout,
+    channels: Vec<Vec<f32>>,
+}
+
+impl ImpulseResponse {
+    /// A response applied to every channel
+    pub fn mono(samples: Vec<f32>) -> Self {
+        ImpulseResponse {
+            layout: ChannelLayout::Mono,
+            channels: vec![samples],
+        }
+    }
+
+    /// Separate responses for left & right channels
+    pub fn stereo(left: Vec<f32>, right: Vec<f32>) -> Self {
+        ImpulseResponse {
+            layout: ChannelLayout::Stereo,
+            channels: vec![left, right],
+        }
+    }
+
+    /// True-stereo response, where each input channel feeds both output channels
+    pub fn true_stereo(
+        left_to_left: Vec<f32>,
+        left_to_right: Vec<f32>,
+        right_to_left: Vec<f32>,
+        right_to_right: Vec<f32>,
+    ) -> Self {
+        ImpulseResponse {
+            layout: ChannelLayout::TrueStereo,
+            channels: vec![left_to_left, left_to_right, right_to_left, right_to_right],
+        }
+    }
+
+    /// Build a response from the channels of an audio file. 1 channel files are mono, 2 channel
+    /// files are stereo & 4 channel files are true-stereo (L→L, L→R, R→L, R→R).
+    ///
+    /// Returns `None` for any other number of channels.
+    pub fn from_channels(mut channels: Vec<Vec<f32>>) -> Option<Self> {
+        match channels.len() {
+            1 => Some(Self::mono(channels.remove(0))),
+            2 => {
+                let right = channels.remove(1);
+                Some(Self::stereo(channels.remove(0), right))
+            }
+            4 => Some(ImpulseResponse {
+                layout: ChannelLayout::TrueStereo,
+                channels,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
+    pub fn channels(&self) -> &[Vec<f32>] {
+        &self.channels
+    }
+
+    /// Length in samples of the longest channel
+    pub fn len(&self) -> usize {
+        self.channels
+            .iter()
+            .map(|channel| channel.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Routes for processing `num_channels` channels. Output channels without a route are left
+    /// untouched (e.g. channels past the first two of a true-stereo response).
+    pub(crate) fn routes(&self, num_channels: usize) -> Vec<Route> {
+        match self.layout {
+            ChannelLayout::Mono => (0..num_channels)
+                .map(|channel| Route {
+                    input: channel,
+                    output: channel,
+                    response: 0,
+                })
+                .collect(),
+            ChannelLayout::Stereo => (0..num_channels)
+                .map(|channel| Route {
+                    input: channel,
+                    output: channel,
+                    response: channel % 2,
+                })
+                .collect(),
+            ChannelLayout::TrueStereo => {
+                if num_channels == 0 {
+                    vec![]
+                } else if num_channels == 1 {
+                    vec![Route {
+                        input: 0,
+                        output: 0,
+                        response: 0,
+                    }]
+                } else {
+                    let mut routes = Vec::with_capacity(4);
+                    for input in 0..2 {
+                        for output in 0..2 {
+                            routes.push(Route {
+                                input,
+                                output,
+                                response: input * 2 + output,
+                            });
+                        }
+                    }
+                    routes
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_channels_picks_layout() {
+        let layout = |n: usize| {
+            ImpulseResponse::from_channels(vec![vec![0.0]; n]).map(|response| response.layout())
+        };
+        assert_eq!(layout(1), Some(ChannelLayout::Mono));
+        assert_eq!(layout(2), Some(ChannelLayout::Stereo));
+        assert_eq!(layout(3), None);
+        assert_eq!(layout(4), Some(ChannelLayout::TrueStereo));
+    }
+
+    #[test]
+    fn test_true_stereo_routes() {
+        let response = ImpulseResponse::true_stereo(vec![], vec![], vec![], vec![]);
+        let routes = response.routes(3);
+        assert_eq!(routes.len(), 4);
+        assert!(routes.contains(&Route {
+            input: 1,
+            output: 0,
+            response: 2
+        }));
+        assert!(routes.iter().all(|route| route.output < 2));
+    }
+}
@@ -0,0 +1,406 @@
+use std::sync::Arc;
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::impulse_response::ImpulseResponse;
+
+/// One impulse response channel, split into partitions.
+///
+/// The first partition ("head") is kept in the time domain, reversed, for direct convolution. The
+/// remaining partitions are kept as spectra, zero-padded to twice the partition size & scaled by
+/// the inverse FFT normalization.
+struct FilterPartitions {
+    head: Vec<f32>,
+    spectra: Vec<Vec<Complex<f32>>>,
+}
+
+/// Input channel state: the overlap-save window & the frequency-domain delay line.
+struct InputState {
+    used: bool,
+    /// Last `2 * partition_size` input samples; the second half is the block being filled
+    window: Vec<f32>,
+    /// Spectra of the last `num_partitions` windows
+    delay_line: Vec<Vec<Complex<f32>>>,
+}
+
+/// Output channel state: the routes into it & the tail output for the current block.
+struct OutputState {
+    routes: Vec<(usize, usize)>,
+    accumulator: Vec<Complex<f32>>,
+    block: Vec<f32>,
+}
+
+/// Uniformly partitioned convolution of a multi-channel signal with an [`ImpulseResponse`].
+///
+/// The head partition is convolved directly, the tail with FFT overlap-save over a
+/// frequency-domain delay line. The tail's one block latency lines up with the head's length, so
+/// the output isn't delayed.
+///
+/// Everything is allocated in [`PartitionedConvolver::new`]; [`PartitionedConvolver::process_frame`]
+/// doesn't allocate.
+pub struct PartitionedConvolver {
+    partition_size: usize,
+    num_partitions: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    scratch: Vec<Complex<f32>>,
+    time_buffer: Vec<f32>,
+    filters: Vec<FilterPartitions>,
+    inputs: Vec<InputState>,
+    outputs: Vec<OutputState>,
+    wet_frame: Vec<f32>,
+    position: usize,
+    delay_line_position: usize,
+}
+
+impl PartitionedConvolver {
+    /// Create a convolver for `num_channels` channels, partitioning the response into blocks of
+    /// `partition_size` samples.
+    ///
+    /// Bigger partitions make the tail cheaper but the direct head more expensive.
+    pub fn new(
+        impulse_response: &ImpulseResponse,
+        num_channels: usize,
+        partition_size: usize,
+    ) -> Self {
+        assert!(partition_size > 0, "Partition size must be positive");
+        let fft_size = partition_size * 2;
+        let num_bins = partition_size + 1;
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        let ifft = planner.plan_fft_inverse(fft_size);
+        let mut scratch = Vec::new();
+        scratch.resize(
+            fft.get_scratch_len().max(ifft.get_scratch_len()),
+            Complex::default(),
+        );
+        let mut time_buffer = Vec::new();
+        time_buffer.resize(fft_size, 0.0);
+
+        let num_partitions = if impulse_response.len() > partition_size {
+            (impulse_response.len() - 1) / partition_size
+        } else {
+            0
+        };
+        let normalization = 1.0 / fft_size as f32;
+        let filters = impulse_response
+            .channels()
+            .iter()
+            .map(|channel| {
+                let mut head: Vec<f32> = channel.iter().take(partition_size).cloned().collect();
+                head.resize(partition_size, 0.0);
+                head.reverse();
+
+                let spectra = (0..num_partitions)
+                    .map(|partition| {
+                        let start = (partition + 1) * partition_size;
+                        for (i, sample) in time_buffer.iter_mut().enumerate() {
+                            *sample = if i < partition_size {
+                                channel.get(start + i).cloned().unwrap_or(0.0) * normalization
+                            } else {
+                                0.0
+                            };
+                        }
+                        let mut spectrum = vec![Complex::default(); num_bins];
+                        fft.process_with_scratch(&mut time_buffer, &mut spectrum, &mut scratch)
+                            .expect("Failed to transform impulse response partition");
+                        spectrum
+                    })
+                    .collect();
+
+                FilterPartitions { head, spectra }
+            })
+            .collect();
+
+        let routes = impulse_response.routes(num_channels);
+        let inputs = (0..num_channels)
+            .map(|channel| InputState {
+                used: routes.iter().any(|route| route.input == channel),
+                window: vec![0.0; fft_size],
+                delay_line: vec![vec![Complex::default(); num_bins]; num_partitions],
+            })
+            .collect();
+        let outputs = (0..num_channels)
+            .map(|channel| OutputState {
+                routes: routes
+                    .iter()
+                    .filter(|route| route.output == channel)
+                    .map(|route| (route.input, route.response))
+                    .collect(),
+                accumulator: vec![Complex::default(); num_bins],
+                block: vec![0.0; partition_size],
+            })
+            .collect();
+
+        PartitionedConvolver {
+            partition_size,
+            num_partitions,
+            fft,
+            ifft,
+            scratch,
+            time_buffer,
+            filters,
+            inputs,
+            outputs,
+            wet_frame: vec![0.0; num_channels],
+            position: 0,
+            delay_line_position: 0,
+        }
+    }
+
+    pub fn partition_size(&self) -> usize {
+        self.partition_size
+    }
+
+    /// Number of partitions convolved in the frequency domain
+    pub fn num_partitions(&self) -> usize {
+        self.num_partitions
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Clear all internal state
+    pub fn reset(&mut self) {
+        for input in &mut self.inputs {
+            input.window.iter_mut().for_each(|sample| *sample = 0.0);
+            for spectrum in &mut input.delay_line {
+                spectrum
+                    .iter_mut()
+                    .for_each(|bin| *bin = Complex::default());
+            }
+        }
+        for output in &mut self.outputs {
+            output.block.iter_mut().for_each(|sample| *sample = 0.0);
+        }
+        self.position = 0;
+        self.delay_line_position = 0;
+    }
+
+    /// Process one frame in place. Routed channels are set to `dry * input + wet * convolved`,
+    /// channels without a route are left untouched.
+    pub fn process_frame(&mut self, frame: &mut [f32], dry: f32, wet: f32) {
+        let partition_size = self.partition_size;
+        let position = self.position;
+
+        for (input, sample) in self.inputs.iter_mut().zip(frame.iter()) {
+            input.window[partition_size + position] = *sample;
+        }
+
+        for (channel, output) in self.outputs.iter().enumerate() {
+            let mut value = output.block[position];
+            for (input, response) in &output.routes {
+                let history = &self.inputs[*input].window[position + 1..=position + partition_size];
+                let head = &self.filters[*response].head;
+                value += head
+                    .iter()
+                    .zip(history)
+                    .map(|(coefficient, sample)| coefficient * sample)
+                    .sum::<f32>();
+            }
+            self.wet_frame[channel] = value;
+        }
+
+        for ((output, sample), wet_sample) in self
+            .outputs
+            .iter()
+            .zip(frame.iter_mut())
+            .zip(&self.wet_frame)
+        {
+            if !output.routes.is_empty() {
+                *sample = dry * *sample + wet * wet_sample;
+            }
+        }
+
+        self.position += 1;
+        if self.position == partition_size {
+            self.position = 0;
+            self.process_block();
+        }
+    }
+
+    /// Runs when a block of input is complete; transforms it & computes the tail output for the
+    /// next block.
+    fn process_block(&mut self) {
+        let partition_size = self.partition_size;
+        let num_partitions = self.num_partitions;
+
+        if num_partitions > 0 {
+            self.delay_line_position = (self.delay_line_position + 1) % num_partitions;
+        }
+
+        for input in self.inputs.iter_mut().filter(|input| input.used) {
+            if num_partitions > 0 {
+                self.time_buffer.copy_from_slice(&input.window);
+                self.fft
+                    .process_with_scratch(
+                        &mut self.time_buffer,
+                        &mut input.delay_line[self.delay_line_position],
+                        &mut self.scratch,
+                    )
+                    .expect("Forward FFT failed");
+            }
+            input.window.copy_within(partition_size.., 0);
+        }
+
+        if num_partitions == 0 {
+            return;
+        }
+
+        for output in self.outputs.iter_mut() {
+            if output.routes.is_empty() {
+                continue;
+            }
+
+            output
+                .accumulator
+                .iter_mut()
+                .for_each(|bin| *bin = Complex::default());
+            for (input, response) in &output.routes {
+                let delay_line = &self.inputs[*input].delay_line;
+                let spectra = &self.filters[*response].spectra;
+                for (partition, filter_spectrum) in spectra.iter().enumerate() {
+                    let index =
+                        (self.delay_line_position + num_partitions - partition) % num_partitions;
+                    for ((accumulator, input_bin), filter_bin) in output
+                        .accumulator
+                        .iter_mut()
+                        .zip(&delay_line[index])
+                        .zip(filter_spectrum)
+                    {
+                        *accumulator += input_bin * filter_bin;
+                    }
+                }
+            }
+
+            // The DC & Nyquist bins of a real signal's spectrum are real
+            output.accumulator[0].im = 0.0;
+            output.accumulator[partition_size].im = 0.0;
+            self.ifft
+                .process_with_scratch(
+                    &mut output.accumulator,
+                    &mut self.time_buffer,
+                    &mut self.scratch,
+                )
+                .expect("Inverse FFT failed");
+            output
+                .block
+                .copy_from_slice(&self.time_buffer[partition_size..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use super::*;
+
+    fn direct_convolution(input: &[f32], response: &[f32]) -> Vec<f32> {
+        (0..input.len())
+            .map(|i| {
+                response
+                    .iter()
+                    .enumerate()
+                    .take(i + 1)
+                    .map(|(k, coefficient)| coefficient * input[i - k])
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn random_signal(len: usize) -> Vec<f32> {
+        let mut rng = rand::thread_rng();
+        (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect()
+    }
+
+    fn assert_close(result: &[f32], expected: &[f32]) {
+        for (i, (r, e)) in result.iter().zip(expected).enumerate() {
+            assert!((r - e).abs() < 1e-3, "sample {}: {} != {}", i, r, e);
+        }
+    }
+
+    #[test]
+    fn test_matches_direct_convolution() {
+        let response = random_signal(1000);
+        let input = random_signal(3000);
+        let expected = direct_convolution(&input, &response);
+
+        let mut convolver =
+            PartitionedConvolver::new(&ImpulseResponse::mono(response.clone()), 1, 64);
+        assert_eq!(convolver.num_partitions(), 15);
+        let result: Vec<f32> = input
+            .iter()
+            .map(|sample| {
+                let mut frame = [*sample];
+                convolver.process_frame(&mut frame, 0.0, 1.0);
+                frame[0]
+            })
+            .collect();
+
+        assert_close(&result, &expected);
+    }
+
+    #[test]
+    fn test_response_shorter_than_partition_is_exact() {
+        let response = vec![0.5, 0.25, -0.125];
+        let input = random_signal(300);
+        let expected = direct_convolution(&input, &response);
+
+        let mut convolver = PartitionedConvolver::new(&ImpulseResponse::mono(response), 1, 32);
+        assert_eq!(convolver.num_partitions(), 0);
+        let result: Vec<f32> = input
+            .iter()
+            .map(|sample| {
+                let mut frame = [*sample];
+                convolver.process_frame(&mut frame, 0.0, 1.0);
+                frame[0]
+            })
+            .collect();
+
+        assert_close(&result, &expected);
+    }
+
+    #[test]
+    fn test_true_stereo_routes_left_into_both_outputs() {
+        let left_to_left = random_signal(200);
+        let left_to_right = random_signal(200);
+        let response = ImpulseResponse::true_stereo(
+            left_to_left.clone(),
+            left_to_right.clone(),
+            random_signal(200),
+            random_signal(200),
+        );
+        let mut convolver = PartitionedConvolver::new(&response, 2, 16);
+
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for i in 0..400 {
+            let mut frame = [if i == 0 { 1.0 } else { 0.0 }, 0.0];
+            convolver.process_frame(&mut frame, 0.0, 1.0);
+            left.push(frame[0]);
+            right.push(frame[1]);
+        }
+
+        assert_close(&left[..200], &left_to_left);
+        assert_close(&right[..200], &left_to_right);
+        assert!(left[200..].iter().all(|sample| sample.abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_reset_clears_the_tail() {
+        let response = random_signal(100);
+        let mut convolver = PartitionedConvolver::new(&ImpulseResponse::mono(response), 1, 8);
+        let mut frame = [1.0];
+        convolver.process_frame(&mut frame, 0.0, 1.0);
+        convolver.reset();
+
+        for _ in 0..200 {
+            let mut frame = [0.0];
+            convolver.process_frame(&mut frame, 0.0, 1.0);
+            assert_eq!(frame[0], 0.0);
+        }
+    }
+}
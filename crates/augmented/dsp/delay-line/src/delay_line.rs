@@ -0,0 +1,153 @@
+use audio_processor_traits::Float;
+use circular_data_structures::CircularVec;
+
+use crate::interpolation::{self, Interpolation};
+
+/// Samples kept past the maximum delay, so interpolation never reads overwritten samples
+const INTERPOLATION_MARGIN: usize = 3;
+
+/// A single channel delay line, which may be read at any, fractional, delay.
+///
+/// Delays are in samples, relative to the next [`DelayLine::write`]; a delay of 1 reads the last
+/// written sample. Reading before writing the current input gives the input from `delay` samples
+/// ago.
+///
+/// Delays are clamped between the smallest delay the [`Interpolation`] can read &
+/// [`DelayLine::max_delay`].
+pub struct DelayLine<Sample> {
+    buffer: CircularVec<Sample>,
+    write_position: usize,
+    max_delay: usize,
+}
+
+impl<Sample: Float> DelayLine<Sample> {
+    /// Create a delay line holding up to `max_delay` samples
+    pub fn new(max_delay: usize) -> Self {
+        DelayLine {
+            buffer: CircularVec::with_size(max_delay.max(1) + INTERPOLATION_MARGIN, Sample::zero()),
+            write_position: 0,
+            max_delay: max_delay.max(1),
+        }
+    }
+
+    pub fn max_delay(&self) -> usize {
+        self.max_delay
+    }
+
+    /// Change the maximum delay. Allocates & clears the line.
+    pub fn resize(&mut self, max_delay: usize) {
+        *self = DelayLine::new(max_delay);
+    }
+
+    /// Fill the line with silence
+    pub fn clear(&mut self) {
+        for index in 0..self.buffer.len() {
+            self.buffer[index] = Sample::zero();
+        }
+    }
+
+    /// Push a sample into the line
+    #[inline]
+    pub fn write(&mut self, sample: Sample) {
+        self.buffer[self.write_position] = sample;
+        self.write_position = (self.write_position + 1) % self.buffer.len();
+    }
+
+    /// Read the sample written `delay` writes ago
+    #[inline]
+    pub fn read(&self, delay: usize) -> Sample {
+        let delay = delay.max(1).min(self.max_delay);
+        self.read_unchecked(delay)
+    }
+
+    /// Read between samples. [`Interpolation::Allpass`] needs state, so reads linearly here; use
+    /// a [`crate::Tap`] for it.
+    #[inline]
+    pub fn read_interpolated(&self, delay: Sample, interpolation: Interpolation) -> Sample {
+        let delay = self.clamp_delay(delay, interpolation);
+        match interpolation {
+            Interpolation::None => self.read_unchecked(delay.round().to_usize().unwrap()),
+            Interpolation::Linear | Interpolation::Allpass => {
+                let (index, fraction) = split(delay);
+                interpolation::linear(
+                    self.read_unchecked(index),
+                    self.read_unchecked(index + 1),
+                    fraction,
+                )
+            }
+            Interpolation::Cubic => {
+                let (index, fraction) = split(delay);
+                interpolation::lagrange(
+                    self.read_unchecked(index - 1),
+                    self.read_unchecked(index),
+                    self.read_unchecked(index + 1),
+                    self.read_unchecked(index + 2),
+                    fraction,
+                )
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn clamp_delay(&self, delay: Sample, interpolation: Interpolation) -> Sample {
+        let max_delay = Sample::from(self.max_delay).unwrap();
+        delay.max(interpolation.min_delay()).min(max_delay)
+    }
+
+    /// `delay` must be between 1 & the buffer length
+    #[inline]
+    pub(crate) fn read_unchecked(&self, delay: usize) -> Sample {
+        self.buffer[self.write_position + self.buffer.len() - delay]
+    }
+}
+
+/// Split a delay into its whole samples & fraction
+#[inline]
+pub(crate) fn split<Sample: Float>(delay: Sample) -> (usize, Sample) {
+    let index = delay.floor();
+    (index.to_usize().unwrap(), delay - index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ramp_line(max_delay: usize, num_samples: usize) -> DelayLine<f64> {
+        let mut line = DelayLine::new(max_delay);
+        for i in 0..num_samples {
+            line.write(i as f64);
+        }
+        line
+    }
+
+    #[test]
+    fn test_integer_reads() {
+        let line = ramp_line(10, 25);
+        assert_eq!(line.read(1), 24.0);
+        assert_eq!(line.read(10), 15.0);
+        // Clamped to the maximum delay
+        assert_eq!(line.read(100), 15.0);
+    }
+
+    #[test]
+    fn test_fractional_reads_of_a_ramp() {
+        let line = ramp_line(10, 25);
+        for interpolation in [Interpolation::Linear, Interpolation::Cubic].iter() {
+            let value = line.read_interpolated(3.25, *interpolation);
+            assert!(
+                (value - 21.75).abs() < 1e-9,
+                "{:?} {}",
+                interpolation,
+                value
+            );
+        }
+        assert_eq!(line.read_interpolated(3.25, Interpolation::None), 22.0);
+    }
+
+    #[test]
+    fn test_max_delay_can_be_read_with_cubic_interpolation() {
+        let line = ramp_line(10, 25);
+        let value = line.read_interpolated(9.5, Interpolation::Cubic);
+        assert!((value - 15.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,79 @@
+use audio_processor_traits::Float;
+
+/// How a [`crate::DelayLine`] is read between samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Round to the nearest sample. Cheapest, but zippers when the delay is modulated
+    None,
+    /// Linear interpolation between the 2 nearest samples. Slightly dulls the high end
+    #[default]
+    Linear,
+    /// 4 point, 3rd order Lagrange interpolation
+    Cubic,
+    /// 1st order all-pass interpolation. Flat magnitude response, best for fixed or slowly
+    /// changing delays. Needs state, so only a [`crate::Tap`] reads with it.
+    Allpass,
+}
+
+impl Interpolation {
+    /// Smallest delay, in samples, which may be read with this interpolation
+    pub fn min_delay<Sample: Float>(&self) -> Sample {
+        match self {
+            Interpolation::None | Interpolation::Linear => Sample::one(),
+            Interpolation::Cubic => Sample::from(2.0).unwrap(),
+            Interpolation::Allpass => Sample::from(1.5).unwrap(),
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn linear<Sample: Float>(x0: Sample, x1: Sample, fraction: Sample) -> Sample {
+    x0 + (x1 - x0) * fraction
+}
+
+/// Lagrange interpolation of `x1..x2`, with `x0` one sample before & `x3` one sample after
+#[inline]
+pub(crate) fn lagrange<Sample: Float>(
+    x0: Sample,
+    x1: Sample,
+    x2: Sample,
+    x3: Sample,
+    fraction: Sample,
+) -> Sample {
+    let one = Sample::one();
+    let two = Sample::from(2.0).unwrap();
+    let six = Sample::from(6.0).unwrap();
+    let d0 = fraction + one;
+    let d1 = fraction;
+    let d2 = fraction - one;
+    let d3 = fraction - two;
+
+    -x0 * d1 * d2 * d3 / six + x1 * d0 * d2 * d3 / two - x2 * d0 * d1 * d3 / two
+        + x3 * d0 * d1 * d2 / six
+}
+
+/// Coefficient of a 1st order all-pass with a delay of `fraction` samples at DC
+#[inline]
+pub(crate) fn allpass_coefficient<Sample: Float>(fraction: Sample) -> Sample {
+    (Sample::one() - fraction) / (Sample::one() + fraction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lagrange_is_exact_for_cubic_polynomials() {
+        let polynomial = |x: f64| 0.5 * x * x * x - 2.0 * x * x + x - 3.0;
+        for fraction in [0.0, 0.25, 0.5, 0.9].iter() {
+            let value = lagrange(
+                polynomial(-1.0),
+                polynomial(0.0),
+                polynomial(1.0),
+                polynomial(2.0),
+                *fraction,
+            );
+            assert!((value - polynomial(*fraction)).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Delay lines, for echoes, chorus, flanger & reverb.
+//!
+//! * [`DelayLine`] is a single channel buffer which may be read at fractional delays, with any
+//!   [`Interpolation`]
+//! * [`Tap`] is a read position into a line, which may glide between delay times instead of
+//!   clicking. Several taps may read a line
+//! * [`DelayProcessor`] is a feedback delay [`audio_processor_traits::AudioProcessor`], with
+//!   filters in the feedback loop
+pub use delay_line::DelayLine;
+pub use interpolation::Interpolation;
+pub use processor::DelayProcessor;
+pub use tap::Tap;
+
+mod delay_line;
+mod interpolation;
+mod processor;
+mod tap;
@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use dsp_filters::rbj::Filter;
+
+use crate::{DelayLine, Interpolation, Tap};
+
+/// Time delay time changes glide over
+const DELAY_SMOOTHING_TIME: Duration = Duration::from_millis(50);
+
+struct DelayChannel {
+    line: DelayLine<f32>,
+    tap: Tap<f32>,
+    low_pass: Filter<f32>,
+    high_pass: Filter<f32>,
+}
+
+/// A feedback delay, with low-pass & high-pass filters in the feedback loop, so each repeat is
+/// darker & thinner than the last.
+///
+/// Delay time changes glide instead of clicking. All buffers are allocated on `prepare`.
+pub struct DelayProcessor {
+    max_delay_time: Duration,
+    delay_time: Duration,
+    feedback: f32,
+    mix: f32,
+    low_pass_cutoff: f32,
+    high_pass_cutoff: f32,
+    interpolation: Interpolation,
+    sample_rate: f32,
+    channels: Vec<DelayChannel>,
+}
+
+impl DelayProcessor {
+    /// Create a delay which may be set up to `max_delay_time`
+    pub fn new(max_delay_time: Duration) -> Self {
+        DelayProcessor {
+            max_delay_time,
+            delay_time: Duration::from_millis(500).min(max_delay_time),
+            feedback: 0.3,
+            mix: 0.5,
+            low_pass_cutoff: 8000.0,
+            high_pass_cutoff: 40.0,
+            interpolation: Interpolation::Linear,
+            sample_rate: 44100.0,
+            channels: Vec::new(),
+        }
+    }
+
+    pub fn max_delay_time(&self) -> Duration {
+        self.max_delay_time
+    }
+
+    pub fn delay_time(&self) -> Duration {
+        self.delay_time
+    }
+
+    /// Set the delay time, clamped to the maximum delay time
+    pub fn set_delay_time(&mut self, delay_time: Duration) {
+        self.delay_time = delay_time.min(self.max_delay_time);
+        let delay = self.delay_samples();
+        for channel in &mut self.channels {
+            channel.tap.set_delay(delay);
+        }
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Set the feedback, between 0 & 0.99
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(0.0, 0.99);
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Set the dry/wet mix, between 0 (dry) and 1 (wet)
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    pub fn low_pass_cutoff(&self) -> f32 {
+        self.low_pass_cutoff
+    }
+
+    /// Set the cut-off of the low-pass filter in the feedback loop
+    pub fn set_low_pass_cutoff(&mut self, cutoff: f32) {
+        self.low_pass_cutoff = cutoff;
+        self.setup_filters();
+    }
+
+    pub fn high_pass_cutoff(&self) -> f32 {
+        self.high_pass_cutoff
+    }
+
+    /// Set the cut-off of the high-pass filter in the feedback loop
+    pub fn set_high_pass_cutoff(&mut self, cutoff: f32) {
+        self.high_pass_cutoff = cutoff;
+        self.setup_filters();
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+        for channel in &mut self.channels {
+            channel.tap.set_interpolation(interpolation);
+        }
+    }
+
+    /// Clear the delay lines & filters
+    pub fn reset(&mut self) {
+        for channel in &mut self.channels {
+            channel.line.clear();
+            channel.tap.reset();
+            channel.low_pass = Filter::new();
+            channel.high_pass = Filter::new();
+        }
+        self.setup_filters();
+    }
+
+    fn delay_samples(&self) -> f32 {
+        self.delay_time.as_secs_f32() * self.sample_rate
+    }
+
+    fn setup_filters(&mut self) {
+        // Keep cut-offs under Nyquist
+        let max_cutoff = self.sample_rate * 0.49;
+        let low_pass_cutoff = self.low_pass_cutoff.min(max_cutoff);
+        let high_pass_cutoff = self.high_pass_cutoff.min(max_cutoff);
+        for channel in &mut self.channels {
+            channel.low_pass.setup_low_pass(
+                self.sample_rate,
+                low_pass_cutoff,
+                std::f32::consts::FRAC_1_SQRT_2,
+            );
+            channel.high_pass.setup_high_pass(
+                self.sample_rate,
+                high_pass_cutoff,
+                std::f32::consts::FRAC_1_SQRT_2,
+            );
+        }
+    }
+}
+
+impl AudioProcessor for DelayProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        let max_delay = (self.max_delay_time.as_secs_f32() * self.sample_rate).ceil() as usize + 1;
+        let delay = self.delay_samples();
+        let smoothing = DELAY_SMOOTHING_TIME.as_secs_f32() * self.sample_rate;
+        let interpolation = self.interpolation;
+        self.channels = (0..settings.output_channels())
+            .map(|_| {
+                let mut tap = Tap::new(delay, interpolation);
+                tap.set_smoothing(smoothing);
+                DelayChannel {
+                    line: DelayLine::new(max_delay),
+                    tap,
+                    low_pass: Filter::new(),
+                    high_pass: Filter::new(),
+                }
+            })
+            .collect();
+        self.setup_filters();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let dry = 1.0 - self.mix;
+        let wet = self.mix;
+        let feedback = self.feedback;
+        for frame in data.frames_mut() {
+            for (sample, channel) in frame.iter_mut().zip(&mut self.channels) {
+                let input = *sample;
+                let delayed = channel.tap.read(&channel.line);
+                let repeat = channel
+                    .high_pass
+                    .process1(channel.low_pass.process1(delayed));
+                channel.line.write(input + repeat * feedback);
+                *sample = dry * input + wet * delayed;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::{AudioProcessorSettings, InterleavedAudioBuffer};
+
+    use super::*;
+
+    fn impulse_response(processor: &mut DelayProcessor, num_samples: usize) -> Vec<f32> {
+        let mut samples = vec![0.0; num_samples];
+        samples[0] = 1.0;
+        let mut buffer = InterleavedAudioBuffer::new(1, &mut samples);
+        processor.process(&mut buffer);
+        samples
+    }
+
+    #[test]
+    fn test_repeats_are_spaced_by_the_delay_time() {
+        let mut processor = DelayProcessor::new(Duration::from_secs(1));
+        processor.set_delay_time(Duration::from_millis(10));
+        processor.set_mix(1.0);
+        processor.set_feedback(0.5);
+        processor.set_low_pass_cutoff(20000.0);
+        processor.set_high_pass_cutoff(1.0);
+        processor.prepare(AudioProcessorSettings::new(1000.0, 1, 1, 512));
+
+        let output = impulse_response(&mut processor, 35);
+        let peaks: Vec<usize> = (0..output.len())
+            .filter(|i| output[*i].abs() > 0.1)
+            .collect();
+        assert_eq!(peaks, vec![10, 20, 30]);
+        // Each repeat is quieter
+        assert!(output[20].abs() < output[10].abs());
+        assert!(output[30].abs() < output[20].abs());
+    }
+
+    #[test]
+    fn test_low_pass_in_the_loop_darkens_repeats() {
+        let energy_above = |low_pass_cutoff: f32| {
+            let mut processor = DelayProcessor::new(Duration::from_secs(1));
+            processor.set_delay_time(Duration::from_millis(100));
+            processor.set_mix(1.0);
+            processor.set_feedback(0.9);
+            processor.set_low_pass_cutoff(low_pass_cutoff);
+            processor.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 512));
+            let output = impulse_response(&mut processor, 44100);
+            // Energy of the first difference, which weights high frequencies
+            output[8000..]
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).powi(2))
+                .sum::<f32>()
+        };
+        assert!(energy_above(1000.0) < energy_above(16000.0) * 0.1);
+    }
+}
@@ -0,0 +1,200 @@
+use audio_processor_traits::Float;
+
+use crate::delay_line::{split, DelayLine};
+use crate::interpolation::{self, Interpolation};
+
+/// A read position into a [`DelayLine`].
+///
+/// Once a smoothing time is set with [`Tap::set_smoothing`], changes to the delay glide to the new
+/// value, so a moving delay changes pitch instead of clicking. New taps don't glide, so
+/// [`Tap::set_delay`] jumps until then. Several taps may read the same line for multi-tap delays.
+///
+/// ```
+/// use delay_line::{DelayLine, Interpolation, Tap};
+///
+/// let mut line = DelayLine::new(1000);
+/// let mut taps = vec![
+///     Tap::new(100.0, Interpolation::Linear),
+///     Tap::new(250.5, Interpolation::Cubic),
+/// ];
+///
+/// let input = 1.0;
+/// let output: f32 = taps.iter_mut().map(|tap| tap.read(&line)).sum();
+/// line.write(input);
+/// ```
+pub struct Tap<Sample> {
+    delay: Sample,
+    target_delay: Sample,
+    /// One-pole coefficient, 1 when the delay doesn't glide
+    smoothing_coefficient: Sample,
+    interpolation: Interpolation,
+    /// The last output, for all-pass interpolation
+    allpass_output: Sample,
+}
+
+impl<Sample: Float> Tap<Sample> {
+    /// Create a tap at `delay` samples, which doesn't glide until [`Tap::set_smoothing`] is called
+    pub fn new(delay: Sample, interpolation: Interpolation) -> Self {
+        Tap {
+            delay,
+            target_delay: delay,
+            smoothing_coefficient: Sample::one(),
+            interpolation,
+            allpass_output: Sample::zero(),
+        }
+    }
+
+    /// The delay, in samples, the next read will be at
+    pub fn delay(&self) -> Sample {
+        self.delay
+    }
+
+    /// The delay, in samples, the tap is gliding to
+    pub fn target_delay(&self) -> Sample {
+        self.target_delay
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+        self.allpass_output = Sample::zero();
+    }
+
+    /// Glide to a new delay, in samples, over the smoothing time. Jumps if no smoothing time is set.
+    pub fn set_delay(&mut self, delay: Sample) {
+        self.target_delay = delay;
+    }
+
+    /// Jump to a new delay, in samples, without gliding
+    pub fn jump_delay(&mut self, delay: Sample) {
+        self.delay = delay;
+        self.target_delay = delay;
+    }
+
+    /// Set the glide time constant, in samples. Glides take about 5 times as long to complete. 0
+    /// disables gliding.
+    pub fn set_smoothing(&mut self, smoothing_samples: Sample) {
+        self.smoothing_coefficient = if smoothing_samples <= Sample::one() {
+            Sample::one()
+        } else {
+            Sample::one() - (-smoothing_samples.recip()).exp()
+        };
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.delay != self.target_delay
+    }
+
+    /// Clear the interpolation state
+    pub fn reset(&mut self) {
+        self.allpass_output = Sample::zero();
+    }
+
+    /// Read the next sample from `line` & advance the glide. Read once per sample, before writing
+    /// the current input into the line.
+    #[inline]
+    pub fn read(&mut self, line: &DelayLine<Sample>) -> Sample {
+        self.tick();
+
+        match self.interpolation {
+            Interpolation::Allpass => {
+                let delay = line.clamp_delay(self.delay, Interpolation::Allpass);
+                // Keep the fraction between 0.5 & 1.5, where the all-pass is best behaved
+                let (index, _) = split(delay - Sample::from(0.5).unwrap());
+                let fraction = delay - Sample::from(index).unwrap();
+                let coefficient = interpolation::allpass_coefficient(fraction);
+                let output = coefficient * (line.read_unchecked(index) - self.allpass_output)
+                    + line.read_unchecked(index + 1);
+                self.allpass_output = output;
+                output
+            }
+            interpolation => line.read_interpolated(self.delay, interpolation),
+        }
+    }
+
+    #[inline]
+    fn tick(&mut self) {
+        if self.delay == self.target_delay {
+            return;
+        }
+
+        let delay = self.delay + (self.target_delay - self.delay) * self.smoothing_coefficient;
+        // Snap once close, or once steps are lost to rounding
+        if delay == self.delay || (self.target_delay - delay).abs() < Sample::from(1e-4).unwrap() {
+            self.delay = self.target_delay;
+        } else {
+            self.delay = delay;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sine(frequency: f64, num_samples: usize) -> Vec<f64> {
+        (0..num_samples)
+            .map(|i| (2.0 * std::f64::consts::PI * frequency * i as f64).sin())
+            .collect()
+    }
+
+    /// Delays a sine & compares with the exact delayed sine, after `settle` samples
+    fn max_error(interpolation: Interpolation, delay: f64, frequency: f64) -> f64 {
+        let input = sine(frequency, 2000);
+        let mut line = DelayLine::new(100);
+        let mut tap = Tap::new(delay, interpolation);
+        let mut max_error: f64 = 0.0;
+        for (i, sample) in input.iter().enumerate() {
+            let output = tap.read(&line);
+            line.write(*sample);
+            if i > 200 {
+                let expected = (2.0 * std::f64::consts::PI * frequency * (i as f64 - delay)).sin();
+                max_error = max_error.max((output - expected).abs());
+            }
+        }
+        max_error
+    }
+
+    #[test]
+    fn test_interpolated_reads_delay_a_sine() {
+        // Normalized frequency, ~440Hz at 44.1kHz
+        let frequency = 0.01;
+        assert!(max_error(Interpolation::Linear, 10.3, frequency) < 1e-3);
+        assert!(max_error(Interpolation::Cubic, 10.3, frequency) < 1e-5);
+        assert!(max_error(Interpolation::Allpass, 10.3, frequency) < 1e-3);
+    }
+
+    #[test]
+    fn test_delay_changes_jump_without_smoothing() {
+        let line = DelayLine::<f32>::new(1000);
+        let mut tap = Tap::new(10.0, Interpolation::Linear);
+        tap.set_delay(500.0);
+
+        tap.read(&line);
+        assert_eq!(tap.delay(), 500.0);
+    }
+
+    #[test]
+    fn test_delay_changes_glide() {
+        let line = DelayLine::<f32>::new(1000);
+        let mut tap = Tap::new(10.0, Interpolation::Linear);
+        tap.set_smoothing(100.0);
+        tap.set_delay(500.0);
+
+        let mut previous = tap.delay();
+        for _ in 0..100 {
+            tap.read(&line);
+            assert!(tap.delay() > previous);
+            assert!(tap.delay() - previous < 10.0);
+            previous = tap.delay();
+        }
+        for _ in 0..5000 {
+            tap.read(&line);
+        }
+        assert!(!tap.is_smoothing());
+        assert_eq!(tap.delay(), 500.0);
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+/// Bias of [`TransferCurve::Tube`], which makes it clip the negative side harder
+const TUBE_BIAS: f32 = 0.3;
+
+/// A waveshaper transfer curve, read with linear interpolation. Maps inputs between -1 & 1 onto
+/// the table, inputs outside of that range read the first or last value.
+///
+/// Cheap to clone, the values are shared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupTable {
+    values: Arc<[f32]>,
+}
+
+impl LookupTable {
+    /// Create a table from at least 2 values, spread evenly between inputs of -1 & 1
+    pub fn new(values: Vec<f32>) -> Self {
+        assert!(values.len() >= 2, "a lookup table needs at least 2 values");
+        LookupTable {
+            values: values.into(),
+        }
+    }
+
+    /// Create a table of `size` values by sampling `curve` between -1 & 1
+    pub fn from_fn(size: usize, curve: impl Fn(f32) -> f32) -> Self {
+        let size = size.max(2);
+        let values = (0..size)
+            .map(|index| curve(index as f32 / (size - 1) as f32 * 2.0 - 1.0))
+            .collect();
+        Self::new(values)
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    #[inline]
+    pub fn apply(&self, input: f32) -> f32 {
+        let last = self.values.len() - 1;
+        let position = (input.clamp(-1.0, 1.0) + 1.0) / 2.0 * last as f32;
+        let index = (position as usize).min(last - 1);
+        let fraction = position - index as f32;
+        let x0 = self.values[index];
+        let x1 = self.values[index + 1];
+        x0 + (x1 - x0) * fraction
+    }
+}
+
+/// Shape of a [`crate::DistortionProcessor`]'s saturation
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TransferCurve {
+    /// Smooth, symmetric saturation
+    #[default]
+    Tanh,
+    /// Clips at -1 & 1
+    HardClip,
+    /// Reflects the signal back from -1 & 1, so louder inputs fold over & over
+    Foldback,
+    /// Biased, asymmetric saturation, adding even harmonics like a tube stage. Adds a DC offset,
+    /// which the processor removes.
+    Tube,
+    /// A user supplied curve
+    Table(LookupTable),
+}
+
+impl TransferCurve {
+    #[inline]
+    pub fn apply(&self, input: f32) -> f32 {
+        match self {
+            TransferCurve::Tanh => input.tanh(),
+            TransferCurve::HardClip => input.clamp(-1.0, 1.0),
+            TransferCurve::Foldback => ((input - 1.0).rem_euclid(4.0) - 2.0).abs() - 1.0,
+            TransferCurve::Tube => (input + TUBE_BIAS).tanh() - TUBE_BIAS.tanh(),
+            TransferCurve::Table(table) => table.apply(input),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_foldback_reflects_at_the_limits() {
+        let curve = TransferCurve::Foldback;
+        for (input, expected) in [(0.0, 0.0), (0.5, 0.5), (1.0, 1.0), (1.5, 0.5), (2.0, 0.0)]
+            .iter()
+            .chain([(3.0, -1.0), (-1.5, -0.5), (5.0, 1.0)].iter())
+        {
+            assert!((curve.apply(*input) - expected).abs() < 1e-6, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_lookup_table_interpolates_linearly() {
+        let table = LookupTable::new(vec![-1.0, 0.0, 0.5]);
+        assert_eq!(table.apply(-1.0), -1.0);
+        assert_eq!(table.apply(-0.5), -0.5);
+        assert_eq!(table.apply(0.5), 0.25);
+        assert_eq!(table.apply(1.0), 0.5);
+        assert_eq!(table.apply(10.0), 0.5);
+
+        let table = LookupTable::from_fn(1025, |x| x.tanh());
+        assert!((table.apply(0.3) - 0.3_f32.tanh()).abs() < 1e-5);
+    }
+}
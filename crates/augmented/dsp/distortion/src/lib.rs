@@ -0,0 +1,12 @@
+//! Waveshaping distortion, with built-in oversampling.
+//!
+//! [`DistortionProcessor`] shapes its input with a [`TransferCurve`]: tanh, hard clipping,
+//! foldback, an asymmetric tube-like curve or a user supplied [`LookupTable`]. It has pre & post
+//! gain, a tone filter & DC blocking, & runs at 2, 4 or 8 times the sample rate with the
+//! [`oversampling`] crate's half-band filters, so the harmonics it generates don't alias.
+pub use curves::{LookupTable, TransferCurve};
+pub use oversampling::{OversamplingFactor, OversamplingQuality};
+pub use processor::DistortionProcessor;
+
+mod curves;
+mod processor;
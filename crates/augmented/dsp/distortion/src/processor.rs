@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use audio_processor_utility::dc_blocker::DcBlockerProcessor;
+use audio_volume::db_to_amplitude;
+use dsp_filters::rbj::Filter;
+use oversampling::{Oversampled, OversamplingFactor, OversamplingQuality};
+use smooth_value::InterpolatedValue;
+
+use crate::TransferCurve;
+
+/// How long gain changes are ramped for
+const SMOOTHING_TIME: Duration = Duration::from_millis(10);
+
+/// A gain of `gain_db`, ramped over `SMOOTHING_TIME`, at the default sample rate until prepared
+fn smoothed_gain(gain_db: f32) -> InterpolatedValue {
+    let sample_rate = AudioProcessorSettings::default().sample_rate();
+    InterpolatedValue::new(sample_rate, SMOOTHING_TIME, db_to_amplitude(gain_db, 1.0))
+}
+
+/// The drive & transfer curve, run by [`Oversampled`]
+struct Waveshaper {
+    curve: TransferCurve,
+    drive_db: f32,
+    drive: InterpolatedValue,
+}
+
+impl Waveshaper {
+    fn set_drive_db(&mut self, drive_db: f32) {
+        self.drive_db = drive_db;
+        self.drive.set(db_to_amplitude(drive_db, 1.0));
+    }
+}
+
+impl AudioProcessor for Waveshaper {
+    type SampleType = f32;
+
+    /// Called with the oversampled settings
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.drive.set_sample_rate(settings.sample_rate());
+        self.drive.jump();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            let drive = self.drive.next_sample();
+            for sample in frame.iter_mut() {
+                *sample = self.curve.apply(*sample * drive);
+            }
+        }
+    }
+}
+
+/// A waveshaping distortion, running at 2, 4 or 8 times the sample rate so its harmonics don't
+/// alias back into the audio band.
+///
+/// The input is boosted by the drive, shaped by the [`TransferCurve`] while oversampled, then
+/// DC blocked, low-passed by the tone filter & scaled by the output gain.
+///
+/// Drive & output gain changes are ramped over 10ms. The oversampling filters delay the output by
+/// [`DistortionProcessor::latency`]. All buffers are allocated on `prepare`.
+pub struct DistortionProcessor {
+    waveshaper: Oversampled<Waveshaper>,
+    output_gain_db: f32,
+    output_gain: InterpolatedValue,
+    tone: f32,
+    settings: AudioProcessorSettings,
+    tone_filters: Vec<Filter<f32>>,
+    /// Removes the offset asymmetric curves add
+    dc_blocker: DcBlockerProcessor<f32>,
+}
+
+impl Default for DistortionProcessor {
+    fn default() -> Self {
+        Self::new(TransferCurve::default())
+    }
+}
+
+impl DistortionProcessor {
+    pub fn new(curve: TransferCurve) -> Self {
+        let drive_db = 12.0;
+        let output_gain_db = -6.0;
+        let waveshaper = Waveshaper {
+            curve,
+            drive_db,
+            drive: smoothed_gain(drive_db),
+        };
+        DistortionProcessor {
+            waveshaper: Oversampled::new(waveshaper, OversamplingFactor::default()),
+            output_gain_db,
+            output_gain: smoothed_gain(output_gain_db),
+            tone: 12000.0,
+            settings: AudioProcessorSettings::default(),
+            tone_filters: Vec::new(),
+            dc_blocker: DcBlockerProcessor::default(),
+        }
+    }
+
+    pub fn curve(&self) -> &TransferCurve {
+        &self.waveshaper.processor().curve
+    }
+
+    pub fn set_curve(&mut self, curve: TransferCurve) {
+        self.waveshaper.processor_mut().curve = curve;
+    }
+
+    pub fn drive_db(&self) -> f32 {
+        self.waveshaper.processor().drive_db
+    }
+
+    /// Set the gain before the transfer curve, in dB
+    pub fn set_drive_db(&mut self, drive_db: f32) {
+        self.waveshaper.processor_mut().set_drive_db(drive_db);
+    }
+
+    pub fn output_gain_db(&self) -> f32 {
+        self.output_gain_db
+    }
+
+    /// Set the gain after the tone filter, in dB
+    pub fn set_output_gain_db(&mut self, output_gain_db: f32) {
+        self.output_gain_db = output_gain_db;
+        self.output_gain.set(db_to_amplitude(output_gain_db, 1.0));
+    }
+
+    pub fn tone(&self) -> f32 {
+        self.tone
+    }
+
+    /// Set the cut-off of the low-pass after the transfer curve, in Hz
+    pub fn set_tone(&mut self, tone: f32) {
+        self.tone = tone.max(20.0);
+        self.setup_tone_filters();
+    }
+
+    pub fn oversampling_factor(&self) -> OversamplingFactor {
+        self.waveshaper.factor()
+    }
+
+    pub fn oversampling_quality(&self) -> OversamplingQuality {
+        self.waveshaper.quality()
+    }
+
+    /// Change the oversampling. Re-allocates the filters if the processor was prepared, so it
+    /// shouldn't be called on the audio thread.
+    pub fn set_oversampling(&mut self, factor: OversamplingFactor, quality: OversamplingQuality) {
+        let filter = self.waveshaper.filter();
+        self.waveshaper.set_oversampling(factor, quality, filter);
+    }
+
+    /// Clear the filters
+    pub fn reset(&mut self) {
+        self.waveshaper.reset();
+        for filter in &mut self.tone_filters {
+            *filter = Filter::new();
+        }
+        self.setup_tone_filters();
+        self.dc_blocker.reset();
+    }
+
+    fn setup_tone_filters(&mut self) {
+        let sample_rate = self.settings.sample_rate();
+        let cutoff = self.tone.min(sample_rate * 0.49);
+        for filter in &mut self.tone_filters {
+            filter.setup_low_pass(sample_rate, cutoff, std::f32::consts::FRAC_1_SQRT_2);
+        }
+    }
+}
+
+impl AudioProcessor for DistortionProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.settings = settings;
+        self.output_gain.set_sample_rate(settings.sample_rate());
+        self.output_gain.jump();
+        self.waveshaper.prepare(settings);
+        self.dc_blocker.prepare(settings);
+        self.tone_filters = (0..settings.output_channels())
+            .map(|_| Filter::new())
+            .collect();
+        self.setup_tone_filters();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        self.waveshaper.process(data);
+        self.dc_blocker.process(data);
+
+        for frame in data.frames_mut() {
+            let output_gain = self.output_gain.next_sample();
+            for (sample, tone_filter) in frame.iter_mut().zip(&mut self.tone_filters) {
+                *sample = output_gain * tone_filter.process1(*sample);
+            }
+        }
+    }
+
+    /// Delay added by the oversampling filters, in samples. See [`Oversampled`].
+    fn latency(&self) -> usize {
+        self.waveshaper.latency()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    fn process_sine(distortion: &mut DistortionProcessor, amplitude: f32) -> Vec<f32> {
+        distortion.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 512));
+        let mut samples: Vec<f32> = (0..44100)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / 44100.0).sin())
+            .collect();
+        distortion.process(&mut InterleavedAudioBuffer::new(1, &mut samples));
+        samples
+    }
+
+    #[test]
+    fn test_hard_clip_bounds_the_output() {
+        let mut distortion = DistortionProcessor::new(TransferCurve::HardClip);
+        distortion.set_drive_db(40.0);
+        distortion.set_output_gain_db(0.0);
+        distortion.set_tone(20000.0);
+        let output = process_sine(&mut distortion, 1.0);
+        let peak = output[4410..]
+            .iter()
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        // Band-limiting a square wave overshoots a little
+        assert!(peak > 0.9 && peak < 1.35, "peak {}", peak);
+    }
+
+    #[test]
+    fn test_dc_from_asymmetric_curves_is_removed() {
+        let mut distortion = DistortionProcessor::new(TransferCurve::Tube);
+        distortion.set_drive_db(20.0);
+        let output = process_sine(&mut distortion, 1.0);
+        let tail = &output[22050..];
+        let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+        assert!(mean.abs() < 1e-3, "mean {}", mean);
+    }
+
+    #[test]
+    fn test_gain_changes_are_ramped() {
+        let mut distortion = DistortionProcessor::new(TransferCurve::Tanh);
+        distortion.set_drive_db(0.0);
+        distortion.set_output_gain_db(0.0);
+        let output = process_sine(&mut distortion, 0.5);
+        let max_step = |samples: &[f32]| {
+            samples
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).abs())
+                .fold(0.0_f32, f32::max)
+        };
+        let steady_step = max_step(&output[4410..]);
+
+        // Jumping either gain at the peak of the sine would step by about 0.4
+        let mut samples: Vec<f32> = (0..4410)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / 44100.0).sin())
+            .collect();
+        let (first, second) = samples.split_at_mut(110);
+        distortion.process(&mut InterleavedAudioBuffer::new(1, first));
+        distortion.set_drive_db(-40.0);
+        distortion.set_output_gain_db(-40.0);
+        distortion.process(&mut InterleavedAudioBuffer::new(1, second));
+
+        assert!(max_step(&samples) < steady_step * 1.5, "{}", max_step(&samples));
+        assert!(samples[1000..].iter().all(|sample| sample.abs() < 0.01));
+    }
+
+    #[test]
+    fn test_reports_the_oversampling_latency() {
+        let mut distortion = DistortionProcessor::default();
+        assert_eq!(distortion.latency(), 0);
+        distortion.prepare(AudioProcessorSettings::default());
+        assert!(distortion.latency() > 0);
+
+        let mut oversampled = Oversampled::new(
+            audio_processor_traits::NoopAudioProcessor::new(),
+            distortion.oversampling_factor(),
+        );
+        oversampled.prepare(AudioProcessorSettings::default());
+        assert_eq!(distortion.latency(), oversampled.latency());
+    }
+}
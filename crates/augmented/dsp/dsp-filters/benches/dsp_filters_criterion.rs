@@ -0,0 +1,47 @@
+use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dsp_filters::rbj::{FilterProcessor, FilterType};
+
+const NUM_SAMPLES: usize = 10000;
+
+fn make_buffer() -> VecAudioBuffer<f32> {
+    let mut buffer = VecAudioBuffer::new();
+    buffer.resize(1, NUM_SAMPLES, 0.0);
+    buffer
+}
+
+fn make_processor(ramp_time: f32) -> FilterProcessor<f32> {
+    let mut processor = FilterProcessor::new(FilterType::LowPass);
+    processor.set_ramp_time(ramp_time);
+    processor.set_cutoff(880.0);
+    // The buffer is filtered in-place over & over, so the filter must not have gain above unity
+    processor.set_q(std::f32::consts::FRAC_1_SQRT_2);
+    processor.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 512));
+    processor
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    c.bench_function("rbj_low_pass - static - 10k samples", |b| {
+        let mut processor = make_processor(0.02);
+        let mut buffer = make_buffer();
+
+        b.iter(|| processor.process(black_box(&mut buffer)))
+    });
+
+    c.bench_function("rbj_low_pass - ramping - 10k samples", |b| {
+        // A long ramp time so the cut-off never settles between iterations
+        let mut processor = make_processor(10.0);
+        let mut buffer = make_buffer();
+        let mut cutoff = 880.0;
+
+        b.iter(|| {
+            cutoff = if cutoff > 1000.0 { 500.0 } else { 5000.0 };
+            processor.set_cutoff(cutoff);
+            processor.process(black_box(&mut buffer))
+        })
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
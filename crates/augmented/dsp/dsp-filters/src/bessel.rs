@@ -0,0 +1,80 @@
+//! Bessel filters. Maximally flat group delay.
+//!
+//! Ported from [vinniefalco/DSPFilters](https://github.com/vinniefalco/DSPFilters/). As in
+//! the original, the prototypes are delay-normalized, so the cut-off is not the -3dB point.
+use std::f64::consts::PI;
+
+use num::complex::Complex64;
+
+use crate::layout::{infinity, Layout};
+use crate::root_finder::find_roots;
+
+fn factorial(n: usize) -> f64 {
+    (1..=n).fold(1.0, |acc, i| acc * i as f64)
+}
+
+/// Coefficient `k` of the reverse Bessel polynomial of degree `n`
+fn reverse_bessel(k: usize, n: usize) -> f64 {
+    factorial(2 * n - k) / ((factorial(n - k) * factorial(k)) * 2.0_f64.powi((n - k) as i32))
+}
+
+fn reverse_bessel_coefficients(n: usize) -> Vec<f64> {
+    (0..=n).map(|k| reverse_bessel(k, n)).collect()
+}
+
+/// Analog low-pass prototype with `num_poles` poles
+pub fn analog_low_pass(num_poles: usize) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(0.0, 1.0);
+
+    let roots = find_roots(&reverse_bessel_coefficients(num_poles));
+    let pairs = num_poles / 2;
+    for root in roots.iter().take(pairs) {
+        layout.add_pole_zero_conjugate_pairs(*root, infinity());
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(Complex64::new(roots[pairs].re, 0.0), infinity());
+    }
+
+    layout
+}
+
+/// Analog low-shelf prototype with `num_poles` poles. Gain is `gain_db` at DC & unity at
+/// infinity.
+pub fn analog_low_shelf(num_poles: usize, gain_db: f64) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(PI, 1.0);
+
+    let g = 10.0_f64.powf(gain_db / 20.0) - 1.0;
+    let pole_coefficients = reverse_bessel_coefficients(num_poles);
+    let mut zero_coefficients = pole_coefficients.clone();
+    zero_coefficients[0] += g * pole_coefficients[0];
+
+    let poles = find_roots(&pole_coefficients);
+    let zeros = find_roots(&zero_coefficients);
+
+    // Unlike the poles, the zeros may have several real roots, which are paired up amongst
+    // themselves
+    let (real_zeros, complex_zeros): (Vec<Complex64>, Vec<Complex64>) =
+        zeros.into_iter().partition(|zero| zero.im == 0.0);
+    let zero_pairs = complex_zeros
+        .iter()
+        .filter(|zero| zero.im > 0.0)
+        .map(|zero| (*zero, zero.conj()))
+        .chain(real_zeros.chunks_exact(2).map(|zeros| (zeros[0], zeros[1])));
+
+    let pairs = num_poles / 2;
+    for (pole, zeros) in poles.iter().take(pairs).zip(zero_pairs) {
+        layout.add_pair((*pole, pole.conj()), zeros);
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(
+            Complex64::new(poles[pairs].re, 0.0),
+            Complex64::new(real_zeros[real_zeros.len() - 1].re, 0.0),
+        );
+    }
+
+    layout
+}
@@ -0,0 +1,54 @@
+//! Butterworth filters. Maximally flat pass-band.
+//!
+//! Ported from [vinniefalco/DSPFilters](https://github.com/vinniefalco/DSPFilters/)
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use num::complex::Complex64;
+
+use crate::layout::{infinity, Layout};
+
+/// Analog low-pass prototype with `num_poles` poles
+pub fn analog_low_pass(num_poles: usize) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(0.0, 1.0);
+
+    let n2 = 2.0 * num_poles as f64;
+    let pairs = num_poles / 2;
+    for i in 0..pairs {
+        let c = Complex64::from_polar(1.0, FRAC_PI_2 + (2 * i + 1) as f64 * PI / n2);
+        layout.add_pole_zero_conjugate_pairs(c, infinity());
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(Complex64::new(-1.0, 0.0), infinity());
+    }
+
+    layout
+}
+
+/// Analog low-shelf prototype with `num_poles` poles. Gain is `gain_db` at DC & unity at
+/// infinity.
+pub fn analog_low_shelf(num_poles: usize, gain_db: f64) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(PI, 1.0);
+
+    let n2 = num_poles as f64 * 2.0;
+    let g = 10.0_f64.powf(gain_db / 20.0).powf(1.0 / n2);
+    let gp = -1.0 / g;
+    let gz = -g;
+
+    let pairs = num_poles / 2;
+    for i in 1..=pairs {
+        let theta = PI * (0.5 - (2 * i - 1) as f64 / n2);
+        layout.add_pole_zero_conjugate_pairs(
+            Complex64::from_polar(gp, theta),
+            Complex64::from_polar(gz, theta),
+        );
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(Complex64::new(gp, 0.0), Complex64::new(gz, 0.0));
+    }
+
+    layout
+}
@@ -0,0 +1,163 @@
+//! A series of second order sections, used to realize higher order filters.
+use std::f64::consts::PI;
+
+use audio_processor_traits::AudioBuffer;
+use num::Float;
+
+use crate::coefficients::BiquadCoefficients;
+use crate::denormal_prevention::DenormalPrevention;
+use crate::layout::Layout;
+use crate::response::FrequencyResponse;
+use crate::state::{DirectFormIState, FilterState};
+
+/// A cascade of [`BiquadCoefficients`], each with its own [`FilterState`].
+///
+/// `State` defaults to [`DirectFormIState`]; [`crate::state::DirectFormIIState`] may be used
+/// instead.
+///
+/// Changing the number of stages allocates, so the layout order should be kept stable on the
+/// audio-thread.
+pub struct Cascade<Sample: Float, State = DirectFormIState<Sample>> {
+    stages: Vec<BiquadCoefficients<Sample>>,
+    states: Vec<State>,
+    denormal_prevention: DenormalPrevention<Sample>,
+}
+
+impl<Sample: Float, State: FilterState<Sample = Sample> + Default> Default
+    for Cascade<Sample, State>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Sample: Float, State: FilterState<Sample = Sample> + Default> Cascade<Sample, State> {
+    /// Create an empty cascade, which passes its input through
+    pub fn new() -> Self {
+        Cascade {
+            stages: Vec::new(),
+            states: Vec::new(),
+            denormal_prevention: DenormalPrevention::default(),
+        }
+    }
+
+    /// The number of second order sections in use
+    pub fn num_stages(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// The coefficients for each stage
+    pub fn stages(&self) -> &[BiquadCoefficients<Sample>] {
+        &self.stages
+    }
+
+    /// Set-up one stage per [`crate::layout::PoleZeroPair`] in the digital `layout` and scale
+    /// the stages so the cascade has the layout's normal gain at its normal frequency.
+    pub fn set_layout(&mut self, layout: &Layout) {
+        let num_stages = layout.pairs().len();
+        self.stages
+            .resize_with(num_stages, BiquadCoefficients::default);
+        if self.states.len() != num_stages {
+            self.states.resize_with(num_stages, State::default);
+            self.reset();
+        }
+
+        // Unlike DSPFilters, each stage is normalized to unity gain first. Otherwise the first
+        // stage carries all the attenuation & the signal between stages may be small enough for
+        // the denormal prevention offset to be audible.
+        let normal_frequency = layout.normal_w() / (2.0 * PI);
+        for (stage, pair) in self.stages.iter_mut().zip(layout.pairs()) {
+            stage.set_pole_zero_pair(pair);
+            let stage_gain = stage.response(normal_frequency).norm();
+            if stage_gain.is_normal() {
+                stage.apply_scale(Sample::from(1.0 / stage_gain).unwrap());
+            }
+        }
+
+        let scale = layout.normal_gain() / self.response(normal_frequency).norm();
+        self.apply_scale(Sample::from(scale).unwrap());
+    }
+
+    /// Scales the output by scaling the first stage
+    pub fn apply_scale(&mut self, scale: Sample) {
+        if let Some(stage) = self.stages.first_mut() {
+            stage.apply_scale(scale);
+        }
+    }
+
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        for state in &mut self.states {
+            state.reset();
+        }
+    }
+
+    /// Process a single sample through all stages
+    #[inline]
+    pub fn process1(&mut self, input: Sample) -> Sample {
+        // Only the first stage gets the denormal prevention offset, later stages would amplify
+        // it by the gain of the preceding sections
+        let mut very_small_amount = self.denormal_prevention.alternating_current();
+        let mut output = input;
+        for (stage, state) in self.stages.iter().zip(self.states.iter_mut()) {
+            output = state.process1(stage, output, very_small_amount);
+            very_small_amount = Sample::zero();
+        }
+        output
+    }
+
+    /// Process a channel of `buffer` in-place
+    pub fn process_channel<Buffer: AudioBuffer<SampleType = Sample>>(
+        &mut self,
+        buffer: &mut Buffer,
+        channel_index: usize,
+    ) {
+        for frame in buffer.frames_mut() {
+            frame[channel_index] = self.process1(frame[channel_index]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pole_filter::{setup, FilterFamily, PoleFilterParameters, PoleFilterType};
+    use crate::state::DirectFormIIState;
+
+    use super::*;
+
+    #[test]
+    fn test_empty_cascade_passes_input_through() {
+        let mut cascade = Cascade::<f32>::new();
+        assert_eq!(cascade.process1(0.5), 0.5);
+    }
+
+    #[test]
+    fn test_direct_form_i_and_ii_cascades_match() {
+        let parameters = PoleFilterParameters {
+            order: 6,
+            cutoff: 1000.0,
+            ..PoleFilterParameters::default()
+        };
+        let mut direct_form_i = Cascade::<f64>::new();
+        let mut direct_form_ii = Cascade::<f64, DirectFormIIState<f64>>::new();
+        setup(
+            &mut direct_form_i,
+            FilterFamily::ChebyshevI,
+            PoleFilterType::LowPass,
+            &parameters,
+        );
+        setup(
+            &mut direct_form_ii,
+            FilterFamily::ChebyshevI,
+            PoleFilterType::LowPass,
+            &parameters,
+        );
+
+        // Step response; the forms only differ in where the denormal prevention is injected
+        for _ in 0..1000 {
+            let output_i = direct_form_i.process1(1.0);
+            let output_ii = direct_form_ii.process1(1.0);
+            assert!((output_i - output_ii).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Chebyshev Type I filters. Equi-ripple pass-band, monotonic stop-band.
+//!
+//! Ported from [vinniefalco/DSPFilters](https://github.com/vinniefalco/DSPFilters/)
+use std::f64::consts::{LN_10, PI};
+
+use num::complex::Complex64;
+
+use crate::layout::{infinity, Layout};
+
+/// Analog low-pass prototype with `num_poles` poles and `ripple_db` of pass-band ripple
+pub fn analog_low_pass(num_poles: usize, ripple_db: f64) -> Layout {
+    let mut layout = Layout::new();
+
+    let eps = (1.0 / (-ripple_db * 0.1 * LN_10).exp() - 1.0).sqrt();
+    let v0 = (1.0 / eps).asinh() / num_poles as f64;
+    let sinh_v0 = -v0.sinh();
+    let cosh_v0 = v0.cosh();
+
+    let n2 = 2.0 * num_poles as f64;
+    let pairs = num_poles / 2;
+    for i in 0..pairs {
+        let k = (2 * i + 1) as f64 - num_poles as f64;
+        let a = sinh_v0 * (k * PI / n2).cos();
+        let b = cosh_v0 * (k * PI / n2).sin();
+        layout.add_pole_zero_conjugate_pairs(Complex64::new(a, b), infinity());
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(Complex64::new(sinh_v0, 0.0), infinity());
+        layout.set_normal(0.0, 1.0);
+    } else {
+        layout.set_normal(0.0, 10.0_f64.powf(-ripple_db / 20.0));
+    }
+
+    layout
+}
+
+/// Parameters shared by the Chebyshev low-shelf prototypes, from Orfanidis' "High-Order
+/// Digital Parametric Equalizer Design". Returns `(sinh_u, cosh_u, sinh_v, cosh_v)`.
+pub(crate) fn shelf_parameters(
+    num_poles: usize,
+    gain_db: f64,
+    ripple_db: f64,
+) -> (f64, f64, f64, f64) {
+    let gain_db = -gain_db;
+    let mut ripple_db = ripple_db.min(gain_db.abs());
+    if gain_db < 0.0 {
+        ripple_db = -ripple_db;
+    }
+
+    let n = num_poles as f64;
+    let g = 10.0_f64.powf(gain_db / 20.0);
+    let gb = 10.0_f64.powf((gain_db - ripple_db) / 20.0);
+    let g0 = 1.0_f64;
+    let g0_n = g0.powf(1.0 / n);
+
+    let eps = if gb != g0 {
+        ((g * g - gb * gb) / (gb * gb - g0 * g0)).sqrt()
+    } else {
+        g - 1.0
+    };
+
+    let b = (g / eps + gb * (1.0 + 1.0 / (eps * eps)).sqrt()).powf(1.0 / n);
+    let u = (b / g0_n).ln();
+    let v = (1.0 / eps + (1.0 + 1.0 / (eps * eps)).sqrt())
+        .powf(1.0 / n)
+        .ln();
+
+    (u.sinh(), u.cosh(), v.sinh(), v.cosh())
+}
+
+/// Analog low-shelf prototype with `num_poles` poles. Gain is `gain_db` at DC & unity at
+/// infinity, with up to `ripple_db` of ripple in the shelf.
+pub fn analog_low_shelf(num_poles: usize, gain_db: f64, ripple_db: f64) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(PI, 1.0);
+
+    let (sinh_u, cosh_u, sinh_v, cosh_v) = shelf_parameters(num_poles, gain_db, ripple_db);
+
+    let n2 = 2.0 * num_poles as f64;
+    let pairs = num_poles / 2;
+    for i in 1..=pairs {
+        let a = PI * (2 * i - 1) as f64 / n2;
+        let sn = a.sin();
+        let cs = a.cos();
+        layout.add_pole_zero_conjugate_pairs(
+            Complex64::new(-sn * sinh_u, cs * cosh_u),
+            Complex64::new(-sn * sinh_v, cs * cosh_v),
+        );
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(Complex64::new(-sinh_u, 0.0), Complex64::new(-sinh_v, 0.0));
+    }
+
+    layout
+}
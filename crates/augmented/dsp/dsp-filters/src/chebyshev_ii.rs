@@ -0,0 +1,73 @@
+//! Chebyshev Type II (inverse Chebyshev) filters. Monotonic pass-band, equi-ripple stop-band.
+//!
+//! Ported from [vinniefalco/DSPFilters](https://github.com/vinniefalco/DSPFilters/)
+use std::f64::consts::{LN_10, PI};
+
+use num::complex::Complex64;
+
+use crate::chebyshev_i;
+use crate::layout::{infinity, Layout};
+
+/// Analog low-pass prototype with `num_poles` poles and at least `stop_band_db` of stop-band
+/// attenuation
+pub fn analog_low_pass(num_poles: usize, stop_band_db: f64) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(0.0, 1.0);
+
+    let eps = (1.0 / ((stop_band_db * 0.1 * LN_10).exp() - 1.0)).sqrt();
+    let v0 = (1.0 / eps).asinh() / num_poles as f64;
+    let sinh_v0 = -v0.sinh();
+    let cosh_v0 = v0.cosh();
+    let fn_ = PI / (2 * num_poles) as f64;
+
+    let mut k = 1;
+    for _ in 0..num_poles / 2 {
+        let a = sinh_v0 * ((k as f64 - num_poles as f64) * fn_).cos();
+        let b = cosh_v0 * ((k as f64 - num_poles as f64) * fn_).sin();
+        let d2 = a * a + b * b;
+        let im = 1.0 / (k as f64 * fn_).cos();
+        layout
+            .add_pole_zero_conjugate_pairs(Complex64::new(a / d2, b / d2), Complex64::new(0.0, im));
+        k += 2;
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(Complex64::new(1.0 / sinh_v0, 0.0), infinity());
+    }
+
+    layout
+}
+
+/// Analog low-shelf prototype with `num_poles` poles. Gain is `gain_db` at DC & unity at
+/// infinity, with up to `stop_band_db` of ripple outside of the shelf.
+///
+/// This is the Chebyshev I shelf for `-gain_db` under the `s -> 1 / s` mapping, which moves
+/// the ripple from the shelf into the unity-gain region.
+pub fn analog_low_shelf(num_poles: usize, gain_db: f64, stop_band_db: f64) -> Layout {
+    let mut layout = Layout::new();
+    layout.set_normal(PI, 1.0);
+
+    let (sinh_u, cosh_u, sinh_v, cosh_v) =
+        chebyshev_i::shelf_parameters(num_poles, -gain_db, stop_band_db);
+
+    let n2 = 2.0 * num_poles as f64;
+    let pairs = num_poles / 2;
+    for i in 1..=pairs {
+        let a = PI * (2 * i - 1) as f64 / n2;
+        let sn = a.sin();
+        let cs = a.cos();
+        layout.add_pole_zero_conjugate_pairs(
+            Complex64::new(-sn * sinh_u, cs * cosh_u).inv(),
+            Complex64::new(-sn * sinh_v, cs * cosh_v).inv(),
+        );
+    }
+
+    if num_poles % 2 == 1 {
+        layout.add(
+            Complex64::new(-1.0 / sinh_u, 0.0),
+            Complex64::new(-1.0 / sinh_v, 0.0),
+        );
+    }
+
+    layout
+}
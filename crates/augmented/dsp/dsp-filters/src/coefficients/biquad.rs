@@ -1,5 +1,8 @@
+use num::complex::Complex64;
 use num::Float;
 
+use crate::layout::PoleZeroPair;
+
 pub struct BiquadCoefficients<Sample: Float> {
     pub(crate) a0: Sample,
     pub(crate) a1: Sample,
@@ -59,4 +62,70 @@ impl<Sample: Float> BiquadCoefficients<Sample> {
                 && !self.b2.is_nan()
         );
     }
+
+    /// Set-up a first order section from a real pole & zero.
+    pub fn set_one_pole(&mut self, pole: Complex64, zero: Complex64) {
+        assert!(pole.im == 0.0 && zero.im == 0.0);
+
+        let a0 = 1.0;
+        let a1 = -pole.re;
+        let a2 = 0.0;
+        let b0 = 1.0;
+        let b1 = -zero.re;
+        let b2 = 0.0;
+        self.set_coefficients_f64(a0, a1, a2, b0, b1, b2);
+    }
+
+    /// Set-up a second order section from two poles & two zeros. Complex poles or zeros are
+    /// assumed to be conjugate pairs.
+    pub fn set_two_pole(
+        &mut self,
+        pole1: Complex64,
+        zero1: Complex64,
+        pole2: Complex64,
+        zero2: Complex64,
+    ) {
+        let a0 = 1.0;
+        let (a1, a2) = if pole1.im != 0.0 {
+            (-2.0 * pole1.re, pole1.norm_sqr())
+        } else {
+            (-(pole1.re + pole2.re), pole1.re * pole2.re)
+        };
+
+        let b0 = 1.0;
+        let (b1, b2) = if zero1.im != 0.0 {
+            (-2.0 * zero1.re, zero1.norm_sqr())
+        } else {
+            (-(zero1.re + zero2.re), zero1.re * zero2.re)
+        };
+
+        self.set_coefficients_f64(a0, a1, a2, b0, b1, b2);
+    }
+
+    /// Set-up this section from a digital [`PoleZeroPair`]
+    pub fn set_pole_zero_pair(&mut self, pair: &PoleZeroPair) {
+        if pair.is_single_pole() {
+            self.set_one_pole(pair.poles.0, pair.zeros.0);
+        } else {
+            self.set_two_pole(pair.poles.0, pair.zeros.0, pair.poles.1, pair.zeros.1);
+        }
+    }
+
+    /// Multiply the numerator (feed-forward) coefficients by `scale`
+    pub fn apply_scale(&mut self, scale: Sample) {
+        self.b0 = self.b0 * scale;
+        self.b1 = self.b1 * scale;
+        self.b2 = self.b2 * scale;
+    }
+
+    fn set_coefficients_f64(&mut self, a0: f64, a1: f64, a2: f64, b0: f64, b1: f64, b2: f64) {
+        self.set_coefficients(
+            Sample::from(a0).unwrap(),
+            Sample::from(a1).unwrap(),
+            Sample::from(a2).unwrap(),
+            Sample::from(b0).unwrap(),
+            Sample::from(b1).unwrap(),
+            Sample::from(b2).unwrap(),
+        );
+    }
 }
@@ -0,0 +1,651 @@
+//! Linkwitz-Riley crossovers & a multi-band splitter built on them.
+//!
+//! A Linkwitz-Riley filter of order `2N` is a Butterworth filter of order `N` applied twice. The
+//! low-pass & high-pass outputs of a crossover sum to an all-pass, so bands split with
+//! [`MultibandSplitter`] add back up to the input magnitude.
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use num::complex::Complex64;
+use num::Float;
+
+use crate::cascade::Cascade;
+use crate::layout::Layout;
+use crate::pole_filter::{design, FilterFamily, PoleFilterParameters, PoleFilterType};
+use crate::response::FrequencyResponse;
+
+/// Minimum & maximum number of bands [`MultibandSplitter`] supports
+pub const MIN_BANDS: usize = 2;
+pub const MAX_BANDS: usize = 5;
+
+/// Slope of a Linkwitz-Riley crossover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkwitzRileyOrder {
+    /// 12dB/octave. The high-pass output is inverted so the bands sum in phase.
+    LR2,
+    /// 24dB/octave
+    LR4,
+    /// 48dB/octave
+    LR8,
+}
+
+impl LinkwitzRileyOrder {
+    /// Order of the Butterworth filter which is squared
+    pub fn butterworth_order(&self) -> usize {
+        match self {
+            LinkwitzRileyOrder::LR2 => 1,
+            LinkwitzRileyOrder::LR4 => 2,
+            LinkwitzRileyOrder::LR8 => 4,
+        }
+    }
+
+    /// Odd order Butterworth prototypes sum to an all-pass only with the high-pass inverted
+    fn inverts_high_pass(&self) -> bool {
+        self.butterworth_order() % 2 == 1
+    }
+}
+
+fn butterworth_layout(
+    order: LinkwitzRileyOrder,
+    filter_type: PoleFilterType,
+    frequency: f64,
+    sample_rate: f64,
+) -> Layout {
+    let parameters = PoleFilterParameters {
+        order: order.butterworth_order(),
+        sample_rate,
+        cutoff: frequency,
+        ..PoleFilterParameters::default()
+    };
+    design(FilterFamily::Butterworth, filter_type, &parameters)
+        .expect("Butterworth designs are always available")
+}
+
+/// Each section of `layout` twice. Single poles are squared into one second order section.
+fn squared_layout(layout: &Layout) -> Layout {
+    let mut squared = Layout::new();
+    for pair in layout.pairs() {
+        if pair.is_single_pole() {
+            squared.add_pair((pair.poles.0, pair.poles.0), (pair.zeros.0, pair.zeros.0));
+        } else {
+            squared.add_pair(pair.poles, pair.zeros);
+            squared.add_pair(pair.poles, pair.zeros);
+        }
+    }
+    squared.set_normal(layout.normal_w(), layout.normal_gain());
+    squared
+}
+
+/// Digital Linkwitz-Riley low-pass layout
+pub fn low_pass_layout(order: LinkwitzRileyOrder, frequency: f64, sample_rate: f64) -> Layout {
+    squared_layout(&butterworth_layout(
+        order,
+        PoleFilterType::LowPass,
+        frequency,
+        sample_rate,
+    ))
+}
+
+/// Digital Linkwitz-Riley high-pass layout. Note that the [`LinkwitzRileyOrder::LR2`] high-pass
+/// must be inverted to sum flat with the low-pass.
+pub fn high_pass_layout(order: LinkwitzRileyOrder, frequency: f64, sample_rate: f64) -> Layout {
+    squared_layout(&butterworth_layout(
+        order,
+        PoleFilterType::HighPass,
+        frequency,
+        sample_rate,
+    ))
+}
+
+/// Digital all-pass layout with the same phase response as the sum of a Linkwitz-Riley
+/// crossover's outputs. Used to align bands which didn't go through a crossover.
+///
+/// Every pole of the Butterworth prototype is kept & given a zero at its reciprocal.
+pub fn all_pass_layout(order: LinkwitzRileyOrder, frequency: f64, sample_rate: f64) -> Layout {
+    let prototype = butterworth_layout(order, PoleFilterType::LowPass, frequency, sample_rate);
+    let mut layout = Layout::new();
+    for pair in prototype.pairs() {
+        if pair.is_single_pole() {
+            layout.add(pair.poles.0, 1.0 / pair.poles.0);
+        } else {
+            layout.add_pair(
+                pair.poles,
+                (1.0 / pair.poles.0.conj(), 1.0 / pair.poles.1.conj()),
+            );
+        }
+    }
+    layout.set_normal(0.0, 1.0);
+    layout
+}
+
+/// A two-way Linkwitz-Riley crossover for a single channel
+pub struct LinkwitzRileyCrossover<Sample: Float> {
+    order: LinkwitzRileyOrder,
+    frequency: f64,
+    sample_rate: f64,
+    low_pass: Cascade<Sample>,
+    high_pass: Cascade<Sample>,
+}
+
+impl<Sample: Float> LinkwitzRileyCrossover<Sample> {
+    pub fn new(order: LinkwitzRileyOrder, frequency: f64, sample_rate: f64) -> Self {
+        let mut crossover = LinkwitzRileyCrossover {
+            order,
+            frequency,
+            sample_rate,
+            low_pass: Cascade::new(),
+            high_pass: Cascade::new(),
+        };
+        crossover.setup();
+        crossover
+    }
+
+    pub fn order(&self) -> LinkwitzRileyOrder {
+        self.order
+    }
+
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Set the crossover frequency in Hz
+    pub fn set_frequency(&mut self, frequency: f64) {
+        self.frequency = frequency;
+        self.setup();
+    }
+
+    /// Changing the order changes the number of stages, which allocates
+    pub fn set_order(&mut self, order: LinkwitzRileyOrder) {
+        self.order = order;
+        self.setup();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.setup();
+    }
+
+    pub fn reset(&mut self) {
+        self.low_pass.reset();
+        self.high_pass.reset();
+    }
+
+    /// Split a sample into its (low, high) bands
+    #[inline]
+    pub fn process1(&mut self, input: Sample) -> (Sample, Sample) {
+        let low = self.low_pass.process1(input);
+        let high = self.high_pass.process1(input);
+        if self.order.inverts_high_pass() {
+            (low, -high)
+        } else {
+            (low, high)
+        }
+    }
+
+    /// Response of the (low, high) outputs at a normalized frequency
+    pub fn band_responses(&self, normalized_frequency: f64) -> (Complex64, Complex64) {
+        let low = self.low_pass.response(normalized_frequency);
+        let high = self.high_pass.response(normalized_frequency);
+        if self.order.inverts_high_pass() {
+            (low, -high)
+        } else {
+            (low, high)
+        }
+    }
+
+    fn setup(&mut self) {
+        self.low_pass.set_layout(&low_pass_layout(
+            self.order,
+            self.frequency,
+            self.sample_rate,
+        ));
+        self.high_pass.set_layout(&high_pass_layout(
+            self.order,
+            self.frequency,
+            self.sample_rate,
+        ));
+    }
+}
+
+/// The all-pass counterpart of a [`LinkwitzRileyCrossover`]
+struct PhaseCompensation<Sample: Float> {
+    all_pass: Cascade<Sample>,
+}
+
+impl<Sample: Float> PhaseCompensation<Sample> {
+    fn new() -> Self {
+        PhaseCompensation {
+            all_pass: Cascade::new(),
+        }
+    }
+
+    fn setup(&mut self, order: LinkwitzRileyOrder, frequency: f64, sample_rate: f64) {
+        self.all_pass
+            .set_layout(&all_pass_layout(order, frequency, sample_rate));
+        // Normalization only fixes the magnitude, odd sections may come out inverted
+        if self.all_pass.response(0.0).re < 0.0 {
+            self.all_pass.apply_scale(-Sample::one());
+        }
+    }
+}
+
+/// Splits a single channel into 2-5 phase-aligned bands.
+///
+/// The signal goes through a chain of crossovers, lowest frequency first. Each band split off
+/// early is then passed through the all-passes of the crossovers it skipped, so every band has
+/// the same phase response & the bands sum back to an all-pass.
+pub struct MultibandSplitter<Sample: Float> {
+    crossovers: Vec<LinkwitzRileyCrossover<Sample>>,
+    /// For each band below the last, an all-pass per crossover above it
+    compensation: Vec<Vec<PhaseCompensation<Sample>>>,
+}
+
+impl<Sample: Float> MultibandSplitter<Sample> {
+    /// Create a splitter with one band more than `frequencies`. Frequencies should be ascending.
+    pub fn new(order: LinkwitzRileyOrder, frequencies: &[f64], sample_rate: f64) -> Self {
+        assert!(
+            frequencies.len() + 1 >= MIN_BANDS && frequencies.len() < MAX_BANDS,
+            "Between {} and {} bands are supported",
+            MIN_BANDS,
+            MAX_BANDS
+        );
+        let crossovers = frequencies
+            .iter()
+            .map(|frequency| LinkwitzRileyCrossover::new(order, *frequency, sample_rate))
+            .collect();
+        let compensation = (0..frequencies.len())
+            .map(|band| {
+                (band + 1..frequencies.len())
+                    .map(|_| PhaseCompensation::new())
+                    .collect()
+            })
+            .collect();
+        let mut splitter = MultibandSplitter {
+            crossovers,
+            compensation,
+        };
+        splitter.setup_compensation();
+        splitter
+    }
+
+    pub fn num_bands(&self) -> usize {
+        self.crossovers.len() + 1
+    }
+
+    pub fn crossovers(&self) -> &[LinkwitzRileyCrossover<Sample>] {
+        &self.crossovers
+    }
+
+    /// Set the frequency of crossover `index`, which sits between bands `index` & `index + 1`
+    pub fn set_frequency(&mut self, index: usize, frequency: f64) {
+        self.crossovers[index].set_frequency(frequency);
+        self.setup_compensation();
+    }
+
+    pub fn set_order(&mut self, order: LinkwitzRileyOrder) {
+        for crossover in &mut self.crossovers {
+            crossover.set_order(order);
+        }
+        self.setup_compensation();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        for crossover in &mut self.crossovers {
+            crossover.set_sample_rate(sample_rate);
+        }
+        self.setup_compensation();
+    }
+
+    pub fn reset(&mut self) {
+        for crossover in &mut self.crossovers {
+            crossover.reset();
+        }
+        for all_pass in self.compensation.iter_mut().flatten() {
+            all_pass.all_pass.reset();
+        }
+    }
+
+    /// Split one sample into `bands`, lowest band first. `bands` must have
+    /// [`MultibandSplitter::num_bands`] elements.
+    #[inline]
+    pub fn process1(&mut self, input: Sample, bands: &mut [Sample]) {
+        let mut rest = input;
+        for (band, (crossover, compensation)) in self
+            .crossovers
+            .iter_mut()
+            .zip(self.compensation.iter_mut())
+            .enumerate()
+        {
+            let (low, high) = crossover.process1(rest);
+            bands[band] = compensation
+                .iter_mut()
+                .fold(low, |output, all_pass| all_pass.all_pass.process1(output));
+            rest = high;
+        }
+        bands[self.crossovers.len()] = rest;
+    }
+
+    /// Response of `band` at a normalized frequency
+    pub fn band_response(&self, band: usize, normalized_frequency: f64) -> Complex64 {
+        let mut response = Complex64::new(1.0, 0.0);
+        for crossover in self.crossovers.iter().take(band) {
+            response *= crossover.band_responses(normalized_frequency).1;
+        }
+        if let Some(crossover) = self.crossovers.get(band) {
+            response *= crossover.band_responses(normalized_frequency).0;
+            for all_pass in &self.compensation[band] {
+                response *= all_pass.all_pass.response(normalized_frequency);
+            }
+        }
+        response
+    }
+
+    fn setup_compensation(&mut self) {
+        for (band, compensation) in self.compensation.iter_mut().enumerate() {
+            for (all_pass, crossover) in compensation
+                .iter_mut()
+                .zip(self.crossovers.iter().skip(band + 1))
+            {
+                all_pass.setup(
+                    crossover.order(),
+                    crossover.frequency(),
+                    crossover.sample_rate(),
+                );
+            }
+        }
+    }
+}
+
+/// Splits every channel into 2-5 bands with [`MultibandSplitter`].
+///
+/// [`CrossoverProcessor::split`] writes each band into its own buffer. As an [`AudioProcessor`]
+/// the bands are mixed back together with per-band gains, which sums flat at unity gain.
+///
+/// Changing the number of bands or the order allocates.
+pub struct CrossoverProcessor<Sample: Float> {
+    order: LinkwitzRileyOrder,
+    frequencies: Vec<f64>,
+    sample_rate: f64,
+    band_gains: Vec<Sample>,
+    splitters: Vec<MultibandSplitter<Sample>>,
+    band_frame: Vec<Sample>,
+}
+
+impl<Sample: Float> CrossoverProcessor<Sample> {
+    /// Create a processor with one band more than `frequencies`. Frequencies should be ascending.
+    pub fn new(order: LinkwitzRileyOrder, frequencies: Vec<f64>) -> Self {
+        let num_bands = frequencies.len() + 1;
+        let mut processor = CrossoverProcessor {
+            order,
+            frequencies,
+            sample_rate: 44100.0,
+            band_gains: vec![Sample::one(); num_bands],
+            splitters: Vec::new(),
+            band_frame: vec![Sample::zero(); num_bands],
+        };
+        processor.set_num_channels(2);
+        processor
+    }
+
+    pub fn num_bands(&self) -> usize {
+        self.frequencies.len() + 1
+    }
+
+    pub fn order(&self) -> LinkwitzRileyOrder {
+        self.order
+    }
+
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    pub fn band_gain(&self, band: usize) -> Sample {
+        self.band_gains[band]
+    }
+
+    /// Linear gain applied to `band` when mixing the bands back in `process`
+    pub fn set_band_gain(&mut self, band: usize, gain: Sample) {
+        self.band_gains[band] = gain;
+    }
+
+    /// Set the frequency of crossover `index`, which sits between bands `index` & `index + 1`
+    pub fn set_frequency(&mut self, index: usize, frequency: f64) {
+        self.frequencies[index] = frequency;
+        for splitter in &mut self.splitters {
+            splitter.set_frequency(index, frequency);
+        }
+    }
+
+    pub fn set_order(&mut self, order: LinkwitzRileyOrder) {
+        self.order = order;
+        for splitter in &mut self.splitters {
+            splitter.set_order(order);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        for splitter in &mut self.splitters {
+            splitter.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// The splitter for each channel
+    pub fn splitters(&self) -> &[MultibandSplitter<Sample>] {
+        &self.splitters
+    }
+
+    pub fn reset(&mut self) {
+        for splitter in &mut self.splitters {
+            splitter.reset();
+        }
+    }
+
+    /// Split `input` into one buffer per band. Each of `bands` must have the same shape as
+    /// `input`.
+    pub fn split<InputBuffer, OutputBuffer>(
+        &mut self,
+        input: &InputBuffer,
+        bands: &mut [OutputBuffer],
+    ) where
+        InputBuffer: AudioBuffer<SampleType = Sample>,
+        OutputBuffer: AudioBuffer<SampleType = Sample>,
+    {
+        assert_eq!(bands.len(), self.num_bands());
+        for (channel, splitter) in self
+            .splitters
+            .iter_mut()
+            .enumerate()
+            .take(input.num_channels())
+        {
+            for sample in 0..input.num_samples() {
+                splitter.process1(*input.get(channel, sample), &mut self.band_frame);
+                for (band, value) in bands.iter_mut().zip(&self.band_frame) {
+                    band.set(channel, sample, *value);
+                }
+            }
+        }
+    }
+
+    fn set_num_channels(&mut self, num_channels: usize) {
+        let (order, sample_rate) = (self.order, self.sample_rate);
+        let frequencies = &self.frequencies;
+        self.splitters.resize_with(num_channels, || {
+            MultibandSplitter::new(order, frequencies, sample_rate)
+        });
+    }
+}
+
+impl<Sample: Float> AudioProcessor for CrossoverProcessor<Sample> {
+    type SampleType = Sample;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.set_num_channels(settings.output_channels());
+        self.set_sample_rate(settings.sample_rate() as f64);
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            for (sample, splitter) in frame.iter_mut().zip(self.splitters.iter_mut()) {
+                splitter.process1(*sample, &mut self.band_frame);
+                *sample = self
+                    .band_frame
+                    .iter()
+                    .zip(&self.band_gains)
+                    .fold(Sample::zero(), |sum, (band, gain)| sum + *band * *gain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use crate::response::log_spaced_frequencies;
+
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 44100.0;
+    const ORDERS: [LinkwitzRileyOrder; 3] = [
+        LinkwitzRileyOrder::LR2,
+        LinkwitzRileyOrder::LR4,
+        LinkwitzRileyOrder::LR8,
+    ];
+    const FREQUENCIES: [f64; 4] = [120.0, 800.0, 3000.0, 9000.0];
+
+    #[test]
+    fn test_crossover_outputs_are_down_6db_at_the_crossover_frequency() {
+        for order in ORDERS.iter() {
+            let crossover = LinkwitzRileyCrossover::<f64>::new(*order, 1000.0, SAMPLE_RATE);
+            let (low, high) = crossover.band_responses(1000.0 / SAMPLE_RATE);
+            assert!(
+                (low.norm() - 0.5).abs() < 1e-6,
+                "{:?} {}",
+                order,
+                low.norm()
+            );
+            assert!(
+                (high.norm() - 0.5).abs() < 1e-6,
+                "{:?} {}",
+                order,
+                high.norm()
+            );
+        }
+    }
+
+    #[test]
+    fn test_band_responses_sum_to_unity_magnitude() {
+        for order in ORDERS.iter() {
+            for num_bands in MIN_BANDS..=MAX_BANDS {
+                let splitter = MultibandSplitter::<f64>::new(
+                    *order,
+                    &FREQUENCIES[..num_bands - 1],
+                    SAMPLE_RATE,
+                );
+                for frequency in log_spaced_frequencies(20.0, 20000.0, 100) {
+                    let sum: Complex64 = (0..num_bands)
+                        .map(|band| splitter.band_response(band, frequency / SAMPLE_RATE))
+                        .sum();
+                    assert!(
+                        (sum.norm() - 1.0).abs() < 1e-6,
+                        "{:?} bands={} frequency={} magnitude={}",
+                        order,
+                        num_bands,
+                        frequency,
+                        sum.norm()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_bands_sum_back_to_input_magnitude() {
+        for order in ORDERS.iter() {
+            let mut processor = CrossoverProcessor::<f64>::new(*order, FREQUENCIES.to_vec());
+            processor.prepare(AudioProcessorSettings::new(SAMPLE_RATE as f32, 1, 1, 512));
+
+            let num_samples = 8192;
+            let mut input = VecAudioBuffer::new();
+            input.resize(1, num_samples, 0.0);
+            input.set(0, 0, 1.0);
+            let mut bands: Vec<VecAudioBuffer<f64>> = (0..processor.num_bands())
+                .map(|_| {
+                    let mut band = VecAudioBuffer::new();
+                    band.resize(1, num_samples, 0.0);
+                    band
+                })
+                .collect();
+            processor.split(&input, &mut bands);
+
+            let impulse_response: Vec<f64> = (0..num_samples)
+                .map(|sample| bands.iter().map(|band| *band.get(0, sample)).sum())
+                .collect();
+            for frequency in log_spaced_frequencies(30.0, 18000.0, 40) {
+                let w = 2.0 * std::f64::consts::PI * frequency / SAMPLE_RATE;
+                let magnitude = impulse_response
+                    .iter()
+                    .enumerate()
+                    .map(|(n, value)| Complex64::from_polar(*value, -w * n as f64))
+                    .sum::<Complex64>()
+                    .norm();
+                assert!(
+                    (magnitude - 1.0).abs() < 1e-3,
+                    "{:?} frequency={} magnitude={}",
+                    order,
+                    frequency,
+                    magnitude
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_processor_with_unity_gains_is_all_pass() {
+        let mut processor =
+            CrossoverProcessor::<f32>::new(LinkwitzRileyOrder::LR4, vec![200.0, 2000.0]);
+        processor.prepare(AudioProcessorSettings::default());
+
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 44100, 0.0);
+        for sample in 0..44100 {
+            let value = (2.0 * std::f32::consts::PI * 1000.0 * sample as f32 / 44100.0).sin();
+            buffer.set(0, sample, value);
+            buffer.set(1, sample, value);
+        }
+        processor.process(&mut buffer);
+
+        let peak = (22050..44100)
+            .map(|sample| buffer.get(0, sample).abs())
+            .fold(0.0, f32::max);
+        assert!((peak - 1.0).abs() < 1e-2, "peak={}", peak);
+    }
+
+    #[test]
+    fn test_muting_a_band_removes_it() {
+        let mut processor =
+            CrossoverProcessor::<f32>::new(LinkwitzRileyOrder::LR8, vec![500.0, 5000.0]);
+        processor.set_band_gain(1, 0.0);
+        processor.prepare(AudioProcessorSettings::default());
+
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 44100, 0.0);
+        for sample in 0..44100 {
+            let value = (2.0 * std::f32::consts::PI * 1600.0 * sample as f32 / 44100.0).sin();
+            buffer.set(0, sample, value);
+        }
+        processor.process(&mut buffer);
+
+        let peak = (22050..44100)
+            .map(|sample| buffer.get(0, sample).abs())
+            .fold(0.0, f32::max);
+        assert!(peak < 0.05, "peak={}", peak);
+    }
+}
@@ -0,0 +1,127 @@
+//! Elliptic (Cauer) filters. Equi-ripple pass-band & stop-band, steepest transition.
+//!
+//! DSPFilters designs these with an iterative method parameterized by a "rolloff" value. This
+//! port instead follows Orfanidis' "Lecture Notes on Elliptic Filter Design", which uses
+//! Landen transformations & is parameterized by pass-band ripple & stop-band attenuation.
+//!
+//! As in DSPFilters there are no shelf prototypes for this family.
+use std::f64::consts::PI;
+
+use num::complex::Complex64;
+
+use crate::layout::{infinity, Layout};
+
+const LANDEN_ITERATIONS: usize = 10;
+
+/// Descending Landen sequence of elliptic moduli, starting at `k`
+fn landen(k: f64) -> Vec<f64> {
+    let mut result = Vec::with_capacity(LANDEN_ITERATIONS);
+    if k == 0.0 || k == 1.0 {
+        result.push(k);
+        return result;
+    }
+
+    let mut k = k;
+    for _ in 0..LANDEN_ITERATIONS {
+        k = (k / (1.0 + (1.0 - k * k).sqrt())).powi(2);
+        result.push(k);
+    }
+    result
+}
+
+/// Complete elliptic integral of the first kind, `K(k)`
+fn ellipk(k: f64) -> f64 {
+    landen(k).iter().fold(PI / 2.0, |acc, v| acc * (1.0 + v))
+}
+
+/// `cd(u * K, k)`, with `u` normalized to the quarter period
+fn cde(u: Complex64, k: f64) -> Complex64 {
+    let mut w = (u * PI / 2.0).cos();
+    for v in landen(k).iter().rev() {
+        w = (1.0 + v) * w / (1.0 + v * w * w);
+    }
+    w
+}
+
+/// `sn(u * K, k)`, with `u` normalized to the quarter period
+fn sne(u: Complex64, k: f64) -> Complex64 {
+    let mut w = (u * PI / 2.0).sin();
+    for v in landen(k).iter().rev() {
+        w = (1.0 + v) * w / (1.0 + v * w * w);
+    }
+    w
+}
+
+/// Symmetric remainder of `x / y`, in `[-y / 2, y / 2]`
+fn srem(x: f64, y: f64) -> f64 {
+    let z = x % y;
+    if z.abs() > y / 2.0 {
+        z - y * z.signum()
+    } else {
+        z
+    }
+}
+
+/// Inverse of [`cde`]
+fn acde(w: Complex64, k: f64) -> Complex64 {
+    let v = landen(k);
+    let mut w = w;
+    for n in 0..v.len() {
+        let v1 = if n == 0 { k } else { v[n - 1] };
+        w = w / (1.0 + (1.0 - w * w * v1 * v1).sqrt()) * 2.0 / (1.0 + v[n]);
+    }
+
+    let u = 2.0 / PI * w.acos();
+    let kp = (1.0 - k * k).sqrt();
+    let r = ellipk(kp) / ellipk(k);
+    Complex64::new(srem(u.re, 4.0), srem(u.im, 2.0 * r))
+}
+
+/// Inverse of [`sne`]
+fn asne(w: Complex64, k: f64) -> Complex64 {
+    1.0 - acde(w, k)
+}
+
+/// Solves the degree equation for the selectivity modulus `k`, given the order & the
+/// discrimination modulus `k1`
+fn ellipdeg(n: usize, k1: f64) -> f64 {
+    let k1p = (1.0 - k1 * k1).sqrt();
+    let product = (1..=n / 2)
+        .map(|i| sne(Complex64::new((2 * i - 1) as f64 / n as f64, 0.0), k1p).re)
+        .product::<f64>();
+    let kp = k1p.powi(n as i32) * product.powi(4);
+    (1.0 - kp * kp).sqrt()
+}
+
+/// Analog low-pass prototype with `num_poles` poles, `ripple_db` of pass-band ripple and
+/// `stop_band_db` of stop-band attenuation. The pass-band edge is at 1 rad/s; the stop-band
+/// edge follows from the order & the ripple specifications.
+pub fn analog_low_pass(num_poles: usize, ripple_db: f64, stop_band_db: f64) -> Layout {
+    let mut layout = Layout::new();
+
+    let ep = (10.0_f64.powf(ripple_db / 10.0) - 1.0).sqrt();
+    let es = (10.0_f64.powf(stop_band_db / 10.0) - 1.0).sqrt();
+    let k1 = ep / es;
+    let k = ellipdeg(num_poles, k1);
+
+    let j = Complex64::new(0.0, 1.0);
+    let v0 = (-j * asne(j / ep, k1) / num_poles as f64).re;
+
+    for i in 1..=num_poles / 2 {
+        let ui = Complex64::new((2 * i - 1) as f64 / num_poles as f64, 0.0);
+        let zeta = cde(ui, k);
+        let zero = j / (k * zeta);
+        let pole = j * cde(ui - j * v0, k);
+        layout.add_pole_zero_conjugate_pairs(pole, zero);
+    }
+
+    if num_poles % 2 == 1 {
+        let pole = j * sne(j * v0, k);
+        layout.add(Complex64::new(pole.re, 0.0), infinity());
+        layout.set_normal(0.0, 1.0);
+    } else {
+        layout.set_normal(0.0, 10.0_f64.powf(-ripple_db / 20.0));
+    }
+
+    layout
+}
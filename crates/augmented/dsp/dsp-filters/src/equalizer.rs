@@ -0,0 +1,734 @@
+//! Multi-band parametric equalizer built on the RBJ designs.
+//!
+//! See [`EqualizerProcessor`]. Parameter changes are smoothed & coefficients are recalculated
+//! every [`COEFFICIENT_UPDATE_INTERVAL`] samples while a band is moving, so automation is
+//! click-free.
+use std::time::Duration;
+
+use num::complex::Complex64;
+use smooth_value::InterpolatedValue;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+use crate::coefficients::BiquadCoefficients;
+use crate::denormal_prevention::DenormalPrevention;
+use crate::rbj;
+use crate::response::FrequencyResponse;
+use crate::state::{DirectFormIState, FilterState};
+
+/// How many samples to process between coefficient updates while a band is smoothing
+pub const COEFFICIENT_UPDATE_INTERVAL: usize = 16;
+/// Parameter changes are ramped over this long
+const SMOOTHING_TIME: Duration = Duration::from_millis(20);
+
+/// Type of an equalizer band
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EqualizerBandType {
+    Peak,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+}
+
+impl EqualizerBandType {
+    /// All band types, in the order used by [`EqualizerBandType::from_index`]
+    pub const ALL: [EqualizerBandType; 7] = [
+        EqualizerBandType::Peak,
+        EqualizerBandType::LowShelf,
+        EqualizerBandType::HighShelf,
+        EqualizerBandType::LowPass,
+        EqualizerBandType::HighPass,
+        EqualizerBandType::BandPass,
+        EqualizerBandType::Notch,
+    ];
+
+    /// Index of this type in [`EqualizerBandType::ALL`]
+    pub fn index(&self) -> usize {
+        EqualizerBandType::ALL
+            .iter()
+            .position(|band_type| band_type == self)
+            .unwrap()
+    }
+
+    /// Type at `index` in [`EqualizerBandType::ALL`], clamped to the last type
+    pub fn from_index(index: usize) -> Self {
+        EqualizerBandType::ALL[index.min(EqualizerBandType::ALL.len() - 1)]
+    }
+}
+
+/// Settings for one equalizer band
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqualizerBand {
+    pub band_type: EqualizerBandType,
+    /// Center or cut-off frequency in Hz
+    pub frequency: f32,
+    /// Gain in dB. Only used by peak & shelf bands.
+    pub gain_db: f32,
+    /// Q. Shelves use this as the RBJ shelf slope, where 1.0 is the steepest slope without
+    /// overshoot.
+    pub q: f32,
+    pub enabled: bool,
+}
+
+impl EqualizerBand {
+    /// An enabled band
+    pub fn new(band_type: EqualizerBandType, frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self {
+            band_type,
+            frequency,
+            gain_db,
+            q,
+            enabled: true,
+        }
+    }
+
+    /// Set-up `coefficients` for these settings
+    pub fn setup_coefficients(&self, coefficients: &mut BiquadCoefficients<f32>, sample_rate: f32) {
+        setup_band_coefficients(
+            coefficients,
+            self.band_type,
+            sample_rate,
+            self.frequency,
+            self.gain_db,
+            self.q,
+        );
+    }
+}
+
+impl Default for EqualizerBand {
+    fn default() -> Self {
+        Self::new(EqualizerBandType::Peak, 1000.0, 0.0, 1.0)
+    }
+}
+
+fn setup_band_coefficients<Sample>(
+    coefficients: &mut BiquadCoefficients<Sample>,
+    band_type: EqualizerBandType,
+    sample_rate: Sample,
+    frequency: Sample,
+    gain_db: Sample,
+    q: Sample,
+) where
+    Sample: num::Float + num::traits::FloatConst + num::pow::Pow<Sample, Output = Sample>,
+{
+    // Keep the designs away from DC, Nyquist & zero Q
+    let frequency = frequency
+        .max(Sample::one())
+        .min(sample_rate * Sample::from(0.49).unwrap());
+    let q = q.max(Sample::from(0.01).unwrap());
+
+    match band_type {
+        EqualizerBandType::Peak => {
+            rbj::setup_peak(coefficients, sample_rate, frequency, gain_db, q)
+        }
+        EqualizerBandType::LowShelf => {
+            rbj::setup_low_shelf(coefficients, sample_rate, frequency, gain_db, q)
+        }
+        EqualizerBandType::HighShelf => {
+            rbj::setup_high_shelf(coefficients, sample_rate, frequency, gain_db, q)
+        }
+        EqualizerBandType::LowPass => rbj::setup_low_pass(coefficients, sample_rate, frequency, q),
+        EqualizerBandType::HighPass => {
+            rbj::setup_high_pass(coefficients, sample_rate, frequency, q)
+        }
+        EqualizerBandType::BandPass => {
+            rbj::setup_band_pass2(coefficients, sample_rate, frequency, q)
+        }
+        EqualizerBandType::Notch => rbj::setup_band_stop(coefficients, sample_rate, frequency, q),
+    }
+}
+
+/// A band setting, ramped at the coefficient update rate of the default sample rate
+fn smoothed_value(value: f32) -> InterpolatedValue {
+    let update_rate =
+        AudioProcessorSettings::default().sample_rate() / COEFFICIENT_UPDATE_INTERVAL as f32;
+    InterpolatedValue::new(update_rate, SMOOTHING_TIME, value)
+}
+
+/// Runtime state for one band
+struct BandProcessor {
+    band: EqualizerBand,
+    /// Frequency is smoothed in octaves, so sweeps are perceptually even
+    octaves: InterpolatedValue,
+    gain_db: InterpolatedValue,
+    q: InterpolatedValue,
+    /// Dry/wet amount, used to fade the band in & out when it's enabled or disabled
+    mix: InterpolatedValue,
+    coefficients: BiquadCoefficients<f32>,
+    states: Vec<DirectFormIState<f32>>,
+}
+
+impl BandProcessor {
+    fn new(band: EqualizerBand) -> Self {
+        Self {
+            band,
+            octaves: smoothed_value(band.frequency.log2()),
+            gain_db: smoothed_value(band.gain_db),
+            q: smoothed_value(band.q),
+            mix: smoothed_value(if band.enabled { 1.0 } else { 0.0 }),
+            coefficients: BiquadCoefficients::default(),
+            states: Vec::new(),
+        }
+    }
+
+    fn set_band(&mut self, band: EqualizerBand) {
+        if self.is_bypassed() && band.enabled {
+            // Stale state from before the band was disabled would click
+            self.reset();
+        }
+
+        let type_changed = band.band_type != self.band.band_type;
+        self.band = band;
+        self.octaves.set(band.frequency.log2());
+        self.gain_db.set(band.gain_db);
+        self.q.set(band.q);
+        self.mix.set(if band.enabled { 1.0 } else { 0.0 });
+
+        // There's no meaningful way to smooth between types
+        if type_changed {
+            self.octaves.jump();
+            self.gain_db.jump();
+            self.q.jump();
+        }
+    }
+
+    fn values(&mut self) -> [&mut InterpolatedValue; 4] {
+        [
+            &mut self.octaves,
+            &mut self.gain_db,
+            &mut self.q,
+            &mut self.mix,
+        ]
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        // Coefficients are updated every `COEFFICIENT_UPDATE_INTERVAL` samples
+        let update_rate = sample_rate / COEFFICIENT_UPDATE_INTERVAL as f32;
+        for value in self.values() {
+            value.set_sample_rate(update_rate);
+        }
+    }
+
+    fn is_smoothing(&mut self) -> bool {
+        self.values().iter().any(|value| value.is_smoothing())
+    }
+
+    fn is_bypassed(&self) -> bool {
+        self.mix.get() == 0.0 && !self.mix.is_smoothing()
+    }
+
+    fn jump(&mut self) {
+        for value in self.values() {
+            value.jump();
+        }
+    }
+
+    fn tick(&mut self) {
+        for value in self.values() {
+            value.tick();
+        }
+    }
+
+    fn update_coefficients(&mut self, sample_rate: f32) {
+        setup_band_coefficients(
+            &mut self.coefficients,
+            self.band.band_type,
+            sample_rate,
+            2.0_f32.powf(self.octaves.get()),
+            self.gain_db.get(),
+            self.q.get(),
+        );
+    }
+
+    fn reset(&mut self) {
+        for state in &mut self.states {
+            state.reset();
+        }
+    }
+}
+
+/// An [`AudioProcessor`] applying N bands of RBJ filters in series to every channel.
+///
+/// ```
+/// use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+/// use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
+/// use dsp_filters::equalizer::{EqualizerBand, EqualizerBandType, EqualizerProcessor};
+///
+/// let mut equalizer = EqualizerProcessor::new(vec![
+///     EqualizerBand::new(EqualizerBandType::HighPass, 40.0, 0.0, 0.7),
+///     EqualizerBand::new(EqualizerBandType::Peak, 2500.0, -3.0, 2.0),
+///     EqualizerBand::new(EqualizerBandType::HighShelf, 8000.0, 2.0, 1.0),
+/// ]);
+/// equalizer.prepare(AudioProcessorSettings::default());
+///
+/// let mut audio_buffer = VecAudioBuffer::new();
+/// audio_buffer.resize(2, 512, 0.0);
+/// equalizer.process(&mut audio_buffer);
+/// ```
+pub struct EqualizerProcessor {
+    sample_rate: f32,
+    bands: Vec<BandProcessor>,
+    denormal_prevention: DenormalPrevention<f32>,
+    /// Samples left until the next coefficient update
+    samples_until_update: usize,
+    #[cfg(feature = "parameter_store")]
+    parameters: Option<audio_parameter_store::ParameterReader>,
+}
+
+impl EqualizerProcessor {
+    /// Create an equalizer with `bands`. The number of bands is fixed.
+    pub fn new(bands: Vec<EqualizerBand>) -> Self {
+        let mut processor = Self {
+            sample_rate: 44100.0,
+            bands: bands.into_iter().map(BandProcessor::new).collect(),
+            denormal_prevention: DenormalPrevention::default(),
+            samples_until_update: 0,
+            #[cfg(feature = "parameter_store")]
+            parameters: None,
+        };
+        processor.set_sample_rate(44100.0);
+        processor
+    }
+
+    pub fn num_bands(&self) -> usize {
+        self.bands.len()
+    }
+
+    /// Settings for the band at `index`
+    pub fn band(&self, index: usize) -> Option<&EqualizerBand> {
+        self.bands.get(index).map(|band| &band.band)
+    }
+
+    /// Settings for all bands
+    pub fn bands(&self) -> impl Iterator<Item = &EqualizerBand> {
+        self.bands.iter().map(|band| &band.band)
+    }
+
+    /// Change the band at `index`. The change is smoothed.
+    pub fn set_band(&mut self, index: usize, band: EqualizerBand) {
+        if let Some(band_processor) = self.bands.get_mut(index) {
+            band_processor.set_band(band);
+        }
+    }
+
+    /// Set the sample-rate
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for band in &mut self.bands {
+            band.set_sample_rate(sample_rate);
+            band.jump();
+            band.update_coefficients(sample_rate);
+        }
+    }
+
+    /// Clear the state of all filters
+    pub fn reset(&mut self) {
+        for band in &mut self.bands {
+            band.reset();
+        }
+    }
+
+    fn update_smoothing(&mut self) {
+        let sample_rate = self.sample_rate;
+        for band in &mut self.bands {
+            if band.is_smoothing() {
+                band.tick();
+                band.update_coefficients(sample_rate);
+            }
+        }
+    }
+}
+
+impl AudioProcessor for EqualizerProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        let num_channels = settings.output_channels();
+        for band in &mut self.bands {
+            band.states
+                .resize_with(num_channels, DirectFormIState::default);
+        }
+        self.set_sample_rate(settings.sample_rate());
+        self.reset();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        #[cfg(feature = "parameter_store")]
+        self.update_from_parameters();
+
+        for frame in data.frames_mut() {
+            if self.samples_until_update == 0 {
+                self.update_smoothing();
+                self.samples_until_update = COEFFICIENT_UPDATE_INTERVAL;
+            }
+            self.samples_until_update -= 1;
+
+            let very_small_amount = self.denormal_prevention.alternating_current();
+            for band in &mut self.bands {
+                if band.is_bypassed() {
+                    continue;
+                }
+
+                let mix = band.mix.get();
+                for (sample, state) in frame.iter_mut().zip(band.states.iter_mut()) {
+                    let input = *sample;
+                    let output = state.process1(&band.coefficients, input, very_small_amount);
+                    *sample = input + mix * (output - input);
+                }
+            }
+        }
+    }
+}
+
+/// Combined response of all enabled bands, at their target settings
+impl FrequencyResponse for EqualizerProcessor {
+    fn response(&self, normalized_frequency: f64) -> Complex64 {
+        let sample_rate = self.sample_rate as f64;
+        let mut coefficients = BiquadCoefficients::<f64>::default();
+        self.bands()
+            .filter(|band| band.enabled)
+            .fold(Complex64::new(1.0, 0.0), |response, band| {
+                setup_band_coefficients(
+                    &mut coefficients,
+                    band.band_type,
+                    sample_rate,
+                    band.frequency as f64,
+                    band.gain_db as f64,
+                    band.q as f64,
+                );
+                response * coefficients.response(normalized_frequency)
+            })
+    }
+}
+
+/// [`audio_parameter_store::ParameterStore`] integration, so the equalizer may be shipped as a
+/// plugin.
+///
+/// Each band gets "Type", "Frequency", "Gain", "Q" & "Enabled" parameters.
+#[cfg(feature = "parameter_store")]
+pub mod parameters {
+    use std::sync::Arc;
+
+    use audio_parameter_store::{ParameterReader, ParameterStore, PluginParameter};
+
+    use super::{EqualizerBand, EqualizerBandType, EqualizerProcessor};
+
+    const PARAMETERS_PER_BAND: usize = 5;
+
+    /// Parameter IDs for one band
+    pub struct BandParameterIds {
+        pub band_type: String,
+        pub frequency: String,
+        pub gain_db: String,
+        pub q: String,
+        pub enabled: String,
+    }
+
+    impl BandParameterIds {
+        /// IDs for the band at `index`, such as `band_0_frequency`
+        pub fn new(index: usize) -> Self {
+            let id = |name: &str| format!("band_{}_{}", index, name);
+            Self {
+                band_type: id("type"),
+                frequency: id("frequency"),
+                gain_db: id("gain"),
+                q: id("q"),
+                enabled: id("enabled"),
+            }
+        }
+
+        /// The IDs in the order the equalizer reads them
+        fn into_array(self) -> [String; PARAMETERS_PER_BAND] {
+            [
+                self.band_type,
+                self.frequency,
+                self.gain_db,
+                self.q,
+                self.enabled,
+            ]
+        }
+    }
+
+    /// Add parameters for `bands` to `store`, using their settings as initial values
+    pub fn add_parameters(store: &mut ParameterStore, bands: &[EqualizerBand]) {
+        for (index, band) in bands.iter().enumerate() {
+            let ids = BandParameterIds::new(index);
+            let name = |name: &str| format!("Band {} {}", index + 1, name);
+            store.add_parameter(
+                &ids.band_type,
+                Arc::new(
+                    PluginParameter::builder()
+                        .name(&name("Type"))
+                        .initial_value(band.band_type.index() as f32)
+                        .value_precision(0)
+                        .value_range(0.0, (EqualizerBandType::ALL.len() - 1) as f32)
+                        .build(),
+                ),
+            );
+            store.add_parameter(
+                &ids.frequency,
+                Arc::new(
+                    PluginParameter::builder()
+                        .name(&name("Frequency"))
+                        .label("Hz")
+                        .initial_value(band.frequency)
+                        .value_precision(0)
+                        .value_range(20.0, 20000.0)
+                        .build(),
+                ),
+            );
+            store.add_parameter(
+                &ids.gain_db,
+                Arc::new(
+                    PluginParameter::builder()
+                        .name(&name("Gain"))
+                        .label("dB")
+                        .initial_value(band.gain_db)
+                        .value_precision(1)
+                        .value_range(-24.0, 24.0)
+                        .build(),
+                ),
+            );
+            store.add_parameter(
+                &ids.q,
+                Arc::new(
+                    PluginParameter::builder()
+                        .name(&name("Q"))
+                        .initial_value(band.q)
+                        .value_precision(2)
+                        .value_range(0.1, 18.0)
+                        .build(),
+                ),
+            );
+            store.add_parameter(
+                &ids.enabled,
+                Arc::new(
+                    PluginParameter::builder()
+                        .name(&name("Enabled"))
+                        .initial_value(if band.enabled { 1.0 } else { 0.0 })
+                        .value_precision(0)
+                        .value_range(0.0, 1.0)
+                        .build(),
+                ),
+            );
+        }
+    }
+
+    impl EqualizerProcessor {
+        /// Read band settings from `store` at the start of every block. The store should have
+        /// been set-up with [`add_parameters`], parameters it's missing are left as they are.
+        pub fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+            let ids: Vec<String> = (0..self.num_bands())
+                .flat_map(|index| BandParameterIds::new(index).into_array())
+                .collect();
+            self.parameters = Some(ParameterReader::new(&store, &ids));
+            self.update_from_parameters();
+        }
+
+        pub(super) fn update_from_parameters(&mut self) {
+            let parameters = match &self.parameters {
+                Some(parameters) => parameters,
+                None => return,
+            };
+
+            for (index, band) in self.bands.iter_mut().enumerate() {
+                let current = band.band;
+                let value = |field: usize, current: f32| {
+                    parameters
+                        .value(index * PARAMETERS_PER_BAND + field)
+                        .unwrap_or(current)
+                };
+                let settings = EqualizerBand {
+                    band_type: EqualizerBandType::from_index(
+                        value(0, current.band_type.index() as f32).round().max(0.0) as usize,
+                    ),
+                    frequency: value(1, current.frequency),
+                    gain_db: value(2, current.gain_db),
+                    q: value(3, current.q),
+                    enabled: value(4, if current.enabled { 1.0 } else { 0.0 }) >= 0.5,
+                };
+                if settings != current {
+                    band.set_band(settings);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use crate::response::magnitude_db;
+
+    use super::*;
+
+    fn settings() -> AudioProcessorSettings {
+        AudioProcessorSettings::default()
+    }
+
+    fn sine_buffer(frequency: f32, num_samples: usize) -> VecAudioBuffer<f32> {
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, num_samples, 0.0);
+        for (i, frame) in buffer.frames_mut().enumerate() {
+            let value = (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin();
+            for sample in frame.iter_mut() {
+                *sample = value;
+            }
+        }
+        buffer
+    }
+
+    fn peak_of_tail(buffer: &VecAudioBuffer<f32>) -> f32 {
+        let skip = buffer.num_samples() / 2;
+        buffer
+            .frames()
+            .skip(skip)
+            .map(|frame| frame[0].abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_combined_response_is_product_of_bands() {
+        let equalizer = EqualizerProcessor::new(vec![
+            EqualizerBand::new(EqualizerBandType::Peak, 1000.0, 6.0, 1.0),
+            EqualizerBand::new(EqualizerBandType::Peak, 1000.0, 6.0, 1.0),
+        ]);
+        assert!((magnitude_db(&equalizer, 1000.0, 44100.0) - 12.0).abs() < 0.01);
+        assert!(magnitude_db(&equalizer, 20.0, 44100.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_disabled_bands_are_not_in_the_response() {
+        let mut band = EqualizerBand::new(EqualizerBandType::LowPass, 100.0, 0.0, 0.7);
+        band.enabled = false;
+        let equalizer = EqualizerProcessor::new(vec![band]);
+        assert!(magnitude_db(&equalizer, 10000.0, 44100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_processing_matches_the_response() {
+        let mut equalizer = EqualizerProcessor::new(vec![
+            EqualizerBand::new(EqualizerBandType::Peak, 1000.0, -12.0, 1.0),
+            EqualizerBand::new(EqualizerBandType::HighShelf, 8000.0, 6.0, 1.0),
+        ]);
+        equalizer.prepare(settings());
+
+        let mut buffer = sine_buffer(1000.0, 44100);
+        equalizer.process(&mut buffer);
+        let expected = 10.0_f32.powf(magnitude_db(&equalizer, 1000.0, 44100.0) as f32 / 20.0);
+        let peak = peak_of_tail(&buffer);
+        assert!((peak - expected).abs() < 0.01, "{} {}", peak, expected);
+    }
+
+    #[test]
+    fn test_band_changes_are_smoothed() {
+        let mut equalizer = EqualizerProcessor::new(vec![EqualizerBand::new(
+            EqualizerBandType::Peak,
+            1000.0,
+            0.0,
+            1.0,
+        )]);
+        equalizer.prepare(settings());
+        equalizer.set_band(
+            0,
+            EqualizerBand::new(EqualizerBandType::Peak, 1000.0, -24.0, 1.0),
+        );
+
+        let mut buffer = sine_buffer(1000.0, 44100);
+        equalizer.process(&mut buffer);
+
+        // The gain falls over time, rather than in one step
+        let frames: Vec<f32> = buffer.frames().map(|frame| frame[0]).collect();
+        let peak_in = |range: std::ops::Range<usize>| {
+            frames[range].iter().map(|s| s.abs()).fold(0.0, f32::max)
+        };
+        assert!(peak_in(0..100) > 0.5);
+        assert!(peak_in(500..600) < peak_in(0..100));
+        assert!(peak_in(40000..44100) < 0.1);
+        let max_step = frames
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .fold(0.0, f32::max);
+        // A 1kHz sine moves at most ~0.15 per sample
+        assert!(max_step < 0.16, "{}", max_step);
+    }
+
+    #[test]
+    fn test_disabling_a_band_fades_it_out() {
+        let mut equalizer = EqualizerProcessor::new(vec![EqualizerBand::new(
+            EqualizerBandType::LowPass,
+            100.0,
+            0.0,
+            0.7,
+        )]);
+        equalizer.prepare(settings());
+        let mut band = *equalizer.band(0).unwrap();
+        band.enabled = false;
+        equalizer.set_band(0, band);
+
+        let mut buffer = sine_buffer(5000.0, 44100);
+        equalizer.process(&mut buffer);
+        assert!((peak_of_tail(&buffer) - 1.0).abs() < 1e-3);
+        assert!(equalizer.bands[0].is_bypassed());
+    }
+
+    #[test]
+    fn test_band_type_index_round_trips() {
+        for band_type in EqualizerBandType::ALL {
+            assert_eq!(EqualizerBandType::from_index(band_type.index()), band_type);
+        }
+    }
+
+    #[cfg(feature = "parameter_store")]
+    #[test]
+    fn test_parameter_store_drives_bands() {
+        use std::sync::Arc;
+
+        use audio_parameter_store::ParameterStore;
+
+        let bands = vec![EqualizerBand::default(), EqualizerBand::default()];
+        let mut store = ParameterStore::new();
+        parameters::add_parameters(&mut store, &bands);
+        assert_eq!(store.get_num_parameters(), 10);
+
+        let store = Arc::new(store);
+        let mut equalizer = EqualizerProcessor::new(bands);
+        equalizer.set_parameter_store(store.clone());
+        equalizer.prepare(settings());
+
+        store.find_parameter("band_1_gain").unwrap().set_value(-6.0);
+        store.find_parameter("band_1_type").unwrap().set_value(1.0);
+        let mut buffer = sine_buffer(1000.0, 64);
+        equalizer.process(&mut buffer);
+
+        let band = equalizer.band(1).unwrap();
+        assert_eq!(band.gain_db, -6.0);
+        assert_eq!(band.band_type, EqualizerBandType::LowShelf);
+    }
+
+    #[cfg(feature = "parameter_store")]
+    #[test]
+    fn test_missing_parameters_are_left_as_they_are() {
+        use std::sync::Arc;
+
+        use audio_parameter_store::ParameterStore;
+
+        let band = EqualizerBand {
+            gain_db: 3.0,
+            ..EqualizerBand::default()
+        };
+        let mut equalizer = EqualizerProcessor::new(vec![band]);
+        equalizer.set_parameter_store(Arc::new(ParameterStore::new()));
+        equalizer.prepare(settings());
+        let mut buffer = sine_buffer(1000.0, 64);
+        equalizer.process(&mut buffer);
+        assert_eq!(equalizer.band(0), Some(&band));
+    }
+}
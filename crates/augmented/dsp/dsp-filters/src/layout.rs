@@ -0,0 +1,126 @@
+//! Pole/zero layouts, used to describe analog prototypes & their digital transforms.
+//!
+//! Mirrors `LayoutBase` & `PoleZeroPair` from DSPFilters.
+use num::complex::Complex64;
+
+/// The point at infinity on the s-plane. Used for zeros of all-pole prototypes.
+pub fn infinity() -> Complex64 {
+    Complex64::new(f64::INFINITY, 0.0)
+}
+
+/// Whether `c` is the point at infinity
+pub fn is_infinity(c: Complex64) -> bool {
+    c.re.is_infinite() || c.im.is_infinite()
+}
+
+/// Either a single real pole & zero or a pair of poles & zeros. Each pair maps to one biquad.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoleZeroPair {
+    pub poles: (Complex64, Complex64),
+    pub zeros: (Complex64, Complex64),
+    single: bool,
+}
+
+impl PoleZeroPair {
+    /// A first order section
+    pub fn single(pole: Complex64, zero: Complex64) -> Self {
+        Self {
+            poles: (pole, Complex64::new(0.0, 0.0)),
+            zeros: (zero, Complex64::new(0.0, 0.0)),
+            single: true,
+        }
+    }
+
+    /// A second order section
+    pub fn pair(pole1: Complex64, zero1: Complex64, pole2: Complex64, zero2: Complex64) -> Self {
+        Self {
+            poles: (pole1, pole2),
+            zeros: (zero1, zero2),
+            single: false,
+        }
+    }
+
+    pub fn is_single_pole(&self) -> bool {
+        self.single
+    }
+}
+
+/// A list of [`PoleZeroPair`]s plus the frequency (in radians) & gain the filter should be
+/// normalized to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    num_poles: usize,
+    pairs: Vec<PoleZeroPair>,
+    normal_w: f64,
+    normal_gain: f64,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Layout {
+            num_poles: 0,
+            pairs: Vec::new(),
+            normal_w: 0.0,
+            normal_gain: 1.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.num_poles = 0;
+        self.pairs.clear();
+    }
+
+    pub fn num_poles(&self) -> usize {
+        self.num_poles
+    }
+
+    pub fn pairs(&self) -> &[PoleZeroPair] {
+        &self.pairs
+    }
+
+    pub fn normal_w(&self) -> f64 {
+        self.normal_w
+    }
+
+    pub fn normal_gain(&self) -> f64 {
+        self.normal_gain
+    }
+
+    pub fn set_normal(&mut self, w: f64, gain: f64) {
+        self.normal_w = w;
+        self.normal_gain = gain;
+    }
+
+    /// Add a single real pole & zero. Used for the odd pole of odd-order designs.
+    pub fn add(&mut self, pole: Complex64, zero: Complex64) {
+        assert_eq!(
+            self.num_poles % 2,
+            0,
+            "A layout may only hold one single pole"
+        );
+        assert!(!pole.re.is_nan() && !pole.im.is_nan());
+        self.pairs.push(PoleZeroPair::single(pole, zero));
+        self.num_poles += 1;
+    }
+
+    /// Add a pole & zero together with their complex conjugates
+    pub fn add_pole_zero_conjugate_pairs(&mut self, pole: Complex64, zero: Complex64) {
+        assert!(!pole.re.is_nan() && !pole.im.is_nan());
+        self.pairs
+            .push(PoleZeroPair::pair(pole, zero, pole.conj(), zero.conj()));
+        self.num_poles += 2;
+    }
+
+    /// Add two arbitrary poles & zeros as one section
+    pub fn add_pair(&mut self, poles: (Complex64, Complex64), zeros: (Complex64, Complex64)) {
+        self.pairs
+            .push(PoleZeroPair::pair(poles.0, zeros.0, poles.1, zeros.1));
+        self.num_poles += 2;
+    }
+}
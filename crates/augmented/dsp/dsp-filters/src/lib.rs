@@ -11,10 +11,65 @@
 //! * [`rbj::FilterType::BandStop`]
 //! * [`rbj::FilterType::LowShelf`]
 //! * [`rbj::FilterType::HighShelf`]
+//! * [`rbj::FilterType::Peak`]
+//!
+//! And higher order filters, designed from analog prototypes & realized as cascades of
+//! biquads (see [`pole_filter::PoleFilterProcessor`]):
+//!
+//! * [`pole_filter::FilterFamily::Butterworth`]
+//! * [`pole_filter::FilterFamily::ChebyshevI`]
+//! * [`pole_filter::FilterFamily::ChebyshevII`]
+//! * [`pole_filter::FilterFamily::Elliptic`]
+//! * [`pole_filter::FilterFamily::Bessel`]
+//!
+//! In low-pass, high-pass, band-pass, band-stop, low-shelf, high-shelf & band-shelf variants.
+//!
+//! A multi-band parametric EQ is in [`equalizer::EqualizerProcessor`].
+//!
+//! A modulation-friendly state-variable filter is in [`svf::SvfProcessor`].
+//!
+//! Linkwitz-Riley crossovers & a 2-5 band splitter are in [`crossover`].
+//!
+//! Magnitude, phase & group delay of any of these may be queried with the [`response`]
+//! module.
 
 /// RBJ filters
 pub mod rbj;
 
+/// Zero-delay-feedback state-variable filter
+pub mod svf;
+
+/// Multi-band parametric equalizer
+pub mod equalizer;
+
+/// Linkwitz-Riley crossovers & multi-band splitting
+pub mod crossover;
+
+/// Higher order filters from analog prototypes
+pub mod pole_filter;
+
+/// Bessel analog prototypes
+pub mod bessel;
+/// Butterworth analog prototypes
+pub mod butterworth;
+/// Chebyshev Type I analog prototypes
+pub mod chebyshev_i;
+/// Chebyshev Type II analog prototypes
+pub mod chebyshev_ii;
+/// Elliptic analog prototypes
+pub mod elliptic;
+
+/// Frequency, phase & group delay response
+pub mod response;
+
+/// Cascades of second order sections
+pub mod cascade;
+/// Pole/zero layouts
+pub mod layout;
+mod root_finder;
+/// Analog to digital transforms
+pub mod transforms;
+
 /// Filter coefficient structs for internal or low-level use
 pub mod coefficients;
 /// Denormal prevention struct
@@ -0,0 +1,571 @@
+//! Higher order filters designed from analog pole/zero prototypes.
+//!
+//! See [`PoleFilterProcessor`] for a starting-point. Lower level, a digital [`Layout`] may be
+//! designed with [`design`] & realized with a [`Cascade`].
+use std::fmt::Debug;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use num::traits::FloatConst;
+use num::Float;
+
+use crate::cascade::Cascade;
+use crate::layout::Layout;
+use crate::state::FilterState;
+use crate::transforms::{
+    band_pass_transform, band_stop_transform, high_pass_transform, low_pass_transform,
+};
+use crate::{bessel, butterworth, chebyshev_i, chebyshev_ii, elliptic};
+
+/// Family of the analog prototype
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterFamily {
+    Butterworth,
+    ChebyshevI,
+    ChebyshevII,
+    /// Has no shelf variants; shelf types set-up a pass-through filter
+    Elliptic,
+    Bessel,
+}
+
+/// Type of a pole filter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoleFilterType {
+    LowPass,
+    HighPass,
+    BandPass,
+    BandStop,
+    LowShelf,
+    HighShelf,
+    BandShelf,
+}
+
+impl PoleFilterType {
+    fn is_shelf(&self) -> bool {
+        matches!(
+            self,
+            PoleFilterType::LowShelf | PoleFilterType::HighShelf | PoleFilterType::BandShelf
+        )
+    }
+}
+
+/// Design parameters. Not all of them apply to every family & type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoleFilterParameters {
+    /// Number of poles of the analog prototype. Band filters will have twice as many.
+    pub order: usize,
+    pub sample_rate: f64,
+    /// Cut-off or center frequency in Hz
+    pub cutoff: f64,
+    /// Band-width in Hz, for band filters
+    pub width: f64,
+    /// Gain in dB, for shelves
+    pub gain_db: f64,
+    /// Pass-band ripple in dB, for Chebyshev I & Elliptic (also shelf ripple for Chebyshev I)
+    pub ripple_db: f64,
+    /// Stop-band attenuation in dB, for Chebyshev II & Elliptic (also ripple outside of the
+    /// shelf for Chebyshev II)
+    pub stop_band_db: f64,
+}
+
+impl Default for PoleFilterParameters {
+    fn default() -> Self {
+        Self {
+            order: 4,
+            sample_rate: 44100.0,
+            cutoff: 880.0,
+            width: 440.0,
+            gain_db: 1.0,
+            ripple_db: 1.0,
+            stop_band_db: 48.0,
+        }
+    }
+}
+
+fn analog_low_pass(family: FilterFamily, parameters: &PoleFilterParameters) -> Layout {
+    let order = parameters.order;
+    match family {
+        FilterFamily::Butterworth => butterworth::analog_low_pass(order),
+        FilterFamily::ChebyshevI => chebyshev_i::analog_low_pass(order, parameters.ripple_db),
+        FilterFamily::ChebyshevII => chebyshev_ii::analog_low_pass(order, parameters.stop_band_db),
+        FilterFamily::Elliptic => {
+            elliptic::analog_low_pass(order, parameters.ripple_db, parameters.stop_band_db)
+        }
+        FilterFamily::Bessel => bessel::analog_low_pass(order),
+    }
+}
+
+fn analog_low_shelf(family: FilterFamily, parameters: &PoleFilterParameters) -> Option<Layout> {
+    let order = parameters.order;
+    let gain_db = parameters.gain_db;
+    match family {
+        FilterFamily::Butterworth => Some(butterworth::analog_low_shelf(order, gain_db)),
+        FilterFamily::ChebyshevI => Some(chebyshev_i::analog_low_shelf(
+            order,
+            gain_db,
+            parameters.ripple_db,
+        )),
+        FilterFamily::ChebyshevII => Some(chebyshev_ii::analog_low_shelf(
+            order,
+            gain_db,
+            parameters.stop_band_db,
+        )),
+        FilterFamily::Elliptic => None,
+        FilterFamily::Bessel => Some(bessel::analog_low_shelf(order, gain_db)),
+    }
+}
+
+/// Design a digital [`Layout`] for a filter. Returns `None` for combinations which have no
+/// prototype (Elliptic shelves).
+pub fn design(
+    family: FilterFamily,
+    filter_type: PoleFilterType,
+    parameters: &PoleFilterParameters,
+) -> Option<Layout> {
+    let analog = if filter_type.is_shelf() {
+        analog_low_shelf(family, parameters)?
+    } else {
+        analog_low_pass(family, parameters)
+    };
+
+    let fc = parameters.cutoff / parameters.sample_rate;
+    let fw = parameters.width / parameters.sample_rate;
+    let mut digital = Layout::new();
+    match filter_type {
+        PoleFilterType::LowPass | PoleFilterType::LowShelf => {
+            low_pass_transform(fc, &mut digital, &analog)
+        }
+        PoleFilterType::HighPass | PoleFilterType::HighShelf => {
+            high_pass_transform(fc, &mut digital, &analog)
+        }
+        PoleFilterType::BandPass => band_pass_transform(fc, fw, &mut digital, &analog),
+        PoleFilterType::BandStop => band_stop_transform(fc, fw, &mut digital, &analog),
+        PoleFilterType::BandShelf => {
+            band_pass_transform(fc, fw, &mut digital, &analog);
+            let normal_w = if fc < 0.25 { std::f64::consts::PI } else { 0.0 };
+            digital.set_normal(normal_w, 1.0);
+        }
+    }
+
+    Some(digital)
+}
+
+/// Set-up `cascade` with a design. Unsupported combinations set-up a pass-through filter.
+pub fn setup<Sample: Float, State: FilterState<Sample = Sample> + Default>(
+    cascade: &mut Cascade<Sample, State>,
+    family: FilterFamily,
+    filter_type: PoleFilterType,
+    parameters: &PoleFilterParameters,
+) {
+    let layout = design(family, filter_type, parameters).unwrap_or_default();
+    cascade.set_layout(&layout);
+}
+
+/// An [`AudioProcessor`] which filters every channel with a [`Cascade`] designed from a
+/// [`FilterFamily`] prototype.
+///
+/// Unlike the RBJ `FilterProcessor`, every setter re-designs the filter from its analog prototype,
+/// which allocates. Change parameters off the audio thread.
+///
+/// ```
+/// use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+/// use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
+/// use dsp_filters::pole_filter::{FilterFamily, PoleFilterProcessor, PoleFilterType};
+///
+/// let mut audio_buffer = VecAudioBuffer::new();
+/// audio_buffer.resize(2, 1 * 44100, 0.0);
+/// let settings = AudioProcessorSettings {
+///     sample_rate: 44100.0,
+///     ..AudioProcessorSettings::default()
+/// };
+///
+/// let mut filter_processor =
+///     PoleFilterProcessor::new(FilterFamily::Butterworth, PoleFilterType::LowPass);
+/// filter_processor.set_order(8);
+/// filter_processor.set_cutoff(880.0);
+///
+/// filter_processor.prepare(settings);
+///
+/// filter_processor.process(&mut audio_buffer);
+/// ```
+pub struct PoleFilterProcessor<SampleType: Float> {
+    family: FilterFamily,
+    filter_type: PoleFilterType,
+    parameters: PoleFilterParameters,
+    filters: Vec<Cascade<SampleType>>,
+}
+
+impl<SampleType: Debug + Float + FloatConst> PoleFilterProcessor<SampleType> {
+    /// Create a new [`PoleFilterProcessor`] with the [`FilterFamily`], [`PoleFilterType`] and
+    /// default [`PoleFilterParameters`], which should be changed.
+    pub fn new(family: FilterFamily, filter_type: PoleFilterType) -> Self {
+        let mut processor = Self {
+            family,
+            filter_type,
+            parameters: PoleFilterParameters::default(),
+            filters: (0..2).map(|_| Cascade::new()).collect(),
+        };
+        processor.setup();
+        processor
+    }
+
+    /// Current design parameters
+    pub fn parameters(&self) -> &PoleFilterParameters {
+        &self.parameters
+    }
+
+    /// The filter for the first channel. Every channel shares the same coefficients.
+    pub fn filter(&self) -> Option<&Cascade<SampleType>> {
+        self.filters.first()
+    }
+
+    /// Change the filter-family
+    pub fn set_family(&mut self, family: FilterFamily) {
+        self.family = family;
+        self.setup();
+    }
+
+    /// Change the filter-type
+    pub fn set_filter_type(&mut self, filter_type: PoleFilterType) {
+        self.filter_type = filter_type;
+        self.setup();
+    }
+
+    /// Change the order
+    pub fn set_order(&mut self, order: usize) {
+        self.parameters.order = order;
+        self.setup();
+    }
+
+    /// Change the cut-off
+    pub fn set_cutoff(&mut self, cutoff: SampleType) {
+        self.parameters.cutoff = cutoff.to_f64().unwrap();
+        self.setup();
+    }
+
+    /// Change the center-frequency
+    pub fn set_center_frequency(&mut self, center_frequency: SampleType) {
+        self.set_cutoff(center_frequency);
+    }
+
+    /// Change the band-width in Hz
+    pub fn set_width(&mut self, width: SampleType) {
+        self.parameters.width = width.to_f64().unwrap();
+        self.setup();
+    }
+
+    /// Change the gain
+    pub fn set_gain_db(&mut self, gain_db: SampleType) {
+        self.parameters.gain_db = gain_db.to_f64().unwrap();
+        self.setup();
+    }
+
+    /// Change the pass-band ripple
+    pub fn set_ripple_db(&mut self, ripple_db: SampleType) {
+        self.parameters.ripple_db = ripple_db.to_f64().unwrap();
+        self.setup();
+    }
+
+    /// Change the stop-band attenuation
+    pub fn set_stop_band_db(&mut self, stop_band_db: SampleType) {
+        self.parameters.stop_band_db = stop_band_db.to_f64().unwrap();
+        self.setup();
+    }
+
+    /// Set the sample-rate
+    pub fn set_sample_rate(&mut self, sample_rate: SampleType) {
+        self.parameters.sample_rate = sample_rate.to_f64().unwrap();
+        self.setup();
+    }
+
+    /// Set-up the filter for playback. This allocates, as does every setter which calls it.
+    pub fn setup(&mut self) {
+        let layout = design(self.family, self.filter_type, &self.parameters).unwrap_or_default();
+        for filter in &mut self.filters {
+            filter.set_layout(&layout);
+        }
+    }
+}
+
+impl<SampleType> AudioProcessor for PoleFilterProcessor<SampleType>
+where
+    SampleType: Debug + Float + FloatConst + Send + Sync,
+{
+    type SampleType = SampleType;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.parameters.sample_rate = settings.sample_rate() as f64;
+        self.filters
+            .resize_with(settings.output_channels(), Cascade::new);
+        self.setup();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        for frame in data.frames_mut() {
+            for (sample, filter) in frame.iter_mut().zip(self.filters.iter_mut()) {
+                *sample = filter.process1(*sample);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::response::magnitude_db;
+
+    use super::*;
+
+    const FAMILIES: [FilterFamily; 5] = [
+        FilterFamily::Butterworth,
+        FilterFamily::ChebyshevI,
+        FilterFamily::ChebyshevII,
+        FilterFamily::Elliptic,
+        FilterFamily::Bessel,
+    ];
+
+    fn make_cascade(
+        family: FilterFamily,
+        filter_type: PoleFilterType,
+        parameters: &PoleFilterParameters,
+    ) -> Cascade<f64> {
+        let mut cascade = Cascade::new();
+        setup(&mut cascade, family, filter_type, parameters);
+        cascade
+    }
+
+    fn assert_stable(cascade: &Cascade<f64>) {
+        for stage in cascade.stages() {
+            let a1 = stage.a1;
+            let a2 = stage.a2;
+            // Stability triangle for a second order section
+            assert!(a2.abs() < 1.0 + 1e-9, "{} {}", a1, a2);
+            assert!(a1.abs() < 1.0 + a2 + 1e-9, "{} {}", a1, a2);
+        }
+    }
+
+    #[test]
+    fn test_butterworth_low_pass_is_3db_down_at_cutoff() {
+        for order in 1..=8 {
+            let parameters = PoleFilterParameters {
+                order,
+                cutoff: 1000.0,
+                ..PoleFilterParameters::default()
+            };
+            let cascade = make_cascade(
+                FilterFamily::Butterworth,
+                PoleFilterType::LowPass,
+                &parameters,
+            );
+            assert_eq!(cascade.num_stages(), order / 2 + order % 2);
+            assert_stable(&cascade);
+            assert!(magnitude_db(&cascade, 0.0, 44100.0).abs() < 1e-6);
+            assert!((magnitude_db(&cascade, 1000.0, 44100.0) + 3.0103).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_low_pass_and_high_pass_designs_are_stable_and_normalized() {
+        for family in FAMILIES {
+            for order in 1..=10 {
+                let parameters = PoleFilterParameters {
+                    order,
+                    cutoff: 2000.0,
+                    ..PoleFilterParameters::default()
+                };
+                let low_pass = make_cascade(family, PoleFilterType::LowPass, &parameters);
+                assert_stable(&low_pass);
+                assert!(magnitude_db(&low_pass, 0.0, 44100.0) < 1e-6);
+                assert!(magnitude_db(&low_pass, 0.0, 44100.0) > -parameters.ripple_db - 1e-6);
+                assert!(magnitude_db(&low_pass, 20000.0, 44100.0) < -10.0);
+
+                let high_pass = make_cascade(family, PoleFilterType::HighPass, &parameters);
+                assert_stable(&high_pass);
+                assert!(magnitude_db(&high_pass, 22050.0, 44100.0) < 1e-6);
+                assert!(magnitude_db(&high_pass, 22050.0, 44100.0) > -parameters.ripple_db - 1e-6);
+                assert!(magnitude_db(&high_pass, 100.0, 44100.0) < -10.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chebyshev_i_pass_band_ripple() {
+        let parameters = PoleFilterParameters {
+            order: 6,
+            cutoff: 4000.0,
+            ripple_db: 0.5,
+            ..PoleFilterParameters::default()
+        };
+        let cascade = make_cascade(
+            FilterFamily::ChebyshevI,
+            PoleFilterType::LowPass,
+            &parameters,
+        );
+        for i in 0..400 {
+            let frequency = 4000.0 * i as f64 / 400.0;
+            let db = magnitude_db(&cascade, frequency, 44100.0);
+            assert!(db < 1e-6 && db > -0.5 - 1e-3, "{} {}", frequency, db);
+        }
+    }
+
+    #[test]
+    fn test_chebyshev_ii_stop_band_attenuation() {
+        let parameters = PoleFilterParameters {
+            order: 6,
+            cutoff: 4000.0,
+            stop_band_db: 60.0,
+            ..PoleFilterParameters::default()
+        };
+        let cascade = make_cascade(
+            FilterFamily::ChebyshevII,
+            PoleFilterType::LowPass,
+            &parameters,
+        );
+        for i in 0..400 {
+            let frequency = 4000.0 + 18000.0 * i as f64 / 400.0;
+            let db = magnitude_db(&cascade, frequency, 44100.0);
+            assert!(db < -60.0 + 1e-3, "{} {}", frequency, db);
+        }
+    }
+
+    #[test]
+    fn test_elliptic_pass_band_and_stop_band() {
+        let parameters = PoleFilterParameters {
+            order: 5,
+            cutoff: 2000.0,
+            ripple_db: 1.0,
+            stop_band_db: 50.0,
+            ..PoleFilterParameters::default()
+        };
+        let cascade = make_cascade(FilterFamily::Elliptic, PoleFilterType::LowPass, &parameters);
+        assert_stable(&cascade);
+        for i in 0..200 {
+            let frequency = 2000.0 * i as f64 / 200.0;
+            let db = magnitude_db(&cascade, frequency, 44100.0);
+            assert!(db < 1e-6 && db > -1.0 - 1e-3, "{} {}", frequency, db);
+        }
+        for i in 0..200 {
+            let frequency = 4000.0 + 18000.0 * i as f64 / 200.0;
+            let db = magnitude_db(&cascade, frequency, 44100.0);
+            assert!(db < -50.0 + 1e-3, "{} {}", frequency, db);
+        }
+    }
+
+    #[test]
+    fn test_band_pass_and_band_stop() {
+        for family in FAMILIES {
+            for order in 1..=6 {
+                let parameters = PoleFilterParameters {
+                    order,
+                    cutoff: 4000.0,
+                    width: 2000.0,
+                    ..PoleFilterParameters::default()
+                };
+                let band_pass = make_cascade(family, PoleFilterType::BandPass, &parameters);
+                assert_eq!(band_pass.num_stages(), order);
+                assert_stable(&band_pass);
+                assert!(magnitude_db(&band_pass, 100.0, 44100.0) < -10.0);
+                assert!(magnitude_db(&band_pass, 20000.0, 44100.0) < -10.0);
+
+                let band_stop = make_cascade(family, PoleFilterType::BandStop, &parameters);
+                assert_stable(&band_stop);
+                assert!(magnitude_db(&band_stop, 4000.0, 44100.0) < -10.0);
+                assert!(magnitude_db(&band_stop, 22050.0, 44100.0).abs() <= 1.0 + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shelves_reach_their_gain() {
+        for family in FAMILIES {
+            for order in 1..=6 {
+                for gain_db in [-12.0, 6.0] {
+                    let parameters = PoleFilterParameters {
+                        order,
+                        cutoff: 2000.0,
+                        width: 2000.0,
+                        gain_db,
+                        ripple_db: 0.1,
+                        stop_band_db: 0.1,
+                        ..PoleFilterParameters::default()
+                    };
+
+                    let low_shelf = make_cascade(family, PoleFilterType::LowShelf, &parameters);
+                    let high_shelf = make_cascade(family, PoleFilterType::HighShelf, &parameters);
+                    let band_shelf = make_cascade(family, PoleFilterType::BandShelf, &parameters);
+                    if family == FilterFamily::Elliptic {
+                        assert_eq!(low_shelf.num_stages(), 0);
+                        continue;
+                    }
+                    assert_stable(&low_shelf);
+                    assert_stable(&high_shelf);
+                    assert_stable(&band_shelf);
+
+                    let tolerance = 0.2;
+                    assert!(
+                        (magnitude_db(&low_shelf, 0.0, 44100.0) - gain_db).abs() < tolerance,
+                        "{:?} {} {}",
+                        family,
+                        order,
+                        magnitude_db(&low_shelf, 0.0, 44100.0)
+                    );
+                    assert!(magnitude_db(&low_shelf, 22050.0, 44100.0).abs() < tolerance);
+                    assert!(
+                        (magnitude_db(&high_shelf, 22050.0, 44100.0) - gain_db).abs() < tolerance
+                    );
+                    assert!(magnitude_db(&high_shelf, 0.0, 44100.0).abs() < tolerance);
+                    // Low orders (& Bessel's delay-normalized prototypes) have a wide
+                    // transition, so the center of the band doesn't fully reach the shelf gain
+                    assert!(
+                        order < 4 || magnitude_db(&band_shelf, 2000.0, 44100.0) / gain_db > 0.75,
+                        "{:?} {} {}",
+                        family,
+                        order,
+                        magnitude_db(&band_shelf, 2000.0, 44100.0)
+                    );
+                    assert!(magnitude_db(&band_shelf, 22050.0, 44100.0).abs() < tolerance);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_changing_the_sample_rate_redesigns_the_filter() {
+        let mut processor =
+            PoleFilterProcessor::<f32>::new(FilterFamily::Butterworth, PoleFilterType::LowPass);
+        processor.set_order(4);
+        processor.set_cutoff(1000.0);
+        processor.prepare(AudioProcessorSettings::default());
+
+        processor.set_sample_rate(96000.0);
+
+        assert!((magnitude_db(&processor, 1000.0, 96000.0) + 3.0103).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_processor_filters_every_channel() {
+        use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+        let mut processor =
+            PoleFilterProcessor::new(FilterFamily::Butterworth, PoleFilterType::LowPass);
+        processor.set_order(4);
+        processor.set_cutoff(100.0);
+        processor.prepare(AudioProcessorSettings::default());
+
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 1000, 0.0_f32);
+        for (i, frame) in buffer.frames_mut().enumerate() {
+            let value = if i % 2 == 0 { 1.0 } else { -1.0 };
+            for sample in frame.iter_mut() {
+                *sample = value;
+            }
+        }
+        processor.process(&mut buffer);
+
+        for frame in buffer.frames().skip(500) {
+            assert!(frame[0].abs() < 1e-3);
+            assert!(frame[1].abs() < 1e-3);
+        }
+    }
+}
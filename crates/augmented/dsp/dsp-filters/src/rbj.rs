@@ -2,16 +2,23 @@
 //!
 //! Ported from [vinniefalco/DSPFilters](https://github.com/vinniefalco/DSPFilters/)
 use std::fmt::Debug;
+use std::time::Duration;
 
 use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
 use num::pow::Pow;
 use num::traits::FloatConst;
 use num::Float;
+use smooth_value::InterpolatedValue;
 
 use crate::coefficients::BiquadCoefficients;
 use crate::denormal_prevention;
 use crate::state::{DirectFormIState, FilterState};
 
+/// How many samples [`FilterProcessor`] processes between coefficient updates while ramping
+pub const COEFFICIENT_UPDATE_INTERVAL: usize = 16;
+/// Default [`FilterProcessor`] ramp time
+pub const DEFAULT_RAMP_TIME_SECS: f32 = 0.02;
+
 /// Type of a filter
 pub enum FilterType {
     LowPass,
@@ -21,6 +28,7 @@ pub enum FilterType {
     BandStop,
     LowShelf,
     HighShelf,
+    Peak,
     // TODO: BandShelf, AllPass,
 }
 
@@ -190,6 +198,33 @@ pub fn setup_high_shelf<Sample: Float + FloatConst + Pow<Sample, Output = Sample
     coefficients.set_coefficients(a0, a1, a2, b0, b1, b2);
 }
 
+pub fn setup_peak<Sample: Float + FloatConst + Pow<Sample, Output = Sample>>(
+    coefficients: &mut BiquadCoefficients<Sample>,
+    sample_rate: Sample,
+    center_frequency: Sample,
+    gain_db: Sample,
+    q: Sample,
+) {
+    let gain = Sample::from(10.0)
+        .unwrap()
+        .pow(gain_db / Sample::from(40.0).unwrap());
+    let one = Sample::from(1.0).unwrap();
+    let two = Sample::from(2.0).unwrap();
+
+    let w0 = two * Sample::PI() * center_frequency / sample_rate;
+    let cs = w0.cos();
+    let sn = w0.sin();
+    let al = sn / (two * q);
+    let b0 = one + al * gain;
+    let b1 = -two * cs;
+    let b2 = one - al * gain;
+    let a0 = one + al / gain;
+    let a1 = -two * cs;
+    let a2 = one - al / gain;
+
+    coefficients.set_coefficients(a0, a1, a2, b0, b1, b2);
+}
+
 /// Holds the state and coefficients for a filter.
 pub struct Filter<Sample: Float> {
     coefficients: BiquadCoefficients<Sample>,
@@ -206,6 +241,11 @@ impl<Sample: Float> Filter<Sample> {
             denormal_prevention: denormal_prevention::DenormalPrevention::default(),
         }
     }
+
+    /// The current coefficients
+    pub fn coefficients(&self) -> &BiquadCoefficients<Sample> {
+        &self.coefficients
+    }
 }
 
 impl<Sample: Float> Default for Filter<Sample> {
@@ -309,6 +349,23 @@ impl<Sample: Pow<Sample, Output = Sample> + Debug + Float + FloatConst> Filter<S
         );
     }
 
+    /// Set-up the filter as a peaking EQ with a certain center frequency, gain and Q
+    pub fn setup_peak(
+        &mut self,
+        sample_rate: Sample,
+        center_frequency: Sample,
+        gain_db: Sample,
+        q: Sample,
+    ) {
+        setup_peak(
+            &mut self.coefficients,
+            sample_rate,
+            center_frequency,
+            gain_db,
+            q,
+        );
+    }
+
     /// Process an input [`AudioBuffer`] instance. The [`Filter`] struct is mono (see
     /// [`FilterProcessor`] for multi-channel usage).
     ///
@@ -319,15 +376,19 @@ impl<Sample: Pow<Sample, Output = Sample> + Debug + Float + FloatConst> Filter<S
         channel_index: usize,
     ) {
         for frame in buffer.frames_mut() {
-            let input = frame[channel_index];
-            let output = self.state.process1(
-                &self.coefficients,
-                input,
-                self.denormal_prevention.alternating_current(),
-            );
-            frame[channel_index] = output;
+            frame[channel_index] = self.process1(frame[channel_index]);
         }
     }
+
+    /// Process a single sample
+    #[inline]
+    pub fn process1(&mut self, input: Sample) -> Sample {
+        self.state.process1(
+            &self.coefficients,
+            input,
+            self.denormal_prevention.alternating_current(),
+        )
+    }
 }
 
 /// An [`AudioProcessor`] which holds a [`Filter`]. Easy to use DSP filter.
@@ -335,6 +396,9 @@ impl<Sample: Pow<Sample, Output = Sample> + Debug + Float + FloatConst> Filter<S
 /// After setting the filter type with [`FilterProcessor::set_filter_type`], use the filter with the
 /// [`AudioProcessor::prepare`] and [`AudioProcessor::process`] methods.
 ///
+/// After `prepare`, changes to cut-off, q, gain & slope are ramped over
+/// [`FilterProcessor::ramp_time`] so automation doesn't click.
+///
 /// ```
 /// use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
 /// use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
@@ -365,6 +429,14 @@ pub struct FilterProcessor<
     q: SampleType,
     gain_db: SampleType,
     slope: SampleType,
+    ramp_time: SampleType,
+    is_prepared: bool,
+    /// Cut-off is ramped in octaves, so sweeps are perceptually even
+    smoothed_octaves: InterpolatedValue,
+    smoothed_q: InterpolatedValue,
+    smoothed_gain_db: InterpolatedValue,
+    smoothed_slope: InterpolatedValue,
+    samples_until_update: usize,
 }
 
 impl<SampleType: Pow<SampleType, Output = SampleType> + Debug + Float + FloatConst>
@@ -374,18 +446,44 @@ impl<SampleType: Pow<SampleType, Output = SampleType> + Debug + Float + FloatCon
     ///
     /// Sample-rate, cut-off, q, gain and slope will be set to defaults, but should be changed.
     pub fn new(filter_type: FilterType) -> Self {
+        let cutoff = SampleType::from(880.0).unwrap();
+        let q = SampleType::from(1.0).unwrap();
+        let gain_db = SampleType::from(1.0).unwrap();
+        let slope = SampleType::from(0.5).unwrap();
+        let sample_rate = SampleType::from(44100.0).unwrap();
+        let ramp_time = SampleType::from(DEFAULT_RAMP_TIME_SECS).unwrap();
+        let smoothed_value = |value: SampleType| {
+            InterpolatedValue::new(
+                to_f32(sample_rate) / COEFFICIENT_UPDATE_INTERVAL as f32,
+                ramp_duration(ramp_time),
+                to_f32(value),
+            )
+        };
         Self {
             filter_type,
             filter: Filter::new(),
-            sample_rate: SampleType::from(44100.0).unwrap(),
-            cutoff: SampleType::from(880.0).unwrap(),
-            q: SampleType::from(1.0).unwrap(),
-            gain_db: SampleType::from(1.0).unwrap(),
-            slope: SampleType::from(0.5).unwrap(),
+            sample_rate,
+            cutoff,
+            q,
+            gain_db,
+            slope,
+            ramp_time,
+            is_prepared: false,
+            smoothed_octaves: smoothed_value(cutoff.log2()),
+            smoothed_q: smoothed_value(q),
+            smoothed_gain_db: smoothed_value(gain_db),
+            smoothed_slope: smoothed_value(slope),
+            samples_until_update: 0,
         }
     }
 
-    /// Change the filter-type
+    /// The underlying [`Filter`]
+    pub fn filter(&self) -> &Filter<SampleType> {
+        &self.filter
+    }
+
+    /// Change the filter-type. Coefficients change immediately, there's no ramping between
+    /// types.
     pub fn set_filter_type(&mut self, filter_type: FilterType) {
         self.filter_type = filter_type;
         self.setup();
@@ -394,81 +492,164 @@ impl<SampleType: Pow<SampleType, Output = SampleType> + Debug + Float + FloatCon
     /// Change the cut-off
     pub fn set_cutoff(&mut self, cutoff: SampleType) {
         self.cutoff = cutoff;
-        self.setup();
+        self.smoothed_octaves.set(to_f32(cutoff.log2()));
+        self.on_parameter_change();
     }
 
     /// Change the q
     pub fn set_q(&mut self, q: SampleType) {
         self.q = q;
-        self.setup();
+        self.smoothed_q.set(to_f32(q));
+        self.on_parameter_change();
     }
 
     /// Change the center-frequency
     pub fn set_center_frequency(&mut self, center_frequency: SampleType) {
-        self.cutoff = center_frequency;
-        self.setup();
+        self.set_cutoff(center_frequency);
     }
 
     /// Change the slope
     pub fn set_slope(&mut self, slope: SampleType) {
         self.slope = slope;
-        self.setup();
+        self.smoothed_slope.set(to_f32(slope));
+        self.on_parameter_change();
     }
 
     /// Change the gain
     pub fn set_gain_db(&mut self, gain_db: SampleType) {
         self.gain_db = gain_db;
-        self.setup();
+        self.smoothed_gain_db.set(to_f32(gain_db));
+        self.on_parameter_change();
     }
 
     /// Set the sample-rate
     pub fn set_sample_rate(&mut self, sample_rate: SampleType) {
         self.sample_rate = sample_rate;
+        self.update_ramp_time();
+    }
+
+    /// How long parameter changes are ramped for, in seconds
+    pub fn ramp_time(&self) -> SampleType {
+        self.ramp_time
+    }
+
+    /// Set how long parameter changes are ramped for, in seconds. Zero disables ramping.
+    ///
+    /// While ramping, coefficients are recalculated every [`COEFFICIENT_UPDATE_INTERVAL`]
+    /// samples. Ramping only applies after [`AudioProcessor::prepare`]; before that changes are
+    /// immediate.
+    pub fn set_ramp_time(&mut self, ramp_time: SampleType) {
+        self.ramp_time = ramp_time.max(SampleType::zero());
+        self.update_ramp_time();
     }
 
-    /// Set-up the filter for playback
+    /// Whether parameters are still moving towards their targets
+    pub fn is_ramping(&self) -> bool {
+        self.smoothed_octaves.is_smoothing()
+            || self.smoothed_q.is_smoothing()
+            || self.smoothed_gain_db.is_smoothing()
+            || self.smoothed_slope.is_smoothing()
+    }
+
+    /// Set-up the filter for playback. Parameters jump to their targets.
     pub fn setup(&mut self) {
+        self.smoothed_octaves.jump();
+        self.smoothed_q.jump();
+        self.smoothed_gain_db.jump();
+        self.smoothed_slope.jump();
+        self.setup_coefficients(self.cutoff, self.q, self.gain_db, self.slope);
+    }
+
+    fn on_parameter_change(&mut self) {
+        if !self.is_prepared || self.ramp_time == SampleType::zero() {
+            self.setup();
+        }
+    }
+
+    fn update_ramp_time(&mut self) {
+        let update_rate = to_f32(self.sample_rate) / COEFFICIENT_UPDATE_INTERVAL as f32;
+        let ramp_duration = ramp_duration(self.ramp_time);
+        for value in [
+            &mut self.smoothed_octaves,
+            &mut self.smoothed_q,
+            &mut self.smoothed_gain_db,
+            &mut self.smoothed_slope,
+        ]
+        .iter_mut()
+        {
+            value.set_sample_rate(update_rate);
+            value.set_duration(ramp_duration, false);
+        }
+    }
+
+    fn tick_ramp(&mut self) {
+        self.smoothed_octaves.tick();
+        self.smoothed_q.tick();
+        self.smoothed_gain_db.tick();
+        self.smoothed_slope.tick();
+        if !self.is_ramping() {
+            // Land exactly on the targets, rather than on their round-trip through octaves
+            self.setup_coefficients(self.cutoff, self.q, self.gain_db, self.slope);
+            return;
+        }
+
+        let cutoff = 2.0_f32.powf(self.smoothed_octaves.get());
+        self.setup_coefficients(
+            SampleType::from(cutoff).unwrap(),
+            SampleType::from(self.smoothed_q.get()).unwrap(),
+            SampleType::from(self.smoothed_gain_db.get()).unwrap(),
+            SampleType::from(self.smoothed_slope.get()).unwrap(),
+        );
+    }
+
+    fn setup_coefficients(
+        &mut self,
+        cutoff: SampleType,
+        q: SampleType,
+        gain_db: SampleType,
+        slope: SampleType,
+    ) {
+        let sample_rate = self.sample_rate;
         match self.filter_type {
             FilterType::LowPass => {
-                self.filter
-                    .setup_low_pass(self.sample_rate, self.cutoff, self.q);
+                self.filter.setup_low_pass(sample_rate, cutoff, q);
             }
             FilterType::HighPass => {
-                self.filter
-                    .setup_high_pass(self.sample_rate, self.cutoff, self.q);
+                self.filter.setup_high_pass(sample_rate, cutoff, q);
             }
             FilterType::BandPass1 => {
-                self.filter
-                    .setup_band_pass1(self.sample_rate, self.cutoff, self.q);
+                self.filter.setup_band_pass1(sample_rate, cutoff, q);
             }
             FilterType::BandPass2 => {
-                self.filter
-                    .setup_band_pass2(self.sample_rate, self.cutoff, self.q);
+                self.filter.setup_band_pass2(sample_rate, cutoff, q);
             }
             FilterType::BandStop => {
-                self.filter
-                    .setup_band_stop(self.sample_rate, self.cutoff, self.q);
+                self.filter.setup_band_stop(sample_rate, cutoff, q);
             }
             FilterType::LowShelf => {
-                self.filter.setup_low_shelf(
-                    self.sample_rate,
-                    self.cutoff,
-                    self.gain_db,
-                    self.slope,
-                );
+                self.filter
+                    .setup_low_shelf(sample_rate, cutoff, gain_db, slope);
             }
             FilterType::HighShelf => {
-                self.filter.setup_high_shelf(
-                    self.sample_rate,
-                    self.cutoff,
-                    self.gain_db,
-                    self.slope,
-                );
+                self.filter
+                    .setup_high_shelf(sample_rate, cutoff, gain_db, slope);
+            }
+            FilterType::Peak => {
+                self.filter.setup_peak(sample_rate, cutoff, gain_db, q);
             }
         }
     }
 }
 
+/// Parameters are smoothed in single precision, whatever the filter's sample type
+fn to_f32<SampleType: Float>(value: SampleType) -> f32 {
+    value.to_f32().unwrap_or(0.0)
+}
+
+fn ramp_duration<SampleType: Float>(ramp_time: SampleType) -> Duration {
+    Duration::from_secs_f32(to_f32(ramp_time).max(0.0))
+}
+
 impl<SampleType> AudioProcessor for FilterProcessor<SampleType>
 where
     SampleType: Pow<SampleType, Output = SampleType> + Debug + Float + FloatConst + Send + Sync,
@@ -476,15 +657,27 @@ where
     type SampleType = SampleType;
 
     fn prepare(&mut self, settings: AudioProcessorSettings) {
-        self.sample_rate = SampleType::from(settings.sample_rate()).unwrap();
+        self.set_sample_rate(SampleType::from(settings.sample_rate()).unwrap());
         self.setup();
+        self.is_prepared = true;
     }
 
     fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
         &mut self,
         data: &mut BufferType,
     ) {
-        self.filter.process_channel(data, 0);
+        if self.is_ramping() {
+            for frame in data.frames_mut() {
+                if self.samples_until_update == 0 {
+                    self.tick_ramp();
+                    self.samples_until_update = COEFFICIENT_UPDATE_INTERVAL;
+                }
+                self.samples_until_update -= 1;
+                frame[0] = self.filter.process1(frame[0]);
+            }
+        } else {
+            self.filter.process_channel(data, 0);
+        }
 
         // Mono output
         for frame in data.frames_mut() {
@@ -495,3 +688,92 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use crate::response::FrequencyResponse;
+
+    use super::*;
+
+    fn sine_buffer(frequency: f32, num_samples: usize) -> VecAudioBuffer<f32> {
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(1, num_samples, 0.0);
+        for sample in 0..num_samples {
+            let value = (2.0 * std::f32::consts::PI * frequency * sample as f32 / 44100.0).sin();
+            buffer.set(0, sample, value);
+        }
+        buffer
+    }
+
+    /// Largest second difference; small for a low sine, large around discontinuities
+    fn max_step(buffer: &VecAudioBuffer<f32>) -> f32 {
+        buffer
+            .slice()
+            .windows(3)
+            .map(|window| (window[2] - 2.0 * window[1] + window[0]).abs())
+            .fold(0.0, f32::max)
+    }
+
+    fn process_with_cutoff_jump(ramp_time: f32) -> VecAudioBuffer<f32> {
+        let mut processor = FilterProcessor::new(FilterType::LowPass);
+        processor.set_ramp_time(ramp_time);
+        processor.set_cutoff(200.0);
+        processor.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 512));
+
+        let mut buffer = sine_buffer(1000.0, 8192);
+        let (mut first, mut second) = (VecAudioBuffer::new(), VecAudioBuffer::new());
+        first.resize(1, 4096, 0.0);
+        second.resize(1, 4096, 0.0);
+        first.slice_mut().copy_from_slice(&buffer.slice()[..4096]);
+        second.slice_mut().copy_from_slice(&buffer.slice()[4096..]);
+
+        processor.process(&mut first);
+        processor.set_cutoff(15000.0);
+        processor.process(&mut second);
+
+        buffer.slice_mut()[..4096].copy_from_slice(first.slice());
+        buffer.slice_mut()[4096..].copy_from_slice(second.slice());
+        buffer
+    }
+
+    #[test]
+    fn test_setters_are_immediate_before_prepare() {
+        let mut processor = FilterProcessor::<f32>::new(FilterType::LowPass);
+        processor.set_cutoff(2000.0);
+        assert!(!processor.is_ramping());
+    }
+
+    #[test]
+    fn test_parameter_changes_ramp_after_prepare() {
+        let mut processor = FilterProcessor::<f32>::new(FilterType::LowPass);
+        processor.prepare(AudioProcessorSettings::default());
+        processor.set_cutoff(2000.0);
+        assert!(processor.is_ramping());
+
+        let mut buffer = sine_buffer(440.0, 44100);
+        processor.process(&mut buffer);
+        assert!(!processor.is_ramping());
+
+        let mut expected = Filter::<f32>::new();
+        expected.setup_low_pass(44100.0, 2000.0, 1.0);
+        for frequency in [0.001, 0.01, 0.1].iter() {
+            let difference = processor.filter().coefficients().response(*frequency)
+                - expected.coefficients().response(*frequency);
+            assert!(difference.norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_ramping_removes_the_discontinuity_of_a_cutoff_jump() {
+        let jumped = process_with_cutoff_jump(0.0);
+        let ramped = process_with_cutoff_jump(0.02);
+        assert!(
+            max_step(&ramped) < max_step(&jumped) * 0.1,
+            "ramped={} jumped={}",
+            max_step(&ramped),
+            max_step(&jumped)
+        );
+    }
+}
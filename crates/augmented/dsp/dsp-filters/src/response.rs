@@ -0,0 +1,313 @@
+//! Frequency, phase & group delay response of filters.
+//!
+//! Frequencies passed to [`FrequencyResponse`] are normalized (frequency / sample-rate); the
+//! free functions take frequencies in Hz.
+//!
+//! ```
+//! use dsp_filters::rbj::Filter;
+//! use dsp_filters::response::{magnitude_db, response_curve};
+//!
+//! let mut filter = Filter::<f32>::new();
+//! filter.setup_low_pass(44100.0, 1000.0, 1.0 / 2.0_f32.sqrt());
+//!
+//! assert!((magnitude_db(&filter, 1000.0, 44100.0) + 3.0).abs() < 0.1);
+//! let curve = response_curve(&filter, 44100.0, 20.0, 20000.0, 100);
+//! assert_eq!(curve.len(), 100);
+//! ```
+use std::f64::consts::PI;
+use std::fmt::Debug;
+
+use num::complex::Complex64;
+use num::pow::Pow;
+use num::traits::FloatConst;
+use num::Float;
+
+use crate::cascade::Cascade;
+use crate::coefficients::BiquadCoefficients;
+use crate::pole_filter::PoleFilterProcessor;
+use crate::rbj::{Filter, FilterProcessor};
+use crate::state::FilterState;
+
+/// Step used to differentiate the phase in the default [`FrequencyResponse::group_delay`]
+const GROUP_DELAY_STEP: f64 = 1e-6;
+
+/// A filter which can report its response
+pub trait FrequencyResponse {
+    /// Complex response at `normalized_frequency` (frequency / sample-rate)
+    fn response(&self, normalized_frequency: f64) -> Complex64;
+
+    /// Group delay in samples at `normalized_frequency`. Undefined where the response is
+    /// zero.
+    ///
+    /// The default implementation differentiates the phase numerically.
+    fn group_delay(&self, normalized_frequency: f64) -> f64 {
+        let low = self.response(normalized_frequency - GROUP_DELAY_STEP);
+        let high = self.response(normalized_frequency + GROUP_DELAY_STEP);
+        let phase_difference = (high / low).arg();
+        -phase_difference / (2.0 * PI * 2.0 * GROUP_DELAY_STEP)
+    }
+}
+
+/// Magnitude & phase, plus group delay, at one frequency
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponsePoint {
+    /// Frequency in Hz
+    pub frequency: f64,
+    pub magnitude_db: f64,
+    /// Phase in radians, wrapped to `[-pi, pi]`
+    pub phase: f64,
+    /// Group delay in samples
+    pub group_delay: f64,
+}
+
+/// Magnitude response in dB at `frequency` Hz
+pub fn magnitude_db(filter: &impl FrequencyResponse, frequency: f64, sample_rate: f64) -> f64 {
+    20.0 * filter.response(frequency / sample_rate).norm().log10()
+}
+
+/// Phase response in radians at `frequency` Hz, wrapped to `[-pi, pi]`
+pub fn phase(filter: &impl FrequencyResponse, frequency: f64, sample_rate: f64) -> f64 {
+    filter.response(frequency / sample_rate).arg()
+}
+
+/// Group delay in samples at `frequency` Hz
+pub fn group_delay(filter: &impl FrequencyResponse, frequency: f64, sample_rate: f64) -> f64 {
+    filter.group_delay(frequency / sample_rate)
+}
+
+/// `num_points` frequencies, spaced logarithmically from `start` to `end` inclusive
+pub fn log_spaced_frequencies(start: f64, end: f64, num_points: usize) -> Vec<f64> {
+    assert!(start > 0.0 && end > 0.0);
+    if num_points == 1 {
+        return vec![start];
+    }
+
+    let ratio = (end / start).ln();
+    (0..num_points)
+        .map(|i| start * (ratio * i as f64 / (num_points - 1) as f64).exp())
+        .collect()
+}
+
+/// Sample the response of `filter` at `num_points` log-spaced frequencies from `start` to `end`
+/// Hz. Useful for plotting EQ curves.
+pub fn response_curve(
+    filter: &impl FrequencyResponse,
+    sample_rate: f64,
+    start: f64,
+    end: f64,
+    num_points: usize,
+) -> Vec<ResponsePoint> {
+    log_spaced_frequencies(start, end, num_points)
+        .into_iter()
+        .map(|frequency| {
+            let normalized_frequency = frequency / sample_rate;
+            let response = filter.response(normalized_frequency);
+            ResponsePoint {
+                frequency,
+                magnitude_db: 20.0 * response.norm().log10(),
+                phase: response.arg(),
+                group_delay: filter.group_delay(normalized_frequency),
+            }
+        })
+        .collect()
+}
+
+/// Group delay of the polynomial `c0 + c1 * z^-1 + c2 * z^-2` at `w` radians
+fn polynomial_group_delay(c0: f64, c1: f64, c2: f64, w: f64) -> f64 {
+    let czn1 = Complex64::from_polar(1.0, -w);
+    let czn2 = Complex64::from_polar(1.0, -2.0 * w);
+    let value = c0 + czn1 * c1 + czn2 * c2;
+    let derivative = czn1 * c1 + czn2 * (2.0 * c2);
+    (derivative / value).re
+}
+
+impl<Sample: Float> FrequencyResponse for BiquadCoefficients<Sample> {
+    fn response(&self, normalized_frequency: f64) -> Complex64 {
+        let w = 2.0 * PI * normalized_frequency;
+        let czn1 = Complex64::from_polar(1.0, -w);
+        let czn2 = Complex64::from_polar(1.0, -2.0 * w);
+
+        let b0 = self.b0.to_f64().unwrap();
+        let b1 = self.b1.to_f64().unwrap();
+        let b2 = self.b2.to_f64().unwrap();
+        let a1 = self.a1.to_f64().unwrap();
+        let a2 = self.a2.to_f64().unwrap();
+
+        let top = b0 + czn1 * b1 + czn2 * b2;
+        let bottom = 1.0 + czn1 * a1 + czn2 * a2;
+        top / bottom
+    }
+
+    fn group_delay(&self, normalized_frequency: f64) -> f64 {
+        let w = 2.0 * PI * normalized_frequency;
+        let numerator = polynomial_group_delay(
+            self.b0.to_f64().unwrap(),
+            self.b1.to_f64().unwrap(),
+            self.b2.to_f64().unwrap(),
+            w,
+        );
+        let denominator =
+            polynomial_group_delay(1.0, self.a1.to_f64().unwrap(), self.a2.to_f64().unwrap(), w);
+        numerator - denominator
+    }
+}
+
+impl<Sample: Float, State: FilterState<Sample = Sample> + Default> FrequencyResponse
+    for Cascade<Sample, State>
+{
+    fn response(&self, normalized_frequency: f64) -> Complex64 {
+        self.stages()
+            .iter()
+            .fold(Complex64::new(1.0, 0.0), |response, stage| {
+                response * stage.response(normalized_frequency)
+            })
+    }
+
+    fn group_delay(&self, normalized_frequency: f64) -> f64 {
+        self.stages()
+            .iter()
+            .map(|stage| stage.group_delay(normalized_frequency))
+            .sum()
+    }
+}
+
+impl<Sample: Float> FrequencyResponse for Filter<Sample> {
+    fn response(&self, normalized_frequency: f64) -> Complex64 {
+        self.coefficients().response(normalized_frequency)
+    }
+
+    fn group_delay(&self, normalized_frequency: f64) -> f64 {
+        self.coefficients().group_delay(normalized_frequency)
+    }
+}
+
+impl<Sample> FrequencyResponse for FilterProcessor<Sample>
+where
+    Sample: Pow<Sample, Output = Sample> + Debug + Float + FloatConst,
+{
+    fn response(&self, normalized_frequency: f64) -> Complex64 {
+        self.filter().response(normalized_frequency)
+    }
+
+    fn group_delay(&self, normalized_frequency: f64) -> f64 {
+        self.filter().group_delay(normalized_frequency)
+    }
+}
+
+impl<Sample: Debug + Float + FloatConst> FrequencyResponse for PoleFilterProcessor<Sample> {
+    fn response(&self, normalized_frequency: f64) -> Complex64 {
+        self.filter()
+            .map(|filter| filter.response(normalized_frequency))
+            .unwrap_or_else(|| Complex64::new(1.0, 0.0))
+    }
+
+    fn group_delay(&self, normalized_frequency: f64) -> f64 {
+        self.filter()
+            .map(|filter| filter.group_delay(normalized_frequency))
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pole_filter::{setup, FilterFamily, PoleFilterParameters, PoleFilterType};
+
+    use super::*;
+
+    fn low_pass(cutoff: f64) -> Filter<f64> {
+        let mut filter = Filter::new();
+        filter.setup_low_pass(44100.0, cutoff, 1.0 / 2.0_f64.sqrt());
+        filter
+    }
+
+    #[test]
+    fn test_rbj_low_pass_magnitude_and_phase_at_cutoff() {
+        let filter = low_pass(1000.0);
+        assert!(magnitude_db(&filter, 0.0, 44100.0).abs() < 1e-9);
+        assert!((magnitude_db(&filter, 1000.0, 44100.0) + 3.0103).abs() < 1e-3);
+        assert!((phase(&filter, 1000.0, 44100.0) + PI / 2.0).abs() < 1e-9);
+        assert!(magnitude_db(&filter, 10000.0, 44100.0) < -30.0);
+    }
+
+    #[test]
+    fn test_biquad_group_delay_matches_numerical_derivative() {
+        struct Numerical<'a>(&'a Filter<f64>);
+        impl<'a> FrequencyResponse for Numerical<'a> {
+            fn response(&self, normalized_frequency: f64) -> Complex64 {
+                self.0.response(normalized_frequency)
+            }
+        }
+
+        let filter = low_pass(1000.0);
+        for frequency in log_spaced_frequencies(20.0, 20000.0, 50) {
+            let exact = group_delay(&filter, frequency, 44100.0);
+            let numerical = group_delay(&Numerical(&filter), frequency, 44100.0);
+            assert!(
+                (exact - numerical).abs() < 1e-3,
+                "{} {} {}",
+                frequency,
+                exact,
+                numerical
+            );
+        }
+    }
+
+    #[test]
+    fn test_cascade_response_is_product_of_stages() {
+        let mut cascade = Cascade::<f64>::new();
+        setup(
+            &mut cascade,
+            FilterFamily::Butterworth,
+            PoleFilterType::LowPass,
+            &PoleFilterParameters {
+                order: 4,
+                cutoff: 1000.0,
+                ..PoleFilterParameters::default()
+            },
+        );
+
+        let frequency = 2000.0 / 44100.0;
+        let product = cascade
+            .stages()
+            .iter()
+            .fold(Complex64::new(1.0, 0.0), |acc, stage| {
+                acc * stage.response(frequency)
+            });
+        assert!((cascade.response(frequency) - product).norm() < 1e-12);
+        assert!(
+            (cascade.group_delay(frequency)
+                - cascade
+                    .stages()
+                    .iter()
+                    .map(|stage| stage.group_delay(frequency))
+                    .sum::<f64>())
+            .abs()
+                < 1e-12
+        );
+        // A 4th order butterworth is 24dB/octave
+        assert!((magnitude_db(&cascade, 4000.0, 44100.0) + 48.0).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_log_spaced_frequencies() {
+        let frequencies = log_spaced_frequencies(20.0, 20000.0, 4);
+        assert_eq!(frequencies.len(), 4);
+        assert!((frequencies[0] - 20.0).abs() < 1e-9);
+        assert!((frequencies[1] - 200.0).abs() < 1e-9);
+        assert!((frequencies[2] - 2000.0).abs() < 1e-9);
+        assert!((frequencies[3] - 20000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_response_curve() {
+        let filter = low_pass(1000.0);
+        let curve = response_curve(&filter, 44100.0, 20.0, 20000.0, 64);
+        assert_eq!(curve.len(), 64);
+        assert!(curve[0].magnitude_db.abs() < 0.01);
+        assert!(curve[63].magnitude_db < -40.0);
+        assert!(curve
+            .windows(2)
+            .all(|points| points[1].magnitude_db < points[0].magnitude_db));
+        assert!(curve.iter().all(|point| point.group_delay > 0.0));
+    }
+}
@@ -0,0 +1,129 @@
+//! Polynomial root finding using Laguerre's method, with deflation & polishing.
+//!
+//! Mirrors DSPFilters' `RootFinder`, which is adapted from "Numerical Recipes in C".
+use num::complex::Complex64;
+
+const EPS: f64 = 1e-14;
+const MAX_ITERATIONS_PER_BREAK: usize = 10;
+const BREAK_FRACTIONS: [f64; 9] = [0.0, 0.5, 0.25, 0.75, 0.13, 0.38, 0.62, 0.88, 1.0];
+const MAX_ITERATIONS: usize = MAX_ITERATIONS_PER_BREAK * (BREAK_FRACTIONS.len() - 1);
+
+/// Find a root of the polynomial with `coefficients` (lowest power first), starting from `x`
+fn laguerre(coefficients: &[Complex64], mut x: Complex64) -> Complex64 {
+    let degree = coefficients.len() - 1;
+    let m = degree as f64;
+
+    for iteration in 1..=MAX_ITERATIONS {
+        let mut b = coefficients[degree];
+        let mut err = b.norm();
+        let mut d = Complex64::new(0.0, 0.0);
+        let mut f = Complex64::new(0.0, 0.0);
+        let abx = x.norm();
+
+        for coefficient in coefficients[..degree].iter().rev() {
+            f = x * f + d;
+            d = x * d + b;
+            b = x * b + coefficient;
+            err = b.norm() + abx * err;
+        }
+        err *= EPS;
+
+        if b.norm() <= err {
+            return x;
+        }
+
+        let g = d / b;
+        let g2 = g * g;
+        let h = g2 - 2.0 * f / b;
+        let sq = ((m - 1.0) * (m * h - g2)).sqrt();
+        let gp = g + sq;
+        let gm = g - sq;
+        let abp = gp.norm();
+        let abm = gm.norm();
+        let gp = if abp < abm { gm } else { gp };
+
+        let dx = if abp.max(abm) > 0.0 {
+            m / gp
+        } else {
+            Complex64::from_polar(1.0 + abx, iteration as f64)
+        };
+
+        let x1 = x - dx;
+        if x == x1 {
+            return x;
+        }
+
+        if iteration % MAX_ITERATIONS_PER_BREAK != 0 {
+            x = x1;
+        } else {
+            x -= BREAK_FRACTIONS[iteration / MAX_ITERATIONS_PER_BREAK] * dx;
+        }
+    }
+
+    x
+}
+
+/// Find all roots of the polynomial with real `coefficients` (lowest power first).
+///
+/// Roots are sorted by descending imaginary part, so for a polynomial with `n` roots the
+/// first `n / 2` have positive imaginary parts & real roots sit in the middle.
+pub(crate) fn find_roots(coefficients: &[f64]) -> Vec<Complex64> {
+    let degree = coefficients.len() - 1;
+    let coefficients: Vec<Complex64> = coefficients
+        .iter()
+        .map(|c| Complex64::new(*c, 0.0))
+        .collect();
+    let mut deflated = coefficients.clone();
+    let mut roots = vec![Complex64::new(0.0, 0.0); degree];
+
+    for j in (1..=degree).rev() {
+        let mut x = laguerre(&deflated[..=j], Complex64::new(0.0, 0.0));
+        if x.im.abs() <= 2.0 * EPS * x.re.abs() {
+            x = Complex64::new(x.re, 0.0);
+        }
+        roots[j - 1] = x;
+
+        // forward deflation
+        let mut b = deflated[j];
+        for jj in (0..j).rev() {
+            let c = deflated[jj];
+            deflated[jj] = b;
+            b = x * b + c;
+        }
+    }
+
+    for root in roots.iter_mut() {
+        *root = laguerre(&coefficients, *root);
+        // Roots of real polynomials which should be real may pick up tiny imaginary parts
+        if root.im.abs() <= 1e-10 * root.re.abs().max(1.0) {
+            root.im = 0.0;
+        }
+    }
+
+    roots.sort_by(|a, b| b.im.partial_cmp(&a.im).unwrap());
+    roots
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_roots_of_quadratic() {
+        // (x - 1)(x + 2) = x^2 + x - 2
+        let roots = find_roots(&[-2.0, 1.0, 1.0]);
+        let mut real: Vec<f64> = roots.iter().map(|r| r.re).collect();
+        real.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((real[0] + 2.0).abs() < 1e-10);
+        assert!((real[1] - 1.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_find_complex_roots_are_sorted() {
+        // (x^2 + 1)(x - 3)
+        let roots = find_roots(&[-3.0, 1.0, -3.0, 1.0]);
+        assert!((roots[0] - Complex64::new(0.0, 1.0)).norm() < 1e-10);
+        assert!((roots[1] - Complex64::new(3.0, 0.0)).norm() < 1e-10);
+        assert!((roots[2] - Complex64::new(0.0, -1.0)).norm() < 1e-10);
+    }
+}
@@ -92,6 +92,21 @@ pub struct DirectFormIIState<Sample: Float> {
     v2: Sample, // v[-2]
 }
 
+impl<Sample: Float> Default for DirectFormIIState<Sample> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Sample: Float> DirectFormIIState<Sample> {
+    pub fn new() -> Self {
+        DirectFormIIState {
+            v1: Sample::zero(),
+            v2: Sample::zero(),
+        }
+    }
+}
+
 impl<Sample: Float> FilterState for DirectFormIIState<Sample> {
     type Sample = Sample;
 
@@ -0,0 +1,423 @@
+//! Zero-delay-feedback state-variable filter, using the topology-preserving transform.
+//!
+//! Based on Andrew Simper's "Linear Trapezoidal Integrated SVF" & Vadim Zavalishin's "The Art
+//! of VA Filter Design". Unlike the biquads in [`crate::rbj`], the coefficients may change on
+//! every sample & the filter stays stable under audio-rate modulation of cut-off & Q.
+use std::fmt::Debug;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use num::traits::FloatConst;
+use num::Float;
+
+/// Simultaneous outputs of a [`StateVariableFilter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvfOutput<Sample> {
+    pub low_pass: Sample,
+    pub high_pass: Sample,
+    pub band_pass: Sample,
+    pub notch: Sample,
+}
+
+/// Which output an [`SvfProcessor`] uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SvfMode {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+}
+
+impl SvfMode {
+    fn select<Sample>(&self, output: SvfOutput<Sample>) -> Sample {
+        match self {
+            SvfMode::LowPass => output.low_pass,
+            SvfMode::HighPass => output.high_pass,
+            SvfMode::BandPass => output.band_pass,
+            SvfMode::Notch => output.notch,
+        }
+    }
+}
+
+/// Mono TPT state-variable filter with low-pass, high-pass, band-pass & notch outputs.
+pub struct StateVariableFilter<Sample: Float + FloatConst> {
+    sample_rate: Sample,
+    cutoff: Sample,
+    q: Sample,
+    // Coefficients
+    k: Sample,
+    a1: Sample,
+    a2: Sample,
+    a3: Sample,
+    // Integrator states
+    ic1eq: Sample,
+    ic2eq: Sample,
+}
+
+impl<Sample: Float + FloatConst> Default for StateVariableFilter<Sample> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Sample: Float + FloatConst> StateVariableFilter<Sample> {
+    /// Create a filter at 44.1kHz, with a 880Hz cut-off & a Q of 1/sqrt(2)
+    pub fn new() -> Self {
+        let mut filter = StateVariableFilter {
+            sample_rate: Sample::from(44100.0).unwrap(),
+            cutoff: Sample::from(880.0).unwrap(),
+            q: Sample::one() / Sample::SQRT_2(),
+            k: Sample::zero(),
+            a1: Sample::zero(),
+            a2: Sample::zero(),
+            a3: Sample::zero(),
+            ic1eq: Sample::zero(),
+            ic2eq: Sample::zero(),
+        };
+        filter.setup();
+        filter
+    }
+
+    pub fn sample_rate(&self) -> Sample {
+        self.sample_rate
+    }
+
+    pub fn cutoff(&self) -> Sample {
+        self.cutoff
+    }
+
+    pub fn q(&self) -> Sample {
+        self.q
+    }
+
+    /// Set the sample-rate
+    pub fn set_sample_rate(&mut self, sample_rate: Sample) {
+        self.sample_rate = sample_rate;
+        self.setup();
+    }
+
+    /// Change the cut-off
+    pub fn set_cutoff(&mut self, cutoff: Sample) {
+        self.cutoff = cutoff;
+        self.setup();
+    }
+
+    /// Change the q (resonance)
+    pub fn set_q(&mut self, q: Sample) {
+        self.q = q;
+        self.setup();
+    }
+
+    /// Change cut-off & q at once, recalculating the coefficients a single time
+    pub fn set_cutoff_and_q(&mut self, cutoff: Sample, q: Sample) {
+        self.cutoff = cutoff;
+        self.q = q;
+        self.setup();
+    }
+
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.ic1eq = Sample::zero();
+        self.ic2eq = Sample::zero();
+    }
+
+    fn setup(&mut self) {
+        // Keep the pre-warped frequency finite & Q positive
+        let max_cutoff = self.sample_rate * Sample::from(0.49).unwrap();
+        let cutoff = self.cutoff.max(Sample::zero()).min(max_cutoff);
+        let q = self.q.max(Sample::from(0.01).unwrap());
+
+        let g = (Sample::PI() * cutoff / self.sample_rate).tan();
+        self.k = Sample::one() / q;
+        self.a1 = Sample::one() / (Sample::one() + g * (g + self.k));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+
+    /// Process a single sample, returning all outputs
+    #[inline]
+    pub fn process1(&mut self, input: Sample) -> SvfOutput<Sample> {
+        let two = Sample::from(2.0).unwrap();
+
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = two * v1 - self.ic1eq;
+        self.ic2eq = two * v2 - self.ic2eq;
+
+        let low_pass = v2;
+        let band_pass = v1;
+        let high_pass = input - self.k * v1 - v2;
+        SvfOutput {
+            low_pass,
+            high_pass,
+            band_pass,
+            notch: low_pass + high_pass,
+        }
+    }
+
+    /// Process a single sample with per-sample cut-off & q
+    #[inline]
+    pub fn process1_modulated(
+        &mut self,
+        input: Sample,
+        cutoff: Sample,
+        q: Sample,
+    ) -> SvfOutput<Sample> {
+        self.set_cutoff_and_q(cutoff, q);
+        self.process1(input)
+    }
+}
+
+/// An [`AudioProcessor`] which filters every channel with a [`StateVariableFilter`].
+///
+/// Has the same set-up methods as [`crate::rbj::FilterProcessor`]. Changes to cut-off & q are
+/// ramped over the next block, so they may be driven by MIDI or automation without zipper
+/// noise. For audio-rate modulation use [`SvfProcessor::process_modulated`].
+///
+/// ```
+/// use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+/// use audio_processor_traits::{AudioProcessor, AudioProcessorSettings};
+/// use dsp_filters::svf::{SvfMode, SvfProcessor};
+///
+/// let mut audio_buffer = VecAudioBuffer::new();
+/// audio_buffer.resize(2, 1 * 44100, 0.0);
+///
+/// let mut filter_processor = SvfProcessor::new(SvfMode::LowPass);
+/// filter_processor.set_cutoff(880.0);
+/// filter_processor.set_q(1.0);
+///
+/// filter_processor.prepare(AudioProcessorSettings::default());
+/// filter_processor.process(&mut audio_buffer);
+/// ```
+pub struct SvfProcessor<SampleType: Float + FloatConst> {
+    mode: SvfMode,
+    filters: Vec<StateVariableFilter<SampleType>>,
+    sample_rate: SampleType,
+    cutoff: SampleType,
+    q: SampleType,
+}
+
+impl<SampleType: Debug + Float + FloatConst> SvfProcessor<SampleType> {
+    /// Create a new [`SvfProcessor`] using the [`SvfMode`] output
+    pub fn new(mode: SvfMode) -> Self {
+        let filter = StateVariableFilter::new();
+        Self {
+            mode,
+            sample_rate: filter.sample_rate(),
+            cutoff: filter.cutoff(),
+            q: filter.q(),
+            filters: vec![filter, StateVariableFilter::new()],
+        }
+    }
+
+    pub fn mode(&self) -> SvfMode {
+        self.mode
+    }
+
+    /// Change the output used
+    pub fn set_mode(&mut self, mode: SvfMode) {
+        self.mode = mode;
+    }
+
+    /// Change the cut-off. Ramped over the next block.
+    pub fn set_cutoff(&mut self, cutoff: SampleType) {
+        self.cutoff = cutoff;
+    }
+
+    /// Change the q. Ramped over the next block.
+    pub fn set_q(&mut self, q: SampleType) {
+        self.q = q;
+    }
+
+    /// Set the sample-rate
+    pub fn set_sample_rate(&mut self, sample_rate: SampleType) {
+        self.sample_rate = sample_rate;
+        for filter in &mut self.filters {
+            filter.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// Process `data` in-place, reading cut-off & q for every frame from the `cutoff` & `q`
+    /// slices. Frames past the end of the slices use the last cut-off & q set.
+    pub fn process_modulated<BufferType: AudioBuffer<SampleType = SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+        cutoff: &[SampleType],
+        q: &[SampleType],
+    ) {
+        let mode = self.mode;
+        for (i, frame) in data.frames_mut().enumerate() {
+            let frame_cutoff = cutoff.get(i).copied().unwrap_or(self.cutoff);
+            let frame_q = q.get(i).copied().unwrap_or(self.q);
+            for (sample, filter) in frame.iter_mut().zip(self.filters.iter_mut()) {
+                *sample = mode.select(filter.process1_modulated(*sample, frame_cutoff, frame_q));
+            }
+        }
+    }
+}
+
+impl<SampleType> AudioProcessor for SvfProcessor<SampleType>
+where
+    SampleType: Debug + Float + FloatConst + Send + Sync,
+{
+    type SampleType = SampleType;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = SampleType::from(settings.sample_rate()).unwrap();
+        self.filters
+            .resize_with(settings.output_channels(), StateVariableFilter::new);
+        for filter in &mut self.filters {
+            filter.set_sample_rate(self.sample_rate);
+            filter.set_cutoff_and_q(self.cutoff, self.q);
+            filter.reset();
+        }
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let mode = self.mode;
+        let (start_cutoff, start_q) = self
+            .filters
+            .first()
+            .map(|filter| (filter.cutoff(), filter.q()))
+            .unwrap_or((self.cutoff, self.q));
+
+        if start_cutoff == self.cutoff && start_q == self.q {
+            for frame in data.frames_mut() {
+                for (sample, filter) in frame.iter_mut().zip(self.filters.iter_mut()) {
+                    *sample = mode.select(filter.process1(*sample));
+                }
+            }
+            return;
+        }
+
+        let num_frames = data.num_samples();
+        let cutoff_step = (self.cutoff - start_cutoff) / SampleType::from(num_frames).unwrap();
+        let q_step = (self.q - start_q) / SampleType::from(num_frames).unwrap();
+        for (i, frame) in data.frames_mut().enumerate() {
+            // Land exactly on the target, so the next block doesn't ramp again
+            let (cutoff, q) = if i + 1 == num_frames {
+                (self.cutoff, self.q)
+            } else {
+                let i = SampleType::from(i + 1).unwrap();
+                (start_cutoff + cutoff_step * i, start_q + q_step * i)
+            };
+            for (sample, filter) in frame.iter_mut().zip(self.filters.iter_mut()) {
+                *sample = mode.select(filter.process1_modulated(*sample, cutoff, q));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::audio_buffer::{OwnedAudioBuffer, VecAudioBuffer};
+
+    use super::*;
+
+    fn sine_rms(filter: &mut StateVariableFilter<f64>, mode: SvfMode, frequency: f64) -> f64 {
+        filter.reset();
+        let sample_rate = filter.sample_rate();
+        let mut sum = 0.0;
+        let num_samples = 44100;
+        for i in 0..num_samples {
+            let input = (2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate).sin();
+            let output = mode.select(filter.process1(input));
+            // Skip the transient
+            if i >= num_samples / 2 {
+                sum += output * output;
+            }
+        }
+        (sum / (num_samples / 2) as f64).sqrt() * 2.0_f64.sqrt()
+    }
+
+    #[test]
+    fn test_outputs_at_cutoff() {
+        let mut filter = StateVariableFilter::new();
+        filter.set_cutoff(1000.0);
+        filter.set_q(1.0 / 2.0_f64.sqrt());
+
+        let low_pass = sine_rms(&mut filter, SvfMode::LowPass, 1000.0);
+        let high_pass = sine_rms(&mut filter, SvfMode::HighPass, 1000.0);
+        let notch = sine_rms(&mut filter, SvfMode::Notch, 1000.0);
+        assert!(
+            (low_pass - 1.0 / 2.0_f64.sqrt()).abs() < 0.01,
+            "{}",
+            low_pass
+        );
+        assert!(
+            (high_pass - 1.0 / 2.0_f64.sqrt()).abs() < 0.01,
+            "{}",
+            high_pass
+        );
+        assert!(notch < 0.01, "{}", notch);
+    }
+
+    #[test]
+    fn test_pass_and_stop_bands() {
+        let mut filter = StateVariableFilter::new();
+        filter.set_cutoff(1000.0);
+
+        assert!(sine_rms(&mut filter, SvfMode::LowPass, 50.0) > 0.99);
+        assert!(sine_rms(&mut filter, SvfMode::LowPass, 10000.0) < 0.02);
+        assert!(sine_rms(&mut filter, SvfMode::HighPass, 50.0) < 0.01);
+        assert!(sine_rms(&mut filter, SvfMode::HighPass, 10000.0) > 0.99);
+        assert!(sine_rms(&mut filter, SvfMode::BandPass, 50.0) < 0.1);
+        assert!(sine_rms(&mut filter, SvfMode::BandPass, 10000.0) < 0.1);
+    }
+
+    #[test]
+    fn test_stable_under_audio_rate_modulation() {
+        let mut filter = StateVariableFilter::<f32>::new();
+        let mut seed: u32 = 1;
+        let mut random = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+
+        for _ in 0..44100 {
+            let input = random() * 2.0 - 1.0;
+            let cutoff = 20.0 + random() * 30000.0;
+            let q = 0.1 + random() * 20.0;
+            let output = filter.process1_modulated(input, cutoff, q);
+            assert!(output.low_pass.is_finite() && output.low_pass.abs() < 100.0);
+            assert!(output.high_pass.is_finite() && output.high_pass.abs() < 100.0);
+            assert!(output.band_pass.is_finite() && output.band_pass.abs() < 100.0);
+        }
+    }
+
+    #[test]
+    fn test_processor_ramps_to_new_cutoff() {
+        let mut processor = SvfProcessor::new(SvfMode::LowPass);
+        processor.prepare(AudioProcessorSettings::default());
+        processor.set_cutoff(5000.0);
+
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 512, 0.5_f32);
+        processor.process(&mut buffer);
+
+        assert_eq!(processor.filters[0].cutoff(), 5000.0);
+        assert_eq!(processor.filters[1].cutoff(), 5000.0);
+        for frame in buffer.frames() {
+            assert_eq!(frame[0], frame[1]);
+        }
+    }
+
+    #[test]
+    fn test_processor_modulated() {
+        let mut processor = SvfProcessor::new(SvfMode::HighPass);
+        processor.prepare(AudioProcessorSettings::default());
+
+        let mut buffer = VecAudioBuffer::new();
+        buffer.resize(2, 4096, 1.0_f32);
+        let cutoff: Vec<f32> = (0..4096).map(|i| 100.0 + (i % 64) as f32 * 100.0).collect();
+        let q = vec![2.0; 4096];
+        processor.process_modulated(&mut buffer, &cutoff, &q);
+
+        // DC is removed
+        let last = buffer.frames().last().unwrap();
+        assert!(last[0].abs() < 0.05, "{}", last[0]);
+    }
+}
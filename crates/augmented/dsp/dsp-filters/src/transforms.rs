@@ -0,0 +1,187 @@
+//! Transforms from analog low-pass prototypes to digital filters, using the bilinear transform.
+//!
+//! Mirrors the transforms in DSPFilters' `PoleFilter.cpp`. Frequencies are normalized
+//! (frequency / sample-rate).
+use std::f64::consts::PI;
+
+use num::complex::Complex64;
+
+use crate::layout::{is_infinity, Layout};
+
+/// Low-pass to low-pass transform, `fc` is the normalized cut-off
+pub fn low_pass_transform(fc: f64, digital: &mut Layout, analog: &Layout) {
+    digital.reset();
+
+    // prewarp
+    let f = (PI * fc).tan();
+    let transform = |c: Complex64| {
+        if is_infinity(c) {
+            return Complex64::new(-1.0, 0.0);
+        }
+        let c = f * c;
+        (1.0 + c) / (1.0 - c)
+    };
+
+    for pair in analog.pairs() {
+        if pair.is_single_pole() {
+            digital.add(transform(pair.poles.0), transform(pair.zeros.0));
+        } else {
+            digital.add_pair(
+                (transform(pair.poles.0), transform(pair.poles.1)),
+                (transform(pair.zeros.0), transform(pair.zeros.1)),
+            );
+        }
+    }
+
+    digital.set_normal(analog.normal_w(), analog.normal_gain());
+}
+
+/// Low-pass to high-pass transform, `fc` is the normalized cut-off
+pub fn high_pass_transform(fc: f64, digital: &mut Layout, analog: &Layout) {
+    digital.reset();
+
+    // prewarp
+    let f = 1.0 / (PI * fc).tan();
+    let transform = |c: Complex64| {
+        if is_infinity(c) {
+            return Complex64::new(1.0, 0.0);
+        }
+        let c = f * c;
+        -(1.0 + c) / (1.0 - c)
+    };
+
+    for pair in analog.pairs() {
+        if pair.is_single_pole() {
+            digital.add(transform(pair.poles.0), transform(pair.zeros.0));
+        } else {
+            digital.add_pair(
+                (transform(pair.poles.0), transform(pair.poles.1)),
+                (transform(pair.zeros.0), transform(pair.zeros.1)),
+            );
+        }
+    }
+
+    digital.set_normal(PI - analog.normal_w(), analog.normal_gain());
+}
+
+/// Returns the lower & upper band edges in radians, clamped away from DC & Nyquist
+fn band_edges(fc: f64, fw: f64) -> (f64, f64) {
+    let ww = 2.0 * PI * fw;
+    let wc2 = (2.0 * PI * fc - (ww / 2.0)).max(1e-8);
+    let wc = (wc2 + ww).min(PI - 1e-8);
+    (wc2, wc)
+}
+
+/// Splits the four digital roots a band transform produces for a pair of analog roots into
+/// two sections, each holding a conjugate pair or two real roots
+fn band_sections(
+    roots: (Complex64, Complex64),
+    transform: impl Fn(Complex64) -> (Complex64, Complex64),
+) -> ((Complex64, Complex64), (Complex64, Complex64)) {
+    let (a, b) = transform(roots.0);
+    if roots.0.im != 0.0 {
+        ((a, a.conj()), (b, b.conj()))
+    } else {
+        ((a, b), transform(roots.1))
+    }
+}
+
+/// Low-pass to band-pass transform, `fc` is the normalized center frequency & `fw` the
+/// normalized band-width. Doubles the number of poles.
+pub fn band_pass_transform(fc: f64, fw: f64, digital: &mut Layout, analog: &Layout) {
+    digital.reset();
+
+    let (wc2, wc) = band_edges(fc, fw);
+    let a = ((wc + wc2) * 0.5).cos() / ((wc - wc2) * 0.5).cos();
+    let b = 1.0 / ((wc - wc2) * 0.5).tan();
+    let a2 = a * a;
+    let b2 = b * b;
+    let ab_2 = 2.0 * a * b;
+
+    let transform = |c: Complex64| {
+        if is_infinity(c) {
+            return (Complex64::new(-1.0, 0.0), Complex64::new(1.0, 0.0));
+        }
+
+        // bilinear
+        let c = (1.0 + c) / (1.0 - c);
+
+        let mut v = c * (4.0 * (b2 * (a2 - 1.0) + 1.0));
+        v += 8.0 * (b2 * (a2 - 1.0) - 1.0);
+        v *= c;
+        v += 4.0 * (b2 * (a2 - 1.0) + 1.0);
+        let v = v.sqrt();
+
+        let u = -v + c * ab_2 + ab_2;
+        let v = v + c * ab_2 + ab_2;
+        let d = c * (2.0 * (b - 1.0)) + 2.0 * (1.0 + b);
+
+        (u / d, v / d)
+    };
+
+    for pair in analog.pairs() {
+        if pair.is_single_pole() {
+            digital.add_pair(transform(pair.poles.0), transform(pair.zeros.0));
+        } else {
+            let poles = band_sections(pair.poles, transform);
+            let zeros = band_sections(pair.zeros, transform);
+            digital.add_pair(poles.0, zeros.0);
+            digital.add_pair(poles.1, zeros.1);
+        }
+    }
+
+    let wn = analog.normal_w();
+    digital.set_normal(
+        2.0 * (((wc + wn) * 0.5).tan() * ((wc2 + wn) * 0.5).tan())
+            .sqrt()
+            .atan(),
+        analog.normal_gain(),
+    );
+}
+
+/// Low-pass to band-stop transform, `fc` is the normalized center frequency & `fw` the
+/// normalized band-width. Doubles the number of poles.
+pub fn band_stop_transform(fc: f64, fw: f64, digital: &mut Layout, analog: &Layout) {
+    digital.reset();
+
+    let (wc2, wc) = band_edges(fc, fw);
+    let a = ((wc + wc2) * 0.5).cos() / ((wc - wc2) * 0.5).cos();
+    let b = ((wc - wc2) * 0.5).tan();
+    let a2 = a * a;
+    let b2 = b * b;
+
+    let transform = |c: Complex64| {
+        let c = if is_infinity(c) {
+            Complex64::new(-1.0, 0.0)
+        } else {
+            // bilinear
+            (1.0 + c) / (1.0 - c)
+        };
+
+        let mut u = c * (4.0 * (b2 + a2 - 1.0));
+        u += 8.0 * (b2 - a2 + 1.0);
+        u *= c;
+        u += 4.0 * (a2 + b2 - 1.0);
+        let u = u.sqrt();
+
+        let v = u * -0.5 + a - c * a;
+        let u = u * 0.5 + a - c * a;
+        let d = c * (b - 1.0) + (b + 1.0);
+
+        (u / d, v / d)
+    };
+
+    for pair in analog.pairs() {
+        if pair.is_single_pole() {
+            digital.add_pair(transform(pair.poles.0), transform(pair.zeros.0));
+        } else {
+            let poles = band_sections(pair.poles, transform);
+            let zeros = band_sections(pair.zeros, transform);
+            digital.add_pair(poles.0, zeros.0);
+            digital.add_pair(poles.1, zeros.1);
+        }
+    }
+
+    let normal_w = if fc < 0.25 { PI } else { 0.0 };
+    digital.set_normal(normal_w, analog.normal_gain());
+}
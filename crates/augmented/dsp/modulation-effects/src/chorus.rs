@@ -0,0 +1,402 @@
+//! Multi-voice stereo chorus. See [`ChorusProcessor`].
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use delay_line::{DelayLine, Interpolation};
+use smooth_value::InterpolatedValue;
+
+use crate::{Lfo, LfoShape};
+
+/// Most voices a chorus may have
+pub const MAX_VOICES: usize = 4;
+/// Longest centre delay
+pub const MAX_DELAY: Duration = Duration::from_millis(40);
+/// Largest modulation depth
+pub const MAX_DEPTH: Duration = Duration::from_millis(20);
+/// How long delay & depth changes are ramped for
+const SMOOTHING_TIME: Duration = Duration::from_millis(20);
+
+/// A chorus: several copies of the input, each read from a delay line modulated by the same LFO at
+/// evenly spaced phases.
+///
+/// Odd channels read the LFO `spread` half periods ahead of even channels, so a spread of 1 makes
+/// the left & right voices move in opposite directions.
+///
+/// All buffers are allocated on `prepare`.
+pub struct ChorusProcessor {
+    rate: f32,
+    depth: Duration,
+    delay: Duration,
+    voices: usize,
+    spread: f32,
+    mix: f32,
+    sample_rate: f32,
+    lfo: Lfo,
+    delay_samples: InterpolatedValue,
+    depth_samples: InterpolatedValue,
+    lines: Vec<DelayLine<f32>>,
+    #[cfg(feature = "parameter_store")]
+    parameters: Option<audio_parameter_store::ParameterReader>,
+}
+
+impl Default for ChorusProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChorusProcessor {
+    pub fn new() -> Self {
+        let rate = 0.8;
+        ChorusProcessor {
+            rate,
+            depth: Duration::from_millis(3),
+            delay: Duration::from_millis(15),
+            voices: 3,
+            spread: 1.0,
+            mix: 0.5,
+            sample_rate: 44100.0,
+            lfo: Lfo::new(LfoShape::Sine, 44100.0, rate),
+            delay_samples: InterpolatedValue::new(44100.0, SMOOTHING_TIME, 0.0),
+            depth_samples: InterpolatedValue::new(44100.0, SMOOTHING_TIME, 0.0),
+            lines: Vec::new(),
+            #[cfg(feature = "parameter_store")]
+            parameters: None,
+        }
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Set the LFO frequency, in Hz
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.0);
+        self.lfo.set_frequency(self.rate);
+    }
+
+    pub fn depth(&self) -> Duration {
+        self.depth
+    }
+
+    /// Set how far the delay moves either side of its centre, up to [`MAX_DEPTH`]
+    pub fn set_depth(&mut self, depth: Duration) {
+        self.depth = depth.min(MAX_DEPTH);
+        self.depth_samples
+            .set(self.depth.as_secs_f32() * self.sample_rate);
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Set the centre delay, up to [`MAX_DELAY`]
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay.min(MAX_DELAY);
+        self.delay_samples
+            .set(self.delay.as_secs_f32() * self.sample_rate);
+    }
+
+    pub fn voices(&self) -> usize {
+        self.voices
+    }
+
+    /// Set the number of voices, between 1 & [`MAX_VOICES`]
+    pub fn set_voices(&mut self, voices: usize) {
+        self.voices = voices.clamp(1, MAX_VOICES);
+    }
+
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+
+    /// Set the stereo spread, between 0 (mono) and 1
+    pub fn set_spread(&mut self, spread: f32) {
+        self.spread = spread.clamp(0.0, 1.0);
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Set the dry/wet mix, between 0 (dry) and 1 (wet)
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    pub fn lfo_shape(&self) -> LfoShape {
+        self.lfo.shape()
+    }
+
+    pub fn set_lfo_shape(&mut self, shape: LfoShape) {
+        if shape != self.lfo.shape() {
+            self.lfo.set_shape(shape, self.sample_rate);
+        }
+    }
+
+    /// Clear the delay lines
+    pub fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.clear();
+        }
+    }
+}
+
+impl AudioProcessor for ChorusProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.lfo.set_sample_rate(self.sample_rate);
+
+        self.delay_samples.set_sample_rate(self.sample_rate);
+        self.delay_samples
+            .set(self.delay.as_secs_f32() * self.sample_rate);
+        self.delay_samples.jump();
+        self.depth_samples.set_sample_rate(self.sample_rate);
+        self.depth_samples
+            .set(self.depth.as_secs_f32() * self.sample_rate);
+        self.depth_samples.jump();
+
+        let max_delay = ((MAX_DELAY + MAX_DEPTH).as_secs_f32() * self.sample_rate).ceil() as usize;
+        self.lines = (0..settings.output_channels())
+            .map(|_| DelayLine::new(max_delay + 4))
+            .collect();
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        #[cfg(feature = "parameter_store")]
+        self.update_from_parameters();
+
+        let dry = 1.0 - self.mix;
+        let wet = self.mix / self.voices as f32;
+        let voice_spacing = 1.0 / self.voices as f32;
+        let spread_offset = self.spread * 0.5;
+
+        for frame in data.frames_mut() {
+            let delay = self.delay_samples.next_sample();
+            let depth = self.depth_samples.next_sample();
+
+            for (channel, (sample, line)) in frame.iter_mut().zip(&mut self.lines).enumerate() {
+                let channel_offset = if channel % 2 == 1 { spread_offset } else { 0.0 };
+                let mut voices = 0.0;
+                for voice in 0..self.voices {
+                    let modulation = self
+                        .lfo
+                        .value(voice as f32 * voice_spacing + channel_offset);
+                    voices +=
+                        line.read_interpolated(delay + depth * modulation, Interpolation::Cubic);
+                }
+
+                let input = *sample;
+                line.write(input);
+                *sample = dry * input + wet * voices;
+            }
+
+            self.lfo.tick();
+        }
+    }
+}
+
+/// [`audio_parameter_store::ParameterStore`] integration, so the chorus may be shipped as a
+/// plugin.
+///
+/// Depth & delay are in milliseconds; spread & mix are percentages.
+#[cfg(feature = "parameter_store")]
+pub mod parameters {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use audio_parameter_store::{ParameterReader, ParameterStore};
+
+    use crate::store::build_parameter;
+    use crate::LfoShape;
+
+    use super::{ChorusProcessor, MAX_DELAY, MAX_DEPTH, MAX_VOICES};
+
+    pub const RATE_PARAMETER_ID: &str = "rate";
+    pub const DEPTH_PARAMETER_ID: &str = "depth";
+    pub const DELAY_PARAMETER_ID: &str = "delay";
+    pub const VOICES_PARAMETER_ID: &str = "voices";
+    pub const SPREAD_PARAMETER_ID: &str = "spread";
+    pub const MIX_PARAMETER_ID: &str = "mix";
+    pub const LFO_SHAPE_PARAMETER_ID: &str = "lfo_shape";
+
+    const PARAMETER_IDS: [&str; 7] = [
+        RATE_PARAMETER_ID,
+        DEPTH_PARAMETER_ID,
+        DELAY_PARAMETER_ID,
+        VOICES_PARAMETER_ID,
+        SPREAD_PARAMETER_ID,
+        MIX_PARAMETER_ID,
+        LFO_SHAPE_PARAMETER_ID,
+    ];
+
+    /// Add the chorus' parameters to `store`, using its settings as initial values
+    pub fn add_parameters(store: &mut ParameterStore, chorus: &ChorusProcessor) {
+        let ms = |duration: Duration| duration.as_secs_f32() * 1000.0;
+        store.add_parameter(
+            RATE_PARAMETER_ID,
+            build_parameter("Rate", "Hz", chorus.rate(), 2, (0.01, 10.0)),
+        );
+        store.add_parameter(
+            DEPTH_PARAMETER_ID,
+            build_parameter("Depth", "ms", ms(chorus.depth()), 1, (0.0, ms(MAX_DEPTH))),
+        );
+        store.add_parameter(
+            DELAY_PARAMETER_ID,
+            build_parameter("Delay", "ms", ms(chorus.delay()), 1, (0.0, ms(MAX_DELAY))),
+        );
+        store.add_parameter(
+            VOICES_PARAMETER_ID,
+            build_parameter(
+                "Voices",
+                "",
+                chorus.voices() as f32,
+                0,
+                (1.0, MAX_VOICES as f32),
+            ),
+        );
+        store.add_parameter(
+            SPREAD_PARAMETER_ID,
+            build_parameter("Spread", "%", chorus.spread() * 100.0, 0, (0.0, 100.0)),
+        );
+        store.add_parameter(
+            MIX_PARAMETER_ID,
+            build_parameter("Mix", "%", chorus.mix() * 100.0, 0, (0.0, 100.0)),
+        );
+        store.add_parameter(
+            LFO_SHAPE_PARAMETER_ID,
+            build_parameter(
+                "LFO Shape",
+                "",
+                chorus.lfo_shape().index() as f32,
+                0,
+                (0.0, (LfoShape::ALL.len() - 1) as f32),
+            ),
+        );
+    }
+
+    impl ChorusProcessor {
+        /// Read settings from `store` at the start of every block. The store should have been
+        /// set-up with [`add_parameters`].
+        pub fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+            self.parameters = Some(ParameterReader::new(&store, &PARAMETER_IDS));
+            self.update_from_parameters();
+        }
+
+        pub(super) fn update_from_parameters(&mut self) {
+            let mut parameters = match self.parameters.take() {
+                Some(parameters) => parameters,
+                None => return,
+            };
+            let ms = |value: f32| Duration::from_secs_f32(value.max(0.0) / 1000.0);
+            parameters.read_changes(|index, value| match index {
+                0 => self.set_rate(value),
+                1 => self.set_depth(ms(value)),
+                2 => self.set_delay(ms(value)),
+                3 => self.set_voices(value.round().max(0.0) as usize),
+                4 => self.set_spread(value / 100.0),
+                5 => self.set_mix(value / 100.0),
+                _ => self.set_lfo_shape(LfoShape::from_index(value.round().max(0.0) as usize)),
+            });
+            self.parameters = Some(parameters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    fn process_impulse(chorus: &mut ChorusProcessor, num_frames: usize) -> Vec<f32> {
+        let mut samples = vec![0.0; num_frames * 2];
+        samples[0] = 1.0;
+        samples[1] = 1.0;
+        chorus.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+        samples
+    }
+
+    #[test]
+    fn test_voices_arrive_around_the_centre_delay() {
+        let mut chorus = ChorusProcessor::new();
+        chorus.set_mix(1.0);
+        chorus.set_delay(Duration::from_millis(10));
+        chorus.set_depth(Duration::from_millis(2));
+        chorus.prepare(AudioProcessorSettings::new(1000.0, 2, 2, 512));
+
+        let output = process_impulse(&mut chorus, 20);
+        let left: Vec<f32> = output.iter().step_by(2).cloned().collect();
+        let energy_in = |range: std::ops::Range<usize>| -> f32 {
+            left[range].iter().map(|sample| sample * sample).sum()
+        };
+        assert_eq!(energy_in(0..7), 0.0);
+        assert!(energy_in(7..14) > 0.1);
+    }
+
+    #[test]
+    fn test_spread_decorrelates_channels() {
+        let mut chorus = ChorusProcessor::new();
+        chorus.set_mix(1.0);
+        chorus.set_voices(1);
+        chorus.set_spread(1.0);
+        chorus.prepare(AudioProcessorSettings::new(44100.0, 2, 2, 512));
+
+        // Let the LFO move away from its zero crossing
+        for _ in 0..10 {
+            process_impulse(&mut chorus, 1024);
+        }
+        let output = process_impulse(&mut chorus, 2048);
+        let peak = |channel: usize| {
+            (0..2048)
+                .max_by(|a, b| {
+                    let a = output[a * 2 + channel].abs();
+                    let b = output[b * 2 + channel].abs();
+                    a.partial_cmp(&b).unwrap()
+                })
+                .unwrap()
+        };
+        assert_ne!(peak(0), peak(1));
+
+        chorus.set_spread(0.0);
+        chorus.reset();
+        let output = process_impulse(&mut chorus, 2048);
+        for frame in output.chunks(2) {
+            assert_eq!(frame[0], frame[1]);
+        }
+    }
+
+    #[cfg(feature = "parameter_store")]
+    #[test]
+    fn test_parameter_store_drives_the_chorus() {
+        use std::sync::Arc;
+
+        use audio_parameter_store::ParameterStore;
+
+        let mut chorus = ChorusProcessor::new();
+        let mut store = ParameterStore::new();
+        parameters::add_parameters(&mut store, &chorus);
+        assert_eq!(store.get_num_parameters(), 7);
+
+        let store = Arc::new(store);
+        chorus.set_parameter_store(store.clone());
+        chorus.prepare(AudioProcessorSettings::default());
+        store
+            .find_parameter(parameters::VOICES_PARAMETER_ID)
+            .unwrap()
+            .set_value(2.0);
+        store
+            .find_parameter(parameters::LFO_SHAPE_PARAMETER_ID)
+            .unwrap()
+            .set_value(1.0);
+        process_impulse(&mut chorus, 64);
+
+        assert_eq!(chorus.voices(), 2);
+        assert_eq!(chorus.lfo_shape(), LfoShape::Triangle);
+    }
+}
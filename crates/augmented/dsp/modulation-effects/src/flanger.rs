@@ -0,0 +1,403 @@
+//! Flanger with feedback & through-zero flanging. See [`FlangerProcessor`].
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use delay_line::{DelayLine, Interpolation};
+use smooth_value::InterpolatedValue;
+
+use crate::{Lfo, LfoShape};
+
+/// Longest minimum delay
+pub const MAX_DELAY: Duration = Duration::from_millis(10);
+/// Largest sweep width
+pub const MAX_DEPTH: Duration = Duration::from_millis(10);
+/// How long delay & depth changes are ramped for
+const SMOOTHING_TIME: Duration = Duration::from_millis(20);
+
+/// A flanger: the input mixed with a copy of itself, read from a delay line swept by an LFO, with
+/// feedback.
+///
+/// The delay sweeps from `delay` to `delay + depth`. With through-zero flanging the dry signal is
+/// delayed by `delay + depth / 2` too & the swept copy is inverted, so the copy passes the dry
+/// signal & cancels it completely at the middle of the sweep. This delays the whole output by
+/// [`FlangerProcessor::latency`].
+///
+/// All buffers are allocated on `prepare`.
+pub struct FlangerProcessor {
+    rate: f32,
+    depth: Duration,
+    delay: Duration,
+    feedback: f32,
+    mix: f32,
+    through_zero: bool,
+    sample_rate: f32,
+    lfo: Lfo,
+    delay_samples: InterpolatedValue,
+    depth_samples: InterpolatedValue,
+    lines: Vec<DelayLine<f32>>,
+    /// Last wet sample of each channel, fed back into its line
+    feedback_samples: Vec<f32>,
+    #[cfg(feature = "parameter_store")]
+    parameters: Option<audio_parameter_store::ParameterReader>,
+}
+
+impl Default for FlangerProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlangerProcessor {
+    pub fn new() -> Self {
+        let rate = 0.25;
+        FlangerProcessor {
+            rate,
+            depth: Duration::from_millis(4),
+            delay: Duration::from_millis(1),
+            feedback: 0.5,
+            mix: 0.5,
+            through_zero: false,
+            sample_rate: 44100.0,
+            lfo: Lfo::new(LfoShape::Triangle, 44100.0, rate),
+            delay_samples: InterpolatedValue::new(44100.0, SMOOTHING_TIME, 0.0),
+            depth_samples: InterpolatedValue::new(44100.0, SMOOTHING_TIME, 0.0),
+            lines: Vec::new(),
+            feedback_samples: Vec::new(),
+            #[cfg(feature = "parameter_store")]
+            parameters: None,
+        }
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Set the LFO frequency, in Hz
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.0);
+        self.lfo.set_frequency(self.rate);
+    }
+
+    pub fn depth(&self) -> Duration {
+        self.depth
+    }
+
+    /// Set the width of the sweep, up to [`MAX_DEPTH`]
+    pub fn set_depth(&mut self, depth: Duration) {
+        self.depth = depth.min(MAX_DEPTH);
+        self.depth_samples
+            .set(self.depth.as_secs_f32() * self.sample_rate);
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Set the shortest delay of the sweep, up to [`MAX_DELAY`]
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay.min(MAX_DELAY);
+        self.delay_samples
+            .set(self.delay.as_secs_f32() * self.sample_rate);
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Set the feedback, between -0.95 & 0.95. Negative feedback hollows the sound out.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-0.95, 0.95);
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Set the dry/wet mix, between 0 (dry) and 1 (wet). The notches are deepest at 0.5.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    pub fn through_zero(&self) -> bool {
+        self.through_zero
+    }
+
+    /// Delay the dry signal to the middle of the sweep, see [`FlangerProcessor`]
+    pub fn set_through_zero(&mut self, through_zero: bool) {
+        self.through_zero = through_zero;
+    }
+
+    pub fn lfo_shape(&self) -> LfoShape {
+        self.lfo.shape()
+    }
+
+    pub fn set_lfo_shape(&mut self, shape: LfoShape) {
+        if shape != self.lfo.shape() {
+            self.lfo.set_shape(shape, self.sample_rate);
+        }
+    }
+
+    /// Clear the delay lines
+    pub fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.clear();
+        }
+        for sample in &mut self.feedback_samples {
+            *sample = 0.0;
+        }
+    }
+}
+
+impl AudioProcessor for FlangerProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.lfo.set_sample_rate(self.sample_rate);
+
+        self.delay_samples.set_sample_rate(self.sample_rate);
+        self.delay_samples
+            .set(self.delay.as_secs_f32() * self.sample_rate);
+        self.delay_samples.jump();
+        self.depth_samples.set_sample_rate(self.sample_rate);
+        self.depth_samples
+            .set(self.depth.as_secs_f32() * self.sample_rate);
+        self.depth_samples.jump();
+
+        let max_delay = ((MAX_DELAY + MAX_DEPTH).as_secs_f32() * self.sample_rate).ceil() as usize;
+        self.lines = (0..settings.output_channels())
+            .map(|_| DelayLine::new(max_delay + 4))
+            .collect();
+        self.feedback_samples = vec![0.0; settings.output_channels()];
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        #[cfg(feature = "parameter_store")]
+        self.update_from_parameters();
+
+        let dry = 1.0 - self.mix;
+        let wet = self.mix;
+        let feedback = self.feedback;
+
+        for frame in data.frames_mut() {
+            let delay = self.delay_samples.next_sample();
+            let depth = self.depth_samples.next_sample();
+            // The LFO is between -1 & 1, sweep between 0 & 1
+            let sweep = (self.lfo.value(0.0) + 1.0) / 2.0;
+            let swept_delay = delay + depth * sweep;
+            let dry_delay = delay + depth / 2.0;
+
+            for ((sample, line), feedback_sample) in frame
+                .iter_mut()
+                .zip(&mut self.lines)
+                .zip(&mut self.feedback_samples)
+            {
+                let input = *sample;
+                // Write first, so short delays may be read. A delay of 1 is now the input.
+                line.write(input + feedback * *feedback_sample);
+                let swept = line.read_interpolated(swept_delay + 1.0, Interpolation::Cubic);
+                *feedback_sample = swept;
+
+                *sample = if self.through_zero {
+                    let reference = line.read_interpolated(dry_delay + 1.0, Interpolation::Cubic);
+                    dry * reference - wet * swept
+                } else {
+                    dry * input + wet * swept
+                };
+            }
+
+            self.lfo.tick();
+        }
+    }
+
+    /// Delay of the whole output, in samples. 0 unless flanging through-zero.
+    fn latency(&self) -> usize {
+        if self.through_zero {
+            let delay = self.delay + self.depth / 2;
+            (delay.as_secs_f32() * self.sample_rate).round() as usize
+        } else {
+            0
+        }
+    }
+}
+
+/// [`audio_parameter_store::ParameterStore`] integration, so the flanger may be shipped as a
+/// plugin.
+///
+/// Depth & delay are in milliseconds; feedback & mix are percentages.
+#[cfg(feature = "parameter_store")]
+pub mod parameters {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use audio_parameter_store::{ParameterReader, ParameterStore};
+
+    use crate::store::build_parameter;
+    use crate::LfoShape;
+
+    use super::{FlangerProcessor, MAX_DELAY, MAX_DEPTH};
+
+    pub const RATE_PARAMETER_ID: &str = "rate";
+    pub const DEPTH_PARAMETER_ID: &str = "depth";
+    pub const DELAY_PARAMETER_ID: &str = "delay";
+    pub const FEEDBACK_PARAMETER_ID: &str = "feedback";
+    pub const MIX_PARAMETER_ID: &str = "mix";
+    pub const THROUGH_ZERO_PARAMETER_ID: &str = "through_zero";
+    pub const LFO_SHAPE_PARAMETER_ID: &str = "lfo_shape";
+
+    const PARAMETER_IDS: [&str; 7] = [
+        RATE_PARAMETER_ID,
+        DEPTH_PARAMETER_ID,
+        DELAY_PARAMETER_ID,
+        FEEDBACK_PARAMETER_ID,
+        MIX_PARAMETER_ID,
+        THROUGH_ZERO_PARAMETER_ID,
+        LFO_SHAPE_PARAMETER_ID,
+    ];
+
+    /// Add the flanger's parameters to `store`, using its settings as initial values
+    pub fn add_parameters(store: &mut ParameterStore, flanger: &FlangerProcessor) {
+        let ms = |duration: Duration| duration.as_secs_f32() * 1000.0;
+        store.add_parameter(
+            RATE_PARAMETER_ID,
+            build_parameter("Rate", "Hz", flanger.rate(), 2, (0.01, 10.0)),
+        );
+        store.add_parameter(
+            DEPTH_PARAMETER_ID,
+            build_parameter("Depth", "ms", ms(flanger.depth()), 2, (0.0, ms(MAX_DEPTH))),
+        );
+        store.add_parameter(
+            DELAY_PARAMETER_ID,
+            build_parameter("Delay", "ms", ms(flanger.delay()), 2, (0.0, ms(MAX_DELAY))),
+        );
+        store.add_parameter(
+            FEEDBACK_PARAMETER_ID,
+            build_parameter(
+                "Feedback",
+                "%",
+                flanger.feedback() * 100.0,
+                0,
+                (-95.0, 95.0),
+            ),
+        );
+        store.add_parameter(
+            MIX_PARAMETER_ID,
+            build_parameter("Mix", "%", flanger.mix() * 100.0, 0, (0.0, 100.0)),
+        );
+        store.add_parameter(
+            THROUGH_ZERO_PARAMETER_ID,
+            build_parameter(
+                "Through-zero",
+                "",
+                if flanger.through_zero() { 1.0 } else { 0.0 },
+                0,
+                (0.0, 1.0),
+            ),
+        );
+        store.add_parameter(
+            LFO_SHAPE_PARAMETER_ID,
+            build_parameter(
+                "LFO Shape",
+                "",
+                flanger.lfo_shape().index() as f32,
+                0,
+                (0.0, (LfoShape::ALL.len() - 1) as f32),
+            ),
+        );
+    }
+
+    impl FlangerProcessor {
+        /// Read settings from `store` at the start of every block. The store should have been
+        /// set-up with [`add_parameters`].
+        pub fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+            self.parameters = Some(ParameterReader::new(&store, &PARAMETER_IDS));
+            self.update_from_parameters();
+        }
+
+        pub(super) fn update_from_parameters(&mut self) {
+            let mut parameters = match self.parameters.take() {
+                Some(parameters) => parameters,
+                None => return,
+            };
+            let ms = |value: f32| Duration::from_secs_f32(value.max(0.0) / 1000.0);
+            parameters.read_changes(|index, value| match index {
+                0 => self.set_rate(value),
+                1 => self.set_depth(ms(value)),
+                2 => self.set_delay(ms(value)),
+                3 => self.set_feedback(value / 100.0),
+                4 => self.set_mix(value / 100.0),
+                5 => self.set_through_zero(value >= 0.5),
+                _ => self.set_lfo_shape(LfoShape::from_index(value.round().max(0.0) as usize)),
+            });
+            self.parameters = Some(parameters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    fn sine(frequency: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// A flanger frozen at the middle of its sweep
+    fn static_flanger(through_zero: bool) -> FlangerProcessor {
+        let mut flanger = FlangerProcessor::new();
+        flanger.set_rate(0.0);
+        flanger.set_lfo_shape(LfoShape::Sine);
+        flanger.set_feedback(0.0);
+        flanger.set_through_zero(through_zero);
+        flanger.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 512));
+        flanger
+    }
+
+    #[test]
+    fn test_through_zero_cancels_at_the_middle_of_the_sweep() {
+        let mut flanger = static_flanger(true);
+        let mut samples = sine(440.0, 4096);
+        flanger.process(&mut InterleavedAudioBuffer::new(1, &mut samples));
+        assert!(rms(&samples[1024..]) < 1e-3);
+        assert_eq!(flanger.latency(), (0.003 * 44100.0_f32).round() as usize);
+    }
+
+    #[test]
+    fn test_notches_where_the_delay_is_half_a_period() {
+        let mut flanger = static_flanger(false);
+        // The delay at the middle of the sweep is 3ms, so the first notch is at 1 / 6ms
+        let mut notch = sine(1000.0 / 6.0, 8192);
+        flanger.process(&mut InterleavedAudioBuffer::new(1, &mut notch));
+        flanger.reset();
+        let mut peak = sine(1000.0 / 3.0, 8192);
+        flanger.process(&mut InterleavedAudioBuffer::new(1, &mut peak));
+
+        assert!(rms(&notch[1024..]) < 0.01);
+        assert!(rms(&peak[1024..]) > 0.6);
+    }
+
+    #[test]
+    fn test_feedback_rings() {
+        let mut flanger = static_flanger(false);
+        flanger.set_feedback(0.9);
+        flanger.set_mix(1.0);
+        let mut samples = vec![0.0; 2000];
+        samples[0] = 1.0;
+        flanger.process(&mut InterleavedAudioBuffer::new(1, &mut samples));
+        // Echoes every 3ms
+        assert!(samples[132 * 5..132 * 5 + 10]
+            .iter()
+            .any(|sample| sample.abs() > 0.3));
+    }
+}
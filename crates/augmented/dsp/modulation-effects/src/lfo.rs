@@ -0,0 +1,98 @@
+use oscillator::{generators, Oscillator};
+
+/// Waveform of an [`Lfo`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+}
+
+impl LfoShape {
+    /// All shapes, in the order used by [`LfoShape::from_index`]
+    pub const ALL: [LfoShape; 2] = [LfoShape::Sine, LfoShape::Triangle];
+
+    pub fn index(&self) -> usize {
+        match self {
+            LfoShape::Sine => 0,
+            LfoShape::Triangle => 1,
+        }
+    }
+
+    /// Shape at `index`, or the last shape if it's out of range
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index.min(Self::ALL.len() - 1)]
+    }
+
+    fn generator(&self) -> fn(f32) -> f32 {
+        match self {
+            LfoShape::Sine => generators::sine_generator,
+            LfoShape::Triangle => generators::triangle_generator,
+        }
+    }
+}
+
+/// A low frequency [`Oscillator`] between -1 & 1, which may be read at several phase offsets, for
+/// multiple voices or stereo spread.
+pub struct Lfo {
+    oscillator: Oscillator<f32>,
+    shape: LfoShape,
+}
+
+impl Lfo {
+    pub fn new(shape: LfoShape, sample_rate: f32, frequency: f32) -> Self {
+        let mut oscillator = Oscillator::new_with_sample_rate(sample_rate, shape.generator());
+        oscillator.set_frequency(frequency);
+        Lfo { oscillator, shape }
+    }
+
+    pub fn shape(&self) -> LfoShape {
+        self.shape
+    }
+
+    /// Change the waveform. Keeps the frequency, but restarts the phase.
+    pub fn set_shape(&mut self, shape: LfoShape, sample_rate: f32) {
+        *self = Lfo::new(shape, sample_rate, self.frequency());
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.oscillator.get_frequency()
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.oscillator.set_frequency(frequency);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.oscillator.set_sample_rate(sample_rate);
+    }
+
+    /// Value at the current phase plus `phase_offset`, a fraction of a period
+    #[inline]
+    pub fn value(&self, phase_offset: f32) -> f32 {
+        self.oscillator
+            .value_for_phase(self.oscillator.phase() + phase_offset)
+    }
+
+    /// Advance by one sample
+    #[inline]
+    pub fn tick(&mut self) {
+        self.oscillator.tick();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_triangle_peaks_a_quarter_period_in() {
+        let mut lfo = Lfo::new(LfoShape::Triangle, 100.0, 1.0);
+        assert!(lfo.value(0.0).abs() < 1e-6);
+        for _ in 0..25 {
+            lfo.tick();
+        }
+        assert!((lfo.value(0.0) - 1.0).abs() < 1e-4);
+        assert!((lfo.value(0.5) + 1.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,20 @@
+//! Modulation effects, built on the [`oscillator`] & [`delay_line`] crates.
+//!
+//! * [`ChorusProcessor`] - multi-voice chorus with stereo spread
+//! * [`FlangerProcessor`] - flanger with feedback & through-zero flanging
+//! * [`PhaserProcessor`] - phaser with 2 to 12 swept all-pass stages & feedback
+//!
+//! All are [`audio_processor_traits::AudioProcessor`]s, modulated by an [`Lfo`]. With the
+//! `parameter_store` feature (on by default) each effect's module has a `parameters` module, so its
+//! settings may be driven by an [`audio_parameter_store::ParameterStore`].
+pub use chorus::ChorusProcessor;
+pub use flanger::FlangerProcessor;
+pub use lfo::{Lfo, LfoShape};
+pub use phaser::PhaserProcessor;
+
+pub mod chorus;
+pub mod flanger;
+mod lfo;
+pub mod phaser;
+#[cfg(feature = "parameter_store")]
+mod store;
@@ -0,0 +1,392 @@
+//! Phaser built from swept first order all-pass stages. See [`PhaserProcessor`].
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+
+use crate::{Lfo, LfoShape};
+
+/// Most all-pass stages a phaser may have
+pub const MAX_STAGES: usize = 12;
+/// How many octaves the sweep moves either side of the centre frequency, at full depth
+const DEPTH_OCTAVES: f32 = 2.0;
+/// How many samples to process between coefficient updates
+const COEFFICIENT_UPDATE_INTERVAL: usize = 8;
+
+/// State of a first order all-pass filter
+#[derive(Default, Clone, Copy)]
+struct AllpassStage {
+    input: f32,
+    output: f32,
+}
+
+impl AllpassStage {
+    #[inline]
+    fn process1(&mut self, coefficient: f32, input: f32) -> f32 {
+        let output = coefficient * (input - self.output) + self.input;
+        self.input = input;
+        self.output = output;
+        output
+    }
+}
+
+/// First order all-pass coefficient for a 90° phase shift at `frequency`
+fn allpass_coefficient(frequency: f32, sample_rate: f32) -> f32 {
+    let tan = (std::f32::consts::PI * frequency / sample_rate).tan();
+    (tan - 1.0) / (tan + 1.0)
+}
+
+struct PhaserChannel {
+    stages: [AllpassStage; MAX_STAGES],
+    /// Output of the last stage, fed back into the first
+    last_output: f32,
+    coefficient: f32,
+}
+
+/// A phaser: the input mixed with a copy passed through a chain of all-pass filters, whose
+/// frequency an LFO sweeps. Every 2 stages add a notch.
+///
+/// The sweep is exponential, `depth` octaves around `frequency`. Odd channels read the LFO
+/// `spread` half periods ahead of even channels.
+pub struct PhaserProcessor {
+    rate: f32,
+    depth: f32,
+    frequency: f32,
+    feedback: f32,
+    stages: usize,
+    spread: f32,
+    mix: f32,
+    sample_rate: f32,
+    lfo: Lfo,
+    channels: Vec<PhaserChannel>,
+    samples_until_update: usize,
+    #[cfg(feature = "parameter_store")]
+    parameters: Option<audio_parameter_store::ParameterReader>,
+}
+
+impl Default for PhaserProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhaserProcessor {
+    pub fn new() -> Self {
+        let rate = 0.5;
+        PhaserProcessor {
+            rate,
+            depth: 0.7,
+            frequency: 800.0,
+            feedback: 0.3,
+            stages: 4,
+            spread: 0.5,
+            mix: 0.5,
+            sample_rate: 44100.0,
+            lfo: Lfo::new(LfoShape::Sine, 44100.0, rate),
+            channels: Vec::new(),
+            samples_until_update: 0,
+            #[cfg(feature = "parameter_store")]
+            parameters: None,
+        }
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Set the LFO frequency, in Hz
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.0);
+        self.lfo.set_frequency(self.rate);
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// Set the sweep depth, between 0 & 1. Full depth sweeps 2 octaves either side of the centre
+    /// frequency.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Set the centre frequency of the sweep, in Hz
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.max(20.0);
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Set the feedback, between -0.95 & 0.95. Higher feedback sharpens the notches.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-0.95, 0.95);
+    }
+
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
+
+    /// Set the number of all-pass stages, rounded down to an even number between 2 &
+    /// [`MAX_STAGES`]
+    pub fn set_stages(&mut self, stages: usize) {
+        self.stages = (stages.clamp(2, MAX_STAGES) / 2) * 2;
+    }
+
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+
+    /// Set the stereo spread, between 0 (mono) and 1
+    pub fn set_spread(&mut self, spread: f32) {
+        self.spread = spread.clamp(0.0, 1.0);
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Set the dry/wet mix, between 0 (dry) and 1 (wet). The notches are deepest at 0.5.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    pub fn lfo_shape(&self) -> LfoShape {
+        self.lfo.shape()
+    }
+
+    pub fn set_lfo_shape(&mut self, shape: LfoShape) {
+        if shape != self.lfo.shape() {
+            self.lfo.set_shape(shape, self.sample_rate);
+        }
+    }
+
+    /// Clear the filter states
+    pub fn reset(&mut self) {
+        for channel in &mut self.channels {
+            channel.stages = [AllpassStage::default(); MAX_STAGES];
+            channel.last_output = 0.0;
+        }
+    }
+
+    fn update_coefficients(&mut self) {
+        let max_frequency = self.sample_rate * 0.45;
+        let spread_offset = self.spread * 0.5;
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let channel_offset = if index % 2 == 1 { spread_offset } else { 0.0 };
+            let octaves = DEPTH_OCTAVES * self.depth * self.lfo.value(channel_offset);
+            let frequency = (self.frequency * octaves.exp2()).clamp(20.0, max_frequency);
+            channel.coefficient = allpass_coefficient(frequency, self.sample_rate);
+        }
+    }
+}
+
+impl AudioProcessor for PhaserProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+        self.lfo.set_sample_rate(self.sample_rate);
+        self.channels = (0..settings.output_channels())
+            .map(|_| PhaserChannel {
+                stages: [AllpassStage::default(); MAX_STAGES],
+                last_output: 0.0,
+                coefficient: 0.0,
+            })
+            .collect();
+        self.samples_until_update = 0;
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        #[cfg(feature = "parameter_store")]
+        self.update_from_parameters();
+
+        let dry = 1.0 - self.mix;
+        let wet = self.mix;
+        let feedback = self.feedback;
+        let stages = self.stages;
+
+        for frame in data.frames_mut() {
+            if self.samples_until_update == 0 {
+                self.update_coefficients();
+                self.samples_until_update = COEFFICIENT_UPDATE_INTERVAL;
+            }
+            self.samples_until_update -= 1;
+
+            for (sample, channel) in frame.iter_mut().zip(&mut self.channels) {
+                let input = *sample;
+                let coefficient = channel.coefficient;
+                let mut output = input + feedback * channel.last_output;
+                for stage in &mut channel.stages[..stages] {
+                    output = stage.process1(coefficient, output);
+                }
+                channel.last_output = output;
+                *sample = dry * input + wet * output;
+            }
+
+            self.lfo.tick();
+        }
+    }
+}
+
+/// [`audio_parameter_store::ParameterStore`] integration, so the phaser may be shipped as a
+/// plugin.
+///
+/// Depth, feedback, spread & mix are percentages.
+#[cfg(feature = "parameter_store")]
+pub mod parameters {
+    use std::sync::Arc;
+
+    use audio_parameter_store::{ParameterReader, ParameterStore};
+
+    use crate::store::build_parameter;
+    use crate::LfoShape;
+
+    use super::{PhaserProcessor, MAX_STAGES};
+
+    pub const RATE_PARAMETER_ID: &str = "rate";
+    pub const DEPTH_PARAMETER_ID: &str = "depth";
+    pub const FREQUENCY_PARAMETER_ID: &str = "frequency";
+    pub const FEEDBACK_PARAMETER_ID: &str = "feedback";
+    pub const STAGES_PARAMETER_ID: &str = "stages";
+    pub const SPREAD_PARAMETER_ID: &str = "spread";
+    pub const MIX_PARAMETER_ID: &str = "mix";
+    pub const LFO_SHAPE_PARAMETER_ID: &str = "lfo_shape";
+
+    const PARAMETER_IDS: [&str; 8] = [
+        RATE_PARAMETER_ID,
+        DEPTH_PARAMETER_ID,
+        FREQUENCY_PARAMETER_ID,
+        FEEDBACK_PARAMETER_ID,
+        STAGES_PARAMETER_ID,
+        SPREAD_PARAMETER_ID,
+        MIX_PARAMETER_ID,
+        LFO_SHAPE_PARAMETER_ID,
+    ];
+
+    /// Add the phaser's parameters to `store`, using its settings as initial values
+    pub fn add_parameters(store: &mut ParameterStore, phaser: &PhaserProcessor) {
+        store.add_parameter(
+            RATE_PARAMETER_ID,
+            build_parameter("Rate", "Hz", phaser.rate(), 2, (0.01, 10.0)),
+        );
+        store.add_parameter(
+            DEPTH_PARAMETER_ID,
+            build_parameter("Depth", "%", phaser.depth() * 100.0, 0, (0.0, 100.0)),
+        );
+        store.add_parameter(
+            FREQUENCY_PARAMETER_ID,
+            build_parameter("Frequency", "Hz", phaser.frequency(), 0, (20.0, 10000.0)),
+        );
+        store.add_parameter(
+            FEEDBACK_PARAMETER_ID,
+            build_parameter("Feedback", "%", phaser.feedback() * 100.0, 0, (-95.0, 95.0)),
+        );
+        store.add_parameter(
+            STAGES_PARAMETER_ID,
+            build_parameter(
+                "Stages",
+                "",
+                phaser.stages() as f32,
+                0,
+                (2.0, MAX_STAGES as f32),
+            ),
+        );
+        store.add_parameter(
+            SPREAD_PARAMETER_ID,
+            build_parameter("Spread", "%", phaser.spread() * 100.0, 0, (0.0, 100.0)),
+        );
+        store.add_parameter(
+            MIX_PARAMETER_ID,
+            build_parameter("Mix", "%", phaser.mix() * 100.0, 0, (0.0, 100.0)),
+        );
+        store.add_parameter(
+            LFO_SHAPE_PARAMETER_ID,
+            build_parameter(
+                "LFO Shape",
+                "",
+                phaser.lfo_shape().index() as f32,
+                0,
+                (0.0, (LfoShape::ALL.len() - 1) as f32),
+            ),
+        );
+    }
+
+    impl PhaserProcessor {
+        /// Read settings from `store` at the start of every block. The store should have been
+        /// set-up with [`add_parameters`].
+        pub fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+            self.parameters = Some(ParameterReader::new(&store, &PARAMETER_IDS));
+            self.update_from_parameters();
+        }
+
+        pub(super) fn update_from_parameters(&mut self) {
+            let mut parameters = match self.parameters.take() {
+                Some(parameters) => parameters,
+                None => return,
+            };
+            parameters.read_changes(|index, value| match index {
+                0 => self.set_rate(value),
+                1 => self.set_depth(value / 100.0),
+                2 => self.set_frequency(value),
+                3 => self.set_feedback(value / 100.0),
+                4 => self.set_stages(value.round().max(0.0) as usize),
+                5 => self.set_spread(value / 100.0),
+                6 => self.set_mix(value / 100.0),
+                _ => self.set_lfo_shape(LfoShape::from_index(value.round().max(0.0) as usize)),
+            });
+            self.parameters = Some(parameters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    fn sine_rms(phaser: &mut PhaserProcessor, frequency: f32) -> f32 {
+        phaser.reset();
+        let mut samples: Vec<f32> = (0..8192)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin())
+            .collect();
+        phaser.process(&mut InterleavedAudioBuffer::new(1, &mut samples));
+        let tail = &samples[4096..];
+        (tail.iter().map(|sample| sample * sample).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+
+    /// A phaser frozen at its centre frequency
+    fn static_phaser() -> PhaserProcessor {
+        let mut phaser = PhaserProcessor::new();
+        phaser.set_rate(0.0);
+        phaser.set_feedback(0.0);
+        phaser.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 512));
+        phaser
+    }
+
+    #[test]
+    fn test_two_stages_notch_the_centre_frequency() {
+        let mut phaser = static_phaser();
+        phaser.set_stages(2);
+        // 2 stages shift the centre frequency by 180°, cancelling the dry signal
+        assert!(sine_rms(&mut phaser, 800.0) < 0.01);
+        assert!(sine_rms(&mut phaser, 50.0) > 0.6);
+    }
+
+    #[test]
+    fn test_stages_are_even() {
+        let mut phaser = PhaserProcessor::new();
+        phaser.set_stages(7);
+        assert_eq!(phaser.stages(), 6);
+        phaser.set_stages(100);
+        assert_eq!(phaser.stages(), MAX_STAGES);
+        phaser.set_stages(0);
+        assert_eq!(phaser.stages(), 2);
+    }
+}
@@ -0,0 +1,22 @@
+//! Helpers for the effects' [`audio_parameter_store::ParameterStore`] integrations
+use std::sync::Arc;
+
+use audio_parameter_store::PluginParameter;
+
+pub(crate) fn build_parameter(
+    name: &str,
+    label: &str,
+    initial_value: f32,
+    value_precision: u32,
+    value_range: (f32, f32),
+) -> Arc<PluginParameter> {
+    Arc::new(
+        PluginParameter::builder()
+            .name(name)
+            .label(label)
+            .initial_value(initial_value)
+            .value_precision(value_precision)
+            .value_range(value_range.0, value_range.1)
+            .build(),
+    )
+}
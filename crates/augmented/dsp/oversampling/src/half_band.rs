@@ -0,0 +1,240 @@
+//! Polyphase FIR half-band filters, for changing the sample rate by a factor of 2.
+//!
+//! A half-band low-pass cuts off at a quarter of the sample rate, so every other coefficient is 0
+//! except the centre one, which is 0.5. Split into even & odd phases, one phase is a plain delay
+//! & the other is half as long as the filter, so each 2x step costs about a quarter of a plain FIR.
+
+use convert_sample_rate::window::kaiser;
+
+/// Coefficients of a windowed-sinc half-band low-pass
+#[derive(Debug, Clone)]
+pub struct HalfBandFilter {
+    /// The non-zero coefficients of the long phase, `h[0], h[2], h[4]...`
+    coefficients: Vec<f32>,
+    /// Index of the centre coefficient, which is also the group delay in samples
+    centre: usize,
+}
+
+impl HalfBandFilter {
+    /// Design a filter with `num_taps` coefficients, windowed with a Kaiser window. `num_taps` is
+    /// rounded up to the next `4 * n + 3`. A larger `kaiser_beta` attenuates the stop-band more, but
+    /// widens the transition band.
+    pub fn new(num_taps: usize, kaiser_beta: f32) -> Self {
+        let num_taps = (num_taps.max(3) / 4) * 4 + 3;
+        let centre = (num_taps - 1) / 2;
+
+        let mut coefficients: Vec<f32> = (0..num_taps)
+            .step_by(2)
+            .map(|index| {
+                let offset = index as f64 - centre as f64;
+                let x = std::f64::consts::PI * offset / 2.0;
+                let sinc = x.sin() / x;
+                let window = kaiser(offset / (centre + 1) as f64, kaiser_beta as f64);
+                (0.5 * sinc * window) as f32
+            })
+            .collect();
+
+        // Each phase of a half-band filter sums to 0.5, normalize so DC passes unchanged
+        let sum: f32 = coefficients.iter().sum();
+        for coefficient in &mut coefficients {
+            *coefficient *= 0.5 / sum;
+        }
+
+        HalfBandFilter {
+            coefficients,
+            centre,
+        }
+    }
+
+    pub fn num_taps(&self) -> usize {
+        self.centre * 2 + 1
+    }
+
+    /// Group delay, in samples at the higher sample rate
+    pub fn latency(&self) -> usize {
+        self.centre
+    }
+
+    /// Delay of the short phase, in samples at the lower sample rate
+    fn phase_delay(&self) -> usize {
+        (self.centre - 1) / 2
+    }
+}
+
+/// The last `len` input samples, readable as a contiguous slice from newest to oldest
+#[derive(Debug, Clone)]
+struct History {
+    /// The samples twice over, so a window never wraps
+    buffer: Vec<f32>,
+    position: usize,
+    len: usize,
+}
+
+impl History {
+    fn new(len: usize) -> Self {
+        History {
+            buffer: vec![0.0; len * 2],
+            position: 0,
+            len,
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, sample: f32) {
+        self.position = if self.position == 0 {
+            self.len - 1
+        } else {
+            self.position - 1
+        };
+        self.buffer[self.position] = sample;
+        self.buffer[self.position + self.len] = sample;
+    }
+
+    /// `recent()[k]` is the sample pushed `k` pushes ago
+    #[inline]
+    fn recent(&self) -> &[f32] {
+        &self.buffer[self.position..self.position + self.len]
+    }
+
+    fn clear(&mut self) {
+        for sample in &mut self.buffer {
+            *sample = 0.0;
+        }
+    }
+}
+
+#[inline]
+fn dot(coefficients: &[f32], samples: &[f32]) -> f32 {
+    coefficients
+        .iter()
+        .zip(samples)
+        .map(|(coefficient, sample)| coefficient * sample)
+        .sum()
+}
+
+/// Doubles the sample rate of one channel
+#[derive(Debug, Clone)]
+pub struct Upsampler2x {
+    filter: HalfBandFilter,
+    history: History,
+}
+
+impl Upsampler2x {
+    pub fn new(filter: HalfBandFilter) -> Self {
+        let history = History::new(filter.coefficients.len());
+        Upsampler2x { filter, history }
+    }
+
+    /// Group delay, in samples at the higher sample rate
+    pub fn latency(&self) -> usize {
+        self.filter.latency()
+    }
+
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// The 2 output samples for one input sample
+    #[inline]
+    pub fn process1(&mut self, input: f32) -> [f32; 2] {
+        self.history.push(input);
+        let recent = self.history.recent();
+        // Zero stuffing halves the level, the factor of 2 makes up for it
+        let even = 2.0 * dot(&self.filter.coefficients, recent);
+        let odd = recent[self.filter.phase_delay()];
+        [even, odd]
+    }
+}
+
+/// Halves the sample rate of one channel
+#[derive(Debug, Clone)]
+pub struct Downsampler2x {
+    filter: HalfBandFilter,
+    even_history: History,
+    odd_history: History,
+}
+
+impl Downsampler2x {
+    pub fn new(filter: HalfBandFilter) -> Self {
+        let even_history = History::new(filter.coefficients.len());
+        let odd_history = History::new(filter.phase_delay() + 2);
+        Downsampler2x {
+            filter,
+            even_history,
+            odd_history,
+        }
+    }
+
+    /// Group delay, in samples at the higher sample rate
+    pub fn latency(&self) -> usize {
+        self.filter.latency()
+    }
+
+    pub fn reset(&mut self) {
+        self.even_history.clear();
+        self.odd_history.clear();
+    }
+
+    /// The output sample for 2 input samples
+    #[inline]
+    pub fn process1(&mut self, input: [f32; 2]) -> f32 {
+        self.even_history.push(input[0]);
+        self.odd_history.push(input[1]);
+        dot(&self.filter.coefficients, self.even_history.recent())
+            + 0.5 * self.odd_history.recent()[self.filter.phase_delay() + 1]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sine(frequency: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_taps_are_rounded_to_4n_plus_3() {
+        assert_eq!(HalfBandFilter::new(3, 8.0).num_taps(), 3);
+        assert_eq!(HalfBandFilter::new(20, 8.0).num_taps(), 23);
+        assert_eq!(HalfBandFilter::new(23, 8.0).num_taps(), 23);
+    }
+
+    #[test]
+    fn test_round_trip_delays_by_the_latency() {
+        let filter = HalfBandFilter::new(31, 8.0);
+        let mut upsampler = Upsampler2x::new(filter.clone());
+        let mut downsampler = Downsampler2x::new(filter);
+        // 0.05 of the sample rate, well inside the pass-band
+        let input = sine(0.05, 1000);
+        let output: Vec<f32> = input
+            .iter()
+            .map(|sample| downsampler.process1(upsampler.process1(*sample)))
+            .collect();
+
+        // 2 filters of `centre` samples at the higher rate, so `centre` samples at the input rate
+        let latency = upsampler.latency();
+        for i in 100..1000 {
+            assert!((output[i] - input[i - latency]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_downsampler_rejects_the_top_octave() {
+        let filter = HalfBandFilter::new(63, 10.0);
+        let mut downsampler = Downsampler2x::new(filter);
+        // 0.4 of the higher sample rate would alias to 0.2 of the lower one
+        let input = sine(0.4, 8000);
+        let output: Vec<f32> = input
+            .chunks(2)
+            .map(|pair| downsampler.process1([pair[0], pair[1]]))
+            .collect();
+        assert!(rms(&output[100..]) < 1e-3);
+    }
+}
@@ -0,0 +1,300 @@
+//! Polyphase IIR half-band filters, for changing the sample rate by a factor of 2.
+//!
+//! The filter is the average of two chains of first-order all-pass sections, one of them delayed by
+//! a sample. Each chain runs at the lower sample rate, so a steep filter costs a handful of
+//! multiplies per sample & delays the signal much less than a linear-phase FIR of the same
+//! attenuation. The phase isn't linear though: the delay grows towards the cut-off.
+//!
+//! The coefficients come from the elliptic design of Valenzuela & Constantinides, as in
+//! [HIIR](http://ldesoras.free.fr/prod.html).
+use std::f64::consts::PI;
+
+/// Coefficients of a polyphase all-pass half-band low-pass
+#[derive(Debug, Clone)]
+pub struct IirHalfBandFilter {
+    /// Coefficients of the first chain, `a[0], a[2], a[4]...`
+    even_coefficients: Vec<f32>,
+    /// Coefficients of the second chain, `a[1], a[3], a[5]...`
+    odd_coefficients: Vec<f32>,
+    /// Group delay of both chains at DC, in samples at the lower sample rate
+    latency: f32,
+}
+
+impl IirHalfBandFilter {
+    /// Design a filter with `num_coefficients` all-pass sections. `transition_bandwidth` is the
+    /// width of the transition band as a fraction of the higher sample rate, between 0 & 0.5. More
+    /// coefficients or a wider transition band attenuate the stop-band more.
+    pub fn new(num_coefficients: usize, transition_bandwidth: f32) -> Self {
+        let num_coefficients = num_coefficients.max(1);
+        let transition_bandwidth = (transition_bandwidth as f64).clamp(1e-4, 0.499);
+        let (k, q) = transition_parameters(transition_bandwidth);
+        let order = num_coefficients * 2 + 1;
+        let coefficients: Vec<f64> = (0..num_coefficients)
+            .map(|index| coefficient(index, k, q, order))
+            .collect();
+
+        // A first-order all-pass section `(a + z^-1) / (1 + a z^-1)` delays DC by `(1 - a) / (1 + a)`
+        let latency = coefficients
+            .iter()
+            .map(|a| (1.0 - a) / (1.0 + a))
+            .sum::<f64>() as f32;
+        let phase = |offset: usize| {
+            coefficients
+                .iter()
+                .skip(offset)
+                .step_by(2)
+                .map(|a| *a as f32)
+                .collect()
+        };
+
+        IirHalfBandFilter {
+            even_coefficients: phase(0),
+            odd_coefficients: phase(1),
+            latency,
+        }
+    }
+
+    pub fn num_coefficients(&self) -> usize {
+        self.even_coefficients.len() + self.odd_coefficients.len()
+    }
+
+    /// Delay of an up & down round trip at low frequencies, in samples at the lower sample rate.
+    /// Higher frequencies are delayed more.
+    pub fn latency(&self) -> f32 {
+        self.latency
+    }
+}
+
+/// Elliptic parameters `k` & `q` for a transition band of `transition_bandwidth`
+fn transition_parameters(transition_bandwidth: f64) -> (f64, f64) {
+    let k = ((1.0 - transition_bandwidth * 2.0) * PI / 4.0).tan();
+    let k = k * k;
+    let kk_root = (1.0 - k * k).powf(0.25);
+    let e = 0.5 * (1.0 - kk_root) / (1.0 + kk_root);
+    let e4 = e.powi(4);
+    let q = e * (1.0 + e4 * (2.0 + e4 * (15.0 + 150.0 * e4)));
+    (k, q)
+}
+
+fn coefficient(index: usize, k: f64, q: f64, order: usize) -> f64 {
+    let c = (index + 1) as f64;
+    let order = order as f64;
+
+    // Numerator & denominator series of the elliptic function, summed until the terms vanish
+    let mut numerator = 0.0;
+    let mut sign = 1.0;
+    for i in 0.. {
+        let i = i as f64;
+        let term = q.powf(i * (i + 1.0)) * ((i * 2.0 + 1.0) * c * PI / order).sin() * sign;
+        numerator += term;
+        sign = -sign;
+        if term.abs() <= 1e-100 || i > 100.0 {
+            break;
+        }
+    }
+    let mut denominator = 0.0;
+    let mut sign = -1.0;
+    for i in 1.. {
+        let i = i as f64;
+        let term = q.powf(i * i) * (i * 2.0 * c * PI / order).cos() * sign;
+        denominator += term;
+        sign = -sign;
+        if term.abs() <= 1e-100 || i > 100.0 {
+            break;
+        }
+    }
+
+    let ww = numerator * q.powf(0.25) / (denominator + 0.5);
+    let ww2 = ww * ww;
+    let x = ((1.0 - ww2 * k) * (1.0 - ww2 / k)).sqrt() / (1.0 + ww2);
+    (1.0 - x) / (1.0 + x)
+}
+
+/// A chain of first-order all-pass sections
+#[derive(Debug, Clone)]
+struct AllpassChain {
+    coefficients: Vec<f32>,
+    /// Previous input & output of each section
+    state: Vec<(f32, f32)>,
+}
+
+impl AllpassChain {
+    fn new(coefficients: Vec<f32>) -> Self {
+        let state = vec![(0.0, 0.0); coefficients.len()];
+        AllpassChain {
+            coefficients,
+            state,
+        }
+    }
+
+    #[inline]
+    fn process1(&mut self, input: f32) -> f32 {
+        let mut sample = input;
+        for (coefficient, (previous_input, previous_output)) in
+            self.coefficients.iter().zip(&mut self.state)
+        {
+            let output = coefficient * (sample - *previous_output) + *previous_input;
+            *previous_input = sample;
+            *previous_output = output;
+            sample = output;
+        }
+        sample
+    }
+
+    fn clear(&mut self) {
+        for state in &mut self.state {
+            *state = (0.0, 0.0);
+        }
+    }
+
+    /// Flush values small enough to slow the CPU down
+    #[inline]
+    fn flush_denormals(&mut self) {
+        for (previous_input, previous_output) in &mut self.state {
+            if previous_output.abs() < 1e-15 {
+                *previous_output = 0.0;
+            }
+            if previous_input.abs() < 1e-15 {
+                *previous_input = 0.0;
+            }
+        }
+    }
+}
+
+/// Doubles the sample rate of one channel
+#[derive(Debug, Clone)]
+pub struct IirUpsampler2x {
+    filter: IirHalfBandFilter,
+    even: AllpassChain,
+    odd: AllpassChain,
+}
+
+impl IirUpsampler2x {
+    pub fn new(filter: IirHalfBandFilter) -> Self {
+        let even = AllpassChain::new(filter.even_coefficients.clone());
+        let odd = AllpassChain::new(filter.odd_coefficients.clone());
+        IirUpsampler2x { filter, even, odd }
+    }
+
+    /// Delay of an up & down round trip at low frequencies, in samples at the lower sample rate
+    pub fn latency(&self) -> f32 {
+        self.filter.latency()
+    }
+
+    pub fn reset(&mut self) {
+        self.even.clear();
+        self.odd.clear();
+    }
+
+    /// The 2 output samples for one input sample
+    #[inline]
+    pub fn process1(&mut self, input: f32) -> [f32; 2] {
+        let output = [self.even.process1(input), self.odd.process1(input)];
+        self.even.flush_denormals();
+        self.odd.flush_denormals();
+        output
+    }
+}
+
+/// Halves the sample rate of one channel
+#[derive(Debug, Clone)]
+pub struct IirDownsampler2x {
+    filter: IirHalfBandFilter,
+    even: AllpassChain,
+    odd: AllpassChain,
+}
+
+impl IirDownsampler2x {
+    pub fn new(filter: IirHalfBandFilter) -> Self {
+        let even = AllpassChain::new(filter.even_coefficients.clone());
+        let odd = AllpassChain::new(filter.odd_coefficients.clone());
+        IirDownsampler2x { filter, even, odd }
+    }
+
+    /// Delay of an up & down round trip at low frequencies, in samples at the lower sample rate
+    pub fn latency(&self) -> f32 {
+        self.filter.latency()
+    }
+
+    pub fn reset(&mut self) {
+        self.even.clear();
+        self.odd.clear();
+    }
+
+    /// The output sample for 2 input samples
+    #[inline]
+    pub fn process1(&mut self, input: [f32; 2]) -> f32 {
+        let output = 0.5 * (self.even.process1(input[1]) + self.odd.process1(input[0]));
+        self.even.flush_denormals();
+        self.odd.flush_denormals();
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sine(frequency: f32, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32).sin())
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_coefficients_are_stable_all_pass_sections() {
+        let filter = IirHalfBandFilter::new(8, 0.05);
+        assert_eq!(filter.num_coefficients(), 8);
+        for coefficient in filter
+            .even_coefficients
+            .iter()
+            .chain(&filter.odd_coefficients)
+        {
+            assert!(*coefficient > 0.0 && *coefficient < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_delays_low_frequencies_by_the_latency() {
+        let filter = IirHalfBandFilter::new(8, 0.05);
+        let mut upsampler = IirUpsampler2x::new(filter.clone());
+        let mut downsampler = IirDownsampler2x::new(filter);
+        // 0.01 of the sample rate, where the delay is close to the one at DC
+        let input = sine(0.01, 2000);
+        let output: Vec<f32> = input
+            .iter()
+            .map(|sample| downsampler.process1(upsampler.process1(*sample)))
+            .collect();
+
+        let latency = upsampler.latency();
+        let whole = latency.floor() as usize;
+        let fraction = latency - whole as f32;
+        for i in 500..2000 {
+            let expected = input[i - whole] * (1.0 - fraction) + input[i - whole - 1] * fraction;
+            assert!(
+                (output[i] - expected).abs() < 1e-2,
+                "{}: {} != {}",
+                i,
+                output[i],
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_downsampler_rejects_the_top_octave() {
+        let filter = IirHalfBandFilter::new(12, 0.02);
+        let mut downsampler = IirDownsampler2x::new(filter);
+        // 0.4 of the higher sample rate would alias to 0.2 of the lower one
+        let input = sine(0.4, 8000);
+        let output: Vec<f32> = input
+            .chunks(2)
+            .map(|pair| downsampler.process1([pair[0], pair[1]]))
+            .collect();
+        assert!(rms(&output[100..]) < 1e-3, "{}", rms(&output[100..]));
+    }
+}
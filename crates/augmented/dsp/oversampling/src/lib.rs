@@ -0,0 +1,14 @@
+//! Oversampling, so nonlinear processing doesn't alias.
+//!
+//! [`Oversampler`] upsamples interleaved audio by 2, 4 or 8 with cascaded polyphase half-band
+//! filters, either linear-phase FIR (see [`half_band`]) or low-latency IIR (see
+//! [`iir_half_band`]), lets it be processed at the higher rate & downsamples it back.
+//!
+//! [`Oversampled`] wraps any `AudioProcessor` so it runs at the higher rate.
+pub use oversampled::Oversampled;
+pub use oversampler::{Oversampler, OversamplingFactor, OversamplingFilter, OversamplingQuality};
+
+pub mod half_band;
+pub mod iir_half_band;
+mod oversampled;
+mod oversampler;
@@ -0,0 +1,289 @@
+use audio_processor_traits::{
+    AudioBuffer, AudioProcessor, AudioProcessorSettings, InterleavedAudioBuffer,
+};
+
+use crate::{Oversampler, OversamplingFactor, OversamplingFilter, OversamplingQuality};
+
+/// Runs any [`AudioProcessor`] at 2, 4 or 8 times the sample rate.
+///
+/// On `prepare` the inner processor is prepared with the sample rate & block size multiplied by
+/// the factor. Each block is upsampled, processed by the inner processor & downsampled back.
+///
+/// Buffers must have as many channels as the processor was prepared with. Other buffers are left
+/// untouched & an error is logged; debug builds panic.
+///
+/// The reported [`AudioProcessor::latency`] adds up the filters' & the inner processor's, rounded
+/// to the nearest sample at the original rate.
+///
+/// ```
+/// use audio_processor_traits::{AudioProcessor, AudioProcessorSettings, NoopAudioProcessor};
+/// use oversampling::{Oversampled, OversamplingFactor};
+///
+/// let mut oversampled = Oversampled::new(NoopAudioProcessor::new(), OversamplingFactor::X4);
+/// oversampled.prepare(AudioProcessorSettings::default());
+/// assert!(oversampled.latency() > 0);
+/// ```
+pub struct Oversampled<P> {
+    processor: P,
+    factor: OversamplingFactor,
+    quality: OversamplingQuality,
+    filter: OversamplingFilter,
+    settings: AudioProcessorSettings,
+    oversampler: Option<Oversampler>,
+}
+
+impl<P> Oversampled<P> {
+    /// Wrap `processor`, with the default quality FIR filters
+    pub fn new(processor: P, factor: OversamplingFactor) -> Self {
+        Self::new_with_filter(
+            processor,
+            factor,
+            OversamplingQuality::default(),
+            OversamplingFilter::default(),
+        )
+    }
+
+    pub fn new_with_filter(
+        processor: P,
+        factor: OversamplingFactor,
+        quality: OversamplingQuality,
+        filter: OversamplingFilter,
+    ) -> Self {
+        Oversampled {
+            processor,
+            factor,
+            quality,
+            filter,
+            settings: AudioProcessorSettings::default(),
+            oversampler: None,
+        }
+    }
+
+    pub fn processor(&self) -> &P {
+        &self.processor
+    }
+
+    pub fn processor_mut(&mut self) -> &mut P {
+        &mut self.processor
+    }
+
+    pub fn into_inner(self) -> P {
+        self.processor
+    }
+
+    pub fn factor(&self) -> OversamplingFactor {
+        self.factor
+    }
+
+    pub fn quality(&self) -> OversamplingQuality {
+        self.quality
+    }
+
+    pub fn filter(&self) -> OversamplingFilter {
+        self.filter
+    }
+
+    /// Clear the filters. The inner processor is left alone.
+    pub fn reset(&mut self) {
+        if let Some(oversampler) = &mut self.oversampler {
+            oversampler.reset();
+        }
+    }
+
+    /// Settings the inner processor is prepared with
+    pub fn oversampled_settings(&self) -> AudioProcessorSettings {
+        let factor = self.factor.factor();
+        let mut settings = self.settings;
+        settings.set_sample_rate(self.settings.sample_rate() * factor as f32);
+        settings.set_block_size(self.settings.block_size() * factor);
+        settings
+    }
+}
+
+impl<P> Oversampled<P>
+where
+    P: AudioProcessor<SampleType = f32>,
+{
+    /// Change the oversampling. Re-allocates the filters & re-prepares the inner processor if this
+    /// was prepared, so it shouldn't be called on the audio thread.
+    pub fn set_oversampling(
+        &mut self,
+        factor: OversamplingFactor,
+        quality: OversamplingQuality,
+        filter: OversamplingFilter,
+    ) {
+        self.factor = factor;
+        self.quality = quality;
+        self.filter = filter;
+        if self.oversampler.is_some() {
+            self.prepare(self.settings);
+        }
+    }
+}
+
+impl<P> AudioProcessor for Oversampled<P>
+where
+    P: AudioProcessor<SampleType = f32>,
+{
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.settings = settings;
+        self.oversampler = Some(Oversampler::new_with_filter(
+            settings.output_channels(),
+            settings.block_size(),
+            self.factor,
+            self.quality,
+            self.filter,
+        ));
+        self.processor.prepare(self.oversampled_settings());
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        let oversampler = match &mut self.oversampler {
+            Some(oversampler) => oversampler,
+            None => return,
+        };
+
+        let num_channels = data.num_channels();
+        debug_assert_eq!(
+            oversampler.num_channels(),
+            num_channels,
+            "Oversampled was prepared for a different number of channels"
+        );
+        if oversampler.num_channels() != num_channels {
+            log::error!(
+                "Oversampled was prepared for {} channels, but got {}. Bypassing.",
+                oversampler.num_channels(),
+                num_channels
+            );
+            return;
+        }
+
+        let processor = &mut self.processor;
+        oversampler.process(data.slice_mut(), |oversampled| {
+            processor.process(&mut InterleavedAudioBuffer::new(num_channels, oversampled));
+        });
+    }
+
+    fn latency(&self) -> usize {
+        let filter_latency = self
+            .oversampler
+            .as_ref()
+            .map_or(0.0, |oversampler| oversampler.latency());
+        let processor_latency = self.processor.latency() as f32 / self.factor.factor() as f32;
+        (filter_latency + processor_latency).round() as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::NoopAudioProcessor;
+
+    use super::*;
+
+    /// Records the settings it was prepared with & delays by `latency` samples
+    struct DelayProcessor {
+        settings: Option<AudioProcessorSettings>,
+        latency: usize,
+        history: Vec<f32>,
+    }
+
+    impl AudioProcessor for DelayProcessor {
+        type SampleType = f32;
+
+        fn prepare(&mut self, settings: AudioProcessorSettings) {
+            self.settings = Some(settings);
+            self.history = vec![0.0; self.latency];
+        }
+
+        fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+            &mut self,
+            data: &mut BufferType,
+        ) {
+            for frame in data.frames_mut() {
+                for sample in frame.iter_mut() {
+                    self.history.push(*sample);
+                    *sample = self.history.remove(0);
+                }
+            }
+        }
+
+        fn latency(&self) -> usize {
+            self.latency
+        }
+    }
+
+    #[test]
+    fn test_inner_processor_is_prepared_at_the_higher_rate() {
+        let inner = DelayProcessor {
+            settings: None,
+            latency: 0,
+            history: Vec::new(),
+        };
+        let mut oversampled = Oversampled::new(inner, OversamplingFactor::X4);
+        oversampled.prepare(AudioProcessorSettings::new(48000.0, 1, 1, 256));
+        let settings = oversampled.processor().settings.unwrap();
+        assert_eq!(settings.sample_rate(), 4.0 * 48000.0);
+        assert_eq!(settings.block_size(), 4 * 256);
+        assert_eq!(settings.output_channels(), 1);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn test_channel_mismatch_panics_in_debug_builds() {
+        let mut oversampled = Oversampled::new(NoopAudioProcessor::new(), OversamplingFactor::X2);
+        oversampled.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 64));
+        let mut samples = vec![0.0; 128];
+        oversampled.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+    }
+
+    #[test]
+    fn test_latency_includes_the_inner_processor() {
+        let mut oversampled = Oversampled::new_with_filter(
+            NoopAudioProcessor::new(),
+            OversamplingFactor::X2,
+            OversamplingQuality::Low,
+            OversamplingFilter::Fir,
+        );
+        assert_eq!(oversampled.latency(), 0);
+        oversampled.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 64));
+        let filter_latency = oversampled.latency();
+        assert!(filter_latency > 0);
+
+        let inner = DelayProcessor {
+            settings: None,
+            latency: 8,
+            history: Vec::new(),
+        };
+        let mut oversampled = Oversampled::new_with_filter(
+            inner,
+            OversamplingFactor::X2,
+            OversamplingQuality::Low,
+            OversamplingFilter::Fir,
+        );
+        oversampled.prepare(AudioProcessorSettings::new(44100.0, 1, 1, 64));
+        assert_eq!(oversampled.latency(), filter_latency + 4);
+
+        // An impulse comes out the reported latency later
+        let mut samples = vec![0.0; 256];
+        samples[10] = 1.0;
+        oversampled.process(&mut InterleavedAudioBuffer::new(1, &mut samples));
+        let peak = samples
+            .iter()
+            .enumerate()
+            .fold((0, 0.0), |(peak_index, peak), (index, sample)| {
+                if sample.abs() > peak {
+                    (index, sample.abs())
+                } else {
+                    (peak_index, peak)
+                }
+            })
+            .0;
+        assert_eq!(peak, 10 + oversampled.latency());
+    }
+}
@@ -0,0 +1,525 @@
+use crate::half_band::{Downsampler2x, HalfBandFilter, Upsampler2x};
+use crate::iir_half_band::{IirDownsampler2x, IirHalfBandFilter, IirUpsampler2x};
+
+/// How many times the sample rate is multiplied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversamplingFactor {
+    X2,
+    #[default]
+    X4,
+    X8,
+}
+
+impl OversamplingFactor {
+    /// All factors, in the order used by [`OversamplingFactor::from_index`]
+    pub const ALL: [OversamplingFactor; 3] = [
+        OversamplingFactor::X2,
+        OversamplingFactor::X4,
+        OversamplingFactor::X8,
+    ];
+
+    pub fn index(&self) -> usize {
+        self.num_stages() - 1
+    }
+
+    /// Factor at `index`, or the last factor if it's out of range
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index.min(Self::ALL.len() - 1)]
+    }
+
+    pub fn factor(&self) -> usize {
+        1 << self.num_stages()
+    }
+
+    /// Number of 2x steps
+    pub fn num_stages(&self) -> usize {
+        match self {
+            OversamplingFactor::X2 => 1,
+            OversamplingFactor::X4 => 2,
+            OversamplingFactor::X8 => 3,
+        }
+    }
+}
+
+/// Trades CPU & latency for less aliasing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversamplingQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl OversamplingQuality {
+    /// Filter for the 2x step at `stage`. The first step has the narrowest transition band, later
+    /// steps only need to reject what's above the original Nyquist frequency, so they're shorter.
+    pub fn filter(&self, stage: usize) -> HalfBandFilter {
+        let (num_taps, kaiser_beta) = match (self, stage) {
+            (OversamplingQuality::Low, 0) => (19, 6.0),
+            (OversamplingQuality::Low, _) => (11, 6.0),
+            (OversamplingQuality::Medium, 0) => (35, 8.0),
+            (OversamplingQuality::Medium, _) => (19, 8.0),
+            (OversamplingQuality::High, 0) => (63, 10.0),
+            (OversamplingQuality::High, _) => (31, 10.0),
+        };
+        HalfBandFilter::new(num_taps, kaiser_beta)
+    }
+
+    /// IIR filter for the 2x step at `stage`, see [`OversamplingQuality::filter`]
+    pub fn iir_filter(&self, stage: usize) -> IirHalfBandFilter {
+        let (num_coefficients, transition_bandwidth) = match (self, stage) {
+            (OversamplingQuality::Low, 0) => (4, 0.1),
+            (OversamplingQuality::Low, _) => (2, 0.25),
+            (OversamplingQuality::Medium, 0) => (8, 0.05),
+            (OversamplingQuality::Medium, _) => (4, 0.2),
+            (OversamplingQuality::High, 0) => (12, 0.02),
+            (OversamplingQuality::High, _) => (6, 0.15),
+        };
+        IirHalfBandFilter::new(num_coefficients, transition_bandwidth)
+    }
+}
+
+/// Kind of half-band filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversamplingFilter {
+    /// Linear phase, delays every frequency by the same amount
+    #[default]
+    Fir,
+    /// Cheaper & with much less latency, but delays high frequencies more than low ones
+    Iir,
+}
+
+impl OversamplingFilter {
+    /// All filter kinds, in the order used by [`OversamplingFilter::from_index`]
+    pub const ALL: [OversamplingFilter; 2] = [OversamplingFilter::Fir, OversamplingFilter::Iir];
+
+    pub fn index(&self) -> usize {
+        match self {
+            OversamplingFilter::Fir => 0,
+            OversamplingFilter::Iir => 1,
+        }
+    }
+
+    /// Filter kind at `index`, or the last one if it's out of range
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index.min(Self::ALL.len() - 1)]
+    }
+}
+
+enum Upsampler {
+    Fir(Upsampler2x),
+    Iir(IirUpsampler2x),
+}
+
+impl Upsampler {
+    #[inline]
+    fn process1(&mut self, input: f32) -> [f32; 2] {
+        match self {
+            Upsampler::Fir(upsampler) => upsampler.process1(input),
+            Upsampler::Iir(upsampler) => upsampler.process1(input),
+        }
+    }
+
+    /// Delay of the round trip through this step, in samples at its lower sample rate
+    fn round_trip_latency(&self) -> f32 {
+        match self {
+            Upsampler::Fir(upsampler) => upsampler.latency() as f32,
+            Upsampler::Iir(upsampler) => upsampler.latency(),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Upsampler::Fir(upsampler) => upsampler.reset(),
+            Upsampler::Iir(upsampler) => upsampler.reset(),
+        }
+    }
+}
+
+enum Downsampler {
+    Fir(Downsampler2x),
+    Iir(IirDownsampler2x),
+}
+
+impl Downsampler {
+    #[inline]
+    fn process1(&mut self, input: [f32; 2]) -> f32 {
+        match self {
+            Downsampler::Fir(downsampler) => downsampler.process1(input),
+            Downsampler::Iir(downsampler) => downsampler.process1(input),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Downsampler::Fir(downsampler) => downsampler.reset(),
+            Downsampler::Iir(downsampler) => downsampler.reset(),
+        }
+    }
+}
+
+/// Filters for one 2x step, per channel
+struct Stage {
+    upsamplers: Vec<Upsampler>,
+    downsamplers: Vec<Downsampler>,
+}
+
+impl Stage {
+    fn new(
+        num_channels: usize,
+        stage: usize,
+        quality: OversamplingQuality,
+        filter: OversamplingFilter,
+    ) -> Self {
+        match filter {
+            OversamplingFilter::Fir => {
+                let filter = quality.filter(stage);
+                Stage {
+                    upsamplers: (0..num_channels)
+                        .map(|_| Upsampler::Fir(Upsampler2x::new(filter.clone())))
+                        .collect(),
+                    downsamplers: (0..num_channels)
+                        .map(|_| Downsampler::Fir(Downsampler2x::new(filter.clone())))
+                        .collect(),
+                }
+            }
+            OversamplingFilter::Iir => {
+                let filter = quality.iir_filter(stage);
+                Stage {
+                    upsamplers: (0..num_channels)
+                        .map(|_| Upsampler::Iir(IirUpsampler2x::new(filter.clone())))
+                        .collect(),
+                    downsamplers: (0..num_channels)
+                        .map(|_| Downsampler::Iir(IirDownsampler2x::new(filter.clone())))
+                        .collect(),
+                }
+            }
+        }
+    }
+}
+
+/// Runs interleaved audio at 2, 4 or 8 times its sample rate, with cascaded polyphase half-band
+/// filters.
+///
+/// The filters are linear-phase FIRs unless created with [`Oversampler::new_with_filter`].
+///
+/// Upsampling removes the images above the original Nyquist frequency & downsampling removes
+/// what's been generated above it, such as harmonics of a waveshaper, before it aliases.
+///
+/// ```
+/// use oversampling::{Oversampler, OversamplingFactor, OversamplingQuality};
+///
+/// let mut oversampler =
+///     Oversampler::new(2, 512, OversamplingFactor::X4, OversamplingQuality::Medium);
+/// let mut data = vec![0.0; 2 * 512];
+/// oversampler.process(&mut data, |oversampled| {
+///     assert_eq!(oversampled.len(), 4 * 2 * 512);
+///     for sample in oversampled {
+///         *sample = sample.tanh();
+///     }
+/// });
+/// ```
+pub struct Oversampler {
+    num_channels: usize,
+    max_block_size: usize,
+    factor: OversamplingFactor,
+    quality: OversamplingQuality,
+    filter: OversamplingFilter,
+    stages: Vec<Stage>,
+    /// Interleaved audio at each stage's higher sample rate
+    buffers: Vec<Vec<f32>>,
+    /// Frames in the last upsampled block, at the original rate
+    num_frames: usize,
+}
+
+impl Oversampler {
+    /// Create an oversampler for blocks of up to `max_block_size` frames, with FIR filters.
+    /// Allocates all buffers.
+    pub fn new(
+        num_channels: usize,
+        max_block_size: usize,
+        factor: OversamplingFactor,
+        quality: OversamplingQuality,
+    ) -> Self {
+        Self::new_with_filter(
+            num_channels,
+            max_block_size,
+            factor,
+            quality,
+            OversamplingFilter::default(),
+        )
+    }
+
+    /// Create an oversampler for blocks of up to `max_block_size` frames, with the given kind of
+    /// filters. Allocates all buffers.
+    pub fn new_with_filter(
+        num_channels: usize,
+        max_block_size: usize,
+        factor: OversamplingFactor,
+        quality: OversamplingQuality,
+        filter: OversamplingFilter,
+    ) -> Self {
+        let stages = (0..factor.num_stages())
+            .map(|stage| Stage::new(num_channels, stage, quality, filter))
+            .collect();
+        let buffers = (0..factor.num_stages())
+            .map(|stage| vec![0.0; (num_channels * max_block_size) << (stage + 1)])
+            .collect();
+
+        Oversampler {
+            num_channels,
+            max_block_size,
+            factor,
+            quality,
+            filter,
+            stages,
+            buffers,
+            num_frames: 0,
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    pub fn max_block_size(&self) -> usize {
+        self.max_block_size
+    }
+
+    pub fn factor(&self) -> OversamplingFactor {
+        self.factor
+    }
+
+    pub fn quality(&self) -> OversamplingQuality {
+        self.quality
+    }
+
+    pub fn filter(&self) -> OversamplingFilter {
+        self.filter
+    }
+
+    /// Delay of the round trip through the filters, in samples at the original sample rate. May be
+    /// fractional with more than one stage. IIR filters delay low frequencies by this much & high
+    /// frequencies by more.
+    pub fn latency(&self) -> f32 {
+        self.stages
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| {
+                // Each step's round trip is measured at 2^index times the rate
+                let stage_latency = stage
+                    .upsamplers
+                    .first()
+                    .map_or(0.0, |up| up.round_trip_latency());
+                stage_latency / (1 << index) as f32
+            })
+            .sum()
+    }
+
+    /// Clear the filters
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            for upsampler in &mut stage.upsamplers {
+                upsampler.reset();
+            }
+            for downsampler in &mut stage.downsamplers {
+                downsampler.reset();
+            }
+        }
+    }
+
+    /// Upsample interleaved `input`, of up to `max_block_size` frames. The returned oversampled
+    /// audio may be processed in place, then brought back with [`Oversampler::downsample`].
+    pub fn upsample(&mut self, input: &[f32]) -> &mut [f32] {
+        let num_channels = self.num_channels;
+        self.num_frames = input.len() / num_channels;
+        assert!(self.num_frames <= self.max_block_size);
+
+        for index in 0..self.stages.len() {
+            let (done, rest) = self.buffers.split_at_mut(index);
+            let source = match done.last() {
+                Some(buffer) => &buffer[..input.len() << index],
+                None => input,
+            };
+            let target = &mut rest[0][..input.len() << (index + 1)];
+
+            let stage = &mut self.stages[index];
+            for (source_frame, target_frames) in source
+                .chunks(num_channels)
+                .zip(target.chunks_mut(num_channels * 2))
+            {
+                for (channel, upsampler) in stage.upsamplers.iter_mut().enumerate() {
+                    let [first, second] = upsampler.process1(source_frame[channel]);
+                    target_frames[channel] = first;
+                    target_frames[num_channels + channel] = second;
+                }
+            }
+        }
+
+        let len = self.num_frames * num_channels * self.factor.factor();
+        &mut self.buffers[self.stages.len() - 1][..len]
+    }
+
+    /// Downsample the audio returned by the last [`Oversampler::upsample`] into `output`, which
+    /// must be as long as the input was.
+    pub fn downsample(&mut self, output: &mut [f32]) {
+        let num_channels = self.num_channels;
+        let len = self.num_frames * num_channels;
+        assert_eq!(output.len(), len);
+
+        for index in (0..self.stages.len()).rev() {
+            let (lower, higher) = self.buffers.split_at_mut(index);
+            let source = &higher[0][..len << (index + 1)];
+            let target = match lower.last_mut() {
+                Some(buffer) => &mut buffer[..len << index],
+                None => &mut output[..],
+            };
+
+            let stage = &mut self.stages[index];
+            for (source_frames, target_frame) in source
+                .chunks(num_channels * 2)
+                .zip(target.chunks_mut(num_channels))
+            {
+                for (channel, downsampler) in stage.downsamplers.iter_mut().enumerate() {
+                    target_frame[channel] = downsampler.process1([
+                        source_frames[channel],
+                        source_frames[num_channels + channel],
+                    ]);
+                }
+            }
+        }
+    }
+
+    /// Upsample interleaved `data`, call `process` with the oversampled audio & downsample the
+    /// result back into `data`. Blocks longer than `max_block_size` are split up.
+    pub fn process(&mut self, data: &mut [f32], mut process: impl FnMut(&mut [f32])) {
+        let chunk_size = self.max_block_size * self.num_channels;
+        for chunk in data.chunks_mut(chunk_size) {
+            process(self.upsample(chunk));
+            self.downsample(chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sine(frequency: f32, num_frames: usize, num_channels: usize) -> Vec<f32> {
+        (0..num_frames)
+            .flat_map(|i| {
+                let value = (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin();
+                vec![value; num_channels]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_delays_by_the_latency() {
+        for factor in OversamplingFactor::ALL.iter() {
+            let mut oversampler = Oversampler::new(2, 256, *factor, OversamplingQuality::High);
+            let input = sine(1000.0, 4096, 2);
+            let mut output = input.clone();
+            oversampler.process(&mut output, |oversampled| {
+                assert_eq!(oversampled.len(), 2 * 256 * factor.factor());
+            });
+
+            // Compare with the input interpolated at the fractional latency
+            let latency = oversampler.latency();
+            let whole = latency.floor() as usize;
+            let fraction = latency - whole as f32;
+            for i in 200..4096 {
+                let expected = input[(i - whole) * 2] * (1.0 - fraction)
+                    + input[(i - whole - 1) * 2] * fraction;
+                assert!(
+                    (output[i * 2] - expected).abs() < 1e-2,
+                    "{:?} at {}: {} != {}",
+                    factor,
+                    i,
+                    output[i * 2],
+                    expected
+                );
+                assert_eq!(output[i * 2], output[i * 2 + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iir_round_trip_delays_low_frequencies_by_the_latency() {
+        for factor in OversamplingFactor::ALL.iter() {
+            let mut oversampler = Oversampler::new_with_filter(
+                1,
+                256,
+                *factor,
+                OversamplingQuality::Medium,
+                OversamplingFilter::Iir,
+            );
+            let input = sine(100.0, 4096, 1);
+            let mut output = input.clone();
+            oversampler.process(&mut output, |_| {});
+
+            let latency = oversampler.latency();
+            assert!(
+                latency < Oversampler::new(1, 256, *factor, OversamplingQuality::Medium).latency()
+            );
+            let whole = latency.floor() as usize;
+            let fraction = latency - whole as f32;
+            for i in 1000..4096 {
+                let expected =
+                    input[i - whole] * (1.0 - fraction) + input[i - whole - 1] * fraction;
+                assert!(
+                    (output[i] - expected).abs() < 1e-2,
+                    "{:?} at {}: {} != {}",
+                    factor,
+                    i,
+                    output[i],
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_harmonics_dont_alias() {
+        for filter in OversamplingFilter::ALL.iter() {
+            // Hard clipping a 5kHz sine makes harmonics up to the oversampled Nyquist. Without
+            // oversampling the 5th harmonic would alias to 44.1 - 25 = 19.1kHz, the 7th to 9.1kHz...
+            let mut oversampler = Oversampler::new_with_filter(
+                1,
+                512,
+                OversamplingFactor::X8,
+                OversamplingQuality::High,
+                *filter,
+            );
+            let frequency = 5000.0;
+            let mut output: Vec<f32> = sine(frequency, 44100, 1).iter().map(|s| s * 4.0).collect();
+            oversampler.process(&mut output, |oversampled| {
+                for sample in oversampled {
+                    *sample = sample.clamp(-1.0, 1.0);
+                }
+            });
+
+            // Energy at 9.1kHz, where the 7th harmonic would alias to
+            let magnitude_at = |frequency: f32| {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, sample) in output[4096..].iter().enumerate() {
+                    let phase = 2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0;
+                    re += sample * phase.cos();
+                    im += sample * phase.sin();
+                }
+                (re * re + im * im).sqrt() / (output.len() - 4096) as f32
+            };
+            let fundamental = magnitude_at(frequency);
+            let alias = magnitude_at(44100.0 - 7.0 * frequency);
+            let third = magnitude_at(3.0 * frequency);
+            assert!(third > fundamental * 0.1);
+            assert!(
+                alias < fundamental * 1e-3,
+                "{:?}: alias {} vs {}",
+                filter,
+                alias,
+                fundamental
+            );
+        }
+    }
+}
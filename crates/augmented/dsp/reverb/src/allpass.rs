@@ -0,0 +1,77 @@
+use delay_line::{DelayLine, Interpolation, Tap};
+
+/// A Schroeder all-pass filter. Smears its input over time, without colouring its spectrum.
+pub struct AllpassFilter {
+    line: DelayLine<f32>,
+    tap: Tap<f32>,
+    feedback: f32,
+}
+
+impl AllpassFilter {
+    /// Create an all-pass filter with a delay of `delay` samples, which may be set up to
+    /// `max_delay`
+    pub fn new(max_delay: usize, delay: f32) -> Self {
+        AllpassFilter {
+            line: DelayLine::new(max_delay),
+            tap: Tap::new(delay, Interpolation::Linear),
+            feedback: 0.5,
+        }
+    }
+
+    pub fn delay(&self) -> f32 {
+        self.tap.target_delay()
+    }
+
+    /// Set the delay, in samples. Glides if smoothing is set.
+    pub fn set_delay(&mut self, delay: f32) {
+        self.tap.set_delay(delay);
+    }
+
+    /// Jump to a new delay, in samples, without gliding
+    pub fn jump_delay(&mut self, delay: f32) {
+        self.tap.jump_delay(delay);
+    }
+
+    /// Set how many samples delay changes take to reach ~63% of the new delay
+    pub fn set_smoothing(&mut self, smoothing_samples: f32) {
+        self.tap.set_smoothing(smoothing_samples);
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    /// Set the feedback, which must be between -1 & 1 for the filter to be stable
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback;
+    }
+
+    /// Clear the delay line
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.tap.reset();
+    }
+
+    #[inline]
+    pub fn process1(&mut self, input: f32) -> f32 {
+        let delayed = self.tap.read(&self.line);
+        let state = input + self.feedback * delayed;
+        self.line.write(state);
+        delayed - self.feedback * state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_impulse_response_keeps_energy() {
+        let mut allpass = AllpassFilter::new(100, 7.0);
+        allpass.set_feedback(0.7);
+        let energy: f32 = (0..10000)
+            .map(|i| allpass.process1(if i == 0 { 1.0 } else { 0.0 }).powi(2))
+            .sum();
+        assert!((energy - 1.0).abs() < 1e-4);
+    }
+}
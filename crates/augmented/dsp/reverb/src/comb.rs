@@ -0,0 +1,97 @@
+use delay_line::{DelayLine, Interpolation, Tap};
+
+/// A feedback comb filter with a one-pole low-pass in the loop, as in Freeverb. Higher damping
+/// makes high frequencies decay faster than low frequencies.
+pub struct CombFilter {
+    line: DelayLine<f32>,
+    tap: Tap<f32>,
+    feedback: f32,
+    damping: f32,
+    filter_state: f32,
+}
+
+impl CombFilter {
+    /// Create a comb filter with a delay of `delay` samples, which may be set up to `max_delay`
+    pub fn new(max_delay: usize, delay: f32) -> Self {
+        CombFilter {
+            line: DelayLine::new(max_delay),
+            tap: Tap::new(delay, Interpolation::Linear),
+            feedback: 0.0,
+            damping: 0.0,
+            filter_state: 0.0,
+        }
+    }
+
+    pub fn delay(&self) -> f32 {
+        self.tap.target_delay()
+    }
+
+    /// Set the delay, in samples. Glides if smoothing is set.
+    pub fn set_delay(&mut self, delay: f32) {
+        self.tap.set_delay(delay);
+    }
+
+    /// Jump to a new delay, in samples, without gliding
+    pub fn jump_delay(&mut self, delay: f32) {
+        self.tap.jump_delay(delay);
+    }
+
+    /// Set how many samples delay changes take to reach ~63% of the new delay
+    pub fn set_smoothing(&mut self, smoothing_samples: f32) {
+        self.tap.set_smoothing(smoothing_samples);
+    }
+
+    pub fn feedback(&self) -> f32 {
+        self.feedback
+    }
+
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback;
+    }
+
+    pub fn damping(&self) -> f32 {
+        self.damping
+    }
+
+    /// Set the low-pass coefficient, between 0 (no damping) and 1
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0.0, 1.0);
+    }
+
+    /// Clear the delay line & filter
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.tap.reset();
+        self.filter_state = 0.0;
+    }
+
+    #[inline]
+    pub fn process1(&mut self, input: f32) -> f32 {
+        let output = self.tap.read(&self.line);
+        self.filter_state = output + self.damping * (self.filter_state - output);
+        // Flush the decaying tail before it goes denormal
+        if self.filter_state.abs() < 1e-15 {
+            self.filter_state = 0.0;
+        }
+        self.line.write(input + self.filter_state * self.feedback);
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_echoes_decay_by_feedback() {
+        let mut comb = CombFilter::new(100, 10.0);
+        comb.set_feedback(0.5);
+        let output: Vec<f32> = (0..31)
+            .map(|i| comb.process1(if i == 0 { 1.0 } else { 0.0 }))
+            .collect();
+        assert!((output[10] - 1.0).abs() < 1e-6);
+        assert!((output[20] - 0.5).abs() < 1e-6);
+        assert!((output[30] - 0.25).abs() < 1e-6);
+        assert_eq!(output[15], 0.0);
+    }
+}
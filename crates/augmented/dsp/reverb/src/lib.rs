@@ -0,0 +1,17 @@
+//! Algorithmic reverb, built on the [`delay_line`] crate.
+//!
+//! * [`ReverbProcessor`] is a stereo Freeverb style [`audio_processor_traits::AudioProcessor`],
+//!   with size, decay, damping, pre-delay, width & mix settings
+//! * [`CombFilter`] & [`AllpassFilter`] are its building blocks, for other topologies
+//!
+//! With the `parameter_store` feature (on by default) the reverb's settings may be driven by an
+//! [`audio_parameter_store::ParameterStore`], see [`parameters`].
+pub use allpass::AllpassFilter;
+pub use comb::CombFilter;
+pub use processor::{ReverbProcessor, MAX_DECAY, MAX_PRE_DELAY, MIN_DECAY};
+
+mod allpass;
+mod comb;
+#[cfg(feature = "parameter_store")]
+pub mod parameters;
+mod processor;
@@ -0,0 +1,145 @@
+//! [`audio_parameter_store::ParameterStore`] integration, so the reverb may be shipped as a
+//! plugin.
+//!
+//! Size, damping, width & mix are percentages, decay is in seconds & pre-delay in milliseconds.
+use std::sync::Arc;
+use std::time::Duration;
+
+use audio_parameter_store::{ParameterReader, ParameterStore, PluginParameter};
+
+use crate::{ReverbProcessor, MAX_DECAY, MAX_PRE_DELAY, MIN_DECAY};
+
+pub const SIZE_PARAMETER_ID: &str = "size";
+pub const DECAY_PARAMETER_ID: &str = "decay";
+pub const DAMPING_PARAMETER_ID: &str = "damping";
+pub const PRE_DELAY_PARAMETER_ID: &str = "pre_delay";
+pub const WIDTH_PARAMETER_ID: &str = "width";
+pub const MIX_PARAMETER_ID: &str = "mix";
+
+const PARAMETER_IDS: [&str; 6] = [
+    SIZE_PARAMETER_ID,
+    DECAY_PARAMETER_ID,
+    DAMPING_PARAMETER_ID,
+    PRE_DELAY_PARAMETER_ID,
+    WIDTH_PARAMETER_ID,
+    MIX_PARAMETER_ID,
+];
+
+fn percentage_parameter(name: &str, initial_value: f32) -> Arc<PluginParameter> {
+    Arc::new(
+        PluginParameter::builder()
+            .name(name)
+            .label("%")
+            .initial_value(initial_value * 100.0)
+            .value_precision(0)
+            .value_range(0.0, 100.0)
+            .build(),
+    )
+}
+
+/// Add the reverb's parameters to `store`, using its settings as initial values
+pub fn add_parameters(store: &mut ParameterStore, reverb: &ReverbProcessor) {
+    store.add_parameter(
+        SIZE_PARAMETER_ID,
+        percentage_parameter("Size", reverb.size()),
+    );
+    store.add_parameter(
+        DECAY_PARAMETER_ID,
+        Arc::new(
+            PluginParameter::builder()
+                .name("Decay")
+                .label("s")
+                .initial_value(reverb.decay().as_secs_f32())
+                .value_precision(1)
+                .value_range(MIN_DECAY.as_secs_f32(), MAX_DECAY.as_secs_f32())
+                .build(),
+        ),
+    );
+    store.add_parameter(
+        DAMPING_PARAMETER_ID,
+        percentage_parameter("Damping", reverb.damping()),
+    );
+    store.add_parameter(
+        PRE_DELAY_PARAMETER_ID,
+        Arc::new(
+            PluginParameter::builder()
+                .name("Pre-delay")
+                .label("ms")
+                .initial_value(reverb.pre_delay().as_secs_f32() * 1000.0)
+                .value_precision(0)
+                .value_range(0.0, MAX_PRE_DELAY.as_secs_f32() * 1000.0)
+                .build(),
+        ),
+    );
+    store.add_parameter(
+        WIDTH_PARAMETER_ID,
+        percentage_parameter("Width", reverb.width()),
+    );
+    store.add_parameter(MIX_PARAMETER_ID, percentage_parameter("Mix", reverb.mix()));
+}
+
+impl ReverbProcessor {
+    /// Read settings from `store` at the start of every block. The store should have been set-up
+    /// with [`add_parameters`], parameters it's missing are left as they are.
+    pub fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+        self.parameters = Some(ParameterReader::new(&store, &PARAMETER_IDS));
+        self.update_from_parameters();
+    }
+
+    pub(crate) fn update_from_parameters(&mut self) {
+        let mut parameters = match self.parameters.take() {
+            Some(parameters) => parameters,
+            None => return,
+        };
+
+        // Settings are only recalculated when they change
+        parameters.read_changes(|index, value| match index {
+            0 => self.set_size(value / 100.0),
+            1 => self.set_decay(Duration::from_secs_f32(value.max(0.0))),
+            2 => self.set_damping(value / 100.0),
+            3 => self.set_pre_delay(Duration::from_secs_f32(value.max(0.0) / 1000.0)),
+            4 => self.set_width(value / 100.0),
+            _ => self.set_mix(value / 100.0),
+        });
+        self.parameters = Some(parameters);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::{AudioProcessor, AudioProcessorSettings, InterleavedAudioBuffer};
+
+    use super::*;
+
+    #[test]
+    fn test_parameter_store_drives_the_reverb() {
+        let mut reverb = ReverbProcessor::new();
+        let mut store = ParameterStore::new();
+        add_parameters(&mut store, &reverb);
+        assert_eq!(store.get_num_parameters(), 6);
+        assert!((store.value(MIX_PARAMETER_ID) - 30.0).abs() < 1e-4);
+
+        let store = Arc::new(store);
+        reverb.set_parameter_store(store.clone());
+        reverb.prepare(AudioProcessorSettings::default());
+
+        store
+            .find_parameter(DECAY_PARAMETER_ID)
+            .unwrap()
+            .set_value(5.0);
+        store
+            .find_parameter(PRE_DELAY_PARAMETER_ID)
+            .unwrap()
+            .set_value(20.0);
+        store
+            .find_parameter(WIDTH_PARAMETER_ID)
+            .unwrap()
+            .set_value(50.0);
+        let mut samples = vec![0.0; 128];
+        reverb.process(&mut InterleavedAudioBuffer::new(2, &mut samples));
+
+        assert_eq!(reverb.decay(), Duration::from_secs(5));
+        assert_eq!(reverb.width(), 0.5);
+        assert!((reverb.tail_time().as_secs_f32() - 5.02).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,436 @@
+use std::time::Duration;
+
+use audio_processor_traits::{AudioBuffer, AudioProcessor, AudioProcessorSettings};
+use delay_line::{DelayLine, Interpolation, Tap};
+
+use crate::{AllpassFilter, CombFilter};
+
+/// Freeverb's comb filter delays, in samples at [`TUNING_SAMPLE_RATE`]
+const COMB_TUNINGS: [f32; 8] = [
+    1116.0, 1188.0, 1277.0, 1356.0, 1422.0, 1491.0, 1557.0, 1617.0,
+];
+/// Freeverb's all-pass filter delays, in samples at [`TUNING_SAMPLE_RATE`]
+const ALLPASS_TUNINGS: [f32; 4] = [556.0, 441.0, 341.0, 225.0];
+/// Extra delay of the right channel's filters, so the channels decorrelate
+const STEREO_SPREAD: f32 = 23.0;
+const TUNING_SAMPLE_RATE: f32 = 44100.0;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+/// Freeverb sums both input channels with a gain of 0.015. Inputs are averaged here instead.
+const INPUT_GAIN: f32 = 0.03;
+/// Largest low-pass coefficient in the combs, at full damping
+const DAMPING_SCALE: f32 = 0.4;
+/// Time size changes glide over
+const SIZE_SMOOTHING_TIME: Duration = Duration::from_millis(100);
+
+/// Longest pre-delay
+pub const MAX_PRE_DELAY: Duration = Duration::from_millis(500);
+/// Shortest decay time
+pub const MIN_DECAY: Duration = Duration::from_millis(100);
+/// Longest decay time
+pub const MAX_DECAY: Duration = Duration::from_secs(30);
+
+/// Scale of filter delays for a size between 0 & 1
+fn size_scale(size: f32) -> f32 {
+    0.5 + size
+}
+
+/// Comb & all-pass filters for one output channel
+struct ReverbChannel {
+    combs: Vec<CombFilter>,
+    allpasses: Vec<AllpassFilter>,
+    spread: f32,
+}
+
+impl ReverbChannel {
+    fn new(spread: f32, sample_rate: f32) -> Self {
+        let scale = sample_rate / TUNING_SAMPLE_RATE;
+        let max_delay =
+            |tuning: f32| ((tuning + spread) * scale * size_scale(1.0)).ceil() as usize + 1;
+        let smoothing = SIZE_SMOOTHING_TIME.as_secs_f32() * sample_rate;
+
+        let combs = COMB_TUNINGS
+            .iter()
+            .map(|tuning| {
+                let mut comb = CombFilter::new(max_delay(*tuning), 1.0);
+                comb.set_smoothing(smoothing);
+                comb
+            })
+            .collect();
+        let allpasses = ALLPASS_TUNINGS
+            .iter()
+            .map(|tuning| {
+                let mut allpass = AllpassFilter::new(max_delay(*tuning), 1.0);
+                allpass.set_feedback(ALLPASS_FEEDBACK);
+                allpass.set_smoothing(smoothing);
+                allpass
+            })
+            .collect();
+
+        ReverbChannel {
+            combs,
+            allpasses,
+            spread,
+        }
+    }
+
+    /// Set the filter delays to the tunings times `scale`, gliding or jumping to them
+    fn set_delays(&mut self, scale: f32, glide: bool) {
+        let spread = self.spread;
+        for (comb, tuning) in self.combs.iter_mut().zip(COMB_TUNINGS.iter()) {
+            let delay = (tuning + spread) * scale;
+            if glide {
+                comb.set_delay(delay);
+            } else {
+                comb.jump_delay(delay);
+            }
+        }
+        for (allpass, tuning) in self.allpasses.iter_mut().zip(ALLPASS_TUNINGS.iter()) {
+            let delay = (tuning + spread) * scale;
+            if glide {
+                allpass.set_delay(delay);
+            } else {
+                allpass.jump_delay(delay);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        for comb in &mut self.combs {
+            comb.clear();
+        }
+        for allpass in &mut self.allpasses {
+            allpass.clear();
+        }
+    }
+
+    #[inline]
+    fn process1(&mut self, input: f32) -> f32 {
+        let mut output = 0.0;
+        for comb in &mut self.combs {
+            output += comb.process1(input);
+        }
+        for allpass in &mut self.allpasses {
+            output = allpass.process1(output);
+        }
+        output
+    }
+}
+
+/// A stereo algorithmic reverb, after Jezar's Freeverb: 8 parallel damped comb filters into 4
+/// series all-pass filters, per channel.
+///
+/// The input channels are averaged into a mono signal, pre-delayed & fed to both channels' filters.
+/// Mono buffers get the average of both channels; channels past the 2nd are left dry.
+///
+/// * `size` scales all filter delays, changes glide instead of clicking
+/// * `decay` is the time the reverb takes to fall by 60dB, see [`ReverbProcessor::tail_time`]
+/// * `damping` makes high frequencies decay faster
+/// * `width` blends the channels together, 0 is mono & 1 is fully stereo
+///
+/// All buffers are allocated on `prepare`.
+pub struct ReverbProcessor {
+    size: f32,
+    decay: Duration,
+    damping: f32,
+    pre_delay: Duration,
+    width: f32,
+    mix: f32,
+    sample_rate: f32,
+    pre_delay_line: DelayLine<f32>,
+    pre_delay_tap: Tap<f32>,
+    channels: Vec<ReverbChannel>,
+    #[cfg(feature = "parameter_store")]
+    pub(crate) parameters: Option<audio_parameter_store::ParameterReader>,
+}
+
+impl Default for ReverbProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReverbProcessor {
+    pub fn new() -> Self {
+        ReverbProcessor {
+            size: 0.5,
+            decay: Duration::from_secs(2),
+            damping: 0.5,
+            pre_delay: Duration::from_millis(0),
+            width: 1.0,
+            mix: 0.3,
+            sample_rate: 44100.0,
+            pre_delay_line: DelayLine::new(1),
+            pre_delay_tap: Tap::new(1.0, Interpolation::Linear),
+            channels: Vec::new(),
+            #[cfg(feature = "parameter_store")]
+            parameters: None,
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Set the room size, between 0 & 1
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.clamp(0.0, 1.0);
+        self.update_delays();
+    }
+
+    pub fn decay(&self) -> Duration {
+        self.decay
+    }
+
+    /// Set the time the reverb takes to fall by 60dB, between [`MIN_DECAY`] & [`MAX_DECAY`]
+    pub fn set_decay(&mut self, decay: Duration) {
+        self.decay = decay.max(MIN_DECAY).min(MAX_DECAY);
+        self.update_feedback();
+    }
+
+    pub fn damping(&self) -> f32 {
+        self.damping
+    }
+
+    /// Set the high frequency damping, between 0 & 1
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0.0, 1.0);
+        let coefficient = self.damping * DAMPING_SCALE;
+        for comb in self
+            .channels
+            .iter_mut()
+            .flat_map(|channel| &mut channel.combs)
+        {
+            comb.set_damping(coefficient);
+        }
+    }
+
+    pub fn pre_delay(&self) -> Duration {
+        self.pre_delay
+    }
+
+    /// Set the delay before the reverb starts, up to [`MAX_PRE_DELAY`]
+    pub fn set_pre_delay(&mut self, pre_delay: Duration) {
+        self.pre_delay = pre_delay.min(MAX_PRE_DELAY);
+        self.pre_delay_tap.set_delay(self.pre_delay_samples());
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Set the stereo width, between 0 (mono) and 1
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.clamp(0.0, 1.0);
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    /// Set the dry/wet mix, between 0 (dry) and 1 (wet)
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+
+    /// How long the output keeps ringing after the input stops, until it's fallen by 60dB
+    pub fn tail_time(&self) -> Duration {
+        self.pre_delay + self.decay
+    }
+
+    /// Clear the reverb tail
+    pub fn reset(&mut self) {
+        self.pre_delay_line.clear();
+        self.pre_delay_tap.reset();
+        for channel in &mut self.channels {
+            channel.clear();
+        }
+    }
+
+    fn pre_delay_samples(&self) -> f32 {
+        self.pre_delay.as_secs_f32() * self.sample_rate
+    }
+
+    fn delay_scale(&self) -> f32 {
+        self.sample_rate / TUNING_SAMPLE_RATE * size_scale(self.size)
+    }
+
+    fn update_delays(&mut self) {
+        let scale = self.delay_scale();
+        for channel in &mut self.channels {
+            channel.set_delays(scale, true);
+        }
+        self.update_feedback();
+    }
+
+    /// Set each comb's feedback so it falls by 60dB over the decay time
+    fn update_feedback(&mut self) {
+        let decay_samples = self.decay.as_secs_f32() * self.sample_rate;
+        for comb in self
+            .channels
+            .iter_mut()
+            .flat_map(|channel| &mut channel.combs)
+        {
+            let feedback = 0.001_f32.powf(comb.delay() / decay_samples);
+            comb.set_feedback(feedback);
+        }
+    }
+}
+
+impl AudioProcessor for ReverbProcessor {
+    type SampleType = f32;
+
+    fn prepare(&mut self, settings: AudioProcessorSettings) {
+        self.sample_rate = settings.sample_rate();
+
+        let max_pre_delay = (MAX_PRE_DELAY.as_secs_f32() * self.sample_rate).ceil() as usize + 1;
+        self.pre_delay_line = DelayLine::new(max_pre_delay);
+        self.pre_delay_tap
+            .set_smoothing(SIZE_SMOOTHING_TIME.as_secs_f32() * self.sample_rate);
+        self.pre_delay_tap.jump_delay(self.pre_delay_samples());
+
+        self.channels = vec![
+            ReverbChannel::new(0.0, self.sample_rate),
+            ReverbChannel::new(STEREO_SPREAD, self.sample_rate),
+        ];
+        // Start at the right size, rather than gliding from the initial delays
+        let scale = self.delay_scale();
+        for channel in &mut self.channels {
+            channel.set_delays(scale, false);
+        }
+        self.update_feedback();
+        self.set_damping(self.damping);
+    }
+
+    fn process<BufferType: AudioBuffer<SampleType = Self::SampleType>>(
+        &mut self,
+        data: &mut BufferType,
+    ) {
+        #[cfg(feature = "parameter_store")]
+        self.update_from_parameters();
+
+        if self.channels.is_empty() {
+            return;
+        }
+
+        let dry = 1.0 - self.mix;
+        let wet = self.mix;
+        let wet_same = (1.0 + self.width) / 2.0;
+        let wet_other = (1.0 - self.width) / 2.0;
+
+        for frame in data.frames_mut() {
+            if frame.is_empty() {
+                continue;
+            }
+
+            let input = frame.iter().sum::<f32>() / frame.len() as f32 * INPUT_GAIN;
+            let delayed = self.pre_delay_tap.read(&self.pre_delay_line);
+            self.pre_delay_line.write(input);
+
+            let left = self.channels[0].process1(delayed);
+            let right = self.channels[1].process1(delayed);
+
+            if frame.len() == 1 {
+                frame[0] = dry * frame[0] + wet * (left + right) / 2.0;
+            } else {
+                let output_left = left * wet_same + right * wet_other;
+                let output_right = right * wet_same + left * wet_other;
+                frame[0] = dry * frame[0] + wet * output_left;
+                frame[1] = dry * frame[1] + wet * output_right;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use audio_processor_traits::InterleavedAudioBuffer;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    fn wet_reverb() -> ReverbProcessor {
+        let mut reverb = ReverbProcessor::new();
+        reverb.set_mix(1.0);
+        reverb.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        reverb
+    }
+
+    fn impulse_response(reverb: &mut ReverbProcessor, num_frames: usize) -> Vec<f32> {
+        let mut samples = vec![0.0; num_frames * 2];
+        samples[0] = 1.0;
+        samples[1] = 1.0;
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        reverb.process(&mut buffer);
+        samples
+    }
+
+    /// Energy of the left channel over `range`, in seconds
+    fn energy(samples: &[f32], range: std::ops::Range<f32>) -> f32 {
+        let start = (range.start * SAMPLE_RATE) as usize;
+        let end = (range.end * SAMPLE_RATE) as usize;
+        samples[start * 2..end * 2]
+            .iter()
+            .step_by(2)
+            .map(|sample| sample * sample)
+            .sum()
+    }
+
+    #[test]
+    fn test_tail_falls_by_60db_over_the_decay_time() {
+        let mut reverb = wet_reverb();
+        reverb.set_damping(0.0);
+        reverb.set_decay(Duration::from_secs(1));
+        assert_eq!(reverb.tail_time(), Duration::from_secs(1));
+
+        let output = impulse_response(&mut reverb, 2 * SAMPLE_RATE as usize);
+        let start = energy(&output, 0.1..0.2);
+        let end = energy(&output, 1.1..1.2);
+        let drop_db = 10.0 * (start / end).log10();
+        assert!((drop_db - 60.0).abs() < 6.0, "dropped by {}dB", drop_db);
+    }
+
+    #[test]
+    fn test_pre_delay_holds_the_reverb_back() {
+        let mut reverb = wet_reverb();
+        reverb.set_pre_delay(Duration::from_millis(100));
+        reverb.prepare(AudioProcessorSettings::new(SAMPLE_RATE, 2, 2, 512));
+        assert_eq!(reverb.tail_time(), Duration::from_millis(2100));
+
+        let output = impulse_response(&mut reverb, SAMPLE_RATE as usize);
+        // The shortest comb is ~25ms at the default size
+        assert_eq!(energy(&output, 0.0..0.12), 0.0);
+        assert!(energy(&output, 0.12..0.3) > 0.0);
+    }
+
+    #[test]
+    fn test_zero_width_is_mono() {
+        let mut reverb = wet_reverb();
+        reverb.set_width(0.0);
+        let output = impulse_response(&mut reverb, 10000);
+        assert!(output.iter().any(|sample| sample.abs() > 1e-3));
+        for frame in output.chunks(2) {
+            assert!((frame[0] - frame[1]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_zero_mix_is_dry() {
+        let mut reverb = wet_reverb();
+        reverb.set_mix(0.0);
+        let output = impulse_response(&mut reverb, 10000);
+        assert_eq!(&output[..2], &[1.0, 1.0]);
+        assert!(output[2..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_reset_clears_the_tail() {
+        let mut reverb = wet_reverb();
+        impulse_response(&mut reverb, 1000);
+        reverb.reset();
+        let mut samples = vec![0.0; 2000];
+        let mut buffer = InterleavedAudioBuffer::new(2, &mut samples);
+        reverb.process(&mut buffer);
+        assert!(samples.iter().all(|sample| *sample == 0.0));
+    }
+}